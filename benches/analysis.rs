@@ -0,0 +1,138 @@
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+use trade_bot::analysis::MovingStatistics;
+use trade_bot::market::{Candle, CandleCloser, Field};
+
+use std::hint::black_box;
+
+// One minute candles of a noisy walk, enough of them to fill a day or a week of history.
+fn candles(count: usize) -> Vec<Candle> {
+    let mut price = 30000.0;
+    (0..count)
+        .map(|index| {
+            let wave = (index as f64 / 37.0).sin() * 15.0 + (index as f64 / 5.0).cos() * 4.0;
+            let open = price;
+            price += wave;
+            Candle {
+                time: index as i64 * 60,
+                open,
+                high: open.max(price) + 3.0,
+                low: open.min(price) - 3.0,
+                close: price,
+                vwap: (open + price) / 2.0,
+                volume: 1.0 + (index % 17) as f64,
+                count: 10 + (index % 7) as i64,
+            }
+        })
+        .collect()
+}
+
+// Dozens of windows, as the strategies and the exported features ask for.
+fn windows() -> Vec<usize> {
+    (1..=40).map(|step| step * 25).collect()
+}
+
+fn statistics(candles: &[Candle], capacity: usize) -> MovingStatistics {
+    let mut statistics = MovingStatistics::new(capacity).with_interval(60);
+    for candle in candles {
+        statistics.update(*candle);
+    }
+    statistics
+}
+
+fn update(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("update");
+    for count in [1_000, 10_000] {
+        let history = candles(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(
+            BenchmarkId::new("append", count),
+            &history,
+            |bench, history| {
+                bench.iter(|| statistics(black_box(history), 1_000));
+            },
+        );
+        // every tenth candle arrives after its successor
+        let mut shuffled = history.clone();
+        for chunk in shuffled.chunks_exact_mut(10) {
+            chunk.swap(8, 9);
+        }
+        group.bench_with_input(
+            BenchmarkId::new("out_of_order", count),
+            &shuffled,
+            |bench, history| {
+                bench.iter(|| statistics(black_box(history), 1_000));
+            },
+        );
+    }
+    group.finish();
+}
+
+fn means(criterion: &mut Criterion) {
+    let windows = windows();
+    let mut group = criterion.benchmark_group("means");
+    for capacity in [1_000, 5_000] {
+        let statistics = statistics(&candles(capacity), capacity);
+        group.bench_function(BenchmarkId::new("candles", capacity), |bench| {
+            bench.iter(|| statistics.means(black_box(&windows)));
+        });
+        group.bench_function(BenchmarkId::new("close", capacity), |bench| {
+            bench.iter(|| statistics.means_of(Field::Close, black_box(&windows)));
+        });
+        group.bench_function(BenchmarkId::new("deviations", capacity), |bench| {
+            bench.iter(|| statistics.deviations(black_box(&windows)));
+        });
+    }
+    group.finish();
+}
+
+// Candle per candle as the live pipeline runs: update the universe and recompute the means.
+fn live(criterion: &mut Criterion) {
+    let windows = windows();
+    let history = candles(2_000);
+    let (warmup, incoming) = history.split_at(1_000);
+    criterion.bench_function("live/update_means", |bench| {
+        bench.iter_batched(
+            || statistics(warmup, 1_000),
+            |mut statistics| {
+                for candle in incoming {
+                    statistics.update(*candle);
+                    black_box(statistics.means(&windows));
+                }
+            },
+            BatchSize::LargeInput,
+        );
+    });
+}
+
+// Updates of the forming candles of several pairs closed as they arrive, a dozen per candle.
+fn aggregation(criterion: &mut Criterion) {
+    let pairs = ["BTC/EUR", "ETH/EUR", "SOL/EUR", "XRP/EUR"];
+    let updates: Vec<(&str, Candle)> = candles(2_500)
+        .into_iter()
+        .flat_map(|candle| {
+            (0..12).flat_map(move |tick| {
+                let update = Candle {
+                    close: candle.open + (candle.close - candle.open) * tick as f64 / 11.0,
+                    ..candle
+                };
+                pairs.map(|pair| (pair, update))
+            })
+        })
+        .collect();
+    let mut group = criterion.benchmark_group("aggregation");
+    group.throughput(Throughput::Elements(updates.len() as u64));
+    group.bench_function("close", |bench| {
+        bench.iter(|| {
+            let mut closer = CandleCloser::default();
+            updates
+                .iter()
+                .filter_map(|(ticker, candle)| closer.update(ticker, candle))
+                .count()
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, update, means, live, aggregation);
+criterion_main!(benches);
@@ -0,0 +1,342 @@
+use crate::accounts::MAIN;
+use crate::backtest::Fill;
+use crate::conversion::Rates;
+use crate::execution::Side;
+use crate::journal::Entry;
+use crate::risk::{base, quote};
+
+use chrono::DateTime;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, VecDeque};
+
+// Strategy fees of orders not placed by a strategy are attributed to.
+const UNATTRIBUTED: &str = "unattributed";
+
+// Realized profit and fees of the journaled fills. Buy fees are folded into the cost basis of the
+// holdings and sell fees deducted from the profit realized, fees are attributed to the ticker and
+// the strategy of the filled order. Holdings are kept per account, each account having its own
+// cost basis.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ledger {
+    // realized profit net of fees (in quote currency) per ticker
+    pub realized: HashMap<String, f64>,
+    pub fees_by_ticker: HashMap<String, f64>,
+    pub fees_by_strategy: HashMap<String, f64>,
+    // realized profit net of fees (in quote currency) per account and ticker
+    pub realized_by_account: HashMap<String, HashMap<String, f64>>,
+
+    // base volume held and its cost per account and ticker
+    holdings: HashMap<(String, String), (f64, f64)>,
+    // strategy of the orders by identifier
+    strategies: HashMap<String, String>,
+    // account of the orders placed elsewhere than on the main one by identifier
+    accounts: HashMap<String, String>,
+    // latest fill price per ticker
+    prices: HashMap<String, f64>,
+}
+
+impl Ledger {
+    pub fn new() -> Ledger {
+        Ledger::default()
+    }
+
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Ledger {
+        let mut ledger = Ledger::new();
+        for entry in entries {
+            ledger.record(entry);
+        }
+        ledger
+    }
+
+    pub fn record(&mut self, entry: &Entry) {
+        match entry {
+            Entry::Order {
+                id,
+                order,
+                strategy,
+                ..
+            } => {
+                if let Some(strategy) = strategy {
+                    self.strategies.insert(id.clone(), strategy.clone());
+                }
+                if let Some(account) = &order.account {
+                    self.accounts.insert(id.clone(), account.clone());
+                }
+            }
+            Entry::Fill {
+                id,
+                ticker,
+                side,
+                volume,
+                price,
+                fee,
+                ..
+            } => {
+                let strategy = self
+                    .strategies
+                    .get(id)
+                    .map_or(UNATTRIBUTED, |strategy| strategy.as_str());
+                *self
+                    .fees_by_strategy
+                    .entry(strategy.to_string())
+                    .or_default() += fee;
+                *self.fees_by_ticker.entry(ticker.clone()).or_default() += fee;
+                self.prices.insert(ticker.clone(), *price);
+
+                let account = self
+                    .accounts
+                    .get(id)
+                    .map_or(MAIN, |account| account.as_str());
+                let (held, cost) = self
+                    .holdings
+                    .entry((account.to_string(), ticker.clone()))
+                    .or_default();
+                let profit = match side {
+                    Side::Buy => {
+                        *held += volume;
+                        *cost += volume * price + fee;
+                        0.0
+                    }
+                    Side::Sell => {
+                        // only the held part has a cost basis to realize against
+                        let closed = volume.min(*held);
+                        let basis = if *held > 0.0 {
+                            *cost / *held * closed
+                        } else {
+                            0.0
+                        };
+                        *cost -= basis;
+                        *held -= closed;
+                        closed * price - basis - fee
+                    }
+                };
+                *self.realized.entry(ticker.clone()).or_default() += profit;
+                *self
+                    .realized_by_account
+                    .entry(account.to_string())
+                    .or_default()
+                    .entry(ticker.clone())
+                    .or_default() += profit;
+            }
+            _ => (),
+        }
+    }
+
+    pub fn realized_pnl(&self) -> f64 {
+        self.realized.values().sum()
+    }
+
+    pub fn fees(&self) -> f64 {
+        self.fees_by_ticker.values().sum()
+    }
+
+    // Realized profit per quote currency.
+    pub fn realized_by_currency(&self) -> HashMap<String, f64> {
+        let mut realized: HashMap<String, f64> = HashMap::new();
+        for (ticker, profit) in &self.realized {
+            *realized.entry(quote(ticker).to_string()).or_default() += profit;
+        }
+        realized
+    }
+
+    // Realized profit in a single currency at the rates of the latest fills, None when a quote
+    // currency has no fill linking it to the currency.
+    pub fn realized_in(&self, currency: &str) -> Option<f64> {
+        Rates::from_prices(self.prices.clone()).total(&self.realized_by_currency(), currency)
+    }
+
+    // Realized profit of an account in a single currency, as for realized_in.
+    pub fn account_realized_in(&self, account: &str, currency: &str) -> Option<f64> {
+        let mut realized: HashMap<String, f64> = HashMap::new();
+        for (ticker, profit) in self.realized_by_account.get(account)? {
+            *realized.entry(quote(ticker).to_string()).or_default() += profit;
+        }
+        Rates::from_prices(self.prices.clone()).total(&realized, currency)
+    }
+}
+
+// Order in which the lots of a holding are disposed of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostBasis {
+    // oldest lots first
+    #[default]
+    Fifo,
+    // latest lots first
+    Lifo,
+    // holdings pooled at their average cost
+    Average,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Lot {
+    volume: f64,
+    // acquisition cost including fees (in EUR)
+    cost: f64,
+}
+
+// Sale of (part of) a holding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disposal {
+    pub time: i64,
+    pub ticker: String,
+    pub volume: f64,
+    // sale value net of the fee (in EUR)
+    pub proceeds: f64,
+    // cost basis of the volume sold (in EUR), sold volume not held has none
+    pub cost: f64,
+}
+
+impl Disposal {
+    pub fn gain(&self) -> f64 {
+        self.proceeds - self.cost
+    }
+}
+
+// Tax lots of the holdings, values are converted to EUR at the time of the fills.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxLots {
+    method: CostBasis,
+    // lots per ticker, oldest first
+    lots: HashMap<String, VecDeque<Lot>>,
+    pub disposals: Vec<Disposal>,
+}
+
+impl TaxLots {
+    pub fn new(method: CostBasis) -> TaxLots {
+        TaxLots {
+            method,
+            lots: HashMap::new(),
+            disposals: Vec::new(),
+        }
+    }
+
+    // Book a fill, the rate converting its quote currency to EUR. Returns the disposal of a sale.
+    pub fn fill(&mut self, fill: &Fill, rate: f64) -> Option<Disposal> {
+        let lots = self.lots.entry(fill.ticker.clone()).or_default();
+        if fill.side == Side::Buy {
+            let lot = Lot {
+                volume: fill.volume,
+                cost: (fill.volume * fill.price + fill.fee) * rate,
+            };
+            if self.method == CostBasis::Average
+                && let Some(pooled) = lots.back_mut()
+            {
+                pooled.volume += lot.volume;
+                pooled.cost += lot.cost;
+            } else {
+                lots.push_back(lot);
+            }
+            return None;
+        }
+
+        let mut left = fill.volume;
+        let mut cost = 0.0;
+        while left > 0.0 {
+            let lot = match self.method {
+                CostBasis::Fifo => lots.front_mut(),
+                CostBasis::Lifo | CostBasis::Average => lots.back_mut(),
+            };
+            let Some(lot) = lot else {
+                break;
+            };
+            let used = left.min(lot.volume);
+            let share = lot.cost * used / lot.volume;
+            cost += share;
+            lot.cost -= share;
+            lot.volume -= used;
+            left -= used;
+            if lot.volume <= 0.0 {
+                match self.method {
+                    CostBasis::Fifo => lots.pop_front(),
+                    CostBasis::Lifo | CostBasis::Average => lots.pop_back(),
+                };
+            }
+        }
+
+        let disposal = Disposal {
+            time: fill.time,
+            ticker: fill.ticker.clone(),
+            volume: fill.volume,
+            proceeds: (fill.volume * fill.price - fill.fee) * rate,
+            cost,
+        };
+        self.disposals.push(disposal.clone());
+        Some(disposal)
+    }
+}
+
+// Fills of a journal as a CSV in the universal format of common crypto tax tools (Koinly,
+// CoinTracking and the like), the realized gain of sales being described under the cost basis
+// method. The rate converts a currency to EUR at a unix time (in s).
+pub fn tax_csv<'a>(
+    entries: impl IntoIterator<Item = &'a Entry>,
+    method: CostBasis,
+    rate: impl Fn(&str, i64) -> Option<f64>,
+) -> Result<String, String> {
+    let mut lots = TaxLots::new(method);
+    let mut csv = String::from(
+        "Date,Sent Amount,Sent Currency,Received Amount,Received Currency,Fee Amount,\
+         Fee Currency,Net Worth Amount,Net Worth Currency,Label,Description,TxHash\n",
+    );
+    for entry in entries {
+        let Entry::Fill {
+            time,
+            id,
+            ticker,
+            side,
+            volume,
+            price,
+            fee,
+            ..
+        } = entry
+        else {
+            continue;
+        };
+        let Some(rate) = rate(quote(ticker), *time) else {
+            return Err(format!("No EUR rate for {} at {}", quote(ticker), time));
+        };
+        let Some(date) = DateTime::from_timestamp(*time, 0) else {
+            return Err(format!("Invalid fill time {}", time));
+        };
+        let notional = volume * price;
+        let (sent, sent_currency, received, received_currency) = match side {
+            Side::Buy => (notional, quote(ticker), *volume, base(ticker)),
+            Side::Sell => (*volume, base(ticker), notional, quote(ticker)),
+        };
+        let fill = Fill {
+            time: *time,
+            ticker: ticker.clone(),
+            side: *side,
+            volume: *volume,
+            price: *price,
+            fee: *fee,
+            maker: false,
+        };
+        let description = match lots.fill(&fill, rate) {
+            Some(disposal) => format!(
+                "{:?} cost basis {:.2} EUR gain {:.2} EUR",
+                method,
+                disposal.cost,
+                disposal.gain()
+            ),
+            None => String::new(),
+        };
+        csv += &format!(
+            "{},{},{},{},{},{},{},{:.2},EUR,,{},{}\n",
+            date.format("%Y-%m-%d %H:%M:%S UTC"),
+            sent,
+            sent_currency,
+            received,
+            received_currency,
+            fee,
+            quote(ticker),
+            notional * rate,
+            description,
+            id
+        );
+    }
+    Ok(csv)
+}
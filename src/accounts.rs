@@ -0,0 +1,204 @@
+use crate::execution::{Execution, Executor, Order};
+use crate::margin::Margin;
+use crate::secrets::SecretsConfig;
+
+use futures::future::join_all;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock, RwLock};
+
+// Account of the credentials in the top level secrets section, taking the orders not routed
+// elsewhere.
+pub const MAIN: &str = "main";
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccountConfig {
+    pub name: String,
+    // strategies whose orders are placed on this account, by the name they are journaled under,
+    // e.g. sma#0
+    pub strategies: Vec<String>,
+    // credentials of the account, its environment variables are suffixed with its name in upper
+    // case, e.g. KRAKEN_API_KEY_TEST, its file is trade-bot.<name>.keys.age by default
+    pub secrets: SecretsConfig,
+    // file the nonces of the account are kept in, each API key has its own, trade-bot.<name>.nonce
+    // by default
+    pub nonce: Option<PathBuf>,
+}
+
+impl AccountConfig {
+    pub fn nonce(&self) -> PathBuf {
+        self.nonce
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("trade-bot.{}.nonce", self.name)))
+    }
+
+    // Credentials settings of the account, a file left at the default being its own so that the
+    // main credentials are not picked up instead.
+    pub fn secrets(&self) -> SecretsConfig {
+        let mut secrets = self.secrets.clone();
+        if secrets.file == SecretsConfig::default().file {
+            secrets.file = PathBuf::from(format!("trade-bot.{}.keys.age", self.name));
+        }
+        secrets
+    }
+}
+
+// Check that account names are unique and that no strategy is routed to two accounts.
+pub fn validate(configs: &[AccountConfig]) -> Result<(), String> {
+    let mut routes: HashMap<&str, &str> = HashMap::new();
+    for (index, config) in configs.iter().enumerate() {
+        if config.name.is_empty() || config.name == MAIN {
+            return Err(format!(
+                "Account {} needs a name other than {}",
+                index, MAIN
+            ));
+        }
+        if configs[..index]
+            .iter()
+            .any(|other| other.name == config.name)
+        {
+            return Err(format!("Account {} configured twice", config.name));
+        }
+        for strategy in &config.strategies {
+            if let Some(other) = routes.insert(strategy, &config.name) {
+                return Err(format!(
+                    "Strategy {} routed to both {} and {}",
+                    strategy, other, config.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn routes() -> &'static RwLock<HashMap<String, String>> {
+    static ROUTES: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+    ROUTES.get_or_init(RwLock::default)
+}
+
+// Set the process wide routing of strategies to accounts.
+pub fn install(configs: &[AccountConfig]) {
+    if let Ok(mut routes) = routes().write() {
+        *routes = configs
+            .iter()
+            .flat_map(|config| {
+                config
+                    .strategies
+                    .iter()
+                    .map(|strategy| (strategy.clone(), config.name.clone()))
+            })
+            .collect();
+    }
+}
+
+// Account the orders of a strategy are placed on, None for the main account.
+pub fn route(strategy: &str) -> Option<String> {
+    routes().read().ok()?.get(strategy).cloned()
+}
+
+// Executor placing each order on the account it is routed to, and reporting the balances,
+// margin, fills and open orders of all accounts together.
+pub struct Accounts<E> {
+    main: E,
+    others: HashMap<String, E>,
+    // account of the placed orders by identifier, for cancellations
+    owners: Mutex<HashMap<String, String>>,
+}
+
+impl<E: Executor + Sync> Accounts<E> {
+    pub fn new(main: E, others: HashMap<String, E>) -> Accounts<E> {
+        Accounts {
+            main,
+            others,
+            owners: Mutex::default(),
+        }
+    }
+
+    fn account(&self, name: Option<&str>) -> Result<&E, String> {
+        match name {
+            None | Some(MAIN) => Ok(&self.main),
+            Some(name) => self
+                .others
+                .get(name)
+                .ok_or_else(|| format!("Unknown account {}", name)),
+        }
+    }
+
+    fn all(&self) -> impl Iterator<Item = &E> {
+        std::iter::once(&self.main).chain(self.others.values())
+    }
+}
+
+impl<E: Executor + Sync> Executor for Accounts<E> {
+    async fn submit(&self, order: &Order) -> Result<String, String> {
+        let id = self
+            .account(order.account.as_deref())?
+            .submit(order)
+            .await?;
+        if let Some(account) = &order.account
+            && let Ok(mut owners) = self.owners.lock()
+        {
+            owners.insert(id.clone(), account.clone());
+        }
+        Ok(id)
+    }
+
+    async fn cancel(&self, id: &str) -> Result<(), String> {
+        let owner = self
+            .owners
+            .lock()
+            .ok()
+            .and_then(|owners| owners.get(id).cloned());
+        self.account(owner.as_deref())?.cancel(id).await?;
+        if let Ok(mut owners) = self.owners.lock() {
+            owners.remove(id);
+        }
+        Ok(())
+    }
+
+    fn simulated(&self) -> bool {
+        self.main.simulated()
+    }
+
+    async fn balances(&self) -> Result<HashMap<String, f64>, String> {
+        let mut total: HashMap<String, f64> = HashMap::new();
+        for balances in join_all(self.all().map(|account| account.balances())).await {
+            for (asset, volume) in balances? {
+                *total.entry(asset).or_default() += volume;
+            }
+        }
+        Ok(total)
+    }
+
+    async fn margin(&self) -> Result<Margin, String> {
+        let mut total = Margin::default();
+        for margin in join_all(self.all().map(|account| account.margin())).await {
+            let margin = margin?;
+            total.equity += margin.equity;
+            total.used += margin.used;
+            total.free += margin.free;
+        }
+        total.level = (total.used > 0.0).then(|| 100.0 * total.equity / total.used);
+        Ok(total)
+    }
+
+    async fn fills(&self, since: i64) -> Result<Vec<Execution>, String> {
+        let mut all = Vec::new();
+        for fills in join_all(self.all().map(|account| account.fills(since))).await {
+            all.extend(fills?);
+        }
+        Ok(all)
+    }
+
+    async fn resting(&self) -> Result<HashSet<String>, String> {
+        let mut all = HashSet::new();
+        for resting in join_all(self.all().map(|account| account.resting())).await {
+            all.extend(resting?);
+        }
+        Ok(all)
+    }
+}
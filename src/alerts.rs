@@ -0,0 +1,493 @@
+use crate::journal::now;
+
+use futures::future::BoxFuture;
+
+use hmac::{Hmac, Mac};
+
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use reqwest::Client;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use sha2::Sha256;
+
+use tokio::time::sleep;
+
+use tracing::{Instrument, info_span, warn};
+
+use std::env;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+// Kind of event alerted on, channels subscribe to kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Error,
+    // orders decided by a strategy, before they are checked and submitted
+    Signal,
+    Order,
+    Fill,
+    // halts, limits hit and balance drifts
+    Risk,
+    Summary,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub kind: EventKind,
+    pub title: String,
+    pub message: String,
+    // event alerted on, for the systems consuming alerts, null when there is none
+    pub payload: Value,
+}
+
+impl Alert {
+    pub fn new(kind: EventKind, title: &str, message: &str) -> Alert {
+        Alert {
+            kind,
+            title: title.to_string(),
+            message: message.to_string(),
+            payload: Value::Null,
+        }
+    }
+
+    pub fn with_payload(self, payload: Value) -> Alert {
+        Alert { payload, ..self }
+    }
+}
+
+// Destination alerts are delivered to.
+pub trait Notifier {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<(), String>>;
+}
+
+async fn post(client: &Client, url: &str, body: &Value) -> Result<(), String> {
+    match client.post(url).json(body).send().await {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(format!("{} answered {}", url, response.status())),
+        Err(error) => Err(format!("Could not post to {}: {:?}", url, error)),
+    }
+}
+
+pub struct Discord {
+    client: Client,
+    webhook: String,
+}
+
+impl Discord {
+    pub fn new(webhook: &str) -> Discord {
+        Discord {
+            client: Client::new(),
+            webhook: webhook.to_string(),
+        }
+    }
+}
+
+impl Notifier for Discord {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<(), String>> {
+        let body = json!({"content": format!("**{}**\n{}", alert.title, alert.message)});
+        Box::pin(async move { post(&self.client, &self.webhook, &body).await })
+    }
+}
+
+pub struct Slack {
+    client: Client,
+    webhook: String,
+}
+
+impl Slack {
+    pub fn new(webhook: &str) -> Slack {
+        Slack {
+            client: Client::new(),
+            webhook: webhook.to_string(),
+        }
+    }
+}
+
+impl Notifier for Slack {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<(), String>> {
+        let body = json!({"text": format!("*{}*\n{}", alert.title, alert.message)});
+        Box::pin(async move { post(&self.client, &self.webhook, &body).await })
+    }
+}
+
+pub struct Telegram {
+    client: Client,
+    token: String,
+    chat_id: String,
+}
+
+impl Telegram {
+    pub fn new(token: &str, chat_id: &str) -> Telegram {
+        Telegram {
+            client: Client::new(),
+            token: token.to_string(),
+            chat_id: chat_id.to_string(),
+        }
+    }
+}
+
+impl Notifier for Telegram {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<(), String>> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+        let body = json!({
+            "chat_id": self.chat_id,
+            "text": format!("{}\n{}", alert.title, alert.message),
+        });
+        Box::pin(async move { post(&self.client, &url, &body).await })
+    }
+}
+
+// Posts every alert as JSON to an external system along with the event alerted on, retrying
+// failed deliveries with a doubling delay. With a secret the body is signed with HMAC-SHA256, the
+// hexadecimal signature being sent in the X-Signature header.
+pub struct Webhook {
+    client: Client,
+    url: String,
+    secret: Option<String>,
+    // deliveries attempted after the first one failed
+    retries: u32,
+}
+
+impl Webhook {
+    pub fn new(url: &str, secret: Option<String>, retries: u32) -> Webhook {
+        Webhook {
+            client: Client::new(),
+            url: url.to_string(),
+            secret,
+            retries,
+        }
+    }
+
+    fn sign(&self, body: &str) -> Result<Option<String>, String> {
+        let Some(secret) = &self.secret else {
+            return Ok(None);
+        };
+        let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+            Ok(mac) => mac,
+            Err(error) => return Err(format!("Invalid webhook secret: {}", error)),
+        };
+        mac.update(body.as_bytes());
+        let signature = mac.finalize().into_bytes();
+        Ok(Some(
+            signature
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+        ))
+    }
+
+    async fn deliver(&self, body: &str, signature: &Option<String>) -> Result<(), String> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+        if let Some(signature) = signature {
+            request = request.header("X-Signature", format!("sha256={}", signature));
+        }
+        match request.send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(format!("{} answered {}", self.url, response.status())),
+            Err(error) => Err(format!("Could not post to {}: {:?}", self.url, error)),
+        }
+    }
+}
+
+impl Notifier for Webhook {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let body = json!({
+                "time": now(),
+                "kind": alert.kind,
+                "title": alert.title,
+                "message": alert.message,
+                "payload": alert.payload,
+            })
+            .to_string();
+            let signature = self.sign(&body)?;
+            let mut delay = Duration::from_secs(1);
+            let mut attempt = 0;
+            loop {
+                match self.deliver(&body, &signature).await {
+                    Ok(()) => return Ok(()),
+                    Err(message) if attempt >= self.retries => return Err(message),
+                    Err(message) => warn!("Retrying webhook delivery: {}", message),
+                }
+                sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+        })
+    }
+}
+
+// Fill the {kind}, {title} and {message} placeholders of a template with an alert.
+pub fn render(template: &str, alert: &Alert) -> String {
+    template
+        .replace("{kind}", &format!("{:?}", alert.kind))
+        .replace("{title}", &alert.title)
+        .replace("{message}", &alert.message)
+}
+
+// Encryption of the connection to the mail server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tls {
+    // TLS from the start of the connection, usually on port 465
+    #[default]
+    Implicit,
+    // upgrade of a plain connection, usually on port 587
+    StartTls,
+    // plain text, only for local relays
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EmailConfig {
+    pub host: String,
+    // port of the mail server, the usual one of the encryption when not given
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: Tls,
+    pub username: String,
+    // environment variable holding the password
+    pub password_env: String,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default = "subject_template")]
+    pub subject: String,
+    #[serde(default = "body_template")]
+    pub body: String,
+}
+
+fn subject_template() -> String {
+    "[trade-bot] {title}".into()
+}
+
+fn body_template() -> String {
+    "{message}".into()
+}
+
+// Sends alerts by mail, meant for rare and important events such as summaries and halts.
+pub struct Email {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    subject: String,
+    body: String,
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, String> {
+    match address.parse() {
+        Ok(mailbox) => Ok(mailbox),
+        Err(error) => Err(format!("Invalid address {:?}: {}", address, error)),
+    }
+}
+
+impl Email {
+    pub fn new(config: &EmailConfig) -> Result<Email, String> {
+        let Ok(password) = env::var(&config.password_env) else {
+            return Err(format!(
+                "Set {} to send alerts by mail",
+                config.password_env
+            ));
+        };
+        let builder = match config.tls {
+            Tls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            Tls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host),
+            Tls::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &config.host,
+            )),
+        };
+        let mut builder = match builder {
+            Ok(builder) => builder,
+            Err(error) => return Err(format!("Invalid mail server {}: {}", config.host, error)),
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        Ok(Email {
+            transport: builder
+                .credentials(Credentials::new(config.username.clone(), password))
+                .build(),
+            from: mailbox(&config.from)?,
+            to: config
+                .to
+                .iter()
+                .map(|address| mailbox(address))
+                .collect::<Result<_, _>>()?,
+            subject: config.subject.clone(),
+            body: config.body.clone(),
+        })
+    }
+}
+
+impl Notifier for Email {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let mut builder = Message::builder()
+                .from(self.from.clone())
+                .subject(render(&self.subject, alert));
+            for to in &self.to {
+                builder = builder.to(to.clone());
+            }
+            let message = match builder.body(render(&self.body, alert)) {
+                Ok(message) => message,
+                Err(error) => return Err(format!("Could not build mail: {}", error)),
+            };
+            match self.transport.send(message).await {
+                Ok(_) => Ok(()),
+                Err(error) => Err(format!("Could not send mail: {}", error)),
+            }
+        })
+    }
+}
+
+// Backend of a channel, selected by its `kind`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BackendConfig {
+    Discord {
+        webhook: String,
+    },
+    Slack {
+        webhook: String,
+    },
+    Telegram {
+        token: String,
+        chat_id: String,
+    },
+    Email(EmailConfig),
+    Webhook {
+        url: String,
+        // environment variable holding the signing secret, bodies are not signed without it
+        #[serde(default)]
+        secret_env: Option<String>,
+        #[serde(default = "webhook_retries")]
+        retries: u32,
+    },
+}
+
+fn webhook_retries() -> u32 {
+    3
+}
+
+impl BackendConfig {
+    pub fn build(&self) -> Result<Box<dyn Notifier + Send + Sync>, String> {
+        Ok(match self {
+            BackendConfig::Discord { webhook } => Box::new(Discord::new(webhook)),
+            BackendConfig::Slack { webhook } => Box::new(Slack::new(webhook)),
+            BackendConfig::Telegram { token, chat_id } => Box::new(Telegram::new(token, chat_id)),
+            BackendConfig::Email(config) => Box::new(Email::new(config)?),
+            BackendConfig::Webhook {
+                url,
+                secret_env,
+                retries,
+            } => {
+                let secret = match secret_env {
+                    Some(name) => match env::var(name) {
+                        Ok(secret) => Some(secret),
+                        Err(_) => return Err(format!("Set {} to sign webhooks", name)),
+                    },
+                    None => None,
+                };
+                Box::new(Webhook::new(url, secret, *retries))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ChannelConfig {
+    // kinds of events delivered, all of them but signals when empty
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    #[serde(flatten)]
+    pub backend: BackendConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    pub channels: Vec<ChannelConfig>,
+}
+
+// Whether a channel subscribed to kinds of events delivers those of a kind. Signals are as
+// frequent as candles, they are only delivered to the channels asking for them.
+fn subscribed(events: &[EventKind], kind: EventKind) -> bool {
+    match events.is_empty() {
+        true => kind != EventKind::Signal,
+        false => events.contains(&kind),
+    }
+}
+
+// Routes alerts to the channels subscribed to their kind.
+#[derive(Default)]
+pub struct Alerts {
+    channels: Vec<(Vec<EventKind>, Box<dyn Notifier + Send + Sync>)>,
+}
+
+impl Alerts {
+    pub fn new(config: &AlertConfig) -> Result<Alerts, String> {
+        Ok(Alerts {
+            channels: config
+                .channels
+                .iter()
+                .map(|channel| Ok((channel.events.clone(), channel.backend.build()?)))
+                .collect::<Result<_, String>>()?,
+        })
+    }
+
+    pub fn add(&mut self, events: Vec<EventKind>, notifier: Box<dyn Notifier + Send + Sync>) {
+        self.channels.push((events, notifier));
+    }
+
+    // Deliver an alert to every subscribed channel, failures are logged.
+    pub async fn send(&self, alert: &Alert) {
+        for (events, notifier) in &self.channels {
+            if !subscribed(events, alert.kind) {
+                continue;
+            }
+            if let Err(message) = notifier.notify(alert).await {
+                warn!("Could not deliver alert {:?}: {}", alert.title, message);
+            }
+        }
+    }
+}
+
+fn installed() -> &'static RwLock<Arc<Alerts>> {
+    static ALERTS: OnceLock<RwLock<Arc<Alerts>>> = OnceLock::new();
+    ALERTS.get_or_init(RwLock::default)
+}
+
+// Make the alerts process wide, replacing those installed before. Alerts already being sent
+// still go through the channels they were sent with.
+pub fn install(alerts: Alerts) {
+    if let Ok(mut installed) = installed().write() {
+        *installed = Arc::new(alerts);
+    }
+}
+
+// Send an alert through the installed alerts in the background, nothing is sent before the
+// installation or outside of a runtime.
+pub fn notify(kind: EventKind, title: &str, message: &str) {
+    notify_with(kind, title, message, Value::Null);
+}
+
+// Send an alert along with the event it is about, see notify.
+pub fn notify_with(kind: EventKind, title: &str, message: &str, payload: Value) {
+    let (Ok(alerts), Ok(runtime)) = (installed().read(), tokio::runtime::Handle::try_current())
+    else {
+        return;
+    };
+    let alerts = alerts.clone();
+    let alert = Alert::new(kind, title, message).with_payload(payload);
+    runtime.spawn(async move { alerts.send(&alert).await }.instrument(info_span!("alerts")));
+}
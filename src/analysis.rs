@@ -0,0 +1,441 @@
+use crate::market::{Candle, CandleUpdates, Field};
+use crate::statistics::{deviation, mean, quantile};
+
+use itertools::Either;
+
+use tracing::warn;
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+// Size of a candle on disk: its eight fields as little endian 64 bit values.
+const RECORD: usize = 64;
+
+// Field wise operations used to aggregate candles.
+fn combine(first: &Candle, second: &Candle, operation: impl Fn(f64, f64) -> f64) -> Candle {
+    Candle {
+        time: operation(first.time as f64, second.time as f64).round() as i64,
+        open: operation(first.open, second.open),
+        high: operation(first.high, second.high),
+        low: operation(first.low, second.low),
+        close: operation(first.close, second.close),
+        vwap: operation(first.vwap, second.vwap),
+        volume: operation(first.volume, second.volume),
+        count: operation(first.count as f64, second.count as f64).round() as i64,
+    }
+}
+
+fn scale(candle: &Candle, factor: f64) -> Candle {
+    combine(candle, candle, |value, _| value * factor)
+}
+
+fn encode(candle: &Candle) -> [u8; RECORD] {
+    let mut record = [0; RECORD];
+    let fields = [
+        candle.time.to_le_bytes(),
+        candle.open.to_le_bytes(),
+        candle.high.to_le_bytes(),
+        candle.low.to_le_bytes(),
+        candle.close.to_le_bytes(),
+        candle.vwap.to_le_bytes(),
+        candle.volume.to_le_bytes(),
+        candle.count.to_le_bytes(),
+    ];
+    for (chunk, field) in record.chunks_exact_mut(8).zip(fields) {
+        chunk.copy_from_slice(&field);
+    }
+    record
+}
+
+fn decode(record: &[u8]) -> Candle {
+    let field = |index: usize| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&record[index * 8..(index + 1) * 8]);
+        bytes
+    };
+    Candle {
+        time: i64::from_le_bytes(field(0)),
+        open: f64::from_le_bytes(field(1)),
+        high: f64::from_le_bytes(field(2)),
+        low: f64::from_le_bytes(field(3)),
+        close: f64::from_le_bytes(field(4)),
+        vwap: f64::from_le_bytes(field(5)),
+        volume: f64::from_le_bytes(field(6)),
+        count: i64::from_le_bytes(field(7)),
+    }
+}
+
+// Older candles of a universe stored as fixed size records in a file used as a ring buffer,
+// read back page by page when needed.
+#[derive(Debug)]
+struct Spill {
+    path: PathBuf,
+    file: File,
+    // maximal number of candles on disk
+    capacity: usize,
+    // slot of the oldest candle
+    start: usize,
+    len: usize,
+}
+
+impl Spill {
+    fn create(path: &Path, capacity: usize) -> Result<Spill, String> {
+        let file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+        {
+            Ok(file) => file,
+            Err(error) => return Err(format!("Could not create {:?}: {:?}", path, error)),
+        };
+        Ok(Spill {
+            path: path.to_path_buf(),
+            file,
+            capacity: capacity.max(1),
+            start: 0,
+            len: 0,
+        })
+    }
+
+    // Append a candle, overwriting the oldest one when full.
+    fn push(&mut self, candle: &Candle) -> Result<(), String> {
+        let slot = (self.start + self.len) % self.capacity;
+        if let Err(error) = self
+            .file
+            .write_all_at(&encode(candle), (slot * RECORD) as u64)
+        {
+            return Err(format!("Could not spill to {:?}: {:?}", self.path, error));
+        }
+        if self.len == self.capacity {
+            self.start = (self.start + 1) % self.capacity;
+        } else {
+            self.len += 1;
+        }
+        Ok(())
+    }
+
+    // Candles from the given position (oldest is 0) to the newest.
+    fn read(&self, from: usize) -> Result<Vec<Candle>, String> {
+        let mut candles = Vec::with_capacity(self.len.saturating_sub(from));
+        let mut position = from;
+        while position < self.len {
+            let slot = (self.start + position) % self.capacity;
+            // contiguous slots up to the end of the file
+            let count = (self.len - position).min(self.capacity - slot);
+            let mut page = vec![0; count * RECORD];
+            if let Err(error) = self.file.read_exact_at(&mut page, (slot * RECORD) as u64) {
+                return Err(format!("Could not read {:?}: {:?}", self.path, error));
+            }
+            candles.extend(page.chunks_exact(RECORD).map(decode));
+            position += count;
+        }
+        Ok(candles)
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Outcome of feeding a candle to the statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Insertion {
+    // newer than every candle held
+    Appended,
+    // replaced the candle held for the same time
+    Revised,
+    // older than the latest candle but filling a hole within the universe
+    Inserted,
+    // older than the oldest candle held, discarded
+    Rejected,
+    // update of a forming candle while only closed candles are ingested
+    Ignored,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Counters {
+    // candles received again for a time already held
+    pub duplicates: u64,
+    // candles received after a more recent one but still within the universe
+    pub out_of_order: u64,
+    // candles too old for the universe
+    pub rejected: u64,
+    // candles missing between consecutive appended candles, requires the interval
+    pub gaps: u64,
+}
+
+// Rolling universe of the latest candles of a ticker from which statistics over windows of
+// various lengths are computed. Candles arrive mostly in time order from a single writer, so the
+// universe is a fixed capacity ring buffer: the oldest candle is evicted on insertion once full.
+// Kraken resends and revises candles, a candle for a time already held replaces it in place and
+// late candles are slotted in time order or rejected when older than the universe.
+// Large universes can be spilled to disk: only the latest candles are kept in memory and the
+// older ones are paged from a file when read. The disk tier is append only, late candles older
+// than the memory tier are rejected.
+#[derive(Debug)]
+pub struct MovingStatistics {
+    // maximal number of candles kept
+    capacity: usize,
+    // candles in memory, oldest first, preceded by the spilled ones
+    universe: VecDeque<Candle>,
+    // maximal number of candles kept in memory
+    memory: usize,
+    spill: Option<Spill>,
+
+    // expected time between candles (in s), enables gap accounting
+    interval: Option<i64>,
+    // whether updates of forming candles are ingested besides the closed candles
+    updates: CandleUpdates,
+    counters: Counters,
+}
+
+impl MovingStatistics {
+    pub fn new(capacity: usize) -> MovingStatistics {
+        let capacity = capacity.max(1);
+        MovingStatistics {
+            capacity,
+            universe: VecDeque::with_capacity(capacity),
+            memory: capacity,
+            spill: None,
+            interval: None,
+            updates: CandleUpdates::Closed,
+            counters: Counters::default(),
+        }
+    }
+
+    pub fn with_interval(mut self, interval: i64) -> MovingStatistics {
+        self.interval = Some(interval).filter(|interval| *interval > 0);
+        self
+    }
+
+    pub fn with_updates(mut self, updates: CandleUpdates) -> MovingStatistics {
+        self.updates = updates;
+        self
+    }
+
+    // Keep only the latest `memory` candles in memory, older ones up to the capacity are
+    // written to the file at the given path, removed once the statistics are dropped.
+    pub fn with_spill(mut self, memory: usize, path: &Path) -> Result<MovingStatistics, String> {
+        let memory = memory.max(1);
+        if memory < self.capacity {
+            self.spill = Some(Spill::create(path, self.capacity - memory)?);
+            self.memory = memory;
+            self.universe = VecDeque::with_capacity(memory);
+        }
+        Ok(self)
+    }
+
+    fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.len)
+    }
+
+    // Make room in memory for a candle, moving the oldest one to disk if spilling.
+    fn evict(&mut self) {
+        if self.universe.len() < self.memory {
+            return;
+        }
+        let Some(oldest) = self.universe.pop_front() else {
+            return;
+        };
+        if let Some(spill) = &mut self.spill
+            && let Err(message) = spill.push(&oldest)
+        {
+            warn!("{}", message);
+        }
+    }
+
+    pub fn update(&mut self, candle: Candle) -> Insertion {
+        let Some(latest) = self.universe.back() else {
+            self.universe.push_back(candle);
+            return Insertion::Appended;
+        };
+
+        if candle.time > latest.time {
+            if let Some(interval) = self.interval {
+                self.counters.gaps += ((candle.time - latest.time) / interval - 1).max(0) as u64;
+            }
+            self.evict();
+            self.universe.push_back(candle);
+            return Insertion::Appended;
+        }
+
+        match self
+            .universe
+            .binary_search_by_key(&candle.time, |held| held.time)
+        {
+            Ok(index) => {
+                self.counters.duplicates += 1;
+                self.universe[index] = candle;
+                Insertion::Revised
+            }
+            Err(0) if self.spilled() > 0 || self.universe.len() == self.memory => {
+                self.counters.rejected += 1;
+                Insertion::Rejected
+            }
+            Err(index) => {
+                self.counters.out_of_order += 1;
+                // the hole it fills was accounted as a gap when the later candle arrived
+                if self.interval.is_some() && index > 0 {
+                    self.counters.gaps = self.counters.gaps.saturating_sub(1);
+                }
+                if self.universe.len() == self.memory {
+                    self.evict();
+                    self.universe.insert(index - 1, candle);
+                } else {
+                    self.universe.insert(index, candle);
+                }
+                Insertion::Inserted
+            }
+        }
+    }
+
+    // Feed an update of a forming candle, only ingested when intrabar updates were opted into.
+    // Closed candles are fed with update.
+    pub fn update_forming(&mut self, candle: Candle) -> Insertion {
+        match self.updates {
+            CandleUpdates::Closed => Insertion::Ignored,
+            CandleUpdates::Intrabar => self.update(candle),
+        }
+    }
+
+    pub fn counters(&self) -> Counters {
+        self.counters
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.spilled() + self.universe.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn latest(&self) -> Option<&Candle> {
+        self.universe.back()
+    }
+
+    // Spilled candles from the given position (oldest is 0), a failed read leaves them out.
+    fn paged(&self, from: usize) -> Vec<Candle> {
+        let Some(spill) = &self.spill else {
+            return Vec::new();
+        };
+        spill.read(from).unwrap_or_else(|message| {
+            warn!("{}", message);
+            Vec::new()
+        })
+    }
+
+    // All candles, oldest first.
+    pub fn universe(&self) -> impl DoubleEndedIterator<Item = Candle> + ExactSizeIterator {
+        let mut candles = self.paged(0);
+        candles.extend(self.universe.iter().copied());
+        candles.into_iter()
+    }
+
+    // Latest `length` candles, oldest first, None when fewer are available. Windows within the
+    // memory tier are not read from disk.
+    pub fn window(&self, length: usize) -> Option<impl Iterator<Item = Candle>> {
+        if length == 0 || length > self.len() {
+            return None;
+        }
+        if length <= self.universe.len() {
+            return Some(Either::Left(
+                self.universe
+                    .iter()
+                    .skip(self.universe.len() - length)
+                    .copied(),
+            ));
+        }
+        let mut candles = self.paged(self.len() - length);
+        candles.extend(self.universe.iter().copied());
+        Some(Either::Right(candles.into_iter()))
+    }
+
+    // Field wise mean over each of the windows, None for windows longer than the universe. Time
+    // and count are averaged too, see `means_of` for a single field.
+    pub fn means(&self, windows: &[usize]) -> Vec<Option<Candle>> {
+        windows
+            .iter()
+            .map(|length| {
+                let sum = self
+                    .window(*length)?
+                    .fold(Candle::default(), |sum, candle| {
+                        combine(&sum, &candle, |x, y| x + y)
+                    });
+                Some(scale(&sum, 1.0 / *length as f64))
+            })
+            .collect()
+    }
+
+    // Field wise population standard deviation over each of the windows.
+    pub fn deviations(&self, windows: &[usize]) -> Vec<Option<Candle>> {
+        windows
+            .iter()
+            .zip(self.means(windows))
+            .map(|(length, mean)| {
+                let mean = mean?;
+                let sum = self
+                    .window(*length)?
+                    .fold(Candle::default(), |sum, candle| {
+                        combine(
+                            &sum,
+                            &combine(&candle, &mean, |x, y| (x - y).powi(2)),
+                            |x, y| x + y,
+                        )
+                    });
+                Some(combine(&sum, &sum, |value, _| {
+                    (value / *length as f64).sqrt()
+                }))
+            })
+            .collect()
+    }
+
+    // Values of a field over the latest `length` candles, oldest first.
+    fn values(&self, field: Field, length: usize) -> Option<Vec<f64>> {
+        Some(
+            self.window(length)?
+                .map(|candle| field.of(&candle))
+                .collect(),
+        )
+    }
+
+    // Mean of a single field over each of the windows, None for windows longer than the
+    // universe.
+    pub fn means_of(&self, field: Field, windows: &[usize]) -> Vec<Option<f64>> {
+        windows
+            .iter()
+            .map(|length| mean(&self.values(field, *length)?))
+            .collect()
+    }
+
+    // Population standard deviation of a single field over each of the windows.
+    pub fn deviations_of(&self, field: Field, windows: &[usize]) -> Vec<Option<f64>> {
+        windows
+            .iter()
+            .map(|length| deviation(&self.values(field, *length)?))
+            .collect()
+    }
+
+    // Quantile of a single field over each of the windows, q within [0, 1]. Unlike the means,
+    // quantiles are not pulled by outliers.
+    pub fn quantiles_of(&self, field: Field, windows: &[usize], q: f64) -> Vec<Option<f64>> {
+        windows
+            .iter()
+            .map(|length| quantile(&self.values(field, *length)?, q))
+            .collect()
+    }
+
+    pub fn medians_of(&self, field: Field, windows: &[usize]) -> Vec<Option<f64>> {
+        self.quantiles_of(field, windows, 0.5)
+    }
+}
@@ -0,0 +1,225 @@
+use crate::market::Candle;
+use crate::metrics;
+use crate::statistics::deviation;
+
+use serde::{Deserialize, Serialize};
+
+use tracing::warn;
+
+use std::collections::{HashMap, VecDeque};
+
+// Returns needed before price jumps are judged against their deviation.
+const MIN_RETURNS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anomaly {
+    // high below low or a price outside of the range
+    InvertedRange,
+    // missing, zero or negative price
+    InvalidPrice,
+    // zero or negative volume
+    InvalidVolume,
+    // close to close logarithmic return of that many deviations
+    Jump(f64),
+}
+
+impl Anomaly {
+    fn name(&self) -> &'static str {
+        match self {
+            Anomaly::InvertedRange => "inverted_range",
+            Anomaly::InvalidPrice => "invalid_price",
+            Anomaly::InvalidVolume => "invalid_volume",
+            Anomaly::Jump(_) => "jump",
+        }
+    }
+}
+
+// What to do with anomalous candles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quarantine {
+    // discard them
+    #[default]
+    Drop,
+    // hold a price jump back until the next candle either confirms the new price level, releasing
+    // both, or returns to the previous one, discarding the jump as a bad tick
+    Confirm,
+    // pass them on, only alerting
+    Flag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnomalyConfig {
+    // number of deviations of the close to close returns beyond which a move is a jump
+    pub sigma: f64,
+    // number of returns the deviation is computed over
+    pub window: usize,
+    pub quarantine: Quarantine,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> AnomalyConfig {
+        AnomalyConfig {
+            sigma: 8.0,
+            window: 100,
+            quarantine: Quarantine::Drop,
+        }
+    }
+}
+
+// Accepted history of a ticker.
+#[derive(Default)]
+struct History {
+    latest: Option<Candle>,
+    // close of the candle preceding the latest one
+    reference: Option<f64>,
+    returns: VecDeque<f64>,
+    // jump awaiting confirmation
+    held: Option<Candle>,
+}
+
+impl History {
+    // Close the candle's return is computed from, candles revising the latest one share its
+    // reference.
+    fn reference(&self, candle: &Candle) -> Option<f64> {
+        match self.latest {
+            Some(latest) if latest.time == candle.time => self.reference,
+            Some(latest) => Some(latest.close),
+            None => None,
+        }
+    }
+
+    fn accept(&mut self, candle: Candle, window: usize) {
+        if let Some(latest) = self.latest {
+            if latest.time != candle.time {
+                self.reference = Some(latest.close);
+            } else if self.reference.is_some() {
+                // the revised candle's return is replaced
+                self.returns.pop_back();
+            }
+        }
+        if let Some(reference) = self.reference {
+            if self.returns.len() == window {
+                self.returns.pop_front();
+            }
+            self.returns.push_back((candle.close / reference).ln());
+        }
+        self.latest = Some(candle);
+    }
+
+    // Size of the jump from the reference to the close in deviations, when it exceeds sigma.
+    fn jump(&self, reference: Option<f64>, close: f64, sigma: f64) -> Option<f64> {
+        if self.returns.len() < MIN_RETURNS {
+            return None;
+        }
+        let returns: Vec<f64> = self.returns.iter().copied().collect();
+        let spread = deviation(&returns)?;
+        let size = (close / reference?).ln().abs() / spread;
+        (size > sigma).then_some(size)
+    }
+}
+
+// Sanity checks of incoming candles, run before they reach any statistic: malformed candles and
+// price jumps far beyond the recent volatility are alerted on and quarantined following the policy.
+pub struct AnomalyDetector {
+    config: AnomalyConfig,
+    tickers: HashMap<String, History>,
+}
+
+impl AnomalyDetector {
+    pub fn new(config: AnomalyConfig) -> AnomalyDetector {
+        AnomalyDetector {
+            config: AnomalyConfig {
+                window: config.window.max(MIN_RETURNS),
+                ..config
+            },
+            tickers: HashMap::new(),
+        }
+    }
+
+    // Drop the history of a ticker no longer followed.
+    pub fn forget(&mut self, ticker: &str) {
+        self.tickers.remove(ticker);
+    }
+
+    // Structural problems of a candle, independent of its history.
+    pub fn malformed(candle: &Candle) -> Option<Anomaly> {
+        let prices = [candle.open, candle.high, candle.low, candle.close];
+        if prices
+            .iter()
+            .any(|price| !price.is_finite() || *price <= 0.0)
+        {
+            return Some(Anomaly::InvalidPrice);
+        }
+        if candle.high < candle.low
+            || prices
+                .iter()
+                .any(|price| *price > candle.high || *price < candle.low)
+        {
+            return Some(Anomaly::InvertedRange);
+        }
+        if candle.volume.is_nan() || candle.volume <= 0.0 {
+            return Some(Anomaly::InvalidVolume);
+        }
+        None
+    }
+
+    // Candles to pass downstream for a received candle, oldest first.
+    pub fn check(&mut self, ticker: &str, candle: Candle) -> Vec<Candle> {
+        let AnomalyConfig {
+            sigma,
+            window,
+            quarantine,
+        } = self.config;
+        let history = self.tickers.entry(ticker.to_string()).or_default();
+
+        if let Some(anomaly) = AnomalyDetector::malformed(&candle) {
+            alert(ticker, &candle, anomaly);
+            if quarantine != Quarantine::Flag {
+                return Vec::new();
+            }
+            history.accept(candle, window);
+            return vec![candle];
+        }
+
+        // a held jump is confirmed when the candle stays close to its level
+        let mut released = Vec::new();
+        if let Some(held) = history.held.take()
+            && held.time != candle.time
+        {
+            if history
+                .jump(Some(held.close), candle.close, sigma)
+                .is_none()
+            {
+                history.accept(held, window);
+                released.push(held);
+            } else {
+                warn!("Discarding unconfirmed jump of {} at {}", ticker, held.time);
+            }
+        }
+
+        if let Some(size) = history.jump(history.reference(&candle), candle.close, sigma) {
+            alert(ticker, &candle, Anomaly::Jump(size));
+            match quarantine {
+                Quarantine::Drop => return released,
+                Quarantine::Confirm => {
+                    history.held = Some(candle);
+                    return released;
+                }
+                Quarantine::Flag => {}
+            }
+        }
+        history.accept(candle, window);
+        released.push(candle);
+        released
+    }
+}
+
+fn alert(ticker: &str, candle: &Candle, anomaly: Anomaly) {
+    metrics::increment(&format!("anomalies.{}.{}", ticker, anomaly.name()), 1);
+    warn!(
+        "Anomalous candle for {}: {:?} {:?}",
+        ticker, anomaly, candle
+    );
+}
@@ -0,0 +1,345 @@
+use crate::attribution::{self, Performance};
+use crate::control::{self, Command};
+use crate::events;
+use crate::execution::Executor;
+use crate::feeds::{PairChange, Pairs};
+use crate::health::{self, HealthConfig, Report};
+use crate::indicators::snapshot::{self, Snapshot};
+use crate::journal::{Entry, Journal, now};
+use crate::metrics;
+use crate::risk::{RiskConfig, RiskGuard};
+use crate::runner::Pauses;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+
+use tracing::{info, warn};
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    // address the server listens on (e.g. 127.0.0.1:8080), it is not started without one
+    pub address: Option<String>,
+    // environment variable holding the bearer token requests must carry
+    pub token_env: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> ApiConfig {
+        ApiConfig {
+            address: None,
+            token_env: "TRADE_BOT_API_TOKEN".into(),
+        }
+    }
+}
+
+// Components of the running bot the API reads and controls.
+pub struct Api<E> {
+    pub guard: Arc<RiskGuard<E>>,
+    pub journal: Arc<Mutex<Journal>>,
+    pub pauses: Pauses,
+    pub pairs: Pairs,
+    // names of the strategies run
+    pub strategies: Vec<String>,
+    pub health: HealthConfig,
+}
+
+type Shared<E> = State<Arc<Api<E>>>;
+
+type Failure = (StatusCode, String);
+
+// Check the bearer token of a request, browsers cannot set headers on websockets so it may also
+// be given as the token query parameter.
+async fn authorize(
+    State(token): State<Arc<String>>,
+    Query(query): Query<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(query.get("token").map(|given| given.as_str()))
+        .is_some_and(|given| given == token.as_str());
+    if !authorized {
+        return (StatusCode::UNAUTHORIZED, "Invalid token").into_response();
+    }
+    next.run(request).await
+}
+
+async fn status<E: Executor + Send + Sync + 'static>(State(api): Shared<E>) -> Json<Value> {
+    Json(json!({
+        "halted": api.guard.halted().map(|halt| format!("{:?}", halt)),
+        "positions": api.guard.positions(),
+        "open_orders": api.guard.open_orders(),
+        "daily_pnl": metrics::gauge("risk.daily_pnl"),
+        "strategies": api.strategies,
+        "paused": api.pauses.paused(),
+    }))
+}
+
+async fn feed() -> Json<Value> {
+    let last = metrics::gauge("feed.last_message");
+    Json(json!({
+        "last_message": last,
+        "age": last.map(|last| now() as f64 - last),
+    }))
+}
+
+fn probe(report: Report) -> (StatusCode, Json<Report>) {
+    let status = if report.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+async fn healthz<E: Executor + Send + Sync + 'static>(
+    State(api): Shared<E>,
+) -> (StatusCode, Json<Report>) {
+    probe(health::liveness(&api.health))
+}
+
+async fn readyz<E: Executor + Send + Sync + 'static>(
+    State(api): Shared<E>,
+) -> (StatusCode, Json<Report>) {
+    probe(health::readiness(&api.health))
+}
+
+async fn snapshot() -> Json<metrics::Snapshot> {
+    Json(metrics::snapshot())
+}
+
+async fn indicators(Path(pair): Path<String>) -> Result<Json<Snapshot>, Failure> {
+    match snapshot::snapshot(&pair) {
+        Some(snapshot) => Ok(Json(snapshot)),
+        None => Err((StatusCode::NOT_FOUND, format!("No indicators for {}", pair))),
+    }
+}
+
+async fn risk<E: Executor + Send + Sync + 'static>(State(api): Shared<E>) -> Json<RiskConfig> {
+    Json(api.guard.config())
+}
+
+// Change the risk limits given in the body, the others are kept.
+async fn adjust_risk<E: Executor + Send + Sync + 'static>(
+    State(api): Shared<E>,
+    Json(changes): Json<Value>,
+) -> Result<Json<RiskConfig>, Failure> {
+    let Ok(Value::Object(mut config)) = serde_json::to_value(api.guard.config()) else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Invalid risk limits".into(),
+        ));
+    };
+    let Value::Object(changes) = changes else {
+        return Err((StatusCode::BAD_REQUEST, "Expected an object".into()));
+    };
+    config.extend(changes);
+    let config: RiskConfig = match serde_json::from_value(Value::Object(config)) {
+        Ok(config) => config,
+        Err(error) => return Err((StatusCode::BAD_REQUEST, format!("{}", error))),
+    };
+    info!("Risk limits changed to {:?}", config);
+    api.guard.set_config(config.clone());
+    Ok(Json(config))
+}
+
+async fn performances() -> Json<Vec<Performance>> {
+    Json(attribution::performances())
+}
+
+async fn pause<E: Executor + Send + Sync + 'static>(
+    State(api): Shared<E>,
+    Path(name): Path<String>,
+) -> Result<String, Failure> {
+    if !api.strategies.contains(&name) {
+        return Err((StatusCode::NOT_FOUND, format!("No strategy {}", name)));
+    }
+    api.pauses.pause(&name);
+    info!(strategy = %name, "Strategy paused");
+    Ok(format!("Strategy {} paused", name))
+}
+
+async fn unpause<E: Executor + Send + Sync + 'static>(
+    State(api): Shared<E>,
+    Path(name): Path<String>,
+) -> Result<String, Failure> {
+    if !api.strategies.contains(&name) {
+        return Err((StatusCode::NOT_FOUND, format!("No strategy {}", name)));
+    }
+    api.pauses.resume(&name);
+    info!(strategy = %name, "Strategy resumed");
+    Ok(format!("Strategy {} resumed", name))
+}
+
+async fn cancel<E: Executor + Send + Sync + 'static>(
+    State(api): Shared<E>,
+    Path(id): Path<String>,
+) -> Result<String, Failure> {
+    if let Err(message) = api.guard.cancel(&id).await {
+        return Err((StatusCode::BAD_GATEWAY, message));
+    }
+    let entry = Entry::Cancel {
+        time: now(),
+        id: id.clone(),
+        simulated: api.guard.simulated(),
+    };
+    match api.journal.lock() {
+        Ok(mut journal) => {
+            if let Err(message) = journal.record(&entry) {
+                warn!("{}", message);
+            }
+        }
+        Err(_) => warn!(
+            "Journal lock poisoned, cancellation of {} not journaled",
+            id
+        ),
+    }
+    Ok(format!("Order {} cancelled", id))
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+struct Kill {
+    flatten: bool,
+}
+
+async fn kill<E: Executor + Send + Sync + 'static>(
+    State(api): Shared<E>,
+    Json(Kill { flatten }): Json<Kill>,
+) -> String {
+    control::execute(
+        Command::Kill { flatten },
+        &api.guard,
+        &api.journal,
+        &api.pairs,
+    )
+    .await
+}
+
+async fn resume<E: Executor + Send + Sync + 'static>(State(api): Shared<E>) -> String {
+    control::execute(Command::Resume, &api.guard, &api.journal, &api.pairs).await
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Pair {
+    pair: String,
+}
+
+async fn subscribe<E: Executor + Send + Sync + 'static>(
+    State(api): Shared<E>,
+    Json(Pair { pair }): Json<Pair>,
+) -> Result<String, Failure> {
+    match api.pairs.change(PairChange::Add(pair)).await {
+        Ok(outcome) => Ok(outcome),
+        Err(message) => Err((StatusCode::CONFLICT, message)),
+    }
+}
+
+async fn unsubscribe<E: Executor + Send + Sync + 'static>(
+    State(api): Shared<E>,
+    Json(Pair { pair }): Json<Pair>,
+) -> Result<String, Failure> {
+    match api.pairs.change(PairChange::Remove(pair)).await {
+        Ok(outcome) => Ok(outcome),
+        Err(message) => Err((StatusCode::CONFLICT, message)),
+    }
+}
+
+// Stream the live events as JSON text messages until the client leaves.
+async fn stream(upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(forward)
+}
+
+async fn forward(mut socket: WebSocket) {
+    let mut receiver = events::subscribe();
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(missed)) => {
+                warn!("Websocket client missed {} events", missed);
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        let text = match serde_json::to_string(&event) {
+            Ok(text) => text,
+            Err(error) => {
+                warn!("Could not serialize {:?}: {:?}", event, error);
+                continue;
+            }
+        };
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            return;
+        }
+    }
+}
+
+// Routes of the API, the probes are left open for orchestrators and uptime monitors.
+pub fn router<E: Executor + Send + Sync + 'static>(api: Api<E>, token: String) -> Router {
+    let api = Arc::new(api);
+    let probes = Router::new()
+        .route("/healthz", get(healthz::<E>))
+        .route("/readyz", get(readyz::<E>))
+        .with_state(api.clone());
+    Router::new()
+        .route("/status", get(status::<E>))
+        .route("/status/feed", get(feed))
+        .route("/status/metrics", get(snapshot))
+        .route("/indicators/{*pair}", get(indicators))
+        .route("/risk", get(risk::<E>).post(adjust_risk::<E>))
+        .route("/strategies/performance", get(performances))
+        .route("/strategies/{name}/pause", post(pause::<E>))
+        .route("/strategies/{name}/resume", post(unpause::<E>))
+        .route("/orders/{id}/cancel", post(cancel::<E>))
+        .route("/kill", post(kill::<E>))
+        .route("/resume", post(resume::<E>))
+        .route("/pairs/subscribe", post(subscribe::<E>))
+        .route("/pairs/unsubscribe", post(unsubscribe::<E>))
+        .route("/events", get(stream))
+        .with_state(api)
+        .layer(middleware::from_fn_with_state(Arc::new(token), authorize))
+        .merge(probes)
+}
+
+// Serve the API until the task is aborted, every request but the probes must carry the bearer
+// token.
+pub async fn serve<E: Executor + Send + Sync + 'static>(
+    config: &ApiConfig,
+    api: Api<E>,
+) -> Result<(), String> {
+    let Some(address) = &config.address else {
+        return Ok(());
+    };
+    let Ok(token) = env::var(&config.token_env) else {
+        return Err(format!("Set {} to serve the API", config.token_env));
+    };
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(error) => return Err(format!("Could not bind {}: {:?}", address, error)),
+    };
+    info!("Serving the API on {}", address);
+    match axum::serve(listener, router(api, token)).await {
+        Ok(()) => Ok(()),
+        Err(error) => Err(format!("API server failed: {:?}", error)),
+    }
+}
@@ -0,0 +1,191 @@
+use crate::execution::{Executor, Order, Side, submit_legs};
+
+use serde::{Deserialize, Serialize};
+
+use tracing::{info, warn};
+
+use std::collections::HashMap;
+
+// Three pairs closing a currency loop, e.g. ETH/EUR, BTC/EUR and ETH/BTC.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Triangle {
+    // pair trading the first asset against the quote currency (ETH/EUR)
+    pub direct: String,
+    // pair trading the second asset against the quote currency (BTC/EUR)
+    pub bridge: String,
+    // pair trading the first asset against the second one (ETH/BTC)
+    pub cross: String,
+}
+
+impl Triangle {
+    pub fn pairs(&self) -> [String; 3] {
+        [self.direct.clone(), self.bridge.clone(), self.cross.clone()]
+    }
+}
+
+// Triangle scanned on the quotes of its pairs while trading.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ScannerConfig {
+    #[serde(flatten)]
+    pub triangle: Triangle,
+    // taker fee paid on each leg, the one of Kraken's lowest volume tier by default
+    #[serde(default = "taker")]
+    pub fee: f64,
+    // minimal relative round trip profit net of fees
+    pub threshold: f64,
+    // amount of quote currency engaged in the round trip
+    pub notional: f64,
+    #[serde(default)]
+    pub auto_execute: bool,
+}
+
+fn taker() -> f64 {
+    0.004
+}
+
+impl ScannerConfig {
+    pub fn scanner(&self) -> TriangularScanner {
+        TriangularScanner::new(
+            self.triangle.clone(),
+            self.fee,
+            self.threshold,
+            self.notional,
+            self.auto_execute,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArbitrageSignal {
+    // relative gain of the round trip net of fees
+    pub profit: f64,
+    // orders to execute simultaneously, sized for the scanner's notional
+    pub legs: Vec<Order>,
+}
+
+// Watches the best bid/ask of a triangle of pairs and computes the rate obtained by going around
+// the loop in both directions starting from the quote currency, taker fees included.
+pub struct TriangularScanner {
+    triangle: Triangle,
+
+    // taker fee paid on each leg as a fraction of the traded amount
+    fee: f64,
+    // minimal relative round trip profit for a signal to be emitted
+    threshold: f64,
+    // amount of quote currency engaged in the round trip
+    notional: f64,
+    // submit the legs as soon as a signal is emitted
+    auto_execute: bool,
+
+    // best (bid, ask) per pair
+    quotes: HashMap<String, (f64, f64)>,
+}
+
+impl TriangularScanner {
+    pub fn new(
+        triangle: Triangle,
+        fee: f64,
+        threshold: f64,
+        notional: f64,
+        auto_execute: bool,
+    ) -> TriangularScanner {
+        TriangularScanner {
+            triangle,
+            fee,
+            threshold,
+            notional,
+            auto_execute,
+            quotes: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, ticker: &str, bid: f64, ask: f64) {
+        let pairs = [
+            &self.triangle.direct,
+            &self.triangle.bridge,
+            &self.triangle.cross,
+        ];
+        if pairs.iter().any(|pair| *pair == ticker) {
+            self.quotes.insert(ticker.to_string(), (bid, ask));
+        }
+    }
+
+    fn quote(&self, ticker: &str) -> Option<(f64, f64)> {
+        self.quotes
+            .get(ticker)
+            .copied()
+            .filter(|(bid, ask)| *bid > 0.0 && *ask > 0.0)
+    }
+
+    // Round trip rates (forward, reverse). Forward buys the first asset with the quote currency,
+    // converts it into the second asset and sells that back, reverse goes the other way.
+    pub fn rates(&self) -> Option<(f64, f64)> {
+        let (direct_bid, direct_ask) = self.quote(&self.triangle.direct)?;
+        let (bridge_bid, bridge_ask) = self.quote(&self.triangle.bridge)?;
+        let (cross_bid, cross_ask) = self.quote(&self.triangle.cross)?;
+        let kept = (1.0 - self.fee).powi(3);
+
+        let forward = cross_bid * bridge_bid / direct_ask * kept;
+        let reverse = direct_bid / (bridge_ask * cross_ask) * kept;
+        Some((forward, reverse))
+    }
+
+    pub fn scan(&self) -> Option<ArbitrageSignal> {
+        let (forward, reverse) = self.rates()?;
+        let (_, direct_ask) = self.quote(&self.triangle.direct)?;
+        let (_, bridge_ask) = self.quote(&self.triangle.bridge)?;
+        let (cross_bid, cross_ask) = self.quote(&self.triangle.cross)?;
+        let kept = 1.0 - self.fee;
+
+        if forward - 1.0 >= self.threshold && forward >= reverse {
+            let first = self.notional / direct_ask * kept;
+            let second = first * cross_bid * kept;
+            return Some(ArbitrageSignal {
+                profit: forward - 1.0,
+                legs: vec![
+                    Order::market(&self.triangle.direct, Side::Buy, self.notional / direct_ask),
+                    Order::market(&self.triangle.cross, Side::Sell, first),
+                    Order::market(&self.triangle.bridge, Side::Sell, second),
+                ],
+            });
+        }
+
+        if reverse - 1.0 >= self.threshold {
+            let second = self.notional / bridge_ask * kept;
+            let first = second / cross_ask;
+            return Some(ArbitrageSignal {
+                profit: reverse - 1.0,
+                legs: vec![
+                    Order::market(&self.triangle.bridge, Side::Buy, self.notional / bridge_ask),
+                    Order::market(&self.triangle.cross, Side::Buy, first),
+                    Order::market(&self.triangle.direct, Side::Sell, first * kept),
+                ],
+            });
+        }
+
+        None
+    }
+
+    // Update a quote and report (and optionally execute) any resulting opportunity.
+    pub async fn on_quote<E: Executor>(
+        &mut self,
+        ticker: &str,
+        bid: f64,
+        ask: f64,
+        executor: &E,
+    ) -> Option<ArbitrageSignal> {
+        self.update(ticker, bid, ask);
+        let signal = self.scan()?;
+        info!(
+            "Triangular arbitrage {:?} with profit {:.4}%",
+            signal.legs,
+            signal.profit * 100.0
+        );
+        if self.auto_execute
+            && let Err(message) = submit_legs(executor, &signal.legs).await
+        {
+            warn!("Triangular arbitrage execution failed: {}", message);
+        }
+        Some(signal)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::backtest::Fill;
+use crate::clock;
+use crate::conversion;
+use crate::metrics;
+use crate::risk::quote;
+
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+// Position a strategy holds in a ticker and its average entry price.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Holding {
+    // signed base volume, negative when short
+    position: f64,
+    entry: f64,
+}
+
+// Live profit of the executions of the orders of a strategy, net of their fees. Profits are
+// converted to the reporting currency at the latest rates, those in
+// quote currencies without a rate to it yet are left out.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct Performance {
+    pub strategy: String,
+    // profit of the closed positions (in reporting currency)
+    pub realized: f64,
+    // profit of the open positions at the latest prices (in reporting currency)
+    pub unrealized: f64,
+    // positions closed, entirely or partly, and those closed at a profit
+    pub trades: u64,
+    pub wins: u64,
+    // largest fall of the profit from its peak (in reporting currency)
+    pub max_drawdown: f64,
+    // highest profit reached
+    peak: f64,
+    #[serde(skip)]
+    holdings: HashMap<String, Holding>,
+    // profit of the closed positions per quote currency
+    #[serde(skip)]
+    profits: HashMap<String, f64>,
+}
+
+impl Performance {
+    pub fn pnl(&self) -> f64 {
+        self.realized + self.unrealized
+    }
+
+    // Share of the closed positions that made a profit, None before any was closed.
+    pub fn hit_rate(&self) -> Option<f64> {
+        (self.trades > 0).then(|| self.wins as f64 / self.trades as f64)
+    }
+
+    fn fill(&mut self, fill: &Fill) {
+        *self
+            .profits
+            .entry(quote(&fill.ticker).to_string())
+            .or_default() -= fill.fee;
+        let holding = self.holdings.entry(fill.ticker.clone()).or_default();
+        let signed = fill.side.sign() * fill.volume;
+        if holding.position == 0.0 || holding.position.signum() == signed.signum() {
+            let position = holding.position + signed;
+            holding.entry = (holding.position.abs() * holding.entry + fill.volume * fill.price)
+                / position.abs();
+            holding.position = position;
+            return;
+        }
+
+        let closed = fill.volume.min(holding.position.abs());
+        let profit = closed * (fill.price - holding.entry) * holding.position.signum();
+        *self
+            .profits
+            .entry(quote(&fill.ticker).to_string())
+            .or_default() += profit;
+        self.trades += 1;
+        if profit > 0.0 {
+            self.wins += 1;
+        }
+        let left = fill.volume - closed;
+        holding.position -= holding.position.signum() * closed;
+        if left > 0.0 {
+            // the execution reversed the position
+            holding.position = fill.side.sign() * left;
+            holding.entry = fill.price;
+        }
+    }
+
+    fn mark(&mut self, prices: &HashMap<String, f64>) {
+        self.realized = self
+            .profits
+            .iter()
+            .filter_map(|(currency, profit)| conversion::to_reporting(*profit, currency))
+            .sum();
+        self.unrealized = self
+            .holdings
+            .iter()
+            .filter_map(|(ticker, holding)| {
+                let profit = holding.position * (prices.get(ticker)? - holding.entry);
+                conversion::to_reporting(profit, quote(ticker))
+            })
+            .sum();
+        self.peak = self.peak.max(self.pnl());
+        self.max_drawdown = self.max_drawdown.max(self.peak - self.pnl());
+
+        metrics::set(&format!("strategy.{}.pnl", self.strategy), self.pnl());
+        metrics::set(
+            &format!("strategy.{}.drawdown", self.strategy),
+            self.max_drawdown,
+        );
+        if let Some(hit_rate) = self.hit_rate() {
+            metrics::set(&format!("strategy.{}.hit_rate", self.strategy), hit_rate);
+        }
+    }
+}
+
+// Time (in s) the executions of an order are attributed for after its placement.
+const RETENTION: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Default)]
+struct Attribution {
+    strategies: HashMap<String, Performance>,
+    // latest close per ticker
+    prices: HashMap<String, f64>,
+    // strategy of the orders placed by identifier, with the time (in s) they were placed at
+    orders: HashMap<String, (String, i64)>,
+}
+
+fn attribution() -> &'static Mutex<Attribution> {
+    static ATTRIBUTION: OnceLock<Mutex<Attribution>> = OnceLock::new();
+    ATTRIBUTION.get_or_init(Mutex::default)
+}
+
+// Attribute the executions of an order to the strategy that placed it.
+pub fn place(strategy: &str, id: &str) {
+    let Ok(mut attribution) = attribution().lock() else {
+        return;
+    };
+    let now = clock::seconds();
+    attribution
+        .orders
+        .retain(|_, (_, time)| now - *time < RETENTION);
+    attribution
+        .orders
+        .insert(id.to_string(), (strategy.to_string(), now));
+}
+
+// Book an execution of an order at its price and fee, for the strategy that placed the order.
+// Executions of orders not placed by a strategy are left out.
+pub fn fill(id: &str, fill: &Fill) {
+    let Ok(mut attribution) = attribution().lock() else {
+        return;
+    };
+    let Attribution {
+        strategies,
+        prices,
+        orders,
+    } = &mut *attribution;
+    let Some((strategy, _)) = orders.get(id) else {
+        return;
+    };
+    let performance = strategies
+        .entry(strategy.clone())
+        .or_insert_with(|| Performance {
+            strategy: strategy.clone(),
+            ..Performance::default()
+        });
+    performance.fill(fill);
+    performance.mark(prices);
+}
+
+// Revalue the open positions of every strategy at the latest close of a ticker.
+pub fn mark(ticker: &str, price: f64) {
+    let Ok(mut attribution) = attribution().lock() else {
+        return;
+    };
+    let Attribution {
+        strategies, prices, ..
+    } = &mut *attribution;
+    prices.insert(ticker.to_string(), price);
+    for performance in strategies.values_mut() {
+        performance.mark(prices);
+    }
+}
+
+// Performance of every strategy that placed orders, sorted by name.
+pub fn performances() -> Vec<Performance> {
+    let Ok(attribution) = attribution().lock() else {
+        return Vec::new();
+    };
+    let mut performances: Vec<Performance> = attribution.strategies.values().cloned().collect();
+    performances.sort_by(|first, second| first.strategy.cmp(&second.strategy));
+    performances
+}
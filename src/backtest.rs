@@ -0,0 +1,981 @@
+use crate::clock::SimulatedClock;
+use crate::execution::{Order, OrderKind, Side};
+use crate::feeds::HistoricalFeed;
+use crate::indicators::snapshot::{self, IndicatorConfig, Series, Snapshot};
+use crate::market::Candle;
+use crate::statistics::{deviation, mean, quantile};
+use crate::strategies::Strategy;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tracing::{info, warn};
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Period over which traded volume is accumulated to determine the fee tier (30 days in s).
+const FEE_VOLUME_PERIOD: i64 = 30 * 24 * 3600;
+
+const DAY: f64 = 24.0 * 3600.0;
+
+// Volume under which an order is considered completely filled.
+const DUST: f64 = 1e-12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeTier {
+    // traded quote volume over the fee period from which the tier applies
+    pub volume: f64,
+    // fee for orders adding liquidity as a fraction of the traded amount
+    pub maker: f64,
+    // fee for orders removing liquidity as a fraction of the traded amount
+    pub taker: f64,
+}
+
+// Volume tiered maker/taker fee schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeSchedule {
+    // tiers sorted by increasing volume
+    tiers: Vec<FeeTier>,
+}
+
+impl FeeSchedule {
+    pub fn new(mut tiers: Vec<FeeTier>) -> Result<FeeSchedule, String> {
+        if tiers.is_empty() {
+            return Err("Fee schedule needs at least one tier.".into());
+        }
+        tiers.sort_by(|first, second| first.volume.total_cmp(&second.volume));
+        Ok(FeeSchedule { tiers })
+    }
+
+    // (maker, taker) fees applying to an account having traded the given volume.
+    pub fn rates(&self, volume: f64) -> (f64, f64) {
+        let tier = self
+            .tiers
+            .iter()
+            .rev()
+            .find(|tier| tier.volume <= volume)
+            .unwrap_or(&self.tiers[0]);
+        (tier.maker, tier.taker)
+    }
+}
+
+impl Default for FeeSchedule {
+    // Kraken spot fee schedule.
+    fn default() -> FeeSchedule {
+        let tiers = [
+            (0.0, 0.0025, 0.0040),
+            (10_000.0, 0.0020, 0.0035),
+            (50_000.0, 0.0014, 0.0024),
+            (100_000.0, 0.0012, 0.0022),
+            (250_000.0, 0.0010, 0.0020),
+            (500_000.0, 0.0008, 0.0018),
+            (1_000_000.0, 0.0006, 0.0016),
+            (2_500_000.0, 0.0004, 0.0014),
+            (5_000_000.0, 0.0002, 0.0012),
+            (10_000_000.0, 0.0, 0.0010),
+        ];
+        FeeSchedule {
+            tiers: tiers
+                .into_iter()
+                .map(|(volume, maker, taker)| FeeTier {
+                    volume,
+                    maker,
+                    taker,
+                })
+                .collect(),
+        }
+    }
+}
+
+// Models the price degradation and the limited liquidity met by simulated orders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlippageModel {
+    // relative bid/ask spread, market orders cross half of it
+    pub spread: f64,
+    // relative price impact per unit of candle volume consumed by a market order
+    pub impact: f64,
+    // maximal fraction of a candle's volume an order can fill, the rest waits for later candles
+    pub participation: f64,
+}
+
+impl Default for SlippageModel {
+    fn default() -> SlippageModel {
+        SlippageModel {
+            spread: 0.0005,
+            impact: 0.1,
+            participation: 0.1,
+        }
+    }
+}
+
+impl SlippageModel {
+    // Average price obtained by a market order of the given volume crossing the spread around
+    // the reference price.
+    pub fn price(&self, side: Side, reference: f64, volume: f64, candle_volume: f64) -> f64 {
+        let share = if candle_volume > 0.0 {
+            volume / candle_volume
+        } else {
+            1.0
+        };
+        reference * (1.0 + side.sign() * (self.spread / 2.0 + self.impact * share))
+    }
+
+    // Part of the volume that can be filled within a candle.
+    pub fn fillable(&self, volume: f64, candle_volume: f64) -> f64 {
+        volume.min(self.participation * candle_volume)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fill {
+    pub time: i64,
+    pub ticker: String,
+    pub side: Side,
+    pub volume: f64,
+    pub price: f64,
+    // fee paid in quote currency
+    pub fee: f64,
+    // whether the fill added liquidity
+    pub maker: bool,
+}
+
+// Short selling allowed by the simulated broker.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Shorting {
+    // fee for borrowing the sold asset per day, as a fraction of the value of the short position
+    pub borrow_rate: f64,
+    // collateral held against short positions as a fraction of their value, taken from the equity
+    pub margin: f64,
+}
+
+impl Default for Shorting {
+    fn default() -> Shorting {
+        Shorting {
+            borrow_rate: 0.0,
+            margin: 1.0,
+        }
+    }
+}
+
+// What a simulated broker went through, its fees, slippage and shorting being configured anew.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct BrokerState {
+    cash: f64,
+    positions: HashMap<String, f64>,
+    prices: HashMap<String, f64>,
+    accrued: HashMap<String, i64>,
+    borrow_fees: f64,
+    pending: Vec<Order>,
+    traded: VecDeque<(i64, f64)>,
+    fills: Vec<Fill>,
+}
+
+// Broker simulating order execution against candles. Orders are filled on the candles following
+// their submission so strategies never trade on the prices they decided on.
+// Sells are limited to the positions held unless shorting is allowed, short sales are then limited
+// by the equity left to back them and pay borrow fees for as long as they are open.
+pub struct SimulatedBroker {
+    fees: FeeSchedule,
+    slippage: SlippageModel,
+    shorting: Option<Shorting>,
+
+    // quote currency balance
+    cash: f64,
+    // base volume held per ticker, negative when short
+    positions: HashMap<String, f64>,
+    // latest close per ticker
+    prices: HashMap<String, f64>,
+    // time (in s) borrow fees were last charged per ticker
+    accrued: HashMap<String, i64>,
+    borrow_fees: f64,
+
+    // orders not yet (fully) filled, volumes are what is left to fill
+    pending: Vec<Order>,
+    // (time, quote volume) of fills within the fee period
+    traded: VecDeque<(i64, f64)>,
+    fills: Vec<Fill>,
+}
+
+impl SimulatedBroker {
+    pub fn new(cash: f64, fees: FeeSchedule, slippage: SlippageModel) -> SimulatedBroker {
+        SimulatedBroker {
+            fees,
+            slippage,
+            shorting: None,
+            cash,
+            positions: HashMap::new(),
+            prices: HashMap::new(),
+            accrued: HashMap::new(),
+            borrow_fees: 0.0,
+            pending: Vec::new(),
+            traded: VecDeque::new(),
+            fills: Vec::new(),
+        }
+    }
+
+    pub fn with_shorting(mut self, shorting: Shorting) -> SimulatedBroker {
+        self.shorting = Some(shorting);
+        self
+    }
+
+    pub fn submit(&mut self, order: Order) {
+        self.pending.push(order);
+    }
+
+    fn state(&self) -> BrokerState {
+        BrokerState {
+            cash: self.cash,
+            positions: self.positions.clone(),
+            prices: self.prices.clone(),
+            accrued: self.accrued.clone(),
+            borrow_fees: self.borrow_fees,
+            pending: self.pending.clone(),
+            traded: self.traded.clone(),
+            fills: self.fills.clone(),
+        }
+    }
+
+    fn restore(&mut self, state: BrokerState) {
+        self.cash = state.cash;
+        self.positions = state.positions;
+        self.prices = state.prices;
+        self.accrued = state.accrued;
+        self.borrow_fees = state.borrow_fees;
+        self.pending = state.pending;
+        self.traded = state.traded;
+        self.fills = state.fills;
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    pub fn position(&self, ticker: &str) -> f64 {
+        self.positions.get(ticker).copied().unwrap_or(0.0)
+    }
+
+    pub fn fills(&self) -> &[Fill] {
+        &self.fills
+    }
+
+    // Borrow fees paid on short positions so far (in quote currency).
+    pub fn borrow_fees(&self) -> f64 {
+        self.borrow_fees
+    }
+
+    // Cash plus positions valued at the provided prices.
+    pub fn equity(&self, prices: &HashMap<String, f64>) -> f64 {
+        self.cash
+            + self
+                .positions
+                .iter()
+                .map(|(ticker, volume)| volume * prices.get(ticker).copied().unwrap_or(0.0))
+                .sum::<f64>()
+    }
+
+    // Base volume that can be sold at a price: the position held plus, when shorting, what the
+    // equity not backing short positions yet can back.
+    fn sellable(&self, ticker: &str, price: f64, fee: f64) -> f64 {
+        let held = self.position(ticker).max(0.0);
+        let Some(shorting) = self.shorting else {
+            return held;
+        };
+        let shorts: f64 = self
+            .positions
+            .iter()
+            .filter(|(_, volume)| **volume < 0.0)
+            .map(|(ticker, volume)| -volume * self.prices.get(ticker).copied().unwrap_or(price))
+            .sum();
+        let free = (self.equity(&self.prices) - shorting.margin * shorts).max(0.0);
+        held + free / (shorting.margin * price * (1.0 + fee))
+    }
+
+    // Charge the borrow fees of the short position of a ticker since they were last charged.
+    fn accrue(&mut self, ticker: &str, time: i64) {
+        let last = self.accrued.insert(ticker.to_string(), time);
+        let (Some(shorting), Some(last), Some(price)) =
+            (self.shorting, last, self.prices.get(ticker))
+        else {
+            return;
+        };
+        let short = (-self.position(ticker)).max(0.0);
+        let fee = short * price * shorting.borrow_rate * (time - last) as f64 / DAY;
+        self.cash -= fee;
+        self.borrow_fees += fee;
+    }
+
+    fn traded_volume(&mut self, time: i64) -> f64 {
+        while self
+            .traded
+            .front()
+            .is_some_and(|(traded_at, _)| *traded_at < time - FEE_VOLUME_PERIOD)
+        {
+            self.traded.pop_front();
+        }
+        self.traded.iter().map(|(_, volume)| volume).sum()
+    }
+
+    // Match the pending orders of a ticker against a new candle.
+    pub fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Fill> {
+        self.accrue(ticker, candle.time);
+        let traded = self.traded_volume(candle.time);
+        let (maker_fee, taker_fee) = self.fees.rates(traded);
+        let mut fills = Vec::new();
+        let mut pending = Vec::with_capacity(self.pending.len());
+
+        for mut order in std::mem::take(&mut self.pending) {
+            if order.ticker != ticker {
+                pending.push(order);
+                continue;
+            }
+
+            let volume = self.slippage.fillable(order.volume, candle.volume);
+            // nothing traded in the candle, the order waits for the next one
+            if volume <= 0.0 {
+                pending.push(order);
+                continue;
+            }
+            let (price, fee, maker) = match order.kind {
+                OrderKind::Market => (
+                    self.slippage
+                        .price(order.side, candle.open, volume, candle.volume),
+                    taker_fee,
+                    false,
+                ),
+                OrderKind::Limit(limit) => {
+                    let crossed = match order.side {
+                        Side::Buy => candle.low <= limit,
+                        Side::Sell => candle.high >= limit,
+                    };
+                    if !crossed {
+                        pending.push(order);
+                        continue;
+                    }
+                    (limit, maker_fee, true)
+                }
+            };
+
+            // reduce-only orders are capped by the opposite position
+            let volume = if order.reduce_only {
+                volume.min((-order.side.sign() * self.position(ticker)).max(0.0))
+            } else {
+                volume
+            };
+            let volume = match order.side {
+                Side::Buy => volume.min(self.cash / (price * (1.0 + fee))),
+                Side::Sell => volume.min(self.sellable(ticker, price, fee)),
+            };
+            if volume <= 0.0 {
+                warn!("Dropping unaffordable simulated order {:?}", order);
+                continue;
+            }
+
+            let fill = self.settle(candle.time, &order, volume, price, fee, maker);
+            order.volume -= fill.volume;
+            fills.push(fill);
+
+            if order.volume > DUST {
+                pending.push(order);
+            }
+        }
+
+        self.pending = pending;
+        self.prices.insert(ticker.to_string(), candle.close);
+        self.fills.extend(fills.iter().cloned());
+        fills
+    }
+
+    fn settle(
+        &mut self,
+        time: i64,
+        order: &Order,
+        volume: f64,
+        price: f64,
+        rate: f64,
+        maker: bool,
+    ) -> Fill {
+        let notional = volume * price;
+        let fee = notional * rate;
+        self.cash -= order.side.sign() * notional + fee;
+        *self.positions.entry(order.ticker.clone()).or_insert(0.0) += order.side.sign() * volume;
+        self.traded.push_back((time, notional));
+        Fill {
+            time,
+            ticker: order.ticker.clone(),
+            side: order.side,
+            volume,
+            price,
+            fee,
+            maker,
+        }
+    }
+}
+
+// Round trip from a flat position back to flat, with how far prices moved against and in favour
+// of it while open.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Excursion {
+    pub ticker: String,
+    // side of the fills opening the position, Sell for short positions
+    pub side: Side,
+    // unix times (in s) of the first and last fills
+    pub opened: i64,
+    pub closed: i64,
+    // average entry price
+    pub entry: f64,
+    // maximum adverse and favorable excursions relative to the entry price, the largest moves
+    // against and in favour of the position to the lows and highs of the candles it was held over
+    pub adverse: f64,
+    pub favorable: f64,
+    // realized profit net of fees (in quote currency)
+    pub pnl: f64,
+}
+
+// Position held in a ticker: signed base volume and what it cost including fees, negative for
+// short positions whose sale proceeds net of fees are owed back.
+#[derive(Debug, Clone, Copy, Default)]
+struct Holding {
+    volume: f64,
+    cost: f64,
+}
+
+impl Holding {
+    // Book a fill, returns the profit realized by the part of it closing the position, None when
+    // it only adds to it, and the volume of the rest opening or adding to the position.
+    fn fill(&mut self, fill: &Fill) -> (Option<f64>, f64) {
+        let sign = fill.side.sign();
+        let closed = if self.volume * sign < 0.0 {
+            fill.volume.min(self.volume.abs())
+        } else {
+            0.0
+        };
+        // share of the fee paid by the closing part
+        let share = if fill.volume > 0.0 {
+            closed / fill.volume
+        } else {
+            0.0
+        };
+        let mut realized = None;
+        if closed > 0.0 {
+            let basis = self.cost / self.volume.abs() * closed;
+            realized = Some(-sign * closed * fill.price - share * fill.fee - basis);
+            self.cost -= basis;
+            self.volume += sign * closed;
+        }
+        let opened = fill.volume - closed;
+        if opened > 0.0 {
+            self.volume += sign * opened;
+            self.cost += sign * opened * fill.price + (1.0 - share) * fill.fee;
+        }
+        (realized, opened)
+    }
+}
+
+// Position being built up and unwound.
+#[derive(Debug, Clone, Copy)]
+struct Trip {
+    opened: i64,
+    side: Side,
+    holding: Holding,
+    // base volume entered and its notional, for the average entry price
+    entered: f64,
+    notional: f64,
+    // profit realized so far net of fees
+    pnl: f64,
+}
+
+impl Trip {
+    fn new(fill: &Fill) -> Trip {
+        Trip {
+            opened: fill.time,
+            side: fill.side,
+            holding: Holding::default(),
+            entered: 0.0,
+            notional: 0.0,
+            pnl: 0.0,
+        }
+    }
+}
+
+// Summary of a set of values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Distribution {
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    pub fn of(values: &[f64]) -> Option<Distribution> {
+        Some(Distribution {
+            mean: mean(values)?,
+            median: quantile(values, 0.5)?,
+            p90: quantile(values, 0.9)?,
+            max: values.iter().copied().fold(f64::MIN, f64::max),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BacktestReport {
+    pub fills: Vec<Fill>,
+    // (time, equity) at the close of every step
+    pub equity: Vec<(i64, f64)>,
+    // candles replayed per ticker, oldest first
+    pub candles: HashMap<String, Vec<Candle>>,
+    // orders of the strategy along with the time of the candle they were decided on
+    pub signals: Vec<(i64, Order)>,
+    // fees paid for borrowing the assets sold short (in quote currency)
+    pub borrow_fees: f64,
+}
+
+impl BacktestReport {
+    pub fn pnl(&self) -> f64 {
+        match (self.equity.first(), self.equity.last()) {
+            (Some((_, first)), Some((_, last))) => last - first,
+            _ => 0.0,
+        }
+    }
+
+    pub fn fees(&self) -> f64 {
+        self.fills.iter().map(|fill| fill.fee).sum()
+    }
+
+    pub fn fees_by_ticker(&self) -> HashMap<String, f64> {
+        let mut fees = HashMap::new();
+        for fill in &self.fills {
+            *fees.entry(fill.ticker.clone()).or_default() += fill.fee;
+        }
+        fees
+    }
+
+    // Relative equity change between consecutive steps.
+    pub fn returns(&self) -> Vec<f64> {
+        self.equity
+            .windows(2)
+            .map(|pair| pair[1].1 / pair[0].1 - 1.0)
+            .collect()
+    }
+
+    // Largest relative drop of the equity from a previous peak.
+    pub fn max_drawdown(&self) -> f64 {
+        let mut peak = f64::MIN;
+        let mut drawdown: f64 = 0.0;
+        for (_, equity) in &self.equity {
+            peak = peak.max(*equity);
+            drawdown = drawdown.max(1.0 - equity / peak);
+        }
+        drawdown
+    }
+
+    // Realized profit of every fill closing (part of) a position, long or short, fees of the fills
+    // opening it are folded into its cost basis and those of the fills closing it deducted from
+    // the profit.
+    pub fn trade_pnls(&self) -> Vec<f64> {
+        let mut holdings: HashMap<&str, Holding> = HashMap::new();
+        self.fills
+            .iter()
+            .filter_map(|fill| holdings.entry(&fill.ticker).or_default().fill(fill).0)
+            .collect()
+    }
+
+    fn excursion(&self, trip: &Trip, fill: &Fill) -> Excursion {
+        let entry = trip.notional / trip.entered;
+        let (low, high) = self
+            .candles
+            .get(&fill.ticker)
+            .into_iter()
+            .flatten()
+            .filter(|candle| candle.time >= trip.opened && candle.time <= fill.time)
+            .fold((entry, entry), |(low, high), candle| {
+                (low.min(candle.low), high.max(candle.high))
+            });
+        let (adverse, favorable) = match trip.side {
+            Side::Buy => ((entry - low) / entry, (high - entry) / entry),
+            Side::Sell => ((high - entry) / entry, (entry - low) / entry),
+        };
+        Excursion {
+            ticker: fill.ticker.clone(),
+            side: trip.side,
+            opened: trip.opened,
+            closed: fill.time,
+            entry,
+            adverse,
+            favorable,
+            pnl: trip.pnl,
+        }
+    }
+
+    // Closed round trips along with their excursions, from the candles replayed between their
+    // first and last fills. A fill reversing a position closes a trip and opens the next one.
+    pub fn excursions(&self) -> Vec<Excursion> {
+        let mut open: HashMap<&str, Trip> = HashMap::new();
+        let mut excursions = Vec::new();
+        for fill in &self.fills {
+            let trip = open.entry(&fill.ticker).or_insert_with(|| Trip::new(fill));
+            let (realized, opened) = trip.holding.fill(fill);
+            let Some(realized) = realized else {
+                trip.entered += opened;
+                trip.notional += opened * fill.price;
+                continue;
+            };
+            trip.pnl += realized;
+            if trip.holding.volume.abs() > DUST && opened <= 0.0 {
+                continue;
+            }
+            excursions.push(self.excursion(trip, fill));
+            if opened > 0.0 {
+                *trip = Trip {
+                    holding: trip.holding,
+                    entered: opened,
+                    notional: opened * fill.price,
+                    ..Trip::new(fill)
+                };
+            } else {
+                open.remove(fill.ticker.as_str());
+            }
+        }
+        excursions
+    }
+
+    // Distributions of the maximum adverse and favorable excursions of the closed round trips,
+    // to tune stops and targets.
+    pub fn excursion_distributions(&self) -> Option<(Distribution, Distribution)> {
+        let excursions = self.excursions();
+        let adverse: Vec<f64> = excursions.iter().map(|trade| trade.adverse).collect();
+        let favorable: Vec<f64> = excursions.iter().map(|trade| trade.favorable).collect();
+        Some((Distribution::of(&adverse)?, Distribution::of(&favorable)?))
+    }
+
+    // Per step Sharpe ratio of the returns (not annualized).
+    pub fn sharpe(&self) -> Option<f64> {
+        let returns = self.returns();
+        let spread = deviation(&returns)?;
+        if spread == 0.0 {
+            return None;
+        }
+        Some(mean(&returns)? / spread)
+    }
+}
+
+// State of a backtest after a number of steps, written periodically so that an interrupted run
+// resumes from it rather than from the start. It holds the equity curve so far for inspection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    // steps replayed and the time of the last one
+    pub steps: usize,
+    pub time: Option<i64>,
+    pub equity: Vec<(i64, f64)>,
+    pub signals: Vec<(i64, Order)>,
+    broker: BrokerState,
+    // time the timer fires next
+    next: Option<i64>,
+    // state of the strategy per ticker
+    states: HashMap<String, Value>,
+}
+
+impl Checkpoint {
+    // Checkpoint written to a file, None when there is none yet.
+    pub fn load(path: &Path) -> Result<Option<Checkpoint>, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("Could not read {:?}: {:?}", path, error)),
+        };
+        match serde_json::from_str(&content) {
+            Ok(checkpoint) => Ok(Some(checkpoint)),
+            Err(error) => Err(format!("Could not parse {:?}: {}", path, error)),
+        }
+    }
+
+    // Replace the checkpoint of a file, through a temporary file so that an interruption while
+    // writing leaves the previous one.
+    fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(directory) = path.parent()
+            && let Err(error) = fs::create_dir_all(directory)
+        {
+            return Err(format!("Could not create {:?}: {:?}", directory, error));
+        }
+        let content = match serde_json::to_string(self) {
+            Ok(content) => content,
+            Err(error) => return Err(format!("Could not serialize checkpoint: {}", error)),
+        };
+        let temporary = path.with_extension("json.tmp");
+        if let Err(error) = fs::write(&temporary, content) {
+            return Err(format!("Could not write {:?}: {:?}", temporary, error));
+        }
+        fs::rename(&temporary, path)
+            .map_err(|error| format!("Could not write {:?}: {:?}", path, error))
+    }
+
+    // Results of the backtest up to the checkpoint, without the replayed candles.
+    pub fn report(&self) -> BacktestReport {
+        BacktestReport {
+            fills: self.broker.fills.clone(),
+            equity: self.equity.clone(),
+            candles: HashMap::new(),
+            signals: self.signals.clone(),
+            borrow_fees: self.broker.borrow_fees,
+        }
+    }
+}
+
+pub struct Backtester<S: Strategy> {
+    strategy: S,
+    broker: SimulatedBroker,
+    // clock moved to the time of each step before it is processed
+    clock: Option<Arc<SimulatedClock>>,
+    indicators: Vec<IndicatorConfig>,
+    // file checkpoints are kept in and steps between them
+    checkpoints: Option<(PathBuf, usize)>,
+}
+
+impl<S: Strategy> Backtester<S> {
+    pub fn new(strategy: S, broker: SimulatedBroker) -> Backtester<S> {
+        Backtester {
+            strategy,
+            broker,
+            clock: None,
+            indicators: Vec::new(),
+            checkpoints: None,
+        }
+    }
+
+    // Write a checkpoint to a file every number of steps and at the end, and resume from the one
+    // found there when its steps are those replayed.
+    pub fn with_checkpoints(mut self, path: &Path, every: usize) -> Backtester<S> {
+        self.checkpoints = Some((path.to_path_buf(), every.max(1)));
+        self
+    }
+
+    // Checkpoint to resume from, if one was written for the steps.
+    fn resumed(&self, steps: &[HashMap<String, Candle>]) -> Option<Checkpoint> {
+        let (path, _) = self.checkpoints.as_ref()?;
+        let checkpoint = match Checkpoint::load(path) {
+            Ok(checkpoint) => checkpoint?,
+            Err(message) => {
+                warn!("{}", message);
+                return None;
+            }
+        };
+        let time = checkpoint
+            .steps
+            .checked_sub(1)
+            .and_then(|last| steps.get(last))
+            .and_then(|step| step.values().map(|candle| candle.time).max());
+        if checkpoint.steps == 0 || time != checkpoint.time {
+            warn!("Checkpoint {:?} is of other candles, starting over", path);
+            return None;
+        }
+        info!("Resuming from {:?} after {} steps", path, checkpoint.steps);
+        Some(checkpoint)
+    }
+
+    // Publish indicator snapshots of the replayed candles before each is handed to the strategy,
+    // as the live pipeline does. They are computed over the whole replay up front.
+    pub fn with_indicators(mut self, indicators: Vec<IndicatorConfig>) -> Backtester<S> {
+        self.indicators = indicators;
+        self
+    }
+
+    // Values of the indicators per ticker, by name, aligned with the candles of the ticker in the
+    // steps.
+    fn indicators(&self, steps: &[HashMap<String, Candle>]) -> HashMap<String, Series> {
+        let mut candles: HashMap<String, Vec<Candle>> = HashMap::new();
+        for step in steps {
+            for (ticker, candle) in step {
+                candles.entry(ticker.clone()).or_default().push(*candle);
+            }
+        }
+        candles
+            .into_iter()
+            .map(|(ticker, candles)| {
+                let series = self
+                    .indicators
+                    .iter()
+                    .flat_map(|config| snapshot::series(config, &candles))
+                    .collect();
+                (ticker, series)
+            })
+            .collect()
+    }
+
+    // Drive a simulated clock with the candles replayed, install it for the code reading the
+    // time during the run to see the replayed time.
+    pub fn with_clock(mut self, clock: Arc<SimulatedClock>) -> Backtester<S> {
+        self.clock = Some(clock);
+        self
+    }
+
+    // Restore the state of the strategy, the broker and the report saved in a checkpoint,
+    // returning the time the timer fires next.
+    fn resume(&mut self, checkpoint: &Checkpoint, report: &mut BacktestReport) -> Option<i64> {
+        for (ticker, state) in &checkpoint.states {
+            if let Err(message) = self.strategy.restore(ticker, state.clone()) {
+                warn!("Could not restore the state on {}: {}", ticker, message);
+            }
+        }
+        self.broker.restore(checkpoint.broker.clone());
+        report.equity = checkpoint.equity.clone();
+        report.signals = checkpoint.signals.clone();
+        checkpoint.next
+    }
+
+    // Replay time ordered steps of candles per ticker through the strategy and the broker. When
+    // resuming from a checkpoint, the strategy is fed the steps it covers again to warm up its
+    // indicators, ignoring its orders, before its saved state and the broker's are restored.
+    pub fn run(
+        mut self,
+        steps: impl IntoIterator<Item = HashMap<String, Candle>>,
+    ) -> BacktestReport {
+        let steps: Vec<HashMap<String, Candle>> = steps.into_iter().collect();
+        let indicators = self.indicators(&steps);
+        let resumed = self.resumed(&steps);
+        let start = resumed.as_ref().map_or(0, |checkpoint| checkpoint.steps);
+        let mut prices: HashMap<String, f64> = HashMap::new();
+        let mut report = BacktestReport::default();
+        // the strategy warms up on the replayed candles, there is no history before them
+        self.strategy.on_start(&HashMap::new());
+        let period = self.strategy.timer().filter(|period| *period > 0);
+        // time the timer fires next, from the first step on
+        let mut next: Option<i64> = None;
+        let mut last = None;
+
+        for (index, step) in steps.iter().enumerate() {
+            if index == start
+                && let Some(checkpoint) = &resumed
+            {
+                next = self.resume(checkpoint, &mut report);
+            }
+            // steps covered by the checkpoint only warm the strategy up
+            let replaying = index < start;
+            let time = step.values().map(|candle| candle.time).max();
+            if let (Some(clock), Some(time)) = (&self.clock, time) {
+                clock.set(time as f64);
+            }
+            let mut tickers: Vec<&String> = step.keys().collect();
+            tickers.sort();
+
+            for ticker in tickers {
+                let candle = &step[ticker];
+                let replayed = report.candles.entry(ticker.clone()).or_default();
+                if let Some(series) = indicators.get(ticker) {
+                    let index = replayed.len();
+                    let values: BTreeMap<String, Option<f64>> = series
+                        .iter()
+                        .map(|(name, values)| (name.clone(), values.get(index).copied().flatten()))
+                        .collect();
+                    snapshot::publish(
+                        ticker,
+                        Snapshot {
+                            time: candle.time,
+                            values,
+                        },
+                    );
+                }
+                replayed.push(*candle);
+                prices.insert(ticker.clone(), candle.close);
+                if replaying {
+                    self.strategy.on_candle(ticker, candle);
+                    continue;
+                }
+                let fills = self.broker.on_candle(ticker, candle);
+                for fill in &fills {
+                    for order in self.strategy.on_fill(fill) {
+                        report.signals.push((candle.time, order.clone()));
+                        self.broker.submit(order);
+                    }
+                }
+                let orders = self.strategy.on_candle(ticker, candle);
+                // as live, signals of strategies warming up are dropped
+                if !self.strategy.ready(ticker) {
+                    continue;
+                }
+                for order in orders {
+                    report.signals.push((candle.time, order.clone()));
+                    self.broker.submit(order);
+                }
+            }
+
+            if let (Some(period), Some(time)) = (period, time) {
+                let due = *next.get_or_insert(time + period);
+                if time >= due {
+                    next = Some(due + period * ((time - due) / period + 1));
+                    let orders = self.strategy.on_timer(time);
+                    if !replaying {
+                        for order in orders {
+                            report.signals.push((time, order.clone()));
+                            self.broker.submit(order);
+                        }
+                    }
+                }
+            }
+
+            if time.is_some() {
+                last = time;
+            }
+            if replaying {
+                continue;
+            }
+            if let Some(time) = time {
+                report.equity.push((time, self.broker.equity(&prices)));
+            }
+            if let Some((path, every)) = &self.checkpoints
+                && ((index + 1) % every == 0 || index + 1 == steps.len())
+            {
+                let checkpoint = Checkpoint {
+                    steps: index + 1,
+                    time,
+                    equity: report.equity.clone(),
+                    signals: report.signals.clone(),
+                    broker: self.broker.state(),
+                    next,
+                    states: report
+                        .candles
+                        .keys()
+                        .filter_map(|ticker| Some((ticker.clone(), self.strategy.state(ticker)?)))
+                        .collect(),
+                };
+                if let Err(message) = checkpoint.save(path) {
+                    warn!("{}", message);
+                }
+            }
+        }
+
+        // the run was over when checkpointed
+        if start == steps.len()
+            && let Some(checkpoint) = &resumed
+        {
+            self.resume(checkpoint, &mut report);
+        }
+
+        // orders placed on stop are reported but the replay is over, they are left unfilled
+        for order in self.strategy.on_stop() {
+            report.signals.push((last.unwrap_or(0), order.clone()));
+            self.broker.submit(order);
+        }
+
+        report.borrow_fees = self.broker.borrow_fees;
+        report.fills = self.broker.fills;
+        report
+    }
+
+    // Run over everything left in a historical feed.
+    pub async fn run_feed(self, feed: &mut HistoricalFeed) -> BacktestReport {
+        let mut steps = Vec::new();
+        while let Some(step) = feed.consume().await {
+            steps.push(
+                step.iter()
+                    .map(|(ticker, ohlc)| (ticker.clone(), Candle::from(ohlc)))
+                    .collect(),
+            );
+        }
+        self.run(steps)
+    }
+}
@@ -0,0 +1,146 @@
+use crate::alerts::{self, EventKind};
+use crate::execution::Executor;
+use crate::metrics;
+use crate::risk::RiskGuard;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::interval;
+
+use tracing::{info, warn};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BalanceConfig {
+    // time between balance queries (in s), the balances are not checked without it
+    pub period: Option<u64>,
+    // difference between the expected and the actual balance of an asset that is reported
+    pub threshold: f64,
+    // whether the tracked positions are corrected to match the balances
+    pub correct: bool,
+}
+
+impl Default for BalanceConfig {
+    fn default() -> BalanceConfig {
+        BalanceConfig {
+            period: None,
+            threshold: 1e-6,
+            correct: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    pub asset: String,
+    pub expected: f64,
+    pub actual: f64,
+}
+
+impl Drift {
+    pub fn difference(&self) -> f64 {
+        self.actual - self.expected
+    }
+}
+
+fn amount(amounts: &HashMap<String, f64>, asset: &str) -> f64 {
+    amounts.get(asset).copied().unwrap_or(0.0)
+}
+
+// Compares the balances of the exchange to the holdings tracked by the bot. The first balances
+// seen are the reference, later balances are expected to differ from it by the change of the
+// positions since, anything else was traded outside of the bot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceCheck {
+    threshold: f64,
+    // balances and positions per asset when the reference was taken
+    reference: Option<(HashMap<String, f64>, HashMap<String, f64>)>,
+}
+
+impl BalanceCheck {
+    pub fn new(threshold: f64) -> BalanceCheck {
+        BalanceCheck {
+            threshold,
+            reference: None,
+        }
+    }
+
+    // Assets whose balance drifted from the positions, given per asset.
+    pub fn check(
+        &mut self,
+        balances: &HashMap<String, f64>,
+        positions: &HashMap<String, f64>,
+    ) -> Vec<Drift> {
+        let Some((reference, held)) = &self.reference else {
+            self.reference = Some((balances.clone(), positions.clone()));
+            return Vec::new();
+        };
+        let mut assets: Vec<&String> = balances.keys().chain(positions.keys()).collect();
+        assets.sort();
+        assets.dedup();
+
+        assets
+            .into_iter()
+            .map(|asset| Drift {
+                asset: asset.clone(),
+                expected: amount(reference, asset) + amount(positions, asset) - amount(held, asset),
+                actual: amount(balances, asset),
+            })
+            .filter(|drift| drift.difference().abs() > self.threshold)
+            .collect()
+    }
+}
+
+// Periodically check the balances against the positions of the risk guard, reporting drifts and
+// correcting the positions if configured.
+pub async fn run<E: Executor + Sync>(config: BalanceConfig, guard: Arc<RiskGuard<E>>) {
+    let Some(period) = config.period else {
+        return;
+    };
+    let mut check = BalanceCheck::new(config.threshold);
+    let mut ticker = interval(Duration::from_secs(period.max(1)));
+    loop {
+        ticker.tick().await;
+        let balances = match guard.balances().await {
+            Ok(balances) => balances,
+            Err(message) => {
+                warn!("Could not query balances: {}", message);
+                continue;
+            }
+        };
+        for drift in check.check(&balances, &guard.holdings()) {
+            metrics::set(
+                &format!("balances.drift.{}", drift.asset),
+                drift.difference(),
+            );
+            warn!(
+                "Balance of {} is {} while {} is expected",
+                drift.asset, drift.actual, drift.expected
+            );
+            alerts::notify(
+                EventKind::Risk,
+                "Balance drift",
+                &format!(
+                    "{} balance is {} while {} is expected",
+                    drift.asset, drift.actual, drift.expected
+                ),
+            );
+            if !config.correct {
+                continue;
+            }
+            if guard.adjust(&drift.asset, drift.difference()) {
+                info!(
+                    "Corrected position on {} by {}",
+                    drift.asset,
+                    drift.difference()
+                );
+            } else {
+                warn!("No holding of {} to correct", drift.asset);
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::execution::Side;
+use crate::market::to_float;
+
+use kraken_async_rs::wss::{BidAsk, L2};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Level {
+    pub price: f64,
+    pub volume: f64,
+}
+
+impl From<&BidAsk> for Level {
+    fn from(bid_ask: &BidAsk) -> Level {
+        Level {
+            price: to_float(&bid_ask.price),
+            volume: to_float(&bid_ask.quantity),
+        }
+    }
+}
+
+// Change to the order book of a ticker, snapshots replace the whole book while updates only
+// replace the given levels.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BookUpdate {
+    pub snapshot: bool,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+impl From<&L2> for BookUpdate {
+    fn from(message: &L2) -> BookUpdate {
+        match message {
+            L2::Orderbook(book) => BookUpdate {
+                snapshot: true,
+                bids: book.bids.iter().map(Level::from).collect(),
+                asks: book.asks.iter().map(Level::from).collect(),
+            },
+            L2::Update(update) => BookUpdate {
+                snapshot: false,
+                bids: update.bids.iter().map(Level::from).collect(),
+                asks: update.asks.iter().map(Level::from).collect(),
+            },
+        }
+    }
+}
+
+// Ticker of a book message.
+pub fn symbol(message: &L2) -> &str {
+    match message {
+        L2::Orderbook(book) => &book.symbol,
+        L2::Update(update) => &update.symbol,
+    }
+}
+
+// Level 2 order book of a single ticker truncated to a fixed depth.
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    // maximal number of levels kept on each side
+    depth: usize,
+
+    // bid levels, best (highest) first
+    bids: Vec<Level>,
+    // ask levels, best (lowest) first
+    asks: Vec<Level>,
+}
+
+impl OrderBook {
+    pub fn new(depth: usize) -> OrderBook {
+        OrderBook {
+            depth,
+            bids: Vec::with_capacity(depth),
+            asks: Vec::with_capacity(depth),
+        }
+    }
+
+    // Replace the whole book.
+    pub fn snapshot(&mut self, bids: Vec<Level>, asks: Vec<Level>) {
+        self.bids.clear();
+        self.asks.clear();
+        for level in bids {
+            self.update(Side::Buy, level);
+        }
+        for level in asks {
+            self.update(Side::Sell, level);
+        }
+    }
+
+    // Insert or replace a price level, a null volume removes the level.
+    pub fn update(&mut self, side: Side, level: Level) {
+        let levels = match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        let better = |price: f64| match side {
+            Side::Buy => price > level.price,
+            Side::Sell => price < level.price,
+        };
+
+        match levels.iter().position(|existing| !better(existing.price)) {
+            Some(index) if levels[index].price == level.price => {
+                if level.volume > 0.0 {
+                    levels[index] = level;
+                } else {
+                    levels.remove(index);
+                }
+            }
+            Some(index) if level.volume > 0.0 => levels.insert(index, level),
+            None if level.volume > 0.0 => levels.push(level),
+            _ => (),
+        }
+        levels.truncate(self.depth);
+    }
+
+    pub fn apply(&mut self, update: &BookUpdate) {
+        if update.snapshot {
+            self.snapshot(update.bids.clone(), update.asks.clone());
+            return;
+        }
+        for bid in &update.bids {
+            self.update(Side::Buy, *bid);
+        }
+        for ask in &update.asks {
+            self.update(Side::Sell, *ask);
+        }
+    }
+
+    pub fn bids(&self) -> &[Level] {
+        &self.bids
+    }
+
+    pub fn asks(&self) -> &[Level] {
+        &self.asks
+    }
+
+    pub fn best_bid(&self) -> Option<Level> {
+        self.bids.first().copied()
+    }
+
+    pub fn best_ask(&self) -> Option<Level> {
+        self.asks.first().copied()
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_bid()?.price + self.best_ask()?.price) / 2.0)
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.best_ask()?.price - self.best_bid()?.price)
+    }
+}
@@ -0,0 +1,251 @@
+use crate::clock;
+use crate::metrics;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+use reqwest::Client;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tokio::time::interval;
+
+use tracing::{info, warn};
+
+use std::fs;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CalendarConfig {
+    // URL or file of the economic calendar, an ICS calendar or a JSON list of events like
+    // {"title": "CPI m/m", "country": "USD", "date": "2025-01-15T08:30:00-05:00", "impact": "High"},
+    // no entry is blocked without it
+    pub feed: Option<String>,
+    // time between reloads of the feed (in s)
+    pub period: u64,
+    // impacts of the events blocking entries, events of an unknown impact always do
+    pub impacts: Vec<String>,
+    // currencies of the events blocking entries, all when empty
+    pub currencies: Vec<String>,
+    // time before and after an event during which no position is entered (in min)
+    pub before: i64,
+    pub after: i64,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> CalendarConfig {
+        CalendarConfig {
+            feed: None,
+            period: 3600,
+            impacts: vec![String::from("high")],
+            currencies: Vec::new(),
+            before: 30,
+            after: 30,
+        }
+    }
+}
+
+// Scheduled economic release.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Release {
+    // unix time (in s)
+    pub time: i64,
+    pub title: String,
+    pub impact: Option<String>,
+    pub currency: Option<String>,
+}
+
+impl CalendarConfig {
+    fn keeps(&self, release: &Release) -> bool {
+        let impact = release.impact.as_ref().is_none_or(|impact| {
+            self.impacts
+                .iter()
+                .any(|kept| kept.eq_ignore_ascii_case(impact))
+        });
+        let currency = self.currencies.is_empty()
+            || release.currency.as_ref().is_some_and(|currency| {
+                self.currencies
+                    .iter()
+                    .any(|kept| kept.eq_ignore_ascii_case(currency))
+            });
+        impact && currency
+    }
+}
+
+// Unix time (in s) of an ICS date, times without a zone are taken as UTC and whole days start at
+// midnight UTC.
+fn ics_time(value: &str) -> Option<i64> {
+    let value = value.trim_end_matches('Z');
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Some(time.and_utc().timestamp());
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+}
+
+// Events of an ICS calendar, their impact read from X-IMPACT or CATEGORIES and their currency from
+// X-CURRENCY or LOCATION.
+fn parse_ics(content: &str) -> Vec<Release> {
+    // long lines are folded onto lines starting with a space or a tab
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    let mut releases = Vec::new();
+    let mut current: Option<(Option<i64>, Release)> = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // parameters like ;TZID=... follow the property name
+        let name = name.split(';').next().unwrap_or(name).to_uppercase();
+        let value = value.trim().to_string();
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value == "VEVENT" => {
+                current = Some((
+                    None,
+                    Release {
+                        time: 0,
+                        title: String::new(),
+                        impact: None,
+                        currency: None,
+                    },
+                ))
+            }
+            ("END", Some(_)) if value == "VEVENT" => {
+                if let Some((Some(time), release)) = current.take() {
+                    releases.push(Release { time, ..release });
+                }
+            }
+            ("DTSTART", Some((time, _))) => *time = ics_time(&value),
+            ("SUMMARY", Some((_, release))) => release.title = value,
+            ("X-IMPACT" | "CATEGORIES", Some((_, release))) if release.impact.is_none() => {
+                release.impact = Some(value)
+            }
+            ("X-CURRENCY" | "LOCATION", Some((_, release))) if release.currency.is_none() => {
+                release.currency = Some(value)
+            }
+            _ => {}
+        }
+    }
+    releases
+}
+
+// Events of a JSON list, dated by RFC 3339 strings or unix times (in s).
+fn parse_json(content: &str) -> Result<Vec<Release>, String> {
+    let body: Value = serde_json::from_str(content).map_err(|error| error.to_string())?;
+    let Some(events) = body.as_array().or_else(|| body["events"].as_array()) else {
+        return Err(String::from("No list of events"));
+    };
+    let text = |event: &Value, keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| event[*key].as_str())
+            .map(String::from)
+    };
+    Ok(events
+        .iter()
+        .filter_map(|event| {
+            let date = &event["date"];
+            let time = match date.as_i64() {
+                Some(time) => time,
+                None => DateTime::parse_from_rfc3339(date.as_str()?)
+                    .ok()?
+                    .timestamp(),
+            };
+            Some(Release {
+                time,
+                title: text(event, &["title", "name"]).unwrap_or_default(),
+                impact: text(event, &["impact"]),
+                currency: text(event, &["currency", "country"]),
+            })
+        })
+        .collect())
+}
+
+async fn fetch(client: &Client, feed: &str) -> Result<Vec<Release>, String> {
+    let content = if feed.starts_with("http://") || feed.starts_with("https://") {
+        match client.get(feed).send().await {
+            Ok(response) => match response.text().await {
+                Ok(content) => content,
+                Err(error) => return Err(format!("Invalid calendar response: {:?}", error)),
+            },
+            Err(error) => return Err(format!("{:?}", error)),
+        }
+    } else {
+        match fs::read_to_string(feed) {
+            Ok(content) => content,
+            Err(error) => return Err(format!("Could not read {:?}: {:?}", feed, error)),
+        }
+    };
+    if content.trim_start().starts_with("BEGIN:VCALENDAR") {
+        return Ok(parse_ics(&content));
+    }
+    parse_json(&content).map_err(|message| format!("Could not parse {:?}: {}", feed, message))
+}
+
+// Releases blocking entries with the window around them (in s).
+#[derive(Debug, Clone, Default)]
+struct Blackouts {
+    releases: Vec<Release>,
+    before: i64,
+    after: i64,
+}
+
+fn blackouts() -> &'static RwLock<Blackouts> {
+    static BLACKOUTS: OnceLock<RwLock<Blackouts>> = OnceLock::new();
+    BLACKOUTS.get_or_init(RwLock::default)
+}
+
+// Release whose blackout window covers a unix time (in s), None when entries are allowed.
+pub fn blackout(time: i64) -> Option<Release> {
+    let blackouts = blackouts().read().ok()?;
+    blackouts
+        .releases
+        .iter()
+        .find(|release| {
+            release.time - blackouts.before <= time && time <= release.time + blackouts.after
+        })
+        .cloned()
+}
+
+// Periodically reload the economic calendar, keeping the releases of the configured impacts and
+// currencies that have not passed. The previous releases are kept when the feed is unavailable.
+pub async fn run(config: CalendarConfig) {
+    let Some(feed) = config.feed.clone() else {
+        return;
+    };
+    let client = Client::new();
+    let mut ticker = interval(Duration::from_secs(config.period.max(60)));
+    loop {
+        ticker.tick().await;
+        let mut releases = match fetch(&client, &feed).await {
+            Ok(releases) => releases,
+            Err(message) => {
+                warn!("Could not load the economic calendar: {}", message);
+                continue;
+            }
+        };
+        let after = config.after * 60;
+        let now = clock::seconds();
+        releases.retain(|release| release.time + after >= now && config.keeps(release));
+        releases.sort_by_key(|release| release.time);
+        info!(
+            "{} releases in the economic calendar, next {:?}",
+            releases.len(),
+            releases.first().map(|release| &release.title)
+        );
+        metrics::set("calendar.releases", releases.len() as f64);
+        if let Ok(mut blackouts) = blackouts().write() {
+            *blackouts = Blackouts {
+                releases,
+                before: config.before * 60,
+                after,
+            };
+        }
+    }
+}
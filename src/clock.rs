@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Source of the current time. Time is read through the installed clock so that installing a
+// simulated one makes tests and replays deterministic.
+pub trait Clock: Send + Sync {
+    // unix time (in s) with sub second precision
+    fn now(&self) -> f64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_secs_f64())
+    }
+}
+
+// Clock only moving when told to, e.g. to the time of the candle being processed.
+#[derive(Debug, Default)]
+pub struct SimulatedClock {
+    // bits of the current unix time (in s)
+    time: AtomicU64,
+}
+
+impl SimulatedClock {
+    pub fn new(time: f64) -> SimulatedClock {
+        SimulatedClock {
+            time: AtomicU64::new(time.to_bits()),
+        }
+    }
+
+    pub fn set(&self, time: f64) {
+        self.time.store(time.to_bits(), Ordering::Release);
+    }
+
+    pub fn advance(&self, seconds: f64) {
+        // the closure always returns a value so the update cannot fail
+        let _ = self
+            .time
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |time| {
+                Some((f64::from_bits(time) + seconds).to_bits())
+            });
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> f64 {
+        f64::from_bits(self.time.load(Ordering::Acquire))
+    }
+}
+
+fn installed() -> &'static RwLock<Arc<dyn Clock>> {
+    static CLOCK: OnceLock<RwLock<Arc<dyn Clock>>> = OnceLock::new();
+    CLOCK.get_or_init(|| RwLock::new(Arc::new(SystemClock)))
+}
+
+// Replace the process wide clock, the system clock is used until then.
+pub fn install(clock: Arc<dyn Clock>) {
+    if let Ok(mut installed) = installed().write() {
+        *installed = clock;
+    }
+}
+
+// Current unix time (in s) of the installed clock.
+pub fn now() -> f64 {
+    match installed().read() {
+        Ok(clock) => clock.now(),
+        Err(_) => SystemClock.now(),
+    }
+}
+
+// Current unix time in whole seconds.
+pub fn seconds() -> i64 {
+    now().floor() as i64
+}
@@ -0,0 +1,646 @@
+use crate::accounting::CostBasis;
+use crate::accounts::{self, AccountConfig};
+use crate::alerts::AlertConfig;
+use crate::anomalies::AnomalyConfig;
+use crate::api::ApiConfig;
+use crate::arbitrage::ScannerConfig;
+use crate::backtest::Shorting;
+use crate::balances::BalanceConfig;
+use crate::calendar::CalendarConfig;
+use crate::control::ControlConfig;
+use crate::conversion::ConversionConfig;
+use crate::cooldown::CooldownConfig;
+use crate::environment::EnvironmentConfig;
+use crate::export::ExportConfig;
+use crate::feeds::{BufferConfig, DEPTHS, INTERVALS};
+use crate::fills::FillConfig;
+use crate::gaps::GapPolicy;
+use crate::health::HealthConfig;
+use crate::history::HistoryConfig;
+use crate::indicators::snapshot::{self, IndicatorConfig};
+use crate::instruments::InstrumentConfig;
+use crate::latency::LatencyConfig;
+use crate::logging::LoggingConfig;
+use crate::margin::MarginConfig;
+use crate::market::CandleUpdates;
+use crate::nonce::NonceConfig;
+use crate::optimizer::Parameter;
+use crate::orders::ChaseConfig;
+use crate::random::RandomConfig;
+use crate::retry::RetryConfig;
+use crate::risk::RiskConfig;
+use crate::secrets::SecretsConfig;
+use crate::sessions::TradingHours;
+use crate::state::StateConfig;
+use crate::strategies::ensemble::Rule;
+use crate::strategies::portfolio::Allocation;
+use crate::summary::SummaryConfig;
+use crate::synchronizer::SyncConfig;
+use crate::transforms::View;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::sync::watch;
+use tokio::time::interval;
+
+use toml::Value;
+
+use tracing::{info, warn};
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// Top level sections that can be changed while running, changes to other sections are only
+// picked up on restart.
+const LIVE_SECTIONS: &[&str] = &["strategies", "risk", "alerts"];
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    // file the trading activity is appended to
+    pub journal: PathBuf,
+    pub feed: FeedConfig,
+    pub strategies: Vec<NamedConfig>,
+    // indicators computed on every candle, published as snapshots per pair
+    pub indicators: Vec<IndicatorConfig>,
+    pub runner: RunnerConfig,
+    pub risk: RiskConfig,
+    pub control: ControlConfig,
+    pub accounting: AccountingConfig,
+    pub balances: BalanceConfig,
+    // queries of the executions of the orders
+    pub fills: FillConfig,
+    pub alerts: AlertConfig,
+    pub api: ApiConfig,
+    pub optimizer: OptimizerConfig,
+    pub logging: LoggingConfig,
+    pub instruments: InstrumentConfig,
+    pub export: ExportConfig,
+    pub history: HistoryConfig,
+    pub conversion: ConversionConfig,
+    pub margin: MarginConfig,
+    pub retry: RetryConfig,
+    pub nonce: NonceConfig,
+    pub secrets: SecretsConfig,
+    // accounts besides the main one, with the strategies trading on them
+    pub accounts: Vec<AccountConfig>,
+    // exchange environment traded on, production or demo
+    pub environment: EnvironmentConfig,
+    pub state: StateConfig,
+    // economic releases around which no position is entered
+    pub calendar: CalendarConfig,
+    // report of the trading day sent when it closes
+    pub summary: SummaryConfig,
+    // thresholds of the liveness and readiness probes of the API
+    pub health: HealthConfig,
+    // seed of the random draws of simulations and searches, for reproducible runs
+    pub random: RandomConfig,
+    // short selling allowed in backtests, positions cannot go below zero without it
+    pub shorting: Option<Shorting>,
+    // triangles of pairs scanned for arbitrage on their quotes while trading
+    pub triangles: Vec<ScannerConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            journal: PathBuf::from("trade-bot.journal"),
+            feed: FeedConfig::default(),
+            strategies: Vec::new(),
+            indicators: Vec::new(),
+            runner: RunnerConfig::default(),
+            risk: RiskConfig::default(),
+            control: ControlConfig::default(),
+            accounting: AccountingConfig::default(),
+            balances: BalanceConfig::default(),
+            fills: FillConfig::default(),
+            alerts: AlertConfig::default(),
+            api: ApiConfig::default(),
+            optimizer: OptimizerConfig::default(),
+            logging: LoggingConfig::default(),
+            instruments: InstrumentConfig::default(),
+            export: ExportConfig::default(),
+            history: HistoryConfig::default(),
+            conversion: ConversionConfig::default(),
+            margin: MarginConfig::default(),
+            retry: RetryConfig::default(),
+            nonce: NonceConfig::default(),
+            secrets: SecretsConfig::default(),
+            accounts: Vec::new(),
+            environment: EnvironmentConfig::default(),
+            state: StateConfig::default(),
+            calendar: CalendarConfig::default(),
+            summary: SummaryConfig::default(),
+            health: HealthConfig::default(),
+            random: RandomConfig::default(),
+            shorting: None,
+            triangles: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    // Load a TOML configuration file, missing sections and fields take their default values.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) => return Err(format!("Could not read {:?}: {:?}", path, error)),
+        };
+        let config: Config = match toml::from_str(&content) {
+            Ok(config) => config,
+            Err(error) => return Err(format!("Could not parse {:?}: {}", path, error)),
+        };
+        config.feed.validate()?;
+        accounts::validate(&config.accounts)?;
+        validate(&config.strategies)?;
+        snapshot::validate(&config.indicators)?;
+        Ok(config)
+    }
+
+    // Pairs followed from the start, those configured and those the strategies are bound to.
+    pub fn tickers(&self) -> Vec<String> {
+        let mut tickers = self.feed.pairs.clone();
+        for config in &self.strategies {
+            tickers.extend(config.strategy.tickers().unwrap_or_default());
+        }
+        tickers.sort();
+        tickers.dedup();
+        tickers
+    }
+
+    fn to_value(&self) -> Result<Value, String> {
+        match Value::try_from(self) {
+            Ok(value) => Ok(value),
+            Err(error) => Err(format!("{}", error)),
+        }
+    }
+}
+
+// Check that no two strategies are given the same identifier.
+fn validate(strategies: &[NamedConfig]) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    for id in strategies.iter().filter_map(|config| config.id.as_ref()) {
+        if !ids.insert(id) {
+            return Err(format!("Strategy {} configured twice", id));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FeedConfig {
+    // seconds without any message, heartbeats included, after which the connection is replaced
+    pub watchdog: u64,
+    // candle interval (in min), one of Kraken's intervals
+    pub interval: i32,
+    // price levels per side of the order books followed, one of Kraken's depths
+    pub depth: i32,
+    // messages received but not yet processed
+    pub buffer: BufferConfig,
+    // handling of candles missing from the live feed
+    pub gap_policy: GapPolicy,
+    // sanity checks of the received candles
+    pub anomalies: AnomalyConfig,
+    // timing of the received data against the local clock
+    pub latency: LatencyConfig,
+    // combination of the closed candles of the followed pairs per time
+    pub sync: SyncConfig,
+    // pairs followed from the start, e.g. BTC/EUR, along with those the strategies are bound to,
+    // the strategies not bound to given pairs trading each of them
+    pub pairs: Vec<String>,
+}
+
+impl Default for FeedConfig {
+    fn default() -> FeedConfig {
+        FeedConfig {
+            watchdog: 10,
+            interval: 5,
+            depth: 10,
+            buffer: BufferConfig::default(),
+            gap_policy: GapPolicy::default(),
+            anomalies: AnomalyConfig::default(),
+            latency: LatencyConfig::default(),
+            sync: SyncConfig::default(),
+            pairs: vec!["ETH/EUR".to_string()],
+        }
+    }
+}
+
+impl FeedConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !INTERVALS.contains(&self.interval) {
+            return Err(format!(
+                "Unsupported candle interval {}, expected one of {:?}",
+                self.interval, INTERVALS
+            ));
+        }
+        if !DEPTHS.contains(&self.depth) {
+            return Err(format!(
+                "Unsupported book depth {}, expected one of {:?}",
+                self.depth, DEPTHS
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Strategy to run live, selected by its `kind`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StrategyConfig {
+    Pairs {
+        dependent: String,
+        independent: String,
+        window: usize,
+        entry: f64,
+        exit: f64,
+        volume: f64,
+        #[serde(default)]
+        min_correlation: f64,
+    },
+    Script {
+        path: PathBuf,
+        // number of closes kept per ticker
+        capacity: usize,
+    },
+    Wasm {
+        path: PathBuf,
+        tickers: Vec<String>,
+    },
+    // switch between trend following and mean reversion strategies on the Hurst exponent
+    Regime {
+        // number of returns the exponent is estimated over
+        window: usize,
+        // exponent bounds of the mean reverting and trending behaviours
+        low: f64,
+        high: f64,
+        #[serde(default)]
+        trending: Vec<StrategyConfig>,
+        #[serde(default)]
+        mean_reverting: Vec<StrategyConfig>,
+    },
+    // run a strategy on Heikin-Ashi candles or Renko bricks
+    Transformed {
+        view: View,
+        strategy: Box<StrategyConfig>,
+    },
+    // only let a strategy trade within trading hours
+    Scheduled {
+        hours: TradingHours,
+        strategy: Box<StrategyConfig>,
+    },
+    // buy an amount of quote currency worth of a ticker at every opening of trading hours
+    Dca {
+        ticker: String,
+        amount: f64,
+        #[serde(default)]
+        hours: TradingHours,
+    },
+    // quote both sides of a ticker around the mid-price of its book, skewed against the inventory
+    MarketMaking {
+        ticker: String,
+        // distance of the quotes to the reservation price as a fraction of mid-price
+        half_spread: f64,
+        // shift of the reservation price per unit of inventory as a fraction of mid-price
+        skew: f64,
+        // volume quoted on each side
+        volume: f64,
+        // absolute inventory beyond which the side increasing it is pulled
+        max_inventory: f64,
+        // relative price change required before a resting quote is replaced
+        #[serde(default)]
+        tolerance: f64,
+    },
+    // split capital among strategies and net their orders
+    Portfolio {
+        allocation: Allocation,
+        members: Vec<MemberConfig>,
+    },
+    // only trade when enough strategies agree
+    Ensemble {
+        rule: Rule,
+        // candles a vote stays valid for
+        #[serde(default = "single_candle")]
+        memory: usize,
+        voters: Vec<VoterConfig>,
+    },
+}
+
+impl StrategyConfig {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StrategyConfig::Pairs { .. } => "pairs",
+            StrategyConfig::Script { .. } => "script",
+            StrategyConfig::Wasm { .. } => "wasm",
+            StrategyConfig::Regime { .. } => "regime",
+            StrategyConfig::Transformed { .. } => "transformed",
+            StrategyConfig::Scheduled { .. } => "scheduled",
+            StrategyConfig::Dca { .. } => "dca",
+            StrategyConfig::MarketMaking { .. } => "market_making",
+            StrategyConfig::Portfolio { .. } => "portfolio",
+            StrategyConfig::Ensemble { .. } => "ensemble",
+        }
+    }
+
+    // Whether the strategy quotes on the order book of its ticker rather than trading on candles,
+    // it is then run along the book updates instead of by the runner.
+    pub fn quotes(&self) -> bool {
+        matches!(self, StrategyConfig::MarketMaking { .. })
+    }
+
+    // Tickers the strategy is bound to, None for strategies applying to any ticker.
+    pub fn tickers(&self) -> Option<Vec<String>> {
+        match self {
+            StrategyConfig::Pairs {
+                dependent,
+                independent,
+                ..
+            } => Some(vec![dependent.clone(), independent.clone()]),
+            StrategyConfig::Script { .. } => None,
+            StrategyConfig::Wasm { tickers, .. } => Some(tickers.clone()),
+            StrategyConfig::Regime {
+                trending,
+                mean_reverting,
+                ..
+            } => {
+                let mut bound = Vec::new();
+                for strategy in trending.iter().chain(mean_reverting) {
+                    bound.extend(strategy.tickers()?);
+                }
+                Some(bound)
+            }
+            StrategyConfig::Transformed { strategy, .. }
+            | StrategyConfig::Scheduled { strategy, .. } => strategy.tickers(),
+            StrategyConfig::Dca { ticker, .. } | StrategyConfig::MarketMaking { ticker, .. } => {
+                Some(vec![ticker.clone()])
+            }
+            StrategyConfig::Portfolio { members, .. } => {
+                let mut bound = Vec::new();
+                for member in members {
+                    bound.extend(member.strategy.tickers()?);
+                }
+                Some(bound)
+            }
+            StrategyConfig::Ensemble { voters, .. } => {
+                let mut bound = Vec::new();
+                for voter in voters {
+                    bound.extend(voter.strategy.tickers()?);
+                }
+                Some(bound)
+            }
+        }
+    }
+}
+
+// Strategy run live with the identifier its orders are journaled and its state is kept under,
+// given alongside its settings. Without one it is named after its kind and position, e.g. sma#0,
+// which changes when strategies are inserted before it.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NamedConfig {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub strategy: StrategyConfig,
+}
+
+// Strategy of a portfolio with its allocation weight, given alongside its settings.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MemberConfig {
+    #[serde(default = "unit_weight")]
+    pub weight: f64,
+    #[serde(flatten)]
+    pub strategy: StrategyConfig,
+}
+
+fn unit_weight() -> f64 {
+    1.0
+}
+
+// Strategy of an ensemble with its voting weight, given alongside its settings.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct VoterConfig {
+    #[serde(default = "unit_weight")]
+    pub weight: f64,
+    // whether its votes against a side block it
+    #[serde(default)]
+    pub veto: bool,
+    #[serde(flatten)]
+    pub strategy: StrategyConfig,
+}
+
+fn single_candle() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RunnerConfig {
+    // number of candles buffered per worker before the dispatcher waits
+    pub queue: usize,
+    // single market orders of the strategies are worked as limits chasing the best price when given
+    pub chase: Option<ChaseConfig>,
+    // latest stored candles per ticker the strategies are started with
+    pub warmup: usize,
+    // rules holding back the entries of the strategies
+    pub cooldown: CooldownConfig,
+    // candles the strategies, the risk guard and the managed orders are fed, only the closed ones
+    // unless every update of the forming candles is asked for
+    pub candles: CandleUpdates,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> RunnerConfig {
+        RunnerConfig {
+            queue: 64,
+            chase: None,
+            warmup: 0,
+            cooldown: CooldownConfig::default(),
+            candles: CandleUpdates::Closed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccountingConfig {
+    // lots sales are matched against in tax exports
+    pub cost_basis: CostBasis,
+}
+
+// Settings of the genetic strategy parameter optimizer.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OptimizerConfig {
+    // ranges of the strategy settings explored, by name
+    pub parameters: Vec<Parameter>,
+    // number of parameter sets evaluated per generation
+    pub population: usize,
+    pub generations: usize,
+    // probability for each parameter of a child to be mutated
+    pub mutation_rate: f64,
+    // maximal size of a mutation as a fraction of the parameter range
+    pub mutation_scale: f64,
+    // probability for a child to mix both parents rather than copy the first one
+    pub crossover_rate: f64,
+    // number of best parameter sets carried over unchanged to the next generation
+    pub elitism: usize,
+    // number of contestants drawn when selecting a parent
+    pub tournament: usize,
+    // weight of the maximal drawdown subtracted from the Sharpe ratio in the fitness
+    pub drawdown_penalty: f64,
+}
+
+impl Default for OptimizerConfig {
+    fn default() -> OptimizerConfig {
+        OptimizerConfig {
+            parameters: Vec::new(),
+            population: 50,
+            generations: 30,
+            mutation_rate: 0.1,
+            mutation_scale: 0.2,
+            crossover_rate: 0.7,
+            elitism: 2,
+            tournament: 3,
+            drawdown_penalty: 1.0,
+        }
+    }
+}
+
+// Change of a single configuration field, keys are dotted paths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub key: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+fn diff(before: Option<&Value>, after: Option<&Value>, key: String, changes: &mut Vec<Change>) {
+    match (before, after) {
+        (Some(Value::Table(before)), Some(Value::Table(after))) => {
+            let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for name in keys {
+                let path = if key.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", key, name)
+                };
+                diff(before.get(name), after.get(name), path, changes);
+            }
+        }
+        _ if before != after => changes.push(Change {
+            key,
+            before: before.cloned(),
+            after: after.cloned(),
+        }),
+        _ => (),
+    }
+}
+
+// Field level differences between two configurations.
+pub fn changes(before: &Config, after: &Config) -> Result<Vec<Change>, String> {
+    let mut changes = Vec::new();
+    diff(
+        Some(&before.to_value()?),
+        Some(&after.to_value()?),
+        String::new(),
+        &mut changes,
+    );
+    Ok(changes)
+}
+
+// Watches the configuration file and publishes the live configuration to the components holding
+// a receiver. Only changes to live sections are applied, the audit log (target `config_audit`)
+// records every applied or deferred change.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    // modification time of the last version read
+    modified: Option<SystemTime>,
+    sender: watch::Sender<Config>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf, config: Config) -> (ConfigWatcher, watch::Receiver<Config>) {
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let (sender, receiver) = watch::channel(config);
+        (
+            ConfigWatcher {
+                path,
+                modified,
+                sender,
+            },
+            receiver,
+        )
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Config> {
+        self.sender.subscribe()
+    }
+
+    // Reload the file if it changed and publish the resulting configuration. Returns the applied
+    // changes.
+    pub fn poll(&mut self) -> Result<Vec<Change>, String> {
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_none() || modified == self.modified {
+            return Ok(Vec::new());
+        }
+        self.modified = modified;
+
+        let current = self.sender.borrow().clone();
+        let loaded = Config::load(&self.path)?;
+
+        let Value::Table(mut merged) = current.to_value()? else {
+            return Err("Configuration is not a table.".into());
+        };
+        let Value::Table(mut incoming) = loaded.to_value()? else {
+            return Err("Configuration is not a table.".into());
+        };
+        for section in LIVE_SECTIONS {
+            if let Some(value) = incoming.remove(*section) {
+                merged.insert(section.to_string(), value);
+            }
+        }
+
+        let applied: Config = match Value::Table(merged).try_into() {
+            Ok(config) => config,
+            Err(error) => return Err(format!("{}", error)),
+        };
+        let applied_changes = changes(&current, &applied)?;
+        for change in &applied_changes {
+            info!(
+                target: "config_audit",
+                "Applied {}: {:?} -> {:?}", change.key, change.before, change.after
+            );
+        }
+        for change in changes(&applied, &loaded)? {
+            warn!(
+                target: "config_audit",
+                "Deferred {} until restart: {:?} -> {:?}", change.key, change.before, change.after
+            );
+        }
+
+        if !applied_changes.is_empty() {
+            self.sender.send_replace(applied);
+        }
+        Ok(applied_changes)
+    }
+
+    // Poll the file periodically, runs until every receiver is dropped.
+    pub async fn run(mut self, period: Duration) {
+        let mut ticker = interval(period);
+        while !self.sender.is_closed() {
+            ticker.tick().await;
+            if let Err(message) = self.poll() {
+                warn!(target: "config_audit", "Configuration not reloaded: {}", message);
+            }
+        }
+    }
+}
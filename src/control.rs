@@ -0,0 +1,288 @@
+use crate::execution::{Executor, Side};
+use crate::feeds::{PairChange, Pairs};
+use crate::journal::{Entry, Journal, now};
+use crate::orders::{self, Iceberg, Oco};
+use crate::risk::RiskGuard;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+
+use tracing::{info, warn};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    // unix socket administrative commands are received on
+    pub socket: PathBuf,
+}
+
+impl Default for ControlConfig {
+    fn default() -> ControlConfig {
+        ControlConfig {
+            socket: PathBuf::from("trade-bot.sock"),
+        }
+    }
+}
+
+// Administrative command overriding the strategies, sent as a line of text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    // halt trading and cancel every open order, closing every position if flattening
+    Kill { flatten: bool },
+    Resume,
+    // start or stop following a pair along with the strategies trading it
+    Subscribe(String),
+    Unsubscribe(String),
+    // exit a position at a take profit or a stop loss, whichever comes first
+    Oco(Oco),
+    // fill a limit order showing only a slice of its volume at a time
+    Iceberg(Iceberg),
+}
+
+fn side(word: &str) -> Result<Side, String> {
+    match word {
+        "buy" => Ok(Side::Buy),
+        "sell" => Ok(Side::Sell),
+        _ => Err(format!("Unknown side {:?}", word)),
+    }
+}
+
+fn word(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    }
+}
+
+fn number(word: &str) -> Result<f64, String> {
+    match word.parse() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(format!("Invalid number {:?}", word)),
+    }
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["kill"] => Ok(Command::Kill { flatten: false }),
+            ["kill", "flatten"] => Ok(Command::Kill { flatten: true }),
+            ["resume"] => Ok(Command::Resume),
+            ["subscribe", pair] => Ok(Command::Subscribe(pair.to_string())),
+            ["unsubscribe", pair] => Ok(Command::Unsubscribe(pair.to_string())),
+            ["oco", pair, exit, volume, take_profit, stop_loss] => Ok(Command::Oco(Oco {
+                ticker: pair.to_string(),
+                side: side(exit)?,
+                volume: number(volume)?,
+                take_profit: number(take_profit)?,
+                stop_loss: number(stop_loss)?,
+            })),
+            ["iceberg", pair, order, volume, visible, price] => Ok(Command::Iceberg(Iceberg {
+                ticker: pair.to_string(),
+                side: side(order)?,
+                volume: number(volume)?,
+                visible: number(visible)?,
+                price: number(price)?,
+            })),
+            _ => Err(format!("Unknown command {:?}", line)),
+        }
+    }
+
+    pub fn line(&self) -> String {
+        match self {
+            Command::Kill { flatten: false } => "kill".into(),
+            Command::Kill { flatten: true } => "kill flatten".into(),
+            Command::Resume => "resume".into(),
+            Command::Subscribe(pair) => format!("subscribe {}", pair),
+            Command::Unsubscribe(pair) => format!("unsubscribe {}", pair),
+            Command::Oco(oco) => format!(
+                "oco {} {} {} {} {}",
+                oco.ticker,
+                word(oco.side),
+                oco.volume,
+                oco.take_profit,
+                oco.stop_loss
+            ),
+            Command::Iceberg(iceberg) => format!(
+                "iceberg {} {} {} {} {}",
+                iceberg.ticker,
+                word(iceberg.side),
+                iceberg.volume,
+                iceberg.visible,
+                iceberg.price
+            ),
+        }
+    }
+}
+
+// Carry out a command and describe its outcome.
+pub async fn execute<E: Executor + Sync>(
+    command: Command,
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    pairs: &Pairs,
+) -> String {
+    match command {
+        Command::Kill { flatten } => {
+            guard.halt();
+            format!(
+                "Trading halted, {}",
+                liquidate(guard, journal, flatten).await
+            )
+        }
+        Command::Resume => {
+            guard.resume();
+            "Trading resumed".into()
+        }
+        Command::Subscribe(pair) => match pairs.change(PairChange::Add(pair)).await {
+            Ok(outcome) | Err(outcome) => outcome,
+        },
+        Command::Unsubscribe(pair) => match pairs.change(PairChange::Remove(pair)).await {
+            Ok(outcome) | Err(outcome) => outcome,
+        },
+        Command::Oco(oco) => match orders::place(guard, journal, oco).await {
+            Ok(id) => format!("Take profit {} placed, stop loss armed", id),
+            Err(message) => format!("OCO not placed: {}", message),
+        },
+        Command::Iceberg(iceberg) => match orders::place_iceberg(guard, journal, iceberg).await {
+            Ok(id) => format!("First iceberg slice {} placed", id),
+            Err(message) => format!("Iceberg not placed: {}", message),
+        },
+    }
+}
+
+// Cancel every open order and close every position if flattening, overriding the strategies.
+// Every order sent or cancelled is journaled, the outcome is described.
+pub async fn liquidate<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    flatten: bool,
+) -> String {
+    let mut entries = Vec::new();
+    let mut failures = Vec::new();
+    let cancels = guard.cancel_all().await;
+    for (id, result) in &cancels {
+        match result {
+            Ok(()) => entries.push(Entry::Cancel {
+                time: now(),
+                id: id.clone(),
+                simulated: guard.simulated(),
+            }),
+            Err(message) => failures.push(format!("cancel {}: {}", id, message)),
+        }
+    }
+    let flattened = if flatten {
+        guard.flatten().await
+    } else {
+        Vec::new()
+    };
+    for (order, result) in &flattened {
+        match result {
+            Ok(id) => entries.push(Entry::Order {
+                time: now(),
+                id: id.clone(),
+                order: order.clone(),
+                simulated: guard.simulated(),
+                strategy: None,
+                decision: guard.price(&order.ticker),
+            }),
+            Err(message) => failures.push(format!("close {}: {}", order.ticker, message)),
+        }
+    }
+
+    match journal.lock() {
+        Ok(mut journal) => {
+            for entry in &entries {
+                if let Err(message) = journal.record(entry) {
+                    warn!("{}", message);
+                }
+            }
+        }
+        Err(_) => warn!("Journal lock poisoned, liquidation orders not journaled"),
+    }
+
+    let mut report = format!(
+        "{} orders cancelled, {} positions closed",
+        cancels.len() - cancels.iter().filter(|(_, result)| result.is_err()).count(),
+        flattened.len()
+            - flattened
+                .iter()
+                .filter(|(_, result)| result.is_err())
+                .count(),
+    );
+    if !failures.is_empty() {
+        report += &format!(", failed: {}", failures.join("; "));
+    }
+    report
+}
+
+// Listen for commands on a unix socket, answering each with its outcome.
+pub async fn serve<E: Executor + Sync>(
+    socket: &Path,
+    guard: Arc<RiskGuard<E>>,
+    journal: Arc<Mutex<Journal>>,
+    pairs: Pairs,
+) -> Result<(), String> {
+    // a socket left behind by a previous run prevents binding
+    if socket.exists()
+        && let Err(error) = fs::remove_file(socket)
+    {
+        return Err(format!("Could not remove {:?}: {:?}", socket, error));
+    }
+    let listener = match UnixListener::bind(socket) {
+        Ok(listener) => listener,
+        Err(error) => return Err(format!("Could not bind {:?}: {:?}", socket, error)),
+    };
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                warn!("Could not accept control connection: {:?}", error);
+                continue;
+            }
+        };
+        let (reader, mut writer) = stream.into_split();
+        let mut line = String::new();
+        if let Err(error) = BufReader::new(reader).read_line(&mut line).await {
+            warn!("Could not read control command: {:?}", error);
+            continue;
+        }
+        let reply = match Command::parse(&line) {
+            Ok(command) => {
+                info!(command = ?command, "Received command");
+                execute(command, &guard, &journal, &pairs).await
+            }
+            Err(message) => message,
+        };
+        info!("{}", reply);
+        if let Err(error) = writer.write_all(format!("{}\n", reply).as_bytes()).await {
+            warn!("Could not answer control command: {:?}", error);
+        }
+    }
+}
+
+// Send a command to a running bot and return its answer.
+pub async fn send(socket: &Path, command: Command) -> Result<String, String> {
+    let stream = match UnixStream::connect(socket).await {
+        Ok(stream) => stream,
+        Err(error) => return Err(format!("Could not connect to {:?}: {:?}", socket, error)),
+    };
+    let (reader, mut writer) = stream.into_split();
+    if let Err(error) = writer
+        .write_all(format!("{}\n", command.line()).as_bytes())
+        .await
+    {
+        return Err(format!("Could not send command: {:?}", error));
+    }
+    let mut reply = String::new();
+    match BufReader::new(reader).read_line(&mut reply).await {
+        Ok(_) => Ok(reply.trim_end().to_string()),
+        Err(error) => Err(format!("Could not read answer: {:?}", error)),
+    }
+}
@@ -0,0 +1,124 @@
+use crate::risk::{base, quote};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, RwLock};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConversionConfig {
+    // currency equity and profits are reported in
+    pub currency: String,
+    // pairs followed only to convert between the quote currencies of the traded pairs and the
+    // reporting currency, e.g. EUR/USD when trading ETH/USD and reporting in EUR
+    pub crosses: Vec<String>,
+}
+
+impl Default for ConversionConfig {
+    fn default() -> ConversionConfig {
+        ConversionConfig {
+            currency: String::from("EUR"),
+            crosses: Vec::new(),
+        }
+    }
+}
+
+// Exchange rates implied by the latest prices of pairs, a pair giving the price of its base asset
+// in its quote currency.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Rates {
+    prices: HashMap<String, f64>,
+}
+
+impl Rates {
+    pub fn new() -> Rates {
+        Rates::default()
+    }
+
+    pub fn from_prices(prices: HashMap<String, f64>) -> Rates {
+        Rates { prices }
+    }
+
+    pub fn update(&mut self, ticker: &str, price: f64) {
+        if price.is_finite() && price > 0.0 {
+            self.prices.insert(ticker.to_string(), price);
+        }
+    }
+
+    fn direct(&self, from: &str, to: &str) -> Option<f64> {
+        if from == to {
+            return Some(1.0);
+        }
+        if let Some(price) = self.prices.get(&format!("{}/{}", from, to)) {
+            return Some(*price);
+        }
+        self.prices
+            .get(&format!("{}/{}", to, from))
+            .map(|price| 1.0 / price)
+    }
+
+    // Amount of a currency one unit of another is worth, directly through a pair on both or
+    // through a currency both have a pair with, None when no such pair has a price.
+    pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        if let Some(rate) = self.direct(from, to) {
+            return Some(rate);
+        }
+        self.prices
+            .keys()
+            .flat_map(|ticker| [base(ticker), quote(ticker)])
+            .filter(|through| *through != from && *through != to)
+            .find_map(|through| Some(self.direct(from, through)? * self.direct(through, to)?))
+    }
+
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Option<f64> {
+        Some(amount * self.rate(from, to)?)
+    }
+
+    // Sum of amounts per currency in a single one, None when one of them cannot be converted.
+    pub fn total(&self, amounts: &HashMap<String, f64>, to: &str) -> Option<f64> {
+        amounts
+            .iter()
+            .map(|(currency, amount)| self.convert(*amount, currency, to))
+            .sum()
+    }
+}
+
+fn reporting() -> &'static RwLock<String> {
+    static CURRENCY: OnceLock<RwLock<String>> = OnceLock::new();
+    CURRENCY.get_or_init(|| RwLock::new(ConversionConfig::default().currency))
+}
+
+fn rates() -> &'static Mutex<Rates> {
+    static RATES: OnceLock<Mutex<Rates>> = OnceLock::new();
+    RATES.get_or_init(Mutex::default)
+}
+
+// Set the process wide reporting currency, EUR is used until then.
+pub fn install(config: &ConversionConfig) {
+    if let Ok(mut currency) = reporting().write() {
+        *currency = config.currency.clone();
+    }
+}
+
+// Currency equity and profits are reported in.
+pub fn currency() -> String {
+    reporting()
+        .read()
+        .map(|currency| currency.clone())
+        .unwrap_or_else(|_| ConversionConfig::default().currency)
+}
+
+// Record the latest price of a pair in the process wide rates.
+pub fn mark(ticker: &str, price: f64) {
+    if let Ok(mut rates) = rates().lock() {
+        rates.update(ticker, price);
+    }
+}
+
+// Value of an amount of a currency in the reporting currency at the latest rates, None until the
+// pairs linking them have a price.
+pub fn to_reporting(amount: f64, from: &str) -> Option<f64> {
+    let to = currency();
+    rates().lock().ok()?.convert(amount, from, &to)
+}
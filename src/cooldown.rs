@@ -0,0 +1,189 @@
+use crate::calendar;
+use crate::execution::Order;
+use crate::metrics;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+const DAY: i64 = 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CooldownConfig {
+    // candles of a ticker between two entries of a strategy on it
+    pub min_bars: usize,
+    // distance to the price of the last exit of a strategy on a ticker (relative to it) within
+    // which it does not enter again
+    pub reentry_distance: f64,
+    // entries of a strategy per UTC day
+    pub max_daily_entries: Option<u32>,
+}
+
+// Rule holding back the entry of a strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Block {
+    MinBars,
+    Reentry,
+    DailyLimit,
+    Blackout,
+}
+
+impl Block {
+    fn name(&self) -> &'static str {
+        match self {
+            Block::MinBars => "min_bars",
+            Block::Reentry => "reentry",
+            Block::DailyLimit => "daily_limit",
+            Block::Blackout => "blackout",
+        }
+    }
+}
+
+// Position of a strategy on a ticker as its orders went through.
+#[derive(Debug, Clone, Copy, Default)]
+struct Book {
+    // signed base volume
+    position: f64,
+    // candle of the ticker the last entry was made on
+    entered: Option<usize>,
+    // price the position was last closed at
+    exited: Option<f64>,
+}
+
+// Filters between the signals of the strategies and the risk guard. Orders growing the position
+// of a strategy on a ticker are entries and go through the rules, orders reducing it are exits
+// and always do. A signal is held back whole when one of its entries is.
+#[derive(Debug, Clone, Default)]
+pub struct Cooldowns {
+    config: CooldownConfig,
+    // candles seen per ticker
+    bars: HashMap<String, usize>,
+    books: HashMap<(String, String), Book>,
+    // day and number of entries of each strategy on it
+    entries: HashMap<String, (i64, u32)>,
+}
+
+impl Cooldowns {
+    pub fn new(config: CooldownConfig) -> Cooldowns {
+        Cooldowns {
+            config,
+            ..Cooldowns::default()
+        }
+    }
+
+    pub fn on_candle(&mut self, ticker: &str) {
+        *self.bars.entry(ticker.to_string()).or_default() += 1;
+    }
+
+    fn book(&self, strategy: &str, ticker: &str) -> Book {
+        self.books
+            .get(&(strategy.to_string(), ticker.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn entering(&self, strategy: &str, order: &Order) -> bool {
+        let position = self.book(strategy, &order.ticker).position;
+        position == 0.0 || position.signum() == order.side.sign()
+    }
+
+    fn rule(
+        &self,
+        strategy: &str,
+        orders: &[Order],
+        time: i64,
+        price: impl Fn(&Order) -> Option<f64>,
+    ) -> Option<Block> {
+        let entries: Vec<&Order> = orders
+            .iter()
+            .filter(|order| self.entering(strategy, order))
+            .collect();
+        if entries.is_empty() {
+            return None;
+        }
+        if calendar::blackout(time).is_some() {
+            return Some(Block::Blackout);
+        }
+        if let Some(max) = self.config.max_daily_entries {
+            let today = self
+                .entries
+                .get(strategy)
+                .filter(|(day, _)| *day == time.div_euclid(DAY))
+                .map_or(0, |(_, count)| *count);
+            if today >= max {
+                return Some(Block::DailyLimit);
+            }
+        }
+        for order in entries {
+            let book = self.book(strategy, &order.ticker);
+            let bars = self.bars.get(&order.ticker).copied().unwrap_or(0);
+            if book
+                .entered
+                .is_some_and(|entered| bars - entered < self.config.min_bars)
+            {
+                return Some(Block::MinBars);
+            }
+            if let (Some(exited), Some(price)) = (book.exited, price(order))
+                && (price - exited).abs() < self.config.reentry_distance * exited
+            {
+                return Some(Block::Reentry);
+            }
+        }
+        None
+    }
+
+    // Rule the orders of a strategy decided at prices are held back by at a unix time (in s),
+    // None when they may go through. Held back signals are counted in the metrics.
+    pub fn check(
+        &self,
+        strategy: &str,
+        orders: &[Order],
+        time: i64,
+        price: impl Fn(&Order) -> Option<f64>,
+    ) -> Option<Block> {
+        let block = self.rule(strategy, orders, time, price)?;
+        metrics::increment(&format!("cooldown.{}", block.name()), 1);
+        metrics::increment(&format!("strategy.{}.cooldown", strategy), 1);
+        Some(block)
+    }
+
+    // Book the orders of a strategy that went through at a unix time (in s).
+    pub fn record(
+        &mut self,
+        strategy: &str,
+        orders: &[Order],
+        time: i64,
+        price: impl Fn(&Order) -> Option<f64>,
+    ) {
+        let day = time.div_euclid(DAY);
+        let mut entered = false;
+        for order in orders {
+            let entering = self.entering(strategy, order);
+            let bars = self.bars.get(&order.ticker).copied().unwrap_or(0);
+            let book = self
+                .books
+                .entry((strategy.to_string(), order.ticker.clone()))
+                .or_default();
+            let before = book.position;
+            book.position += order.side.sign() * order.volume;
+            // rounding leftovers of a closed position
+            if book.position.abs() < 1e-12 {
+                book.position = 0.0;
+            }
+            if entering {
+                book.entered = Some(bars);
+                entered = true;
+            } else if book.position == 0.0 || before.signum() != book.position.signum() {
+                book.exited = price(order).or(book.exited);
+            }
+        }
+        if entered {
+            let count = self.entries.entry(strategy.to_string()).or_insert((day, 0));
+            if count.0 != day {
+                *count = (day, 0);
+            }
+            count.1 += 1;
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::market::Candle;
+
+use chrono::NaiveDateTime;
+
+use zip::ZipArchive;
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+// Public dataset layout candles can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // Binance kline dumps, CSV files without a header or zipped ones
+    Binance,
+    // CryptoDataDownload CSV files, newest first below a banner line
+    CryptoDataDownload,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Format, String> {
+        match name {
+            "binance" => Ok(Format::Binance),
+            "cryptodatadownload" | "cdd" => Ok(Format::CryptoDataDownload),
+            _ => Err(format!(
+                "Unknown dataset format {}, expected binance or cryptodatadownload",
+                name
+            )),
+        }
+    }
+}
+
+// Unix time (in s) of a timestamp in s, ms or µs, told apart by their magnitude.
+fn seconds(timestamp: f64) -> i64 {
+    if timestamp.abs() >= 1e14 {
+        (timestamp / 1e6) as i64
+    } else if timestamp.abs() >= 1e11 {
+        (timestamp / 1e3) as i64
+    } else {
+        timestamp as i64
+    }
+}
+
+fn number(field: Option<&str>) -> Option<f64> {
+    field?.trim().trim_matches('"').parse().ok()
+}
+
+// Typical price, the volume weighted one is not provided by every dataset.
+fn typical(high: f64, low: f64, close: f64) -> f64 {
+    (high + low + close) / 3.0
+}
+
+// Rows of open time, open, high, low, close, volume, close time, quote volume and trade count
+// followed by columns not needed. Futures dumps start with a header row which is skipped.
+fn binance(content: &str) -> Result<Vec<Candle>, String> {
+    let mut candles = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let fields: Vec<&str> = line.split(',').collect();
+        let Some(time) = number(fields.first().copied()) else {
+            if index == 0 || line.trim().is_empty() {
+                continue;
+            }
+            return Err(format!("Invalid Binance row {:?}", line));
+        };
+        let values: Option<Vec<f64>> = [1, 2, 3, 4, 5, 7]
+            .into_iter()
+            .map(|column| number(fields.get(column).copied()))
+            .collect();
+        let Some(&[open, high, low, close, volume, quote]) = values.as_deref() else {
+            return Err(format!("Invalid Binance row {:?}", line));
+        };
+        candles.push(Candle {
+            time: seconds(time),
+            open,
+            high,
+            low,
+            close,
+            vwap: if volume > 0.0 {
+                quote / volume
+            } else {
+                typical(high, low, close)
+            },
+            volume,
+            count: number(fields.get(8).copied()).unwrap_or(0.0) as i64,
+        });
+    }
+    Ok(candles)
+}
+
+// Date of a row in the time zone of the dataset, as full timestamps or CryptoDataDownload's
+// older hourly format like 2020-01-01 11-PM.
+fn date(field: &str, offset: i64) -> Option<i64> {
+    let field = field.trim().trim_matches('"');
+    let date = NaiveDateTime::parse_from_str(field, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(field, "%Y-%m-%d %H:%M"))
+        .or_else(|_| NaiveDateTime::parse_from_str(&format!("{}:00", field), "%Y-%m-%d %I-%p:%M"))
+        .ok()?;
+    Some(date.and_utc().timestamp() - offset)
+}
+
+// Rows named by a header row, the unix column being in UTC and the date column in the time zone
+// of the dataset. The base volume is the first volume column and the quote volume the second.
+fn crypto_data_download(content: &str, offset: i64) -> Result<Vec<Candle>, String> {
+    let mut lines = content
+        .lines()
+        .skip_while(|line| !line.to_lowercase().contains("open"));
+    let Some(header) = lines.next() else {
+        return Err("No CryptoDataDownload header row".into());
+    };
+    let header: Vec<String> = header
+        .split(',')
+        .map(|name| name.trim().trim_matches('"').to_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let volumes: Vec<usize> = (0..header.len())
+        .filter(|index| header[*index].starts_with("volume"))
+        .collect();
+    let (Some(open), Some(high), Some(low), Some(close), Some(&volume)) = (
+        column("open"),
+        column("high"),
+        column("low"),
+        column("close"),
+        volumes.first(),
+    ) else {
+        return Err(format!(
+            "Missing CryptoDataDownload columns in {:?}",
+            header
+        ));
+    };
+    let (unix, dated) = (column("unix").or(column("unix timestamp")), column("date"));
+    if unix.is_none() && dated.is_none() {
+        return Err(format!("No time column in {:?}", header));
+    }
+    let quote = volumes.get(1).copied();
+    let count = column("tradecount");
+
+    let mut candles = Vec::new();
+    for line in lines.filter(|line| !line.trim().is_empty()) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let field = |index: usize| fields.get(index).copied();
+        let time = match (unix.and_then(|index| number(field(index))), dated) {
+            (Some(time), _) => Some(seconds(time)),
+            (None, Some(index)) => field(index).and_then(|text| date(text, offset)),
+            (None, None) => None,
+        };
+        let values: Option<Vec<f64>> = [open, high, low, close, volume]
+            .into_iter()
+            .map(|index| number(field(index)))
+            .collect();
+        let (Some(time), Some(&[open, high, low, close, volume])) = (time, values.as_deref())
+        else {
+            return Err(format!("Invalid CryptoDataDownload row {:?}", line));
+        };
+        let vwap = match quote.and_then(|index| number(field(index))) {
+            Some(quote) if volume > 0.0 => quote / volume,
+            _ => typical(high, low, close),
+        };
+        candles.push(Candle {
+            time,
+            open,
+            high,
+            low,
+            close,
+            vwap,
+            volume,
+            count: count.and_then(|index| number(field(index))).unwrap_or(0.0) as i64,
+        });
+    }
+    Ok(candles)
+}
+
+fn parse(content: &str, format: Format, offset: i64) -> Result<Vec<Candle>, String> {
+    match format {
+        Format::Binance => binance(content),
+        Format::CryptoDataDownload => crypto_data_download(content, offset),
+    }
+}
+
+// CSV files of a ZIP archive.
+fn unzip(path: &Path) -> Result<Vec<String>, String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) => return Err(format!("Could not open {:?}: {:?}", path, error)),
+    };
+    let mut archive = match ZipArchive::new(file) {
+        Ok(archive) => archive,
+        Err(error) => return Err(format!("Invalid archive {:?}: {:?}", path, error)),
+    };
+    let mut contents = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = match archive.by_index(index) {
+            Ok(entry) => entry,
+            Err(error) => return Err(format!("Invalid entry in {:?}: {:?}", path, error)),
+        };
+        if !entry.name().to_lowercase().ends_with(".csv") {
+            continue;
+        }
+        let mut content = String::new();
+        if let Err(error) = entry.read_to_string(&mut content) {
+            return Err(format!(
+                "Could not read {} of {:?}: {:?}",
+                entry.name(),
+                path,
+                error
+            ));
+        }
+        contents.push(content);
+    }
+    Ok(contents)
+}
+
+// Candles of a dataset file, plain CSV or zipped, sorted by time with duplicated times dropped.
+// Timestamps in s, ms or µs are converted to s, dates without a time zone are taken to be an
+// offset (in s) ahead of UTC.
+pub fn import(path: &Path, format: Format, offset: i64) -> Result<Vec<Candle>, String> {
+    let zipped = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+    let contents = if zipped {
+        unzip(path)?
+    } else {
+        match fs::read_to_string(path) {
+            Ok(content) => vec![content],
+            Err(error) => return Err(format!("Could not read {:?}: {:?}", path, error)),
+        }
+    };
+    let mut candles = Vec::new();
+    for content in contents {
+        candles.extend(parse(&content, format, offset)?);
+    }
+    candles.sort_by_key(|candle| candle.time);
+    candles.dedup_by_key(|candle| candle.time);
+    Ok(candles)
+}
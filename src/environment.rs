@@ -0,0 +1,86 @@
+use kraken_async_rs::wss::{WS_KRAKEN, WS_KRAKEN_AUTH};
+
+use serde::{Deserialize, Serialize};
+
+use std::sync::{OnceLock, RwLock};
+
+const REST: &str = "https://api.kraken.com";
+const FUTURES: &str = "https://futures.kraken.com";
+const DEMO_FUTURES: &str = "https://demo-futures.kraken.com";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Environment {
+    #[default]
+    Production,
+    // Kraken Futures demo, Kraken has no public spot sandbox: spot orders are simulated unless a
+    // sandbox is given with rest
+    Demo,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EnvironmentConfig {
+    pub name: Environment,
+    // base URLs replacing those of the environment, e.g. a sandbox or a mock of the exchange
+    pub rest: Option<String>,
+    pub websocket: Option<String>,
+    pub websocket_auth: Option<String>,
+    pub futures: Option<String>,
+}
+
+// Base URLs of the exchange APIs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    pub rest: String,
+    pub websocket: String,
+    pub websocket_auth: String,
+    pub futures: String,
+}
+
+impl EnvironmentConfig {
+    pub fn endpoints(&self) -> Endpoints {
+        let futures = match self.name {
+            Environment::Production => FUTURES,
+            Environment::Demo => DEMO_FUTURES,
+        };
+        Endpoints {
+            rest: self.rest.clone().unwrap_or_else(|| REST.to_string()),
+            websocket: self
+                .websocket
+                .clone()
+                .unwrap_or_else(|| WS_KRAKEN.to_string()),
+            websocket_auth: self
+                .websocket_auth
+                .clone()
+                .unwrap_or_else(|| WS_KRAKEN_AUTH.to_string()),
+            futures: self.futures.clone().unwrap_or_else(|| futures.to_string()),
+        }
+    }
+
+    // Whether orders cannot be sent without risking funds, public market data still comes from
+    // production.
+    pub fn simulated(&self) -> bool {
+        self.name == Environment::Demo && self.rest.is_none()
+    }
+}
+
+fn installed() -> &'static RwLock<EnvironmentConfig> {
+    static ENVIRONMENT: OnceLock<RwLock<EnvironmentConfig>> = OnceLock::new();
+    ENVIRONMENT.get_or_init(RwLock::default)
+}
+
+// Set the process wide environment, production is used until then.
+pub fn install(config: &EnvironmentConfig) {
+    if let Ok(mut environment) = installed().write() {
+        *environment = config.clone();
+    }
+}
+
+// Base URLs of the installed environment.
+pub fn endpoints() -> Endpoints {
+    installed()
+        .read()
+        .map(|environment| environment.endpoints())
+        .unwrap_or_else(|_| EnvironmentConfig::default().endpoints())
+}
@@ -0,0 +1,59 @@
+use crate::backtest::Fill;
+use crate::execution::Order;
+use crate::market::Candle;
+use crate::synchronizer::Snapshot;
+
+use serde::Serialize;
+
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use std::sync::OnceLock;
+
+// Number of events kept for subscribers lagging behind.
+const CAPACITY: usize = 1024;
+
+// Live event published to the user interfaces.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Candle {
+        ticker: String,
+        candle: Candle,
+    },
+    // closed candles of the followed pairs for a time
+    Synchronized(Snapshot),
+    // orders decided by a strategy
+    Signal {
+        strategy: String,
+        orders: Vec<Order>,
+    },
+    Order {
+        strategy: Option<String>,
+        id: String,
+        order: Order,
+    },
+    // execution of (part of) an order reported by the venue
+    Fill {
+        id: String,
+        fill: Fill,
+    },
+    // realized and unrealized profit (in reporting currency) of the current UTC day
+    Pnl {
+        daily: f64,
+    },
+}
+
+fn sender() -> &'static Sender<Event> {
+    static SENDER: OnceLock<Sender<Event>> = OnceLock::new();
+    SENDER.get_or_init(|| broadcast::channel(CAPACITY).0)
+}
+
+// Publish an event to the current subscribers, it is dropped when there are none.
+pub fn publish(event: Event) {
+    let _ = sender().send(event);
+}
+
+// Events published from now on, subscribers lagging by more than the capacity miss events.
+pub fn subscribe() -> Receiver<Event> {
+    sender().subscribe()
+}
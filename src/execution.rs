@@ -0,0 +1,508 @@
+use crate::backtest::Fill;
+use crate::environment;
+use crate::instruments;
+use crate::margin::Margin;
+use crate::market::{Quote, to_float};
+use crate::nonce::PersistentNonceProvider;
+
+use kraken_async_rs::clients::core_kraken_client::CoreKrakenClient;
+use kraken_async_rs::clients::http_response_types::ResultErrorResponse;
+use kraken_async_rs::clients::kraken_client::KrakenClient;
+use kraken_async_rs::crypto::nonce_provider::NonceProvider;
+use kraken_async_rs::request_types::{
+    AddOrderRequest, CancelOrderRequest, IntOrString, OpenOrdersRequest, OrderFlag, OrderFlags,
+    TradeBalanceRequest, TradesHistoryRequest,
+};
+use kraken_async_rs::response_types::{BuySell, OrderType};
+use kraken_async_rs::secrets::secrets_provider::{SecretsProvider, StaticSecretsProvider};
+
+use futures::future::join_all;
+
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::sync::Mutex;
+
+use tracing::{info, warn};
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+
+    // Sign applied to quantities when accumulating positions (+1 for buys, -1 for sells).
+    pub fn sign(&self) -> f64 {
+        match self {
+            Side::Buy => 1.0,
+            Side::Sell => -1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OrderKind {
+    Market,
+    // limit price
+    Limit(f64),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    pub ticker: String,
+    pub side: Side,
+    // quantity of the base asset
+    pub volume: f64,
+    pub kind: OrderKind,
+    // leverage of margin orders, None for orders paid in full
+    #[serde(default)]
+    pub leverage: Option<u32>,
+    // limit order only adding liquidity, cancelled instead of crossing the spread
+    #[serde(default)]
+    pub post_only: bool,
+    // order only reducing the position on its ticker
+    #[serde(default)]
+    pub reduce_only: bool,
+    // account the order is placed on, None for the main one
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+impl Order {
+    pub fn market(ticker: &str, side: Side, volume: f64) -> Order {
+        Order {
+            ticker: ticker.to_string(),
+            side,
+            volume,
+            kind: OrderKind::Market,
+            leverage: None,
+            post_only: false,
+            reduce_only: false,
+            account: None,
+        }
+    }
+
+    pub fn limit(ticker: &str, side: Side, volume: f64, price: f64) -> Order {
+        Order {
+            ticker: ticker.to_string(),
+            side,
+            volume,
+            kind: OrderKind::Limit(price),
+            leverage: None,
+            post_only: false,
+            reduce_only: false,
+            account: None,
+        }
+    }
+
+    // Limit order priced relative to the live spread, an aggressiveness of 0 joins the best price
+    // on the order's side while 1 crosses the spread to the best opposite price.
+    pub fn within_spread(
+        ticker: &str,
+        side: Side,
+        volume: f64,
+        quote: &Quote,
+        aggressiveness: f64,
+    ) -> Order {
+        let price = match side {
+            Side::Buy => quote.bid + aggressiveness * quote.spread(),
+            Side::Sell => quote.ask - aggressiveness * quote.spread(),
+        };
+        Order::limit(ticker, side, volume, price)
+    }
+
+    // Same order placed on margin.
+    pub fn with_leverage(self, leverage: u32) -> Order {
+        Order {
+            leverage: (leverage > 1).then_some(leverage),
+            ..self
+        }
+    }
+
+    pub fn with_post_only(self) -> Order {
+        Order {
+            post_only: true,
+            ..self
+        }
+    }
+
+    pub fn with_reduce_only(self) -> Order {
+        Order {
+            reduce_only: true,
+            ..self
+        }
+    }
+
+    pub fn with_account(self, account: &str) -> Order {
+        Order {
+            account: Some(account.to_string()),
+            ..self
+        }
+    }
+
+    // Whether a post-only order would cross the spread of a quote and take liquidity.
+    pub fn crosses(&self, quote: &Quote) -> bool {
+        match (self.kind, self.side) {
+            (OrderKind::Market, _) => true,
+            (OrderKind::Limit(price), Side::Buy) => price >= quote.ask,
+            (OrderKind::Limit(price), Side::Sell) => price <= quote.bid,
+        }
+    }
+
+    // Market order undoing the exposure taken by this order.
+    pub fn offset(&self) -> Order {
+        Order {
+            leverage: self.leverage,
+            account: self.account.clone(),
+            ..Order::market(&self.ticker, self.side.opposite(), self.volume)
+        }
+    }
+}
+
+// Execution of (part of) an order reported by the venue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execution {
+    // identifier of the trade, each execution having its own
+    pub trade: String,
+    // identifier of the executed order
+    pub order: String,
+    pub fill: Fill,
+}
+
+// Venue orders are routed to. Submission returns the identifier attributed to the order by the
+// venue so that it can later be cancelled.
+pub trait Executor {
+    fn submit(&self, order: &Order) -> impl Future<Output = Result<String, String>> + Send;
+
+    fn cancel(&self, id: &str) -> impl Future<Output = Result<(), String>> + Send;
+
+    // Whether orders are only pretended to be sent.
+    fn simulated(&self) -> bool {
+        false
+    }
+
+    // Quantity held per asset on the venue.
+    fn balances(&self) -> impl Future<Output = Result<HashMap<String, f64>, String>> + Send {
+        async { Err("Balances are not available".to_string()) }
+    }
+
+    // Margin state of the account on the venue.
+    fn margin(&self) -> impl Future<Output = Result<Margin, String>> + Send {
+        async { Err("Margin is not available".to_string()) }
+    }
+
+    // Executions of the orders since a unix time (in s).
+    fn fills(&self, _since: i64) -> impl Future<Output = Result<Vec<Execution>, String>> + Send {
+        async { Err("Fills are not available".to_string()) }
+    }
+
+    // Identifiers of the orders resting on the venue.
+    fn resting(&self) -> impl Future<Output = Result<HashSet<String>, String>> + Send {
+        async { Err("Open orders are not available".to_string()) }
+    }
+}
+
+// Submit all legs concurrently. If any leg is rejected the legs that went through are offset with
+// market orders so that no partial exposure is left behind.
+pub async fn submit_legs<E: Executor>(executor: &E, legs: &[Order]) -> Result<Vec<String>, String> {
+    let results = join_all(legs.iter().map(|leg| executor.submit(leg))).await;
+
+    if results.iter().all(|result| result.is_ok()) {
+        return Ok(results.into_iter().flatten().collect());
+    }
+
+    let errors: Vec<String> = results
+        .iter()
+        .filter_map(|result| result.as_ref().err().cloned())
+        .collect();
+
+    let offsets: Vec<Order> = legs
+        .iter()
+        .zip(&results)
+        .filter(|(_, result)| result.is_ok())
+        .map(|(leg, _)| leg.offset())
+        .collect();
+
+    for (offset, result) in offsets
+        .iter()
+        .zip(join_all(offsets.iter().map(|offset| executor.submit(offset))).await)
+    {
+        if let Err(message) = result {
+            warn!("Could not offset leg {:?}: {}", offset, message);
+        }
+    }
+
+    Err(format!("Rejected legs: {}", errors.join(", ")))
+}
+
+// Aggregate orders before execution: market orders on a ticker with the same leverage and account
+// are netted into a single order for the remaining volume, opposing signals cancelling out, limit
+// and reduce-only orders are kept as they are.
+pub fn net(orders: Vec<Order>) -> Vec<Order> {
+    // signed and gross volume per ticker, leverage and account
+    let mut netted: Vec<(Order, f64, f64)> = Vec::new();
+    let mut kept = Vec::new();
+    for order in orders {
+        if order.kind != OrderKind::Market || order.reduce_only {
+            kept.push(order);
+            continue;
+        }
+        let signed = order.side.sign() * order.volume;
+        match netted.iter_mut().find(|(netting, ..)| {
+            netting.ticker == order.ticker
+                && netting.leverage == order.leverage
+                && netting.account == order.account
+        }) {
+            Some((_, volume, gross)) => {
+                *volume += signed;
+                *gross += order.volume;
+            }
+            None => {
+                let volume = order.volume;
+                netted.push((order, signed, volume));
+            }
+        }
+    }
+    netted
+        .into_iter()
+        // rounding leftovers of exactly opposing volumes
+        .filter(|(_, volume, gross)| volume.abs() > gross * 1e-9)
+        .map(|(order, volume, _)| {
+            let side = if volume > 0.0 { Side::Buy } else { Side::Sell };
+            Order {
+                side,
+                volume: volume.abs(),
+                ..order
+            }
+        })
+        .chain(kept)
+        .collect()
+}
+
+// Decimal representation accepted by the exchange for a quantity or a price.
+fn to_decimal(value: f64) -> Result<Decimal, String> {
+    match Decimal::from_f64(value) {
+        Some(decimal) => Ok(decimal.round_dp(8).normalize()),
+        None => Err(format!("{} cannot be sent as a decimal", value)),
+    }
+}
+
+// Asset name as used in tickers from the name Kraken gives it in balances, e.g. ETH for XETH and
+// BTC for XXBT.
+pub fn normalize_asset(asset: &str) -> String {
+    let asset = match asset.len() {
+        4 if asset.starts_with('X') || asset.starts_with('Z') => &asset[1..],
+        _ => asset,
+    };
+    match asset {
+        "XBT" => "BTC".to_string(),
+        "XDG" => "DOGE".to_string(),
+        _ => asset.to_string(),
+    }
+}
+
+// Executor sending orders to Kraken through the authenticated REST API.
+pub struct KrakenExecutor {
+    client: Mutex<CoreKrakenClient>,
+}
+
+impl KrakenExecutor {
+    // Nonces are kept in a file so that they keep increasing across restarts.
+    pub fn new(key: &str, secret: &str, nonces: &Path) -> KrakenExecutor {
+        let secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>> = Box::new(Arc::new(
+            Mutex::new(StaticSecretsProvider::new(key, secret)),
+        ));
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(PersistentNonceProvider::new(nonces))));
+
+        KrakenExecutor {
+            client: Mutex::new(CoreKrakenClient::new_with_url(
+                secrets_provider,
+                nonce_provider,
+                environment::endpoints().rest,
+            )),
+        }
+    }
+}
+
+impl Executor for KrakenExecutor {
+    async fn submit(&self, order: &Order) -> Result<String, String> {
+        let order = &instruments::round(order);
+        let side = match order.side {
+            Side::Buy => BuySell::Buy,
+            Side::Sell => BuySell::Sell,
+        };
+        let volume = to_decimal(order.volume)?;
+        let mut request = match order.kind {
+            OrderKind::Market => {
+                AddOrderRequest::builder(OrderType::Market, side, volume, order.ticker.clone())
+            }
+            OrderKind::Limit(price) => {
+                AddOrderRequest::builder(OrderType::Limit, side, volume, order.ticker.clone())
+                    .price(to_decimal(price)?)
+            }
+        };
+        if let Some(leverage) = order.leverage {
+            request = request.leverage(leverage.to_string());
+        }
+        if order.post_only {
+            request = request.order_flags(OrderFlags::new(vec![OrderFlag::Post]));
+        }
+        // Kraken only takes reduce-only on margin orders, spot orders are only checked locally
+        if order.reduce_only && order.leverage.is_some() {
+            request = request.reduce_only(true);
+        }
+        let request = request.build();
+
+        match self.client.lock().await.add_order(&request).await {
+            Ok(ResultErrorResponse {
+                result: Some(added),
+                ..
+            }) => match added.tx_id.first() {
+                Some(id) => Ok(id.clone()),
+                None => Err(format!("No transaction id returned for {:?}", order)),
+            },
+            Ok(response) => Err(format!("{:?}", response.error)),
+            Err(network_error) => Err(format!("{:?}", network_error)),
+        }
+    }
+
+    async fn cancel(&self, id: &str) -> Result<(), String> {
+        let request = CancelOrderRequest::builder(IntOrString::String(id.to_string())).build();
+        match self.client.lock().await.cancel_order(&request).await {
+            Ok(ResultErrorResponse {
+                result: Some(_), ..
+            }) => Ok(()),
+            Ok(response) => Err(format!("{:?}", response.error)),
+            Err(network_error) => Err(format!("{:?}", network_error)),
+        }
+    }
+
+    async fn balances(&self) -> Result<HashMap<String, f64>, String> {
+        match self.client.lock().await.get_account_balance().await {
+            Ok(ResultErrorResponse {
+                result: Some(balances),
+                ..
+            }) => Ok(balances
+                .iter()
+                // staked and earning variants (e.g. ETH.F) cannot be traded
+                .filter(|(asset, _)| !asset.contains('.'))
+                .map(|(asset, amount)| (normalize_asset(asset), to_float(amount)))
+                .collect()),
+            Ok(response) => Err(format!("{:?}", response.error)),
+            Err(network_error) => Err(format!("{:?}", network_error)),
+        }
+    }
+
+    async fn margin(&self) -> Result<Margin, String> {
+        let request = TradeBalanceRequest::builder().build();
+        match self.client.lock().await.get_trade_balances(&request).await {
+            Ok(ResultErrorResponse {
+                result: Some(balances),
+                ..
+            }) => Ok(Margin {
+                equity: to_float(&balances.equity),
+                used: to_float(&balances.margin_amount),
+                free: to_float(&balances.free_margin),
+                level: balances.margin_level.as_ref().map(to_float),
+            }),
+            Ok(response) => Err(format!("{:?}", response.error)),
+            Err(network_error) => Err(format!("{:?}", network_error)),
+        }
+    }
+
+    // Only the latest trades are returned, the fills are expected to be queried more often than
+    // a page of them can happen.
+    async fn fills(&self, since: i64) -> Result<Vec<Execution>, String> {
+        let request = TradesHistoryRequest::builder()
+            .start(since.max(0) as u64)
+            .build();
+        match self.client.lock().await.get_trades_history(&request).await {
+            Ok(ResultErrorResponse {
+                result: Some(history),
+                ..
+            }) => Ok(history
+                .trades
+                .iter()
+                .map(|(id, trade)| Execution {
+                    trade: id.clone(),
+                    order: trade.order_tx_id.clone(),
+                    fill: Fill {
+                        time: trade.time as i64,
+                        // trades name their pair as the REST API does, e.g. XETHZEUR
+                        ticker: instruments::ticker(&trade.pair)
+                            .unwrap_or_else(|| trade.pair.clone()),
+                        side: match trade.trade_type {
+                            BuySell::Buy => Side::Buy,
+                            BuySell::Sell => Side::Sell,
+                        },
+                        volume: to_float(&trade.volume),
+                        price: to_float(&trade.price),
+                        fee: to_float(&trade.fee),
+                        maker: trade.maker,
+                    },
+                })
+                .collect()),
+            Ok(response) => Err(format!("{:?}", response.error)),
+            Err(network_error) => Err(format!("{:?}", network_error)),
+        }
+    }
+
+    async fn resting(&self) -> Result<HashSet<String>, String> {
+        let request = OpenOrdersRequest::builder().build();
+        match self.client.lock().await.get_open_orders(&request).await {
+            Ok(ResultErrorResponse {
+                result: Some(orders),
+                ..
+            }) => Ok(orders.open.into_keys().collect()),
+            Ok(response) => Err(format!("{:?}", response.error)),
+            Err(network_error) => Err(format!("{:?}", network_error)),
+        }
+    }
+}
+
+// Executor pretending to send orders, used to run the live pipeline without trading.
+#[derive(Default)]
+pub struct DryRunExecutor {
+    // number of orders submitted so far, used to attribute identifiers
+    submitted: AtomicU64,
+}
+
+impl DryRunExecutor {
+    pub fn new() -> DryRunExecutor {
+        DryRunExecutor::default()
+    }
+}
+
+impl Executor for DryRunExecutor {
+    async fn submit(&self, order: &Order) -> Result<String, String> {
+        let id = format!("dry-run-{}", self.submitted.fetch_add(1, Ordering::Relaxed));
+        info!(id = %id, pair = %order.ticker, order = ?order, "Hypothetical order");
+        Ok(id)
+    }
+
+    async fn cancel(&self, id: &str) -> Result<(), String> {
+        info!(id = %id, "Hypothetical cancellation");
+        Ok(())
+    }
+
+    fn simulated(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,257 @@
+use crate::analysis::MovingStatistics;
+use crate::clock;
+use crate::indicators::Indicator;
+use crate::indicators::atr::AverageTrueRange;
+use crate::indicators::volatility::{Estimator, RealizedVolatility};
+use crate::market::{Candle, CandleUpdates};
+use crate::statistics::{deviation, mean};
+
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch};
+
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::task;
+
+use tracing::{info, warn};
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    // directory the Parquet files are written to, one per pair
+    pub directory: PathBuf,
+    // time between exports of the candles received (in s), never exported while running without
+    pub every: Option<u64>,
+    // candles kept per pair for the scheduled exports
+    pub capacity: usize,
+    // candles kept in memory per pair, older ones are spilled to the export directory
+    pub memory: Option<usize>,
+    // windows (in candles) of the moving means and deviations of the close
+    pub windows: Vec<usize>,
+    // window (in candles) of the average true range and realized volatility
+    pub volatility: usize,
+}
+
+impl Default for ExportConfig {
+    fn default() -> ExportConfig {
+        ExportConfig {
+            directory: PathBuf::from("exports"),
+            every: None,
+            capacity: 10_000,
+            memory: None,
+            windows: vec![20, 50],
+            volatility: 14,
+        }
+    }
+}
+
+fn column(values: Vec<Option<f64>>) -> ArrayRef {
+    Arc::new(Float64Array::from(values))
+}
+
+// Candles of a pair, oldest first, along with the indicator series computed over them as
+// columns of a record batch.
+pub fn batch(candles: &[Candle], config: &ExportConfig) -> Result<RecordBatch, String> {
+    let field = |read: fn(&Candle) -> f64| {
+        column(candles.iter().map(|candle| Some(read(candle))).collect())
+    };
+    let mut columns: Vec<(String, ArrayRef)> = vec![
+        (
+            "time".into(),
+            Arc::new(Int64Array::from_iter_values(
+                candles.iter().map(|candle| candle.time),
+            )),
+        ),
+        ("open".into(), field(|candle| candle.open)),
+        ("high".into(), field(|candle| candle.high)),
+        ("low".into(), field(|candle| candle.low)),
+        ("close".into(), field(|candle| candle.close)),
+        ("vwap".into(), field(|candle| candle.vwap)),
+        ("volume".into(), field(|candle| candle.volume)),
+        (
+            "count".into(),
+            Arc::new(Int64Array::from_iter_values(
+                candles.iter().map(|candle| candle.count),
+            )),
+        ),
+    ];
+
+    let closes: Vec<f64> = candles.iter().map(|candle| candle.close).collect();
+    for window in config.windows.iter().filter(|window| **window > 0) {
+        let windowed = |statistic: fn(&[f64]) -> Option<f64>| {
+            column(
+                (0..closes.len())
+                    .map(|end| {
+                        (end + 1 >= *window)
+                            .then(|| statistic(&closes[end + 1 - window..=end]))
+                            .flatten()
+                    })
+                    .collect(),
+            )
+        };
+        columns.push((format!("mean_{}", window), windowed(mean)));
+        columns.push((format!("deviation_{}", window), windowed(deviation)));
+    }
+
+    let mut atr = AverageTrueRange::new(config.volatility);
+    let mut volatility = RealizedVolatility::new(config.volatility, Estimator::CloseToClose);
+    columns.push((
+        format!("atr_{}", config.volatility),
+        column(candles.iter().map(|candle| atr.update(candle)).collect()),
+    ));
+    columns.push((
+        format!("volatility_{}", config.volatility),
+        column(
+            candles
+                .iter()
+                .map(|candle| volatility.update(candle))
+                .collect(),
+        ),
+    ));
+
+    match RecordBatch::try_from_iter(columns) {
+        Ok(batch) => Ok(batch),
+        Err(error) => Err(format!("Could not assemble the export: {:?}", error)),
+    }
+}
+
+// File the candles of a pair are exported to, pairs are named like BTC/EUR.
+pub fn path(directory: &Path, ticker: &str) -> PathBuf {
+    directory.join(format!("{}.parquet", ticker.replace('/', "-")))
+}
+
+// Write the candles of a pair and their indicators to a Parquet file. The file is written aside
+// and moved in place so readers never see a partial export.
+pub fn write(path: &Path, candles: &[Candle], config: &ExportConfig) -> Result<(), String> {
+    let batch = batch(candles, config)?;
+    if let Some(directory) = path.parent()
+        && let Err(error) = fs::create_dir_all(directory)
+    {
+        return Err(format!("Could not create {:?}: {:?}", directory, error));
+    }
+    let partial = path.with_extension("parquet.partial");
+    let file = match File::create(&partial) {
+        Ok(file) => file,
+        Err(error) => return Err(format!("Could not create {:?}: {:?}", partial, error)),
+    };
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let written =
+        ArrowWriter::try_new(file, batch.schema(), Some(properties)).and_then(|mut writer| {
+            writer.write(&batch)?;
+            writer.close()
+        });
+    if let Err(error) = written {
+        return Err(format!("Could not write {:?}: {:?}", partial, error));
+    }
+    match fs::rename(&partial, path) {
+        Ok(()) => Ok(()),
+        Err(error) => Err(format!("Could not move {:?}: {:?}", partial, error)),
+    }
+}
+
+// Keeps the latest candles of each followed pair and exports them on schedule.
+pub struct Exporter {
+    config: ExportConfig,
+    // candle interval (in min) the gaps of the pairs are counted with
+    interval: i32,
+    universes: HashMap<String, MovingStatistics>,
+    // unix time (in s) of the next export
+    next: f64,
+}
+
+impl Exporter {
+    pub fn new(config: ExportConfig, interval: i32) -> Exporter {
+        let next = clock::now() + config.every.unwrap_or_default() as f64;
+        Exporter {
+            config,
+            interval,
+            universes: HashMap::new(),
+            next,
+        }
+    }
+
+    // Feed a closed candle or an update of the forming one, the statistics only ingest the
+    // closed candles.
+    pub fn update(&mut self, ticker: &str, candle: Candle, candles: CandleUpdates) {
+        if self.config.every.is_none() {
+            return;
+        }
+        if !self.universes.contains_key(ticker) {
+            self.universes
+                .insert(ticker.to_string(), self.universe(ticker));
+        }
+        if let Some(statistics) = self.universes.get_mut(ticker) {
+            match candles {
+                CandleUpdates::Closed => statistics.update(candle),
+                CandleUpdates::Intrabar => statistics.update_forming(candle),
+            };
+        }
+    }
+
+    fn universe(&self, ticker: &str) -> MovingStatistics {
+        let statistics =
+            MovingStatistics::new(self.config.capacity).with_interval(self.interval as i64 * 60);
+        let Some(memory) = self.config.memory else {
+            return statistics;
+        };
+        let spill = self
+            .config
+            .directory
+            .join(format!(".{}.spill", ticker.replace('/', "-")));
+        if let Err(error) = fs::create_dir_all(&self.config.directory) {
+            warn!("Could not create {:?}: {:?}", self.config.directory, error);
+        }
+        match statistics.with_spill(memory, &spill) {
+            Ok(statistics) => statistics,
+            Err(message) => {
+                warn!("{}, keeping the candles of {} in memory", message, ticker);
+                MovingStatistics::new(self.config.capacity).with_interval(self.interval as i64 * 60)
+            }
+        }
+    }
+
+    // Drop the candles of a pair no longer followed.
+    pub fn forget(&mut self, ticker: &str) {
+        self.universes.remove(ticker);
+    }
+
+    // Export every pair in the background when due.
+    pub fn poll(&mut self) {
+        let Some(every) = self.config.every else {
+            return;
+        };
+        let now = clock::now();
+        if now < self.next {
+            return;
+        }
+        self.next = now + every as f64;
+
+        let universes: Vec<(String, Vec<Candle>)> = self
+            .universes
+            .iter()
+            .map(|(ticker, statistics)| (ticker.clone(), statistics.universe().collect()))
+            .collect();
+        let config = self.config.clone();
+        task::spawn_blocking(move || {
+            for (ticker, candles) in universes {
+                let file = path(&config.directory, &ticker);
+                match write(&file, &candles, &config) {
+                    Ok(()) => {
+                        info!(pair = %ticker, candles = candles.len(), "Exported to {:?}", file)
+                    }
+                    Err(message) => warn!("{}", message),
+                }
+            }
+        });
+    }
+}
@@ -1,3 +1,10 @@
+use crate::alerts::{self, EventKind};
+use crate::clock;
+use crate::environment;
+use crate::instruments;
+use crate::market::{self, CandleCloser, MarketEvent};
+use crate::metrics;
+
 use kraken_async_rs::clients::core_kraken_client::CoreKrakenClient;
 use kraken_async_rs::clients::http_response_types::ResultErrorResponse;
 use kraken_async_rs::clients::kraken_client::KrakenClient;
@@ -5,84 +12,713 @@ use kraken_async_rs::crypto::nonce_provider::{IncreasingNonceProvider, NonceProv
 use kraken_async_rs::request_types::{CandlestickInterval, OHLCRequest, StringCSV};
 use kraken_async_rs::response_types::OHLC;
 use kraken_async_rs::secrets::secrets_provider::{SecretsProvider, StaticSecretsProvider};
-use kraken_async_rs::wss::{KrakenMessageStream, KrakenWSSClient, WS_KRAKEN, WS_KRAKEN_AUTH};
-use kraken_async_rs::wss::{Message, OhlcSubscription, WssMessage};
+use kraken_async_rs::wss::{
+    BookSubscription, Message, OhlcSubscription, TickerSubscription, TradesSubscription, WssMessage,
+};
+use kraken_async_rs::wss::{KrakenMessageStream, KrakenWSSClient};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use tokio::sync::Mutex;
-use tokio::time::timeout;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::{Mutex, Notify, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
 use tokio_stream::StreamExt;
 
+use tracing::warn;
+
 use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
+// Candle intervals (in min) and order book depths Kraken streams.
+pub const INTERVALS: &[i32] = &[1, 5, 15, 30, 60, 240, 1440, 10080, 21600];
+pub const DEPTHS: &[i32] = &[10, 25, 100, 500, 1000];
+
+fn book(tickers: Vec<String>, depth: i32) -> BookSubscription {
+    let mut subscription = BookSubscription::new(tickers);
+    subscription.depth = Some(depth);
+    subscription
+}
+
+// What to do with an event arriving while the feed buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    // stop reading from the connection until the consumer catches up
+    #[default]
+    Block,
+    // discard the oldest buffered event
+    DropOldest,
+    // replace the buffered update of the same candle, discarding the oldest event when there
+    // is none
+    Coalesce,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BufferConfig {
+    // number of events received but not yet consumed
+    pub capacity: usize,
+    pub overflow: Overflow,
+}
+
+impl Default for BufferConfig {
+    fn default() -> BufferConfig {
+        BufferConfig {
+            capacity: 1024,
+            overflow: Overflow::Block,
+        }
+    }
+}
+
+type Received = Result<MarketEvent, String>;
+
+// Events read from the connection waiting to be consumed. The depth, blocked, dropped and
+// coalesced events are exposed as metrics so that a slow consumer is visible.
+struct Buffer {
+    config: BufferConfig,
+    queue: std::sync::Mutex<VecDeque<Received>>,
+    // whether the connection filling the buffer ended
+    closed: AtomicBool,
+    received: Notify,
+    freed: Notify,
+    // hands the frames received to the writer of a session file when recording
+    recorder: std::sync::Mutex<Option<mpsc::UnboundedSender<Recorded>>>,
+}
+
+// Candle updated by an event, updates of the same candle supersede each other.
+fn updated(received: &Received) -> Option<(&str, i64)> {
+    match received {
+        Ok(MarketEvent::Candle { ticker, candle }) => Some((ticker, candle.time)),
+        _ => None,
+    }
+}
+
+// Line of a session recording, a raw websocket frame along with the local time (in s) it was
+// received at.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct Recorded {
+    time: f64,
+    frame: Value,
+}
+
+// Market events carried by a raw websocket frame, parsed as the connection would.
+fn events(frame: &Value) -> Vec<MarketEvent> {
+    match serde_json::from_value::<WssMessage>(frame.clone()) {
+        Ok(message) => market::events(&message),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Start writing the frames sent through the returned channel to a session file, a JSON line
+// each, for later replay. The writes happen in a task of their own so that the reader never waits
+// on the file.
+fn recorder(path: &Path) -> Result<mpsc::UnboundedSender<Recorded>, String> {
+    let file = match OpenOptions::new().create(true).append(true).open(path) {
+        Ok(file) => tokio::fs::File::from_std(file),
+        Err(error) => return Err(format!("Could not open {:?}: {:?}", path, error)),
+    };
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(write(path.to_path_buf(), file, receiver));
+    Ok(sender)
+}
+
+async fn write(
+    path: PathBuf,
+    mut file: tokio::fs::File,
+    mut frames: mpsc::UnboundedReceiver<Recorded>,
+) {
+    while let Some(recorded) = frames.recv().await {
+        let mut line = match serde_json::to_string(&recorded) {
+            Ok(line) => line,
+            Err(error) => {
+                warn!("Frame not recorded: {:?}", error);
+                continue;
+            }
+        };
+        line.push('\n');
+        // flushed per line so that a crash loses nothing of the incident being recorded
+        let written = match file.write_all(line.as_bytes()).await {
+            Ok(()) => file.flush().await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = written {
+            warn!("Stopped recording: could not write {:?}: {:?}", path, error);
+            return;
+        }
+    }
+}
+
+// Events of a recorded session along with the unix times (in s) their frames were received at.
+pub fn session(path: &Path) -> Result<Vec<(f64, MarketEvent)>, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) => return Err(format!("Could not read {:?}: {:?}", path, error)),
+    };
+    let mut session = Vec::new();
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str::<Recorded>(line) {
+            Ok(recorded) => session.extend(
+                events(&recorded.frame)
+                    .into_iter()
+                    .map(|event| (recorded.time, event)),
+            ),
+            Err(error) => return Err(format!("Invalid recorded frame {:?}: {}", line, error)),
+        }
+    }
+    Ok(session)
+}
+
+// Push the events of the frames of a session recording into the buffer, paced as they were
+// received sped up by the given factor or as fast as the buffer allows without one.
+async fn replay(path: PathBuf, buffer: Arc<Buffer>, speed: Option<f64>) {
+    let file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(error) => {
+            buffer
+                .push(Err(format!("Could not open {:?}: {:?}", path, error)))
+                .await;
+            buffer.close();
+            return;
+        }
+    };
+    let mut lines = tokio::io::BufReader::new(file).lines();
+    let mut previous: Option<f64> = None;
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(error) => {
+                buffer
+                    .push(Err(format!("Could not read {:?}: {:?}", path, error)))
+                    .await;
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let recorded: Recorded = match serde_json::from_str(&line) {
+            Ok(recorded) => recorded,
+            Err(error) => {
+                buffer
+                    .push(Err(format!("Invalid recorded frame {:?}: {}", line, error)))
+                    .await;
+                continue;
+            }
+        };
+        if let (Some(speed), Some(previous)) = (speed.filter(|speed| *speed > 0.0), previous) {
+            let wait = (recorded.time - previous).max(0.0) / speed;
+            sleep(Duration::from_secs_f64(wait)).await;
+        }
+        previous = Some(recorded.time);
+        for event in events(&recorded.frame) {
+            buffer.push(Ok(event)).await;
+        }
+    }
+    buffer.close();
+}
+
+impl Buffer {
+    fn new(config: BufferConfig) -> Buffer {
+        Buffer {
+            config,
+            queue: std::sync::Mutex::new(VecDeque::new()),
+            closed: AtomicBool::new(false),
+            received: Notify::new(),
+            freed: Notify::new(),
+            recorder: std::sync::Mutex::new(None),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Received>> {
+        // the queue stays consistent even if a holder panicked
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Hand a frame received to the recording if any, which stops once its writer gave up.
+    fn record(&self, frame: &Value) {
+        let Ok(mut recorder) = self.recorder.lock() else {
+            return;
+        };
+        let recorded = Recorded {
+            time: clock::now(),
+            frame: frame.clone(),
+        };
+        if let Some(sender) = recorder.as_ref()
+            && sender.send(recorded).is_err()
+        {
+            *recorder = None;
+        }
+    }
+
+    async fn push(&self, received: Received) {
+        loop {
+            {
+                let mut queue = self.lock();
+                if queue.len() < self.config.capacity.max(1) {
+                    queue.push_back(received);
+                    break;
+                }
+                match self.config.overflow {
+                    Overflow::Block => metrics::increment("feed.blocked", 1),
+                    Overflow::DropOldest => {
+                        queue.pop_front();
+                        queue.push_back(received);
+                        metrics::increment("feed.dropped", 1);
+                        break;
+                    }
+                    Overflow::Coalesce => {
+                        let key = updated(&received);
+                        match queue
+                            .iter()
+                            .rposition(|queued| key.is_some() && updated(queued) == key)
+                        {
+                            Some(index) => {
+                                queue[index] = received;
+                                metrics::increment("feed.coalesced", 1);
+                            }
+                            None => {
+                                queue.pop_front();
+                                queue.push_back(received);
+                                metrics::increment("feed.dropped", 1);
+                            }
+                        }
+                        break;
+                    }
+                }
+            }
+            self.freed.notified().await;
+        }
+        metrics::set("feed.depth", self.lock().len() as f64);
+        self.received.notify_one();
+    }
+
+    // Next message, None once the connection ended and every message was consumed.
+    async fn pop(&self) -> Option<Received> {
+        loop {
+            if let Some(received) = self.lock().pop_front() {
+                self.freed.notify_one();
+                metrics::set("feed.depth", self.lock().len() as f64);
+                return Some(received);
+            }
+            if self.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            self.received.notified().await;
+        }
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.received.notify_one();
+    }
+}
+
+// Subscription added to a running connection.
+enum Subscription {
+    // candles of the given interval (in min)
+    Ohlc(Vec<String>, i32),
+    // books of the given depth
+    Book(Vec<String>, i32),
+    Trades(Vec<String>),
+    Ticker(Vec<String>),
+}
+
+type Request = (Subscription, oneshot::Sender<Result<(), String>>);
+
+// Move the messages of the connection into the buffer while forwarding subscriptions to it.
+async fn read(
+    mut stream: KrakenMessageStream<Value>,
+    buffer: Arc<Buffer>,
+    mut requests: mpsc::Receiver<Request>,
+) {
+    loop {
+        tokio::select! {
+            communication = stream.next() => match communication {
+                Some(Ok(frame)) => {
+                    buffer.record(&frame);
+                    for event in events(&frame) {
+                        buffer.push(Ok(event)).await;
+                    }
+                }
+                Some(Err(error)) => buffer.push(Err(format!("{:?}", error))).await,
+                None => break,
+            },
+            Some((subscription, reply)) = requests.recv() => {
+                let result = match subscription {
+                    Subscription::Ohlc(tickers, interval) => {
+                        send(&mut stream, OhlcSubscription::new(tickers, interval)).await
+                    }
+                    Subscription::Book(tickers, depth) => {
+                        send(&mut stream, book(tickers, depth)).await
+                    }
+                    Subscription::Trades(tickers) => {
+                        send(&mut stream, TradesSubscription::new(tickers)).await
+                    }
+                    Subscription::Ticker(tickers) => {
+                        send(&mut stream, TickerSubscription::new(tickers)).await
+                    }
+                };
+                // the subscriber may have given up waiting
+                let _ = reply.send(result);
+            }
+        }
+    }
+    buffer.close();
+}
+
+// Change to the followed pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PairChange {
+    Add(String),
+    Remove(String),
+}
+
+pub type PairRequest = (PairChange, oneshot::Sender<Result<String, String>>);
+
+// Handle through which the control plane changes the pairs followed by the running bot, each
+// request is answered once the feed and the strategies were updated.
+#[derive(Debug, Clone)]
+pub struct Pairs(mpsc::Sender<PairRequest>);
+
+impl Pairs {
+    pub fn channel() -> (Pairs, mpsc::Receiver<PairRequest>) {
+        let (sender, receiver) = mpsc::channel(8);
+        (Pairs(sender), receiver)
+    }
+
+    pub async fn change(&self, change: PairChange) -> Result<String, String> {
+        let (reply, result) = oneshot::channel();
+        if self.0.send((change, reply)).await.is_err() {
+            return Err("Pairs cannot be changed, the bot is not trading".into());
+        }
+        match result.await {
+            Ok(result) => result,
+            Err(_) => Err("Pair change dropped".into()),
+        }
+    }
+}
+
+// Live websocket feed guarded by a watchdog: when no message, heartbeats included, arrives within
+// the timeout the connection is assumed half-open and is replaced by a new one with the same
+// subscriptions. Messages are read in the background and converted into market events kept in a
+// bounded buffer.
 pub struct LiveFeed {
-    // timeout of the websocket connection
+    // seconds without any message after which the connection is replaced
     timeout: u64,
+    // candle interval (in min)
+    interval: i32,
+    // price levels per side of the books followed
+    depth: i32,
+    tickers: Vec<String>,
+    books: Vec<String>,
+    trades: Vec<String>,
+    quotes: Vec<String>,
+    // whether events come from a session recording rather than from Kraken
+    replayed: bool,
+    // closes candles from their updates
+    closer: CandleCloser,
+    // update held back while the close event of the previous candle is consumed
+    pending: Option<MarketEvent>,
 
-    // Websocket stream to Kraken server
-    stream: KrakenMessageStream<WssMessage>,
+    buffer: Arc<Buffer>,
+    // task reading the websocket stream to Kraken server
+    reader: JoinHandle<()>,
+    requests: mpsc::Sender<Request>,
+}
+
+async fn connect() -> Result<KrakenMessageStream<Value>, String> {
+    let endpoints = environment::endpoints();
+    let mut client = KrakenWSSClient::new_with_tracing(
+        &endpoints.websocket,
+        &endpoints.websocket_auth,
+        true,
+        true,
+    );
+    match client.connect::<Value>().await {
+        Ok(stream) => Ok(stream),
+        Err(message) => Err(format!("{:?}", message)),
+    }
+}
+
+async fn send<T: Debug + Serialize>(
+    stream: &mut KrakenMessageStream<Value>,
+    subscription: T,
+) -> Result<(), String> {
+    match stream
+        .send(&Message::new_subscription(subscription, 0))
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(message) => Err(format!("{:?}", message)),
+    }
 }
 
 impl LiveFeed {
     // Create a new web socket feed to Kraken server for OHLC data with specified time interval
-    // (in s) and timeout (in min) following provided tickers.
+    // (in min) and watchdog timeout (in s) following provided tickers, books being followed at the
+    // given depth.
     pub async fn new(
         timeout: u64,
         interval: i32,
+        depth: i32,
         tickers: Vec<String>,
+        buffer: BufferConfig,
     ) -> Result<LiveFeed, String> {
-        let mut client = KrakenWSSClient::new_with_tracing(WS_KRAKEN, WS_KRAKEN_AUTH, true, true);
-        let mut stream = match client.connect::<WssMessage>().await {
-            Ok(stream) => stream,
-            Err(message) => return Err(format!("{:?}", message)),
-        };
+        instruments::check_tickers(&tickers)?;
+        let mut stream = connect().await?;
+        send(
+            &mut stream,
+            OhlcSubscription::new(tickers.clone(), interval),
+        )
+        .await?;
 
-        let ohlc_params = OhlcSubscription::new(tickers, interval);
+        let buffer = Arc::new(Buffer::new(buffer));
+        let (requests, receiver) = mpsc::channel(1);
+        Ok(LiveFeed {
+            timeout,
+            interval,
+            depth,
+            tickers,
+            books: Vec::new(),
+            trades: Vec::new(),
+            quotes: Vec::new(),
+            replayed: false,
+            closer: CandleCloser::default(),
+            pending: None,
+            reader: tokio::spawn(read(stream, buffer.clone(), receiver)),
+            buffer,
+            requests,
+        })
+    }
 
-        let subscription = Message::new_subscription(ohlc_params, 0);
+    // Feed replaying a session recording instead of connecting to Kraken, see record. Events
+    // are paced as recorded sped up by the given factor, or replayed as fast as they are consumed
+    // without one. Subscriptions are refused.
+    pub fn replay(path: &Path, speed: Option<f64>, buffer: BufferConfig) -> LiveFeed {
+        let buffer = Arc::new(Buffer::new(buffer));
+        // no connection takes the subscriptions
+        let (requests, _) = mpsc::channel(1);
+        LiveFeed {
+            timeout: 0,
+            interval: 0,
+            depth: 0,
+            tickers: Vec::new(),
+            books: Vec::new(),
+            trades: Vec::new(),
+            quotes: Vec::new(),
+            replayed: true,
+            closer: CandleCloser::default(),
+            pending: None,
+            reader: tokio::spawn(replay(path.to_path_buf(), buffer.clone(), speed)),
+            buffer,
+            requests,
+        }
+    }
 
-        match stream.send(&subscription).await {
-            Ok(_) => (),
-            Err(message) => return Err(format!("{:?}", message)),
-        };
+    // Append every raw frame received from now on to a session file along with its reception
+    // time.
+    pub fn record(&mut self, path: &Path) -> Result<(), String> {
+        let recorder = recorder(path)?;
+        match self.buffer.recorder.lock() {
+            Ok(mut current) => {
+                *current = Some(recorder);
+                Ok(())
+            }
+            Err(_) => Err("Recorder lock poisoned".into()),
+        }
+    }
 
-        Ok(LiveFeed { timeout, stream })
+    // Whether a replayed session was entirely consumed, live feeds never end.
+    pub fn ended(&self) -> bool {
+        self.replayed && self.buffer.closed.load(Ordering::Acquire) && self.buffer.lock().is_empty()
     }
 
-    // Poll for data from the feed
-    pub async fn consume(&mut self) -> Result<WssMessage, String> {
-        match timeout(Duration::from_secs(self.timeout), self.stream.next()).await {
-            Ok(Some(communication)) => match communication {
-                Ok(message) => Ok(message),
-                Err(error) => Err(format!("{:?}", error)),
-            },
-            Ok(None) => Err("Received None message in feed.".to_string()),
-            Err(contained) => Err(format!("{:?}", contained)),
+    async fn subscribe(&self, subscription: Subscription) -> Result<(), String> {
+        let (reply, result) = oneshot::channel();
+        if self.requests.send((subscription, reply)).await.is_err() {
+            return Err("Feed connection closed".to_string());
+        }
+        match result.await {
+            Ok(result) => result,
+            Err(_) => Err("Feed connection closed".to_string()),
+        }
+    }
+
+    // Additionally follow the level 2 order book of the provided tickers.
+    pub async fn subscribe_book(&mut self, tickers: Vec<String>) -> Result<(), String> {
+        self.subscribe(Subscription::Book(tickers.clone(), self.depth))
+            .await?;
+        self.books.extend(tickers);
+        Ok(())
+    }
+
+    // Additionally follow the individual trades of the provided tickers.
+    pub async fn subscribe_trades(&mut self, tickers: Vec<String>) -> Result<(), String> {
+        self.subscribe(Subscription::Trades(tickers.clone()))
+            .await?;
+        self.trades.extend(tickers);
+        Ok(())
+    }
+
+    // Additionally follow the best bid and ask and the 24h statistics of the provided tickers.
+    pub async fn subscribe_ticker(&mut self, tickers: Vec<String>) -> Result<(), String> {
+        self.subscribe(Subscription::Ticker(tickers.clone()))
+            .await?;
+        self.quotes.extend(tickers);
+        Ok(())
+    }
+
+    pub fn tickers(&self) -> &[String] {
+        &self.tickers
+    }
+
+    // Follow a further ticker on every channel the feed follows.
+    pub async fn add_ticker(&mut self, ticker: &str) -> Result<(), String> {
+        if self.tickers.iter().any(|followed| followed == ticker) {
+            return Err(format!("{} is already followed", ticker));
+        }
+        instruments::check_tickers(&[ticker.to_string()])?;
+        let tickers = vec![ticker.to_string()];
+        self.subscribe(Subscription::Ohlc(tickers.clone(), self.interval))
+            .await?;
+        self.tickers.push(ticker.to_string());
+        if !self.trades.is_empty() {
+            self.subscribe_trades(tickers.clone()).await?;
+        }
+        if !self.quotes.is_empty() {
+            self.subscribe_ticker(tickers).await?;
+        }
+        Ok(())
+    }
+
+    // Stop following a ticker on every channel, the connection is replaced by one without it.
+    pub async fn remove_ticker(&mut self, ticker: &str) -> Result<(), String> {
+        if !self.tickers.iter().any(|followed| followed == ticker) {
+            return Err(format!("{} is not followed", ticker));
+        }
+        for tickers in [
+            &mut self.tickers,
+            &mut self.books,
+            &mut self.trades,
+            &mut self.quotes,
+        ] {
+            tickers.retain(|followed| followed != ticker);
+        }
+        self.closer.forget(ticker);
+        self.reconnect().await
+    }
+
+    // Replace the connection by a new one with the same subscriptions, buffered messages are
+    // kept.
+    pub async fn reconnect(&mut self) -> Result<(), String> {
+        let mut stream = connect().await?;
+        send(
+            &mut stream,
+            OhlcSubscription::new(self.tickers.clone(), self.interval),
+        )
+        .await?;
+        if !self.books.is_empty() {
+            send(&mut stream, book(self.books.clone(), self.depth)).await?;
         }
+        if !self.trades.is_empty() {
+            send(&mut stream, TradesSubscription::new(self.trades.clone())).await?;
+        }
+        if !self.quotes.is_empty() {
+            send(&mut stream, TickerSubscription::new(self.quotes.clone())).await?;
+        }
+        self.reader.abort();
+        self.buffer.closed.store(false, Ordering::Release);
+        let (requests, receiver) = mpsc::channel(1);
+        self.reader = tokio::spawn(read(stream, self.buffer.clone(), receiver));
+        self.requests = requests;
+        Ok(())
+    }
+
+    // Alarm about a silent or closed connection and replace it, a failed reconnection is retried
+    // when the watchdog fires again.
+    async fn revive(&mut self, reason: &str) -> String {
+        metrics::increment("feed.reconnects", 1);
+        alerts::notify(EventKind::Error, "Feed lost", reason);
+        match self.reconnect().await {
+            Ok(()) => format!("{}, reconnected", reason),
+            Err(message) => format!("{}, could not reconnect: {}", reason, message),
+        }
+    }
+
+    // Poll for data from the feed. The close event of a candle comes right before the first update
+    // of the next candle of its ticker, recordings only hold the updates.
+    pub async fn consume(&mut self) -> Result<MarketEvent, String> {
+        if let Some(event) = self.pending.take() {
+            return Ok(event);
+        }
+        let event = self.receive().await?;
+        if let MarketEvent::Candle { ticker, candle } = &event
+            && let Some(closed) = self.closer.update(ticker, candle)
+        {
+            let ticker = ticker.clone();
+            self.pending = Some(event);
+            return Ok(MarketEvent::Closed {
+                ticker,
+                candle: closed,
+            });
+        }
+        Ok(event)
+    }
+
+    async fn receive(&mut self) -> Result<MarketEvent, String> {
+        if self.replayed {
+            return match self.buffer.pop().await {
+                Some(received) => received,
+                None => Err("Replay ended".into()),
+            };
+        }
+        match timeout(Duration::from_secs(self.timeout), self.buffer.pop()).await {
+            Ok(Some(received)) => received,
+            Ok(None) => Err(self.revive("Feed connection closed").await),
+            Err(_) => {
+                let reason = format!("No message received in {}s", self.timeout);
+                Err(self.revive(&reason).await)
+            }
+        }
+    }
+}
+
+impl Drop for LiveFeed {
+    fn drop(&mut self) {
+        self.reader.abort();
     }
 }
 
 pub trait CandlestickIntervalConvertible {
-    fn into_candlestick_interval(&self) -> CandlestickInterval
+    fn into_candlestick_interval(self) -> CandlestickInterval
     where
-        Self: PartialOrd<i32>,
+        Self: Sized + PartialOrd<i32>,
     {
-        if *self < 5 {
+        if self < 5 {
             CandlestickInterval::Minute
-        } else if *self < 15 {
+        } else if self < 15 {
             CandlestickInterval::Minutes5
-        } else if *self < 30 {
+        } else if self < 30 {
             CandlestickInterval::Minutes15
-        } else if *self < 60 {
+        } else if self < 60 {
             CandlestickInterval::Minutes30
-        } else if *self < 240 {
+        } else if self < 240 {
             CandlestickInterval::Hour
-        } else if *self < 1440 {
+        } else if self < 1440 {
             CandlestickInterval::Hours4
-        } else if *self < 10080 {
+        } else if self < 10080 {
             CandlestickInterval::Day
-        } else if *self < 21600 {
+        } else if self < 21600 {
             CandlestickInterval::Week
         } else {
             CandlestickInterval::Days15
@@ -109,7 +745,11 @@ impl HistoricalFeed {
         let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
             Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
 
-        let mut client = CoreKrakenClient::new(secrets_provider, nonce_provider);
+        let mut client = CoreKrakenClient::new_with_url(
+            secrets_provider,
+            nonce_provider,
+            environment::endpoints().rest,
+        );
 
         let server_time = match client.get_server_time().await {
             Ok(response) => {
@@ -150,7 +790,7 @@ impl HistoricalFeed {
             });
         }
 
-        let lengths: Vec<usize> = ohlc_map.iter().map(|(_, ohlc)| ohlc.len()).collect();
+        let lengths: Vec<usize> = ohlc_map.values().map(|ohlc| ohlc.len()).collect();
 
         let reference = lengths[0];
         if !lengths.iter().all(|&length| length == reference) {
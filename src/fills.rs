@@ -0,0 +1,189 @@
+use crate::alerts::{self, EventKind};
+use crate::attribution;
+use crate::clock;
+use crate::events::{self, Event};
+use crate::execution::{Execution, Executor};
+use crate::journal::{Entry, Journal};
+use crate::metrics;
+use crate::orders;
+use crate::risk::RiskGuard;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::interval;
+
+use tracing::{info, warn};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FillConfig {
+    // time between queries of the executions of the orders (in s)
+    pub period: u64,
+}
+
+impl Default for FillConfig {
+    fn default() -> FillConfig {
+        FillConfig { period: 10 }
+    }
+}
+
+// Trades already booked, the queries overlapping so that no execution is missed.
+#[derive(Debug, Clone, PartialEq)]
+struct Booked {
+    // unix time (in s) of the latest trade booked, the next query starts from it
+    since: i64,
+    // time of the trades booked since then by identifier
+    trades: HashMap<String, i64>,
+}
+
+impl Booked {
+    // Trades of the real fills journaled, the queries starting at a given time when there are
+    // none.
+    fn new(entries: &[Entry], start: i64) -> Booked {
+        let trades: HashMap<String, i64> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Fill {
+                    time,
+                    trade: Some(trade),
+                    simulated: false,
+                    ..
+                } => Some((trade.clone(), *time)),
+                _ => None,
+            })
+            .collect();
+        let mut booked = Booked {
+            since: trades.values().max().copied().unwrap_or(start),
+            trades,
+        };
+        booked.prune();
+        booked
+    }
+
+    // Whether an execution was not booked yet, it is taken as booked from then on.
+    fn book(&mut self, execution: &Execution) -> bool {
+        if self.trades.contains_key(&execution.trade) {
+            return false;
+        }
+        self.trades
+            .insert(execution.trade.clone(), execution.fill.time);
+        self.since = self.since.max(execution.fill.time);
+        true
+    }
+
+    // Forget the trades older than the next query.
+    fn prune(&mut self) {
+        let since = self.since;
+        self.trades.retain(|_, time| *time >= since);
+    }
+}
+
+// Book, journal and publish an execution, and follow up on the orders managed by the bot.
+async fn settle<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    execution: Execution,
+) {
+    let known = guard.on_fill(&execution);
+    let Execution { trade, order, fill } = execution;
+    metrics::increment("fills", 1);
+    info!(
+        id = %order,
+        trade = %trade,
+        pair = %fill.ticker,
+        fill = ?fill,
+        known,
+        "Order filled"
+    );
+    let entry = Entry::Fill {
+        time: fill.time,
+        id: order.clone(),
+        ticker: fill.ticker.clone(),
+        side: fill.side,
+        volume: fill.volume,
+        price: fill.price,
+        fee: fill.fee,
+        simulated: guard.simulated(),
+        trade: Some(trade),
+    };
+    match journal.lock() {
+        Ok(mut journal) => {
+            if let Err(message) = journal.record(&entry) {
+                warn!("{}", message);
+            }
+        }
+        Err(_) => warn!("Journal lock poisoned, fill of {} not journaled", order),
+    }
+    attribution::fill(&order, &fill);
+    orders::on_fill(guard, journal, &order, fill.volume).await;
+    let message = format!(
+        "{:?} {} {} at {}, fee {}",
+        fill.side, fill.volume, fill.ticker, fill.price, fill.fee
+    );
+    let event = Event::Fill { id: order, fill };
+    alerts::notify_with(
+        EventKind::Fill,
+        "Order filled",
+        &message,
+        serde_json::to_value(&event).unwrap_or_default(),
+    );
+    events::publish(event);
+}
+
+// Stop counting as open the orders no longer on the venue.
+async fn reconcile<E: Executor + Sync>(guard: &RiskGuard<E>) {
+    match guard.reconcile().await {
+        Ok(closed) => {
+            for id in closed {
+                info!(id = %id, "Order no longer on the venue");
+                orders::on_closed(&id);
+            }
+        }
+        Err(message) => warn!("Could not query open orders: {}", message),
+    }
+}
+
+// Periodically query the executions of the orders, simulated ones included, booking them in the
+// risk guard at their price and fee, journaling them and publishing them to the strategies and
+// the user interfaces. Executions already journaled are skipped, those happening while the bot
+// was stopped are picked up from the latest one journaled. The orders no longer on the venue are
+// then reconciled with the guard.
+pub async fn run<E: Executor + Sync>(
+    config: FillConfig,
+    guard: Arc<RiskGuard<E>>,
+    journal: Arc<Mutex<Journal>>,
+    path: PathBuf,
+) {
+    let entries = Journal::read(&path).unwrap_or_else(|message| {
+        warn!("Fills journaled before not known: {}", message);
+        Vec::new()
+    });
+    let mut booked = Booked::new(&entries, clock::seconds());
+    let mut ticker = interval(Duration::from_secs(config.period.max(1)));
+    loop {
+        ticker.tick().await;
+        let mut executions = match guard.fills(booked.since).await {
+            Ok(executions) => executions,
+            Err(message) => {
+                warn!("Could not query fills: {}", message);
+                continue;
+            }
+        };
+        executions.sort_by_key(|execution| execution.fill.time);
+        for execution in executions {
+            if booked.book(&execution) {
+                settle(&guard, &journal, execution).await;
+            }
+        }
+        booked.prune();
+        // simulated orders are all known to the guard
+        if !guard.simulated() {
+            reconcile(&guard).await;
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::clock;
+use crate::feeds::HistoricalFeed;
+use crate::market::Candle;
+use crate::metrics;
+
+use serde::{Deserialize, Serialize};
+
+use tracing::warn;
+
+use std::collections::HashMap;
+
+// What to do with candles missing from the feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapPolicy {
+    // pass candles on as received, downstream sees the discontinuity
+    #[default]
+    Leave,
+    // insert flat candles at the previous close with no volume
+    ForwardFill,
+    // fetch the missing candles from the REST API, leaving the gap if that fails
+    Backfill,
+}
+
+// Detects holes between consecutive candles of each ticker by comparing their times to the
+// candle interval and fills them according to the policy.
+pub struct GapFiller {
+    // time between candles (in s)
+    interval: i64,
+    policy: GapPolicy,
+    // latest candle seen per ticker
+    latest: HashMap<String, Candle>,
+}
+
+impl GapFiller {
+    pub fn new(interval: i64, policy: GapPolicy) -> GapFiller {
+        GapFiller {
+            interval: interval.max(1),
+            policy,
+            latest: HashMap::new(),
+        }
+    }
+
+    // Drop the latest candle of a ticker no longer followed.
+    pub fn forget(&mut self, ticker: &str) {
+        self.latest.remove(ticker);
+    }
+
+    // Candles to pass downstream for a received candle, oldest first: the candles filling the
+    // gap preceding it if any, followed by the candle itself.
+    pub async fn process(&mut self, ticker: &str, candle: Candle) -> Vec<Candle> {
+        let previous = self.latest.get(ticker).copied();
+        if previous.is_none_or(|previous| candle.time >= previous.time) {
+            self.latest.insert(ticker.to_string(), candle);
+        }
+        let Some(previous) = previous else {
+            return vec![candle];
+        };
+
+        let missing = (candle.time - previous.time) / self.interval - 1;
+        if missing <= 0 {
+            return vec![candle];
+        }
+        metrics::increment(&format!("gaps.{}.missing", ticker), missing as u64);
+        warn!(
+            "{} candles missing for {} between {} and {}",
+            missing, ticker, previous.time, candle.time
+        );
+
+        let mut filled = match self.policy {
+            GapPolicy::Leave => Vec::new(),
+            GapPolicy::ForwardFill => self.forward_fill(&previous, candle.time),
+            GapPolicy::Backfill => match self.backfill(ticker, &previous, candle.time).await {
+                Ok(candles) => candles,
+                Err(message) => {
+                    warn!("Could not backfill {}: {}", ticker, message);
+                    Vec::new()
+                }
+            },
+        };
+        filled.push(candle);
+        filled
+    }
+
+    fn forward_fill(&self, previous: &Candle, until: i64) -> Vec<Candle> {
+        ((previous.time + self.interval)..until)
+            .step_by(self.interval as usize)
+            .map(|time| Candle {
+                time,
+                open: previous.close,
+                high: previous.close,
+                low: previous.close,
+                close: previous.close,
+                vwap: previous.close,
+                volume: 0.0,
+                count: 0,
+            })
+            .collect()
+    }
+
+    async fn backfill(
+        &self,
+        ticker: &str,
+        previous: &Candle,
+        until: i64,
+    ) -> Result<Vec<Candle>, String> {
+        let now = clock::seconds();
+        let mut feed = HistoricalFeed::new(
+            now - previous.time,
+            (self.interval / 60) as i32,
+            vec![ticker.to_string()],
+        )
+        .await?;
+
+        let mut candles = Vec::new();
+        while let Some(step) = feed.consume().await {
+            candles.extend(
+                step.values()
+                    .map(Candle::from)
+                    .filter(|candle| candle.time > previous.time && candle.time < until),
+            );
+        }
+        Ok(candles)
+    }
+}
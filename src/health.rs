@@ -0,0 +1,217 @@
+use crate::clock;
+use crate::execution::Executor;
+use crate::journal::Journal;
+use crate::metrics;
+use crate::risk::RiskGuard;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::{interval, timeout};
+
+use tracing::warn;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    // time between checks of the order API and the storage (in s)
+    pub period: u64,
+    // time the order API has to answer a check (in s)
+    pub timeout: u64,
+    // age of the last feed message past which the feed is taken as disconnected (in s)
+    pub feed_age: i64,
+    // age of the last candle past which the bot is not ready (in s)
+    pub candle_age: i64,
+    // age of the last feed message past which the bot is no longer live, the watchdog having
+    // failed to revive the feed (in s)
+    pub stalled: i64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> HealthConfig {
+        HealthConfig {
+            period: 30,
+            timeout: 10,
+            feed_age: 60,
+            candle_age: 600,
+            stalled: 900,
+        }
+    }
+}
+
+// Outcome of a check with what it found.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(result: Result<String, String>) -> Check {
+        match result {
+            Ok(detail) => Check { ok: true, detail },
+            Err(detail) => Check { ok: false, detail },
+        }
+    }
+
+    fn pending() -> Check {
+        Check::new(Err(String::from("Not checked yet")))
+    }
+}
+
+// Checks of a probe by name, the probe passing when all of them do.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub ok: bool,
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+impl Report {
+    fn new(checks: BTreeMap<&'static str, Check>) -> Report {
+        Report {
+            ok: checks.values().all(|check| check.ok),
+            checks,
+        }
+    }
+}
+
+// Checks too slow to run on every probe, refreshed in the background.
+#[derive(Debug, Clone, Default)]
+struct Probes {
+    // unix time (in s) the checks started at
+    started: Option<i64>,
+    orders: Option<Check>,
+    storage: Option<Check>,
+}
+
+fn probes() -> &'static RwLock<Probes> {
+    static PROBES: OnceLock<RwLock<Probes>> = OnceLock::new();
+    PROBES.get_or_init(RwLock::default)
+}
+
+// Age (in s) of the time held by a gauge, since the checks started when it was never set.
+fn age(gauge: &str) -> Option<i64> {
+    let now = clock::seconds();
+    match metrics::gauge(gauge) {
+        Some(time) => Some(now - time as i64),
+        None => probes().read().ok()?.started.map(|started| now - started),
+    }
+}
+
+fn fresh(gauge: &str, what: &str, limit: i64) -> Check {
+    let Some(time) = metrics::gauge(gauge) else {
+        return Check::new(Err(format!("No {} yet", what)));
+    };
+    let age = clock::seconds() - time as i64;
+    let detail = format!("Last {} {}s ago", what, age);
+    Check::new(if age <= limit {
+        Ok(detail)
+    } else {
+        Err(detail)
+    })
+}
+
+// Whether the bot should keep running: it is not live once the feed has been silent for longer
+// than the watchdog could have revived it, restarting it is then the way out.
+pub fn liveness(config: &HealthConfig) -> Report {
+    let feed = Check::new(match age("feed.last_message") {
+        Some(age) if age > config.stalled => Err(format!("Feed silent for {}s", age)),
+        Some(age) => Ok(format!("Feed silent for {}s", age)),
+        None => Ok(String::from("Starting")),
+    });
+    Report::new(BTreeMap::from([("feed", feed)]))
+}
+
+// Whether the bot can trade: the feed is connected, candles come in, the order API answers and
+// the journal and the directories written to accept writes.
+pub fn readiness(config: &HealthConfig) -> Report {
+    let feed = fresh("feed.last_message", "message", config.feed_age);
+    let candles = fresh("feed.last_candle", "candle", config.candle_age);
+    let (orders, storage) = match probes().read() {
+        Ok(probes) => (probes.orders.clone(), probes.storage.clone()),
+        Err(_) => (None, None),
+    };
+    Report::new(BTreeMap::from([
+        ("feed", feed),
+        ("candles", candles),
+        ("orders", orders.unwrap_or_else(Check::pending)),
+        ("storage", storage.unwrap_or_else(Check::pending)),
+    ]))
+}
+
+async fn orders<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    limit: Duration,
+) -> Result<String, String> {
+    if guard.simulated() {
+        return Ok(String::from("Orders are simulated"));
+    }
+    match timeout(limit, guard.balances()).await {
+        Ok(Ok(balances)) => Ok(format!("Balances of {} assets", balances.len())),
+        Ok(Err(message)) => Err(message),
+        Err(_) => Err(format!("No answer within {}s", limit.as_secs())),
+    }
+}
+
+// Write and remove a file in a directory.
+fn writable(directory: &Path) -> Result<(), String> {
+    let probe = directory.join(".health");
+    match fs::create_dir_all(directory)
+        .and_then(|_| fs::write(&probe, b"ok"))
+        .and_then(|_| fs::remove_file(&probe))
+    {
+        Ok(()) => Ok(()),
+        Err(error) => Err(format!("Could not write to {:?}: {:?}", directory, error)),
+    }
+}
+
+fn storage(journal: &Mutex<Journal>, directories: &[PathBuf]) -> Result<String, String> {
+    match journal.lock() {
+        Ok(journal) => journal.sync()?,
+        Err(_) => return Err(String::from("Journal lock poisoned")),
+    }
+    for directory in directories {
+        writable(directory)?;
+    }
+    Ok(format!(
+        "Journal and {} directories writable",
+        directories.len()
+    ))
+}
+
+// Periodically check that the order API answers and that the journal and the directories accept
+// writes, for the readiness probe.
+pub async fn run<E: Executor + Sync>(
+    config: HealthConfig,
+    guard: Arc<RiskGuard<E>>,
+    journal: Arc<Mutex<Journal>>,
+    directories: Vec<PathBuf>,
+) {
+    if let Ok(mut probes) = probes().write() {
+        probes.started = Some(clock::seconds());
+    }
+    let mut ticker = interval(Duration::from_secs(config.period.max(1)));
+    loop {
+        ticker.tick().await;
+        let orders = Check::new(orders(&guard, Duration::from_secs(config.timeout)).await);
+        let storage = Check::new(storage(&journal, &directories));
+        for (name, check) in [("orders", &orders), ("storage", &storage)] {
+            if !check.ok {
+                warn!("Health check {} failed: {}", name, check.detail);
+            }
+            metrics::set(
+                &format!("health.{}", name),
+                if check.ok { 1.0 } else { 0.0 },
+            );
+        }
+        if let Ok(mut probes) = probes().write() {
+            probes.orders = Some(orders);
+            probes.storage = Some(storage);
+        }
+    }
+}
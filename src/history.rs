@@ -0,0 +1,259 @@
+use crate::clock;
+use crate::environment;
+use crate::market::Candle;
+use crate::storage::CandleStore;
+
+use reqwest::Client;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tokio::time::sleep;
+
+use tracing::{info, warn};
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    // directory the downloaded and imported candles are stored in, a file per pair and interval
+    pub directory: PathBuf,
+    // time waited between requests to stay within the public rate limit (in ms)
+    pub pause: u64,
+    // attempts at a request before the download stops, waiting twice as long after each failure
+    pub retries: u32,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> HistoryConfig {
+        HistoryConfig {
+            directory: PathBuf::from("history"),
+            pause: 1000,
+            retries: 5,
+        }
+    }
+}
+
+// Trade as returned by the trades endpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Trade {
+    // unix time (in s)
+    time: f64,
+    price: f64,
+    volume: f64,
+}
+
+// Aggregates time ordered trades into candles of an interval.
+struct Aggregator {
+    // interval (in s)
+    interval: i64,
+    current: Option<Candle>,
+    // sum of the prices weighted by the volumes of the current candle
+    notional: f64,
+}
+
+impl Aggregator {
+    fn new(interval: i64) -> Aggregator {
+        Aggregator {
+            interval,
+            current: None,
+            notional: 0.0,
+        }
+    }
+
+    // Add a trade, returns the previous candle once a trade falls after it.
+    fn add(&mut self, trade: &Trade) -> Option<Candle> {
+        let time = trade.time as i64;
+        let time = time - time.rem_euclid(self.interval);
+        if let Some(candle) = &mut self.current
+            && candle.time == time
+        {
+            candle.high = candle.high.max(trade.price);
+            candle.low = candle.low.min(trade.price);
+            candle.close = trade.price;
+            candle.volume += trade.volume;
+            candle.count += 1;
+            self.notional += trade.price * trade.volume;
+            candle.vwap = if candle.volume > 0.0 {
+                self.notional / candle.volume
+            } else {
+                trade.price
+            };
+            return None;
+        }
+        self.notional = trade.price * trade.volume;
+        self.current.replace(Candle {
+            time,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            vwap: trade.price,
+            volume: trade.volume,
+            count: 1,
+        })
+    }
+
+    fn finish(&mut self) -> Option<Candle> {
+        self.current.take()
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(value) => value.parse().ok(),
+        value => value.as_f64(),
+    }
+}
+
+// Trades of a pair from a cursor on, along with the cursor following them.
+async fn fetch(client: &Client, ticker: &str, since: &str) -> Result<(Vec<Trade>, String), String> {
+    let response = client
+        .get(format!("{}/0/public/Trades", environment::endpoints().rest))
+        .query(&[("pair", ticker.replace('/', "").as_str()), ("since", since)])
+        .send()
+        .await;
+    let body: Value = match response {
+        Ok(response) => match response.json().await {
+            Ok(body) => body,
+            Err(error) => return Err(format!("Invalid trades response: {:?}", error)),
+        },
+        Err(error) => return Err(format!("{:?}", error)),
+    };
+    if let Some(errors) = body["error"].as_array()
+        && !errors.is_empty()
+    {
+        return Err(format!("{:?}", errors));
+    }
+    let Some(result) = body["result"].as_object() else {
+        return Err(format!("Trades response without result: {}", body));
+    };
+    let last = match &result["last"] {
+        Value::String(last) => last.clone(),
+        last => last.to_string(),
+    };
+    // the trades are keyed by Kraken's name of the pair
+    let trades = result
+        .iter()
+        .filter(|(key, _)| key.as_str() != "last")
+        .filter_map(|(_, trades)| trades.as_array())
+        .flatten()
+        .filter_map(|trade| {
+            let trade = trade.as_array()?;
+            Some(Trade {
+                price: number(trade.first()?)?,
+                volume: number(trade.get(1)?)?,
+                time: number(trade.get(2)?)?,
+            })
+        })
+        .collect();
+    Ok((trades, last))
+}
+
+// Fetch the trades of a pair between times (in s) in chunks and aggregate them into candles of an
+// interval (in min), handing the complete candles of each chunk to a sink. Returns the number of
+// candles handed.
+async fn candles(
+    config: &HistoryConfig,
+    ticker: &str,
+    interval: i32,
+    from: i64,
+    to: i64,
+    mut sink: impl FnMut(&[Candle]) -> Result<(), String>,
+) -> Result<usize, String> {
+    let client = Client::new();
+    let length = interval.max(1) as i64 * 60;
+    // only candles closed by the end are complete
+    let end = to - to.rem_euclid(length);
+
+    let mut aggregator = Aggregator::new(length);
+    let mut cursor = ((from - from.rem_euclid(length)) as i128 * 1_000_000_000).to_string();
+    let mut handed = 0;
+    loop {
+        let mut attempt = 0;
+        let (trades, last) = loop {
+            match fetch(&client, ticker, &cursor).await {
+                Ok(chunk) => break chunk,
+                Err(reason) if attempt + 1 < config.retries => {
+                    attempt += 1;
+                    let wait = config.pause.max(1) << attempt.min(10);
+                    warn!(
+                        pair = %ticker,
+                        reason = %reason,
+                        "Trades request failed, retrying in {} ms",
+                        wait
+                    );
+                    sleep(Duration::from_millis(wait)).await;
+                }
+                Err(reason) => return Err(reason),
+            }
+        };
+
+        let mut candles = Vec::new();
+        let mut done = trades.is_empty() || last == cursor;
+        for trade in &trades {
+            if trade.time as i64 >= end {
+                done = true;
+                break;
+            }
+            candles.extend(aggregator.add(trade));
+        }
+        if done {
+            candles.extend(aggregator.finish());
+        }
+        sink(&candles)?;
+        handed += candles.len();
+        if let Some(candle) = candles.last() {
+            info!(pair = %ticker, handed, time = candle.time, "History downloaded");
+        }
+        if done {
+            return Ok(handed);
+        }
+        cursor = last;
+        sleep(Duration::from_millis(config.pause)).await;
+    }
+}
+
+// Download the candles of a pair from a time (in s) up to now and append them to the store.
+// Downloads resume after the latest candle stored, so an interrupted download only refetches its
+// partial candle. Returns the number of candles stored.
+pub async fn download(
+    config: &HistoryConfig,
+    store: &CandleStore,
+    ticker: &str,
+    interval: i32,
+    from: i64,
+) -> Result<usize, String> {
+    let start = match store.last(ticker, interval)? {
+        Some(candle) => (candle.time + interval.max(1) as i64 * 60).max(from),
+        None => from,
+    };
+    candles(
+        config,
+        ticker,
+        interval,
+        start,
+        clock::seconds(),
+        |candles| store.append(ticker, interval, candles),
+    )
+    .await
+}
+
+// Candles of a pair opening from a time up to another one (in s, excluded).
+pub async fn range(
+    config: &HistoryConfig,
+    ticker: &str,
+    interval: i32,
+    from: i64,
+    to: i64,
+) -> Result<Vec<Candle>, String> {
+    let mut fetched = Vec::new();
+    candles(config, ticker, interval, from, to, |candles| {
+        fetched.extend_from_slice(candles);
+        Ok(())
+    })
+    .await?;
+    Ok(fetched)
+}
@@ -0,0 +1,36 @@
+pub mod atr;
+pub mod batch;
+pub mod channel;
+pub mod garch;
+pub mod hurst;
+pub mod ichimoku;
+pub mod kalman;
+pub mod momentum;
+pub mod moving_average;
+pub mod order_flow;
+pub mod oscillators;
+pub mod quantile;
+pub mod regression;
+pub mod session;
+pub mod snapshot;
+pub mod supertrend;
+pub mod volatility;
+pub mod volume_profile;
+pub mod vwap;
+
+use crate::market::Candle;
+
+// Streaming computation over market data, candles unless stated otherwise.
+pub trait Indicator<Input = Candle> {
+    type Output;
+
+    // Feed the next input and return the updated value, None while it cannot be computed.
+    fn update(&mut self, input: &Input) -> Option<Self::Output>;
+
+    // Inputs to feed before the values are meaningful.
+    fn bars_needed(&self) -> usize;
+
+    // Whether enough inputs were fed for the values to be acted on. Some indicators give values
+    // earlier, computed over fewer inputs than they need.
+    fn ready(&self) -> bool;
+}
@@ -0,0 +1,63 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+
+// Average true range with Wilder's smoothing: the true range extends the candle's range to the
+// previous close so that gaps between candles count as volatility.
+#[derive(Clone)]
+pub struct AverageTrueRange {
+    window: usize,
+
+    previous_close: Option<f64>,
+    // true ranges accumulated until the first average
+    seen: usize,
+    value: Option<f64>,
+    sum: f64,
+}
+
+impl AverageTrueRange {
+    pub fn new(window: usize) -> AverageTrueRange {
+        AverageTrueRange {
+            window: window.max(1),
+            previous_close: None,
+            seen: 0,
+            value: None,
+            sum: 0.0,
+        }
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+pub fn true_range(candle: &Candle, previous_close: Option<f64>) -> f64 {
+    match previous_close {
+        Some(close) => candle.high.max(close) - candle.low.min(close),
+        None => candle.high - candle.low,
+    }
+}
+
+impl Indicator for AverageTrueRange {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let range = true_range(candle, self.previous_close.replace(candle.close));
+        self.value = match self.value {
+            Some(average) => Some(average + (range - average) / self.window as f64),
+            None => {
+                self.sum += range;
+                self.seen += 1;
+                (self.seen == self.window).then(|| self.sum / self.window as f64)
+            }
+        };
+        self.value
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window
+    }
+
+    fn ready(&self) -> bool {
+        self.value.is_some()
+    }
+}
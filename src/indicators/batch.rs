@@ -0,0 +1,108 @@
+// Moving statistics over whole series at once, for backtests replaying known candles. Values are
+// aligned with the series, None while the window fills, and match the streaming indicators up to
+// rounding. The sums run over fixed lanes the compiler turns into SIMD instructions.
+
+const LANES: usize = 8;
+
+// Sum of the values over independent lanes, summed together at the end.
+fn sum(values: &[f64]) -> f64 {
+    let mut lanes = [0.0; LANES];
+    let chunks = values.chunks_exact(LANES);
+    let rest: f64 = chunks.remainder().iter().sum();
+    for chunk in chunks {
+        for (lane, value) in lanes.iter_mut().zip(chunk) {
+            *lane += value;
+        }
+    }
+    lanes.iter().sum::<f64>() + rest
+}
+
+// Sum of the squared distances of the values to a center.
+fn squares(values: &[f64], center: f64) -> f64 {
+    let mut lanes = [0.0; LANES];
+    let chunks = values.chunks_exact(LANES);
+    let rest: f64 = chunks
+        .remainder()
+        .iter()
+        .map(|value| (value - center).powi(2))
+        .sum();
+    for chunk in chunks {
+        for (lane, value) in lanes.iter_mut().zip(chunk) {
+            *lane += (value - center) * (value - center);
+        }
+    }
+    lanes.iter().sum::<f64>() + rest
+}
+
+// Simple moving average over a window.
+pub fn sma(values: &[f64], window: usize) -> Vec<Option<f64>> {
+    smas(values, &[window]).pop().unwrap_or_default()
+}
+
+// Simple moving averages of a series over several windows, sharing one pass over the values.
+// Averages are the differences of prefix sums of the values taken relative to their first, which
+// keeps the sums small over long series.
+pub fn smas(values: &[f64], windows: &[usize]) -> Vec<Vec<Option<f64>>> {
+    let origin = values.first().copied().unwrap_or(0.0);
+    let mut prefix = Vec::with_capacity(values.len() + 1);
+    prefix.push(0.0);
+    let mut total = 0.0;
+    for value in values {
+        total += value - origin;
+        prefix.push(total);
+    }
+    windows
+        .iter()
+        .map(|window| {
+            let window = (*window).max(1);
+            let mut averages = vec![None; values.len()];
+            if values.len() < window {
+                return averages;
+            }
+            let scale = 1.0 / window as f64;
+            for ((average, end), start) in averages[window - 1..]
+                .iter_mut()
+                .zip(&prefix[window..])
+                .zip(&prefix)
+            {
+                *average = Some(origin + (end - start) * scale);
+            }
+            averages
+        })
+        .collect()
+}
+
+// Exponential moving average with a smoothing of 2 / (window + 1), seeded with the simple average
+// of the first window. The recursion is sequential, only the seed is summed in lanes.
+pub fn ema(values: &[f64], window: usize) -> Vec<Option<f64>> {
+    let window = window.max(1);
+    let mut averages = vec![None; values.len()];
+    if values.len() < window {
+        return averages;
+    }
+    let smoothing = 2.0 / (window as f64 + 1.0);
+    let mut average = sum(&values[..window]) / window as f64;
+    averages[window - 1] = Some(average);
+    for (output, value) in averages[window..].iter_mut().zip(&values[window..]) {
+        average += smoothing * (value - average);
+        *output = Some(average);
+    }
+    averages
+}
+
+// Population standard deviation over a window, summed in two passes around the average of each
+// window rather than from running sums of squares, which lose precision on prices far from zero.
+pub fn deviation(values: &[f64], window: usize) -> Vec<Option<f64>> {
+    let window = window.max(1);
+    let averages = sma(values, window);
+    let scale = 1.0 / window as f64;
+    averages
+        .iter()
+        .enumerate()
+        .map(|(index, average)| {
+            let average = (*average)?;
+            let squares = squares(&values[index + 1 - window..=index], average);
+            Some((squares * scale).sqrt())
+        })
+        .collect()
+}
@@ -0,0 +1,143 @@
+use crate::indicators::Indicator;
+use crate::indicators::supertrend::Direction;
+use crate::market::Candle;
+
+use std::collections::VecDeque;
+
+// Highest and lowest of the latest values in amortized constant time. Each bound is a monotonic
+// deque of (position, value): values that can no longer be the extremum before leaving the window
+// are dropped on arrival, so the front is always the extremum of the window.
+#[derive(Clone)]
+pub struct RollingExtrema {
+    window: usize,
+    // values seen, positions the held values arrived at
+    seen: u64,
+    highs: VecDeque<(u64, f64)>,
+    lows: VecDeque<(u64, f64)>,
+}
+
+impl RollingExtrema {
+    pub fn new(window: usize) -> RollingExtrema {
+        RollingExtrema {
+            window: window.max(1),
+            seen: 0,
+            highs: VecDeque::new(),
+            lows: VecDeque::new(),
+        }
+    }
+
+    // Feed the high and low of the next period, the same value twice for plain series.
+    pub fn push(&mut self, high: f64, low: f64) {
+        while self.highs.back().is_some_and(|(_, held)| *held <= high) {
+            self.highs.pop_back();
+        }
+        self.highs.push_back((self.seen, high));
+        while self.lows.back().is_some_and(|(_, held)| *held >= low) {
+            self.lows.pop_back();
+        }
+        self.lows.push_back((self.seen, low));
+        self.seen += 1;
+
+        let oldest = self.seen.saturating_sub(self.window as u64);
+        for bound in [&mut self.highs, &mut self.lows] {
+            while bound
+                .front()
+                .is_some_and(|(position, _)| *position < oldest)
+            {
+                bound.pop_front();
+            }
+        }
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.ready()
+            .then(|| self.highs.front().map(|(_, high)| *high))?
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.ready()
+            .then(|| self.lows.front().map(|(_, low)| *low))?
+    }
+
+    pub fn ready(&self) -> bool {
+        self.seen >= self.window as u64
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+}
+
+// Range of the latest candles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Channel {
+    // highest high
+    pub upper: f64,
+    // lowest low
+    pub lower: f64,
+}
+
+impl Channel {
+    pub fn middle(&self) -> f64 {
+        (self.upper + self.lower) / 2.0
+    }
+
+    pub fn width(&self) -> f64 {
+        self.upper - self.lower
+    }
+}
+
+// Donchian channel: highest high and lowest low of the latest candles.
+#[derive(Clone)]
+pub struct DonchianChannel {
+    extrema: RollingExtrema,
+    // channel before the latest candle, breakouts are measured against it
+    previous: Option<Channel>,
+}
+
+impl DonchianChannel {
+    pub fn new(window: usize) -> DonchianChannel {
+        DonchianChannel {
+            extrema: RollingExtrema::new(window),
+            previous: None,
+        }
+    }
+
+    fn channel(&self) -> Option<Channel> {
+        Some(Channel {
+            upper: self.extrema.max()?,
+            lower: self.extrema.min()?,
+        })
+    }
+
+    // Side a price broke out of the channel of the candles preceding the latest one, None within
+    // it or while the channel is not computed.
+    pub fn breakout(&self, price: f64) -> Option<Direction> {
+        let previous = self.previous?;
+        if price > previous.upper {
+            Some(Direction::Up)
+        } else if price < previous.lower {
+            Some(Direction::Down)
+        } else {
+            None
+        }
+    }
+}
+
+impl Indicator for DonchianChannel {
+    type Output = Channel;
+
+    fn update(&mut self, candle: &Candle) -> Option<Channel> {
+        self.previous = self.channel();
+        self.extrema.push(candle.high, candle.low);
+        self.channel()
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.extrema.window()
+    }
+
+    fn ready(&self) -> bool {
+        self.extrema.ready()
+    }
+}
@@ -0,0 +1,221 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+use crate::statistics::variance;
+
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::spawn_blocking;
+
+use tracing::{debug, warn};
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// GARCH(1,1) model of the conditional variance of returns:
+// sigma2[t + 1] = omega + alpha * r[t]^2 + beta * sigma2[t]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Garch {
+    pub omega: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Garch {
+    // Conditional variance following the returns, the recursion starts at the sample variance.
+    pub fn variance(&self, returns: &[f64]) -> Option<f64> {
+        let mut conditional = variance(returns)?;
+        for value in returns {
+            conditional = self.omega + self.alpha * value.powi(2) + self.beta * conditional;
+        }
+        Some(conditional)
+    }
+
+    // Gaussian log likelihood of the returns under the model.
+    pub fn log_likelihood(&self, returns: &[f64]) -> Option<f64> {
+        let mut conditional = variance(returns)?;
+        let mut likelihood = 0.0;
+        for value in returns {
+            if conditional <= 0.0 {
+                return None;
+            }
+            likelihood -= 0.5 * ((2.0 * PI * conditional).ln() + value.powi(2) / conditional);
+            conditional = self.omega + self.alpha * value.powi(2) + self.beta * conditional;
+        }
+        Some(likelihood)
+    }
+
+    // Maximum likelihood fit with variance targeting: omega is tied to the sample variance so
+    // that the unconditional variance of the model matches it, and (alpha, beta) are found by a
+    // grid search refined with a shrinking pattern search.
+    pub fn fit(returns: &[f64]) -> Option<Garch> {
+        let sample = variance(returns)?;
+        if sample <= 0.0 {
+            return None;
+        }
+        let model = |alpha: f64, beta: f64| Garch {
+            omega: sample * (1.0 - alpha - beta),
+            alpha,
+            beta,
+        };
+        let score = |alpha: f64, beta: f64| {
+            if alpha < 0.0 || beta < 0.0 || alpha + beta >= 0.999 {
+                return f64::NEG_INFINITY;
+            }
+            model(alpha, beta)
+                .log_likelihood(returns)
+                .unwrap_or(f64::NEG_INFINITY)
+        };
+
+        let mut best = (0.05, 0.9, score(0.05, 0.9));
+        for alpha in (0..25).map(|step| step as f64 * 0.02) {
+            for beta in (0..50).map(|step| step as f64 * 0.02) {
+                let value = score(alpha, beta);
+                if value > best.2 {
+                    best = (alpha, beta, value);
+                }
+            }
+        }
+
+        let mut step = 0.01;
+        while step > 1e-5 {
+            let (alpha, beta, value) = best;
+            let moves = [(step, 0.0), (-step, 0.0), (0.0, step), (0.0, -step)];
+            match moves
+                .iter()
+                .map(|(da, db)| (alpha + da, beta + db, score(alpha + da, beta + db)))
+                .filter(|candidate| candidate.2 > value)
+                .max_by(|first, second| first.2.total_cmp(&second.2))
+            {
+                Some(candidate) => best = candidate,
+                None => step /= 2.0,
+            }
+        }
+
+        best.2.is_finite().then(|| model(best.0, best.1))
+    }
+}
+
+type Fit = Box<dyn FnOnce() + Send>;
+
+fn refits() -> &'static OnceLock<UnboundedSender<Fit>> {
+    static REFITS: OnceLock<UnboundedSender<Fit>> = OnceLock::new();
+    &REFITS
+}
+
+// Fit the models of the GARCH indicators one after the other off the runtime threads as they are
+// scheduled. Without this task running, e.g. in backtests, models are fitted inline so that runs
+// are reproducible.
+pub async fn run() {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    if refits().set(sender).is_err() {
+        warn!("GARCH(1,1) refits already running");
+        return;
+    }
+    while let Some(fit) = receiver.recv().await {
+        if let Err(error) = spawn_blocking(fit).await {
+            warn!("GARCH(1,1) fit failed: {}", error);
+        }
+    }
+}
+
+// Next candle volatility forecast from a GARCH(1,1) model of the logarithmic close returns over
+// a rolling window. The model is refitted every `refit` candles, in the refit task when it runs so
+// that candle processing is not held up by the fit.
+pub struct GarchVolatility {
+    window: usize,
+    // number of candles between refits
+    refit: usize,
+
+    previous_close: Option<f64>,
+    returns: VecDeque<f64>,
+    since_fit: usize,
+    model: Arc<Mutex<Option<Garch>>>,
+}
+
+impl GarchVolatility {
+    pub fn new(window: usize, refit: usize) -> GarchVolatility {
+        GarchVolatility {
+            window: window.max(2),
+            refit: refit.max(1),
+            previous_close: None,
+            returns: VecDeque::with_capacity(window.max(2)),
+            // fit as soon as the window fills up
+            since_fit: refit.max(1),
+            model: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn model(&self) -> Option<Garch> {
+        self.model.lock().ok().and_then(|model| *model)
+    }
+
+    fn schedule_fit(&mut self) {
+        self.since_fit = 0;
+        let returns: Vec<f64> = self.returns.iter().copied().collect();
+        let slot = self.model.clone();
+        let fit = move || {
+            let fitted = Garch::fit(&returns);
+            debug!("Refitted GARCH(1,1): {:?}", fitted);
+            match (fitted, slot.lock()) {
+                (Some(fitted), Ok(mut model)) => *model = Some(fitted),
+                (None, _) => warn!("GARCH(1,1) fit failed, keeping previous model"),
+                (_, Err(_)) => warn!("GARCH(1,1) model lock poisoned"),
+            }
+        };
+        match refits().get() {
+            Some(sender) => {
+                // the task stopped, e.g. on shutdown
+                if let Err(unsent) = sender.send(Box::new(fit)) {
+                    (unsent.0)();
+                }
+            }
+            None => fit(),
+        }
+    }
+
+    pub fn forecast(&self) -> Option<f64> {
+        let returns: Vec<f64> = self.returns.iter().copied().collect();
+        Some(self.model()?.variance(&returns)?.sqrt())
+    }
+}
+
+// Forks forecast with the model fitted so far, they never refit it.
+impl Clone for GarchVolatility {
+    fn clone(&self) -> GarchVolatility {
+        GarchVolatility {
+            window: self.window,
+            refit: usize::MAX,
+            previous_close: self.previous_close,
+            returns: self.returns.clone(),
+            since_fit: 0,
+            model: self.model.clone(),
+        }
+    }
+}
+
+impl Indicator for GarchVolatility {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let previous = self.previous_close.replace(candle.close)?;
+        if self.returns.len() == self.window {
+            self.returns.pop_front();
+        }
+        self.returns.push_back((candle.close / previous).ln());
+
+        self.since_fit += 1;
+        if self.returns.len() == self.window && self.since_fit >= self.refit {
+            self.schedule_fit();
+        }
+        self.forecast()
+    }
+
+    fn bars_needed(&self) -> usize {
+        // a return needs the previous close
+        self.window + 1
+    }
+
+    fn ready(&self) -> bool {
+        self.model().is_some()
+    }
+}
@@ -0,0 +1,128 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+use crate::statistics::{LinearFit, deviation, mean};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+
+// Smallest chunk of returns the rescaled range is computed over.
+const MIN_CHUNK: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Behaviour {
+    // Hurst exponent below one half, moves tend to be reversed
+    MeanReverting,
+    RandomWalk,
+    // Hurst exponent above one half, moves tend to persist
+    Trending,
+}
+
+// Rescaled range of a series: range of its cumulated deviations from the mean over its deviation.
+fn rescaled_range(values: &[f64]) -> Option<f64> {
+    let average = mean(values)?;
+    let spread = deviation(values)?;
+    if spread == 0.0 {
+        return None;
+    }
+    let mut cumulated = 0.0;
+    let (mut low, mut high) = (0.0_f64, 0.0_f64);
+    for value in values {
+        cumulated += value - average;
+        low = low.min(cumulated);
+        high = high.max(cumulated);
+    }
+    Some((high - low) / spread)
+}
+
+// Hurst exponent of a series estimated by rescaled range analysis: the slope of the logarithm of
+// the average rescaled range of chunks against the logarithm of the chunk size, for chunk sizes
+// doubling from MIN_CHUNK to half the series.
+pub fn hurst_exponent(values: &[f64]) -> Option<f64> {
+    let mut sizes = Vec::new();
+    let mut ranges = Vec::new();
+    let mut size = MIN_CHUNK;
+    while size <= values.len() / 2 {
+        let chunks: Vec<f64> = values
+            .chunks_exact(size)
+            .filter_map(rescaled_range)
+            .collect();
+        if let Some(average) = mean(&chunks) {
+            sizes.push((size as f64).ln());
+            ranges.push(average.ln());
+        }
+        size *= 2;
+    }
+    if sizes.len() < 2 {
+        return None;
+    }
+    Some(LinearFit::new(&sizes, &ranges)?.slope)
+}
+
+// Rolling Hurst exponent of the logarithmic close returns and the behaviour it indicates.
+pub struct Hurst {
+    window: usize,
+    // exponent below which prices are considered mean reverting
+    low: f64,
+    // exponent above which prices are considered trending
+    high: f64,
+
+    previous_close: Option<f64>,
+    returns: VecDeque<f64>,
+    current: Option<(f64, Behaviour)>,
+}
+
+impl Hurst {
+    pub fn new(window: usize, low: f64, high: f64) -> Hurst {
+        let window = window.max(4 * MIN_CHUNK);
+        Hurst {
+            window,
+            low,
+            high,
+            previous_close: None,
+            returns: VecDeque::with_capacity(window),
+            current: None,
+        }
+    }
+
+    pub fn behaviour(&self) -> Option<Behaviour> {
+        self.current.map(|(_, behaviour)| behaviour)
+    }
+}
+
+impl Indicator for Hurst {
+    type Output = (f64, Behaviour);
+
+    fn update(&mut self, candle: &Candle) -> Option<(f64, Behaviour)> {
+        let previous = self.previous_close.replace(candle.close)?;
+        if self.returns.len() == self.window {
+            self.returns.pop_front();
+        }
+        self.returns.push_back((candle.close / previous).ln());
+        if self.returns.len() < self.window {
+            return None;
+        }
+
+        let returns: Vec<f64> = self.returns.iter().copied().collect();
+        let exponent = hurst_exponent(&returns)?;
+        let behaviour = if exponent < self.low {
+            Behaviour::MeanReverting
+        } else if exponent > self.high {
+            Behaviour::Trending
+        } else {
+            Behaviour::RandomWalk
+        };
+        self.current = Some((exponent, behaviour));
+        self.current
+    }
+
+    fn bars_needed(&self) -> usize {
+        // a return needs the previous close
+        self.window + 1
+    }
+
+    fn ready(&self) -> bool {
+        self.returns.len() == self.window
+    }
+}
@@ -0,0 +1,157 @@
+use crate::indicators::Indicator;
+use crate::indicators::channel::RollingExtrema;
+use crate::market::Candle;
+
+use std::collections::VecDeque;
+
+// Where a price stands relative to the cloud.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Above,
+    Inside,
+    Below,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cloud {
+    // conversion line, midpoint of the range over the short period
+    pub tenkan: f64,
+    // base line, midpoint of the range over the medium period
+    pub kijun: f64,
+    // leading spans computed on the candle displaced ago, bounding the cloud at the current candle
+    pub senkou_a: f64,
+    pub senkou_b: f64,
+    // leading spans computed on the current candle, bounding the cloud displaced ahead
+    pub leading_a: f64,
+    pub leading_b: f64,
+    // lagging span: the current close, compared to the close displaced ago
+    pub chikou: f64,
+    pub past_close: f64,
+}
+
+impl Cloud {
+    pub fn top(&self) -> f64 {
+        self.senkou_a.max(self.senkou_b)
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.senkou_a.min(self.senkou_b)
+    }
+
+    pub fn position(&self, price: f64) -> Position {
+        if price > self.top() {
+            Position::Above
+        } else if price < self.bottom() {
+            Position::Below
+        } else {
+            Position::Inside
+        }
+    }
+
+    // Whether the cloud ahead is bullish, the fast leading span above the slow one.
+    pub fn bullish(&self) -> bool {
+        self.leading_a > self.leading_b
+    }
+
+    // Usual bullish confirmation: close above the cloud, conversion line above the base line and
+    // lagging span above the past price.
+    pub fn confirms_long(&self) -> bool {
+        self.position(self.chikou) == Position::Above
+            && self.tenkan > self.kijun
+            && self.chikou > self.past_close
+    }
+
+    pub fn confirms_short(&self) -> bool {
+        self.position(self.chikou) == Position::Below
+            && self.tenkan < self.kijun
+            && self.chikou < self.past_close
+    }
+}
+
+// Midpoint of the range of the latest candles.
+fn midpoint(extrema: &RollingExtrema) -> Option<f64> {
+    Some((extrema.max()? + extrema.min()?) / 2.0)
+}
+
+// Ichimoku cloud, None until the slow leading span has been computed a displacement ago.
+#[derive(Clone)]
+pub struct Ichimoku {
+    displacement: usize,
+
+    // ranges of the latest candles over the conversion, base and slow periods
+    tenkan_range: RollingExtrema,
+    kijun_range: RollingExtrema,
+    senkou_range: RollingExtrema,
+    // leading spans and closes of the latest candles over the displacement
+    leading: VecDeque<(f64, f64)>,
+    closes: VecDeque<f64>,
+}
+
+impl Ichimoku {
+    pub fn new(tenkan: usize, kijun: usize, senkou: usize, displacement: usize) -> Ichimoku {
+        let senkou = senkou.max(tenkan).max(kijun).max(1);
+        Ichimoku {
+            displacement,
+            tenkan_range: RollingExtrema::new(tenkan),
+            kijun_range: RollingExtrema::new(kijun),
+            senkou_range: RollingExtrema::new(senkou),
+            leading: VecDeque::with_capacity(displacement + 2),
+            closes: VecDeque::with_capacity(displacement + 2),
+        }
+    }
+
+    // Usual 9, 26, 52 periods displaced by 26 candles.
+    pub fn standard() -> Ichimoku {
+        Ichimoku::new(9, 26, 52, 26)
+    }
+}
+
+impl Indicator for Ichimoku {
+    type Output = Cloud;
+
+    fn update(&mut self, candle: &Candle) -> Option<Cloud> {
+        for range in [
+            &mut self.tenkan_range,
+            &mut self.kijun_range,
+            &mut self.senkou_range,
+        ] {
+            range.push(candle.high, candle.low);
+        }
+        self.closes.push_back(candle.close);
+        if self.closes.len() > self.displacement + 1 {
+            self.closes.pop_front();
+        }
+
+        let tenkan = midpoint(&self.tenkan_range)?;
+        let kijun = midpoint(&self.kijun_range)?;
+        let leading_b = midpoint(&self.senkou_range)?;
+        let leading_a = (tenkan + kijun) / 2.0;
+        self.leading.push_back((leading_a, leading_b));
+        if self.leading.len() > self.displacement + 1 {
+            self.leading.pop_front();
+        }
+        if self.leading.len() <= self.displacement {
+            return None;
+        }
+
+        let (senkou_a, senkou_b) = self.leading[0];
+        Some(Cloud {
+            tenkan,
+            kijun,
+            senkou_a,
+            senkou_b,
+            leading_a,
+            leading_b,
+            chikou: candle.close,
+            past_close: self.closes[0],
+        })
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.senkou_range.window() + self.displacement
+    }
+
+    fn ready(&self) -> bool {
+        self.leading.len() > self.displacement
+    }
+}
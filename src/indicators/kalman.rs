@@ -0,0 +1,83 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    // smoothed price
+    pub price: f64,
+    // price change per candle
+    pub velocity: f64,
+}
+
+// Kalman filter tracking the close with a constant velocity model. Raising the process noise
+// makes the estimate follow the price more closely, raising the measurement noise smooths it.
+#[derive(Clone)]
+pub struct KalmanSmoother {
+    // variance of the random acceleration of the price per candle
+    process_noise: f64,
+    // variance of the closes around the true price
+    measurement_noise: f64,
+
+    // (price, velocity) state and its covariance
+    state: Option<[f64; 2]>,
+    covariance: [[f64; 2]; 2],
+}
+
+impl KalmanSmoother {
+    pub fn new(process_noise: f64, measurement_noise: f64) -> KalmanSmoother {
+        KalmanSmoother {
+            process_noise,
+            measurement_noise,
+            state: None,
+            covariance: [[0.0; 2]; 2],
+        }
+    }
+
+    pub fn estimate(&self) -> Option<Estimate> {
+        self.state
+            .map(|[price, velocity]| Estimate { price, velocity })
+    }
+}
+
+impl Indicator for KalmanSmoother {
+    type Output = Estimate;
+
+    fn update(&mut self, candle: &Candle) -> Option<Estimate> {
+        let Some([price, velocity]) = self.state else {
+            self.state = Some([candle.close, 0.0]);
+            self.covariance = [[self.measurement_noise, 0.0], [0.0, self.measurement_noise]];
+            return self.estimate();
+        };
+
+        // predict one candle ahead
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let q = self.process_noise;
+        let predicted = [price + velocity, velocity];
+        let p00 = p00 + p01 + p10 + p11 + q / 4.0;
+        let p01 = p01 + p11 + q / 2.0;
+        let p10 = p10 + p11 + q / 2.0;
+        let p11 = p11 + q;
+
+        // correct with the observed close
+        let innovation = candle.close - predicted[0];
+        let spread = p00 + self.measurement_noise;
+        let (k0, k1) = (p00 / spread, p10 / spread);
+        self.state = Some([
+            predicted[0] + k0 * innovation,
+            predicted[1] + k1 * innovation,
+        ]);
+        self.covariance = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+        self.estimate()
+    }
+
+    fn bars_needed(&self) -> usize {
+        1
+    }
+
+    fn ready(&self) -> bool {
+        self.state.is_some()
+    }
+}
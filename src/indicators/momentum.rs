@@ -0,0 +1,97 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+
+use std::collections::VecDeque;
+
+// Closes of the latest candles, from the one `lookback` candles ago to the current one.
+#[derive(Clone)]
+struct Lookback {
+    lookback: usize,
+    closes: VecDeque<f64>,
+}
+
+impl Lookback {
+    fn new(lookback: usize) -> Lookback {
+        let lookback = lookback.max(1);
+        Lookback {
+            lookback,
+            closes: VecDeque::with_capacity(lookback + 2),
+        }
+    }
+
+    // (close `lookback` candles ago, current close) once enough candles were seen.
+    fn update(&mut self, close: f64) -> Option<(f64, f64)> {
+        self.closes.push_back(close);
+        if self.closes.len() > self.lookback + 1 {
+            self.closes.pop_front();
+        }
+        self.ready().then(|| (self.closes[0], close))
+    }
+
+    fn ready(&self) -> bool {
+        self.closes.len() == self.lookback + 1
+    }
+}
+
+// Close minus the close a number of candles ago, in quote currency.
+#[derive(Clone)]
+pub struct Momentum {
+    closes: Lookback,
+}
+
+impl Momentum {
+    pub fn new(lookback: usize) -> Momentum {
+        Momentum {
+            closes: Lookback::new(lookback),
+        }
+    }
+}
+
+impl Indicator for Momentum {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let (past, close) = self.closes.update(candle.close)?;
+        Some(close - past)
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.closes.lookback + 1
+    }
+
+    fn ready(&self) -> bool {
+        self.closes.ready()
+    }
+}
+
+// Change of the close relative to the close a number of candles ago, in percent. None while the
+// past close is zero.
+#[derive(Clone)]
+pub struct RateOfChange {
+    closes: Lookback,
+}
+
+impl RateOfChange {
+    pub fn new(lookback: usize) -> RateOfChange {
+        RateOfChange {
+            closes: Lookback::new(lookback),
+        }
+    }
+}
+
+impl Indicator for RateOfChange {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let (past, close) = self.closes.update(candle.close)?;
+        (past != 0.0).then(|| 100.0 * (close - past) / past)
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.closes.lookback + 1
+    }
+
+    fn ready(&self) -> bool {
+        self.closes.ready()
+    }
+}
@@ -0,0 +1,347 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+use crate::statistics::deviation;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+
+// Moving average variant, from the smoothest to the most responsive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Average {
+    #[default]
+    Simple,
+    Exponential,
+    // linearly weighted, the latest value weighing the most
+    Weighted,
+    // Hull, weighted averages combined to cancel most of the lag
+    Hull,
+}
+
+// Moving average of the given variant over plain values.
+pub fn build(average: Average, window: usize) -> MovingAverage {
+    match average {
+        Average::Simple => MovingAverage::Simple(SimpleMovingAverage::new(window)),
+        Average::Exponential => MovingAverage::Exponential(ExponentialMovingAverage::new(window)),
+        Average::Weighted => MovingAverage::Weighted(WeightedMovingAverage::new(window)),
+        Average::Hull => MovingAverage::Hull(HullMovingAverage::new(window)),
+    }
+}
+
+// Moving average of any variant.
+#[derive(Clone)]
+pub enum MovingAverage {
+    Simple(SimpleMovingAverage),
+    Exponential(ExponentialMovingAverage),
+    Weighted(WeightedMovingAverage),
+    Hull(HullMovingAverage),
+}
+
+impl MovingAverage {
+    fn inner(&self) -> &dyn Indicator<f64, Output = f64> {
+        match self {
+            MovingAverage::Simple(average) => average,
+            MovingAverage::Exponential(average) => average,
+            MovingAverage::Weighted(average) => average,
+            MovingAverage::Hull(average) => average,
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut dyn Indicator<f64, Output = f64> {
+        match self {
+            MovingAverage::Simple(average) => average,
+            MovingAverage::Exponential(average) => average,
+            MovingAverage::Weighted(average) => average,
+            MovingAverage::Hull(average) => average,
+        }
+    }
+}
+
+impl Indicator<f64> for MovingAverage {
+    type Output = f64;
+
+    fn update(&mut self, value: &f64) -> Option<f64> {
+        self.inner_mut().update(value)
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.inner().bars_needed()
+    }
+
+    fn ready(&self) -> bool {
+        self.inner().ready()
+    }
+}
+
+#[derive(Clone)]
+pub struct SimpleMovingAverage {
+    window: usize,
+    values: VecDeque<f64>,
+    sum: f64,
+}
+
+impl SimpleMovingAverage {
+    pub fn new(window: usize) -> SimpleMovingAverage {
+        let window = window.max(1);
+        SimpleMovingAverage {
+            window,
+            values: VecDeque::with_capacity(window + 1),
+            sum: 0.0,
+        }
+    }
+}
+
+impl Indicator<f64> for SimpleMovingAverage {
+    type Output = f64;
+
+    fn update(&mut self, value: &f64) -> Option<f64> {
+        self.values.push_back(*value);
+        self.sum += value;
+        if self.values.len() > self.window
+            && let Some(oldest) = self.values.pop_front()
+        {
+            self.sum -= oldest;
+        }
+        (self.values.len() == self.window).then(|| self.sum / self.window as f64)
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window
+    }
+
+    fn ready(&self) -> bool {
+        self.values.len() == self.window
+    }
+}
+
+// The averages follow the close when fed candles.
+impl Indicator for SimpleMovingAverage {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        Indicator::<f64>::update(self, &candle.close)
+    }
+
+    fn bars_needed(&self) -> usize {
+        Indicator::<f64>::bars_needed(self)
+    }
+
+    fn ready(&self) -> bool {
+        Indicator::<f64>::ready(self)
+    }
+}
+
+// Exponential moving average with a smoothing of 2 / (window + 1), seeded with the simple
+// average of the first window.
+#[derive(Clone)]
+pub struct ExponentialMovingAverage {
+    window: usize,
+    seed: SimpleMovingAverage,
+    value: Option<f64>,
+}
+
+impl ExponentialMovingAverage {
+    pub fn new(window: usize) -> ExponentialMovingAverage {
+        ExponentialMovingAverage {
+            window: window.max(1),
+            seed: SimpleMovingAverage::new(window),
+            value: None,
+        }
+    }
+}
+
+impl Indicator<f64> for ExponentialMovingAverage {
+    type Output = f64;
+
+    fn update(&mut self, value: &f64) -> Option<f64> {
+        let smoothing = 2.0 / (self.window as f64 + 1.0);
+        self.value = match self.value {
+            Some(average) => Some(average + smoothing * (value - average)),
+            None => self.seed.update(value),
+        };
+        self.value
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window
+    }
+
+    fn ready(&self) -> bool {
+        self.value.is_some()
+    }
+}
+
+impl Indicator for ExponentialMovingAverage {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        Indicator::<f64>::update(self, &candle.close)
+    }
+
+    fn bars_needed(&self) -> usize {
+        Indicator::<f64>::bars_needed(self)
+    }
+
+    fn ready(&self) -> bool {
+        Indicator::<f64>::ready(self)
+    }
+}
+
+// Linearly weighted moving average, the latest value has a weight of the window and the oldest a
+// weight of one.
+#[derive(Clone)]
+pub struct WeightedMovingAverage {
+    window: usize,
+    values: VecDeque<f64>,
+}
+
+impl WeightedMovingAverage {
+    pub fn new(window: usize) -> WeightedMovingAverage {
+        let window = window.max(1);
+        WeightedMovingAverage {
+            window,
+            values: VecDeque::with_capacity(window + 1),
+        }
+    }
+}
+
+impl Indicator<f64> for WeightedMovingAverage {
+    type Output = f64;
+
+    fn update(&mut self, value: &f64) -> Option<f64> {
+        self.values.push_back(*value);
+        if self.values.len() > self.window {
+            self.values.pop_front();
+        }
+        if self.values.len() < self.window {
+            return None;
+        }
+        let weighted: f64 = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (index + 1) as f64 * value)
+            .sum();
+        Some(weighted / (self.window * (self.window + 1) / 2) as f64)
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window
+    }
+
+    fn ready(&self) -> bool {
+        self.values.len() == self.window
+    }
+}
+
+impl Indicator for WeightedMovingAverage {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        Indicator::<f64>::update(self, &candle.close)
+    }
+
+    fn bars_needed(&self) -> usize {
+        Indicator::<f64>::bars_needed(self)
+    }
+
+    fn ready(&self) -> bool {
+        Indicator::<f64>::ready(self)
+    }
+}
+
+// Hull moving average: the weighted average over the square root of the window of twice the
+// weighted average over half the window minus the one over the whole window.
+#[derive(Clone)]
+pub struct HullMovingAverage {
+    half: WeightedMovingAverage,
+    full: WeightedMovingAverage,
+    smoothing: WeightedMovingAverage,
+}
+
+impl HullMovingAverage {
+    pub fn new(window: usize) -> HullMovingAverage {
+        let window = window.max(2);
+        HullMovingAverage {
+            half: WeightedMovingAverage::new(window / 2),
+            full: WeightedMovingAverage::new(window),
+            smoothing: WeightedMovingAverage::new((window as f64).sqrt().round() as usize),
+        }
+    }
+}
+
+impl Indicator<f64> for HullMovingAverage {
+    type Output = f64;
+
+    fn update(&mut self, value: &f64) -> Option<f64> {
+        let half = self.half.update(value);
+        let full = self.full.update(value)?;
+        self.smoothing.update(&(2.0 * half? - full))
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.full.window + self.smoothing.window - 1
+    }
+
+    fn ready(&self) -> bool {
+        Indicator::<f64>::ready(&self.smoothing)
+    }
+}
+
+impl Indicator for HullMovingAverage {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        Indicator::<f64>::update(self, &candle.close)
+    }
+
+    fn bars_needed(&self) -> usize {
+        Indicator::<f64>::bars_needed(self)
+    }
+
+    fn ready(&self) -> bool {
+        Indicator::<f64>::ready(self)
+    }
+}
+
+// Population standard deviation of the latest values over a window, the width of Bollinger bands
+// around the simple average.
+#[derive(Clone)]
+pub struct MovingDeviation {
+    window: usize,
+    values: VecDeque<f64>,
+}
+
+impl MovingDeviation {
+    pub fn new(window: usize) -> MovingDeviation {
+        let window = window.max(1);
+        MovingDeviation {
+            window,
+            values: VecDeque::with_capacity(window + 1),
+        }
+    }
+}
+
+impl Indicator<f64> for MovingDeviation {
+    type Output = f64;
+
+    fn update(&mut self, value: &f64) -> Option<f64> {
+        self.values.push_back(*value);
+        if self.values.len() > self.window {
+            self.values.pop_front();
+        }
+        if self.values.len() < self.window {
+            return None;
+        }
+        deviation(self.values.make_contiguous())
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window
+    }
+
+    fn ready(&self) -> bool {
+        self.values.len() == self.window
+    }
+}
@@ -0,0 +1,123 @@
+use crate::book::OrderBook;
+use crate::execution::Side;
+use crate::indicators::Indicator;
+use crate::market::Trade;
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flow {
+    // volume bought and sold by aggressors over the window
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+    // (buy - sell) / (buy + sell), within [-1, 1]
+    pub imbalance: f64,
+    // net aggressor volume of the large trades over the window, positive when buyers dominate
+    pub large_volume: f64,
+    // whether the latest trade is large
+    pub large: bool,
+    // (bid - ask) / (bid + ask) of the volumes resting on the book, None before it is known
+    pub book_imbalance: Option<f64>,
+}
+
+// Rolling aggressor buy/sell volume imbalance over a time window, along with the detection of
+// trades much larger than the average trade of the window and the imbalance of the book.
+pub struct OrderFlow {
+    // duration (in s) of the window
+    window: f64,
+    // a trade is large when its volume exceeds this multiple of the window's average trade volume
+    large_multiple: f64,
+
+    // trades of the window along with whether they were large
+    trades: VecDeque<(Trade, bool)>,
+    buy_volume: f64,
+    sell_volume: f64,
+    large_volume: f64,
+    book_imbalance: Option<f64>,
+}
+
+impl OrderFlow {
+    pub fn new(window: f64, large_multiple: f64) -> OrderFlow {
+        OrderFlow {
+            window,
+            large_multiple,
+            trades: VecDeque::new(),
+            buy_volume: 0.0,
+            sell_volume: 0.0,
+            large_volume: 0.0,
+            book_imbalance: None,
+        }
+    }
+
+    fn account(&mut self, trade: &Trade, large: bool, sign: f64) {
+        match trade.side {
+            Side::Buy => self.buy_volume += sign * trade.volume,
+            Side::Sell => self.sell_volume += sign * trade.volume,
+        }
+        if large {
+            self.large_volume += sign * trade.side.sign() * trade.volume;
+        }
+    }
+
+    // Follow the volumes resting on the book, returns the updated flow.
+    pub fn on_book(&mut self, book: &OrderBook) -> Option<Flow> {
+        let bid: f64 = book.bids().iter().map(|level| level.volume).sum();
+        let ask: f64 = book.asks().iter().map(|level| level.volume).sum();
+        self.book_imbalance = (bid + ask > 0.0).then(|| (bid - ask) / (bid + ask));
+        self.flow()
+    }
+
+    pub fn flow(&self) -> Option<Flow> {
+        if self.trades.is_empty() && self.book_imbalance.is_none() {
+            return None;
+        }
+        let total = self.buy_volume + self.sell_volume;
+        let large = self.trades.back().is_some_and(|(_, large)| *large);
+        Some(Flow {
+            buy_volume: self.buy_volume,
+            sell_volume: self.sell_volume,
+            imbalance: if total > 0.0 {
+                (self.buy_volume - self.sell_volume) / total
+            } else {
+                0.0
+            },
+            large_volume: self.large_volume,
+            large,
+            book_imbalance: self.book_imbalance,
+        })
+    }
+}
+
+impl Indicator<Trade> for OrderFlow {
+    type Output = Flow;
+
+    fn update(&mut self, trade: &Trade) -> Option<Flow> {
+        while let Some((oldest, large)) = self.trades.front().copied() {
+            if oldest.time >= trade.time - self.window {
+                break;
+            }
+            self.trades.pop_front();
+            self.account(&oldest, large, -1.0);
+        }
+
+        let average = if self.trades.is_empty() {
+            f64::INFINITY
+        } else {
+            (self.buy_volume + self.sell_volume) / self.trades.len() as f64
+        };
+        let large = trade.volume > self.large_multiple * average;
+
+        self.trades.push_back((*trade, large));
+        self.account(trade, large, 1.0);
+        self.flow()
+    }
+
+    fn bars_needed(&self) -> usize {
+        // the window is a duration, any trade gives a flow
+        1
+    }
+
+    fn ready(&self) -> bool {
+        !self.trades.is_empty()
+    }
+}
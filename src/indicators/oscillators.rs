@@ -0,0 +1,226 @@
+use crate::indicators::Indicator;
+use crate::indicators::channel::RollingExtrema;
+use crate::market::Candle;
+
+use std::collections::VecDeque;
+
+// Commodity channel index: deviation of the typical price from its moving average in units of
+// 1.5% of the mean absolute deviation, so most values fall within ±100. None while the typical
+// price is constant over the window.
+#[derive(Clone)]
+pub struct CommodityChannelIndex {
+    window: usize,
+    // typical prices of the latest candles
+    prices: VecDeque<f64>,
+}
+
+impl CommodityChannelIndex {
+    pub fn new(window: usize) -> CommodityChannelIndex {
+        let window = window.max(1);
+        CommodityChannelIndex {
+            window,
+            prices: VecDeque::with_capacity(window + 1),
+        }
+    }
+}
+
+impl Indicator for CommodityChannelIndex {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let typical = (candle.high + candle.low + candle.close) / 3.0;
+        self.prices.push_back(typical);
+        if self.prices.len() > self.window {
+            self.prices.pop_front();
+        }
+        if self.prices.len() < self.window {
+            return None;
+        }
+        let mean = self.prices.iter().sum::<f64>() / self.window as f64;
+        let deviation = self
+            .prices
+            .iter()
+            .map(|price| (price - mean).abs())
+            .sum::<f64>()
+            / self.window as f64;
+        (deviation > 0.0).then(|| (typical - mean) / (0.015 * deviation))
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window
+    }
+
+    fn ready(&self) -> bool {
+        self.prices.len() == self.window
+    }
+}
+
+// Williams %R: position of the close within the range of the latest candles, from -100 at the
+// lowest low to 0 at the highest high. None while the range is empty.
+#[derive(Clone)]
+pub struct WilliamsR {
+    extrema: RollingExtrema,
+}
+
+impl WilliamsR {
+    pub fn new(window: usize) -> WilliamsR {
+        WilliamsR {
+            extrema: RollingExtrema::new(window),
+        }
+    }
+}
+
+impl Indicator for WilliamsR {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        self.extrema.push(candle.high, candle.low);
+        let (high, low) = (self.extrema.max()?, self.extrema.min()?);
+        (high > low).then(|| -100.0 * (high - candle.close) / (high - low))
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.extrema.window()
+    }
+
+    fn ready(&self) -> bool {
+        self.extrema.ready()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Highs, lows and closes of a daily series, the reference values are computed from the
+    // definitions in exact arithmetic: Lambert's CCI over 20 days and Williams %R over 14 days.
+    const SERIES: [(f64, f64, f64); 23] = [
+        (25.05, 24.8, 24.9),
+        (25.2, 24.88, 25.1),
+        (25.4, 25.0, 25.32),
+        (25.38, 25.02, 25.1),
+        (25.3, 24.85, 24.95),
+        (25.1, 24.7, 24.82),
+        (25.0, 24.6, 24.95),
+        (25.25, 24.9, 25.2),
+        (25.5, 25.1, 25.45),
+        (25.7, 25.3, 25.62),
+        (25.8, 25.4, 25.48),
+        (25.6, 25.2, 25.3),
+        (25.45, 25.05, 25.12),
+        (25.3, 24.95, 25.25),
+        (25.6, 25.2, 25.55),
+        (25.9, 25.5, 25.85),
+        (26.1, 25.7, 25.95),
+        (26.0, 25.6, 25.7),
+        (25.8, 25.35, 25.42),
+        (25.6, 25.1, 25.2),
+        (25.4, 24.95, 25.05),
+        (25.3, 24.8, 25.25),
+        (25.7, 25.2, 25.65),
+    ];
+
+    const CCI: [f64; 4] = [
+        -2.0824844945447962,
+        -52.37724819083987,
+        -58.52635592412544,
+        50.66294487090021,
+    ];
+
+    const WILLIAMS_R: [f64; 10] = [
+        -45.833333333333336,
+        -20.833333333333332,
+        -3.8461538461538463,
+        -10.0,
+        -26.666666666666668,
+        -45.333333333333336,
+        -60.0,
+        -87.5,
+        -65.38461538461539,
+        -34.61538461538461,
+    ];
+
+    fn candles() -> Vec<Candle> {
+        SERIES
+            .iter()
+            .enumerate()
+            .map(|(day, (high, low, close))| Candle {
+                time: day as i64 * 86400,
+                open: *close,
+                high: *high,
+                low: *low,
+                close: *close,
+                ..Candle::default()
+            })
+            .collect()
+    }
+
+    // Values of an indicator once ready, along with how many candles it took.
+    fn ready_values(indicator: &mut impl Indicator<Output = f64>) -> (usize, Vec<f64>) {
+        let mut warming = 0;
+        let mut values = Vec::new();
+        for candle in candles() {
+            let value = indicator.update(&candle);
+            if !indicator.ready() {
+                warming += 1;
+                continue;
+            }
+            values.push(value.expect("value of a ready indicator"));
+        }
+        (warming, values)
+    }
+
+    fn assert_close(values: &[f64], expected: &[f64]) {
+        assert_eq!(values.len(), expected.len());
+        for (value, expected) in values.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-9,
+                "{} instead of {}",
+                value,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn commodity_channel_index_matches_the_reference() {
+        let (warming, values) = ready_values(&mut CommodityChannelIndex::new(20));
+        assert_eq!(warming, 19);
+        assert_close(&values, &CCI);
+    }
+
+    #[test]
+    fn commodity_channel_index_is_undefined_on_constant_prices() {
+        let mut cci = CommodityChannelIndex::new(3);
+        let candle = Candle {
+            high: 10.0,
+            low: 10.0,
+            close: 10.0,
+            ..Candle::default()
+        };
+        for _ in 0..5 {
+            assert_eq!(cci.update(&candle), None);
+        }
+    }
+
+    #[test]
+    fn williams_r_matches_the_reference() {
+        let (warming, values) = ready_values(&mut WilliamsR::new(14));
+        assert_eq!(warming, 13);
+        assert_close(&values, &WILLIAMS_R);
+    }
+
+    #[test]
+    fn williams_r_spans_the_range() {
+        let mut williams = WilliamsR::new(2);
+        let candle = |high, low, close| Candle {
+            high,
+            low,
+            close,
+            ..Candle::default()
+        };
+        williams.update(&candle(11.0, 9.0, 10.0));
+        assert_eq!(williams.update(&candle(12.0, 10.0, 12.0)), Some(0.0));
+        assert_eq!(williams.update(&candle(11.0, 8.0, 8.0)), Some(-100.0));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+
+use std::collections::VecDeque;
+
+// Quantile of the latest values with linear interpolation between closest ranks, q within [0, 1].
+// The window is also kept sorted so that any quantile of it is read without sorting: a value is
+// inserted and the oldest one removed by binary search on each update.
+#[derive(Clone)]
+pub struct RollingQuantile {
+    window: usize,
+    q: f64,
+    // values in arrival order
+    values: VecDeque<f64>,
+    sorted: Vec<f64>,
+}
+
+impl RollingQuantile {
+    pub fn new(window: usize, q: f64) -> RollingQuantile {
+        let window = window.max(1);
+        RollingQuantile {
+            window,
+            q: q.clamp(0.0, 1.0),
+            values: VecDeque::with_capacity(window + 1),
+            sorted: Vec::with_capacity(window + 1),
+        }
+    }
+
+    pub fn median(window: usize) -> RollingQuantile {
+        RollingQuantile::new(window, 0.5)
+    }
+
+    // Another quantile of the current window, None until it is full.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.sorted.len() < self.window || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        let rank = q * (self.window - 1) as f64;
+        let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+        Some(self.sorted[below] + (self.sorted[above] - self.sorted[below]) * (rank - below as f64))
+    }
+}
+
+impl Indicator<f64> for RollingQuantile {
+    type Output = f64;
+
+    fn update(&mut self, value: &f64) -> Option<f64> {
+        self.values.push_back(*value);
+        let index = self
+            .sorted
+            .partition_point(|held| held.total_cmp(value).is_lt());
+        self.sorted.insert(index, *value);
+        if self.values.len() > self.window
+            && let Some(oldest) = self.values.pop_front()
+            && let Ok(index) = self.sorted.binary_search_by(|held| held.total_cmp(&oldest))
+        {
+            self.sorted.remove(index);
+        }
+        self.quantile(self.q)
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window
+    }
+
+    fn ready(&self) -> bool {
+        self.values.len() == self.window
+    }
+}
+
+// The quantile follows the close when fed candles.
+impl Indicator for RollingQuantile {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        Indicator::<f64>::update(self, &candle.close)
+    }
+
+    fn bars_needed(&self) -> usize {
+        Indicator::<f64>::bars_needed(self)
+    }
+
+    fn ready(&self) -> bool {
+        Indicator::<f64>::ready(self)
+    }
+}
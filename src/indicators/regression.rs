@@ -0,0 +1,83 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+use crate::statistics::LinearFit;
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Channel {
+    // price change per candle of the fitted trend
+    pub slope: f64,
+    // slope relative to the fitted price, comparable across tickers
+    pub relative_slope: f64,
+    // share of the price variance explained by the trend, the trend strength
+    pub r_squared: f64,
+    // trend value at the latest candle
+    pub fitted: f64,
+    // standard error of the closes around the trend
+    pub error: f64,
+    // fitted value plus or minus the channel width times the standard error
+    pub upper: f64,
+    pub lower: f64,
+}
+
+// Rolling ordinary least squares regression of the closes against time (in candles).
+#[derive(Clone)]
+pub struct RegressionChannel {
+    window: usize,
+    // channel half width in standard errors
+    width: f64,
+    closes: VecDeque<f64>,
+}
+
+impl RegressionChannel {
+    pub fn new(window: usize, width: f64) -> RegressionChannel {
+        let window = window.max(3);
+        RegressionChannel {
+            window,
+            width,
+            closes: VecDeque::with_capacity(window),
+        }
+    }
+
+    pub fn channel(&self) -> Option<Channel> {
+        if self.closes.len() < self.window {
+            return None;
+        }
+        let x: Vec<f64> = (0..self.window).map(|index| index as f64).collect();
+        let y: Vec<f64> = self.closes.iter().copied().collect();
+        let fit = LinearFit::new(&x, &y)?;
+        let fitted = fit.predict((self.window - 1) as f64);
+        let error = (fit.residuals(&x, &y) / (self.window - 2) as f64).sqrt();
+        Some(Channel {
+            slope: fit.slope,
+            relative_slope: fit.slope / fitted,
+            // a flat series is perfectly explained by a flat trend
+            r_squared: fit.r_squared(&x, &y).unwrap_or(1.0),
+            fitted,
+            error,
+            upper: fitted + self.width * error,
+            lower: fitted - self.width * error,
+        })
+    }
+}
+
+impl Indicator for RegressionChannel {
+    type Output = Channel;
+
+    fn update(&mut self, candle: &Candle) -> Option<Channel> {
+        if self.closes.len() == self.window {
+            self.closes.pop_front();
+        }
+        self.closes.push_back(candle.close);
+        self.channel()
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window
+    }
+
+    fn ready(&self) -> bool {
+        self.closes.len() == self.window
+    }
+}
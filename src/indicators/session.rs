@@ -0,0 +1,36 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+use crate::sessions::TradingHours;
+
+// Number of whole candles since the trading session opened, None outside of trading hours.
+#[derive(Clone)]
+pub struct BarsSinceOpen {
+    hours: TradingHours,
+    // time between candles (in s)
+    interval: i64,
+}
+
+impl BarsSinceOpen {
+    pub fn new(hours: TradingHours, interval: i64) -> BarsSinceOpen {
+        BarsSinceOpen {
+            hours,
+            interval: interval.max(1),
+        }
+    }
+}
+
+impl Indicator for BarsSinceOpen {
+    type Output = i64;
+
+    fn update(&mut self, candle: &Candle) -> Option<i64> {
+        Some((candle.time - self.hours.opened_at(candle.time)?) / self.interval)
+    }
+
+    fn bars_needed(&self) -> usize {
+        0
+    }
+
+    fn ready(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,672 @@
+use crate::book::OrderBook;
+use crate::indicators::Indicator;
+use crate::indicators::atr::AverageTrueRange;
+use crate::indicators::batch;
+use crate::indicators::channel::DonchianChannel;
+use crate::indicators::garch::GarchVolatility;
+use crate::indicators::ichimoku::Ichimoku;
+use crate::indicators::kalman::KalmanSmoother;
+use crate::indicators::momentum::{Momentum, RateOfChange};
+use crate::indicators::moving_average::{self, Average, MovingDeviation};
+use crate::indicators::order_flow::OrderFlow;
+use crate::indicators::oscillators::{CommodityChannelIndex, WilliamsR};
+use crate::indicators::quantile::RollingQuantile;
+use crate::indicators::regression::RegressionChannel;
+use crate::indicators::session::BarsSinceOpen;
+use crate::indicators::supertrend::{Direction, SuperTrend};
+use crate::indicators::volatility::{Estimator, RealizedVolatility};
+use crate::indicators::volume_profile::VolumeProfile;
+use crate::indicators::vwap::{RollingVwap, SessionVwap};
+use crate::margin;
+use crate::market::{Candle, CandleUpdates, Field, Trade};
+use crate::sessions::TradingHours;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+// Indicator computed on every candle of the followed pairs, selected by its `kind`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IndicatorKind {
+    MovingAverage {
+        average: Average,
+        window: usize,
+        #[serde(default)]
+        field: Field,
+    },
+    // percentile of a field over a window, q within [0, 1], the median when left out
+    Quantile {
+        window: usize,
+        #[serde(default = "median")]
+        q: f64,
+        #[serde(default)]
+        field: Field,
+    },
+    // population standard deviation of a field over a window
+    Deviation {
+        window: usize,
+        #[serde(default)]
+        field: Field,
+    },
+    Atr {
+        window: usize,
+    },
+    Volatility {
+        window: usize,
+        estimator: Estimator,
+    },
+    // next candle volatility forecast of a GARCH(1,1) model fitted over a window of returns,
+    // refitted every `refit` candles
+    Garch {
+        window: usize,
+        refit: usize,
+    },
+    Vwap {
+        window: usize,
+    },
+    SessionVwap {
+        #[serde(default)]
+        hours: TradingHours,
+    },
+    // whole candles of the given interval (in min), the one of the feed, since the trading
+    // session opened, without value outside of trading hours
+    BarsSinceOpen {
+        #[serde(default)]
+        hours: TradingHours,
+        interval: i64,
+    },
+    Momentum {
+        lookback: usize,
+    },
+    RateOfChange {
+        lookback: usize,
+    },
+    Cci {
+        window: usize,
+    },
+    // close smoothed by a Kalman filter along with its velocity, following the price more
+    // closely with a higher process noise and smoother with a higher measurement noise
+    Kalman {
+        process_noise: f64,
+        measurement_noise: f64,
+    },
+    WilliamsR {
+        window: usize,
+    },
+    Donchian {
+        window: usize,
+    },
+    // ordinary least squares trend of the closes over a window, its channel `width` standard
+    // errors wide
+    Regression {
+        window: usize,
+        width: f64,
+    },
+    SuperTrend {
+        window: usize,
+        multiplier: f64,
+    },
+    Ichimoku {
+        tenkan: usize,
+        kijun: usize,
+        senkou: usize,
+        displacement: usize,
+    },
+    // latest hourly funding rate of a perpetual contract tracked in the margin section, e.g.
+    // PF_ETHUSD, or the rate predicted for the next period
+    Funding {
+        perpetual: String,
+        #[serde(default)]
+        predicted: bool,
+    },
+    // open interest of a tracked perpetual contract, or its relative change over a lookback of
+    // candles when one is given
+    OpenInterest {
+        perpetual: String,
+        #[serde(default)]
+        lookback: usize,
+    },
+    // point of control and value area of the volume traded per price bucket over a rolling
+    // session (in s), computed on the trades of the pair rather than on its candles
+    VolumeProfile {
+        bucket: f64,
+        session: f64,
+        #[serde(default = "value_area")]
+        value_area: f64,
+    },
+    // aggressor volume imbalance and large trades over a rolling window (in s) of the trades of
+    // the pair, along with the imbalance of its book, a trade being large beyond the given
+    // multiple of the average trade
+    OrderFlow {
+        window: f64,
+        large_multiple: f64,
+    },
+}
+
+impl IndicatorKind {
+    // Whether the indicator is computed on the trades of the pair.
+    pub fn trades(&self) -> bool {
+        matches!(
+            self,
+            IndicatorKind::VolumeProfile { .. } | IndicatorKind::OrderFlow { .. }
+        )
+    }
+
+    // Whether the indicator follows the book of the pair.
+    pub fn books(&self) -> bool {
+        matches!(self, IndicatorKind::OrderFlow { .. })
+    }
+}
+
+fn median() -> f64 {
+    0.5
+}
+
+fn value_area() -> f64 {
+    0.7
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct IndicatorConfig {
+    // key of the indicator's values in the snapshots, composite indicators add a suffix per value
+    pub name: String,
+    // closed candles only by default, intrabar indicators ingest every update of the forming one
+    #[serde(default)]
+    pub candles: CandleUpdates,
+    #[serde(flatten)]
+    pub kind: IndicatorKind,
+}
+
+// Feeds a candle to an indicator and names its values, None while it cannot be computed. Forked to
+// compute the values of a forming candle without advancing the indicator.
+trait Computation: Send {
+    fn compute(&mut self, candle: &Candle) -> Vec<(String, Option<f64>)>;
+
+    fn fork(&self) -> Compute;
+}
+
+type Compute = Box<dyn Computation>;
+
+impl<F> Computation for F
+where
+    F: FnMut(&Candle) -> Vec<(String, Option<f64>)> + Clone + Send + 'static,
+{
+    fn compute(&mut self, candle: &Candle) -> Vec<(String, Option<f64>)> {
+        self(candle)
+    }
+
+    fn fork(&self) -> Compute {
+        Box::new(self.clone())
+    }
+}
+
+fn computed<F>(compute: F) -> Compute
+where
+    F: FnMut(&Candle) -> Vec<(String, Option<f64>)> + Clone + Send + 'static,
+{
+    Box::new(compute)
+}
+
+fn single<I>(name: &str, mut indicator: I) -> Compute
+where
+    I: Indicator<Output = f64> + Clone + Send + 'static,
+{
+    let name = name.to_string();
+    computed(move |candle| vec![(name.clone(), indicator.update(candle))])
+}
+
+// Market data besides candles.
+#[derive(Clone, Copy)]
+enum Tick<'a> {
+    Trade(&'a Trade),
+    Book(&'a OrderBook),
+}
+
+// Feeds a trade or a book to an indicator and names its values, None when it ignores the tick.
+type Tape = Box<dyn FnMut(Tick) -> Option<Vec<(String, Option<f64>)>> + Send>;
+
+// Computation of an indicator over the candles of a pair, or over its trades and book.
+enum Source {
+    Candles(Compute),
+    Tape(Tape),
+}
+
+fn build(config: &IndicatorConfig) -> Source {
+    let name = config.name.clone();
+    Source::Candles(match config.kind.clone() {
+        IndicatorKind::MovingAverage {
+            average,
+            window,
+            field,
+        } => {
+            let mut average = moving_average::build(average, window);
+            computed(move |candle| vec![(name.clone(), average.update(&field.of(candle)))])
+        }
+        IndicatorKind::Quantile { window, q, field } => {
+            let mut quantile = RollingQuantile::new(window, q);
+            computed(move |candle| {
+                vec![(
+                    name.clone(),
+                    Indicator::<f64>::update(&mut quantile, &field.of(candle)),
+                )]
+            })
+        }
+        IndicatorKind::Deviation { window, field } => {
+            let mut deviation = MovingDeviation::new(window);
+            computed(move |candle| vec![(name.clone(), deviation.update(&field.of(candle)))])
+        }
+        IndicatorKind::Atr { window } => single(&name, AverageTrueRange::new(window)),
+        IndicatorKind::Volatility { window, estimator } => {
+            single(&name, RealizedVolatility::new(window, estimator))
+        }
+        IndicatorKind::Garch { window, refit } => {
+            single(&name, GarchVolatility::new(window, refit))
+        }
+        IndicatorKind::Vwap { window } => single(&name, RollingVwap::new(window)),
+        IndicatorKind::SessionVwap { hours } => single(&name, SessionVwap::new(hours)),
+        IndicatorKind::BarsSinceOpen { hours, interval } => {
+            let mut bars = BarsSinceOpen::new(hours, interval * 60);
+            computed(move |candle| {
+                vec![(name.clone(), bars.update(candle).map(|bars| bars as f64))]
+            })
+        }
+        IndicatorKind::Momentum { lookback } => single(&name, Momentum::new(lookback)),
+        IndicatorKind::RateOfChange { lookback } => single(&name, RateOfChange::new(lookback)),
+        IndicatorKind::Cci { window } => single(&name, CommodityChannelIndex::new(window)),
+        IndicatorKind::Kalman {
+            process_noise,
+            measurement_noise,
+        } => {
+            let mut kalman = KalmanSmoother::new(process_noise, measurement_noise);
+            computed(move |candle| {
+                let estimate = kalman.update(candle);
+                vec![
+                    (name.clone(), estimate.map(|estimate| estimate.price)),
+                    (
+                        format!("{}.velocity", name),
+                        estimate.map(|estimate| estimate.velocity),
+                    ),
+                ]
+            })
+        }
+        IndicatorKind::WilliamsR { window } => single(&name, WilliamsR::new(window)),
+        IndicatorKind::Donchian { window } => {
+            let mut donchian = DonchianChannel::new(window);
+            computed(move |candle| {
+                let channel = donchian.update(candle);
+                vec![
+                    (
+                        format!("{}.upper", name),
+                        channel.map(|channel| channel.upper),
+                    ),
+                    (
+                        format!("{}.lower", name),
+                        channel.map(|channel| channel.lower),
+                    ),
+                ]
+            })
+        }
+        IndicatorKind::Regression { window, width } => {
+            let mut regression = RegressionChannel::new(window, width);
+            computed(move |candle| {
+                let channel = regression.update(candle);
+                [
+                    ("slope", channel.map(|channel| channel.slope)),
+                    (
+                        "relative_slope",
+                        channel.map(|channel| channel.relative_slope),
+                    ),
+                    ("r_squared", channel.map(|channel| channel.r_squared)),
+                    ("fitted", channel.map(|channel| channel.fitted)),
+                    ("error", channel.map(|channel| channel.error)),
+                    ("upper", channel.map(|channel| channel.upper)),
+                    ("lower", channel.map(|channel| channel.lower)),
+                ]
+                .into_iter()
+                .map(|(value, channel)| (format!("{}.{}", name, value), channel))
+                .collect()
+            })
+        }
+        IndicatorKind::SuperTrend { window, multiplier } => {
+            let mut supertrend = SuperTrend::new(window, multiplier);
+            computed(move |candle| {
+                let trend = supertrend.update(candle);
+                vec![
+                    (name.clone(), trend.map(|trend| trend.value)),
+                    (
+                        format!("{}.direction", name),
+                        trend.map(|trend| match trend.direction {
+                            Direction::Up => 1.0,
+                            Direction::Down => -1.0,
+                        }),
+                    ),
+                ]
+            })
+        }
+        IndicatorKind::Ichimoku {
+            tenkan,
+            kijun,
+            senkou,
+            displacement,
+        } => {
+            let mut ichimoku = Ichimoku::new(tenkan, kijun, senkou, displacement);
+            computed(move |candle| {
+                let cloud = ichimoku.update(candle);
+                [
+                    ("tenkan", cloud.map(|cloud| cloud.tenkan)),
+                    ("kijun", cloud.map(|cloud| cloud.kijun)),
+                    ("senkou_a", cloud.map(|cloud| cloud.senkou_a)),
+                    ("senkou_b", cloud.map(|cloud| cloud.senkou_b)),
+                    ("leading_a", cloud.map(|cloud| cloud.leading_a)),
+                    ("leading_b", cloud.map(|cloud| cloud.leading_b)),
+                ]
+                .into_iter()
+                .map(|(line, value)| (format!("{}.{}", name, line), value))
+                .collect()
+            })
+        }
+        IndicatorKind::Funding {
+            perpetual,
+            predicted,
+        } => computed(move |_| {
+            let funding = margin::funding(&perpetual);
+            let rate = if predicted {
+                funding.and_then(|funding| funding.predicted)
+            } else {
+                funding.map(|funding| funding.rate)
+            };
+            vec![(name.clone(), rate)]
+        }),
+        IndicatorKind::OpenInterest {
+            perpetual,
+            lookback,
+        } => {
+            let mut history: VecDeque<f64> = VecDeque::with_capacity(lookback + 1);
+            computed(move |_| {
+                let Some(funding) = margin::funding(&perpetual) else {
+                    return vec![(name.clone(), None)];
+                };
+                if lookback == 0 {
+                    return vec![(name.clone(), Some(funding.open_interest))];
+                }
+                history.push_back(funding.open_interest);
+                if history.len() > lookback + 1 {
+                    history.pop_front();
+                }
+                let change = history
+                    .front()
+                    .filter(|first| history.len() > lookback && **first > 0.0)
+                    .map(|first| funding.open_interest / first - 1.0);
+                vec![(name.clone(), change)]
+            })
+        }
+        IndicatorKind::VolumeProfile {
+            bucket,
+            session,
+            value_area,
+        } => {
+            let mut profile = VolumeProfile::new(bucket, session, value_area).ok();
+            return Source::Tape(Box::new(move |tick| {
+                let Tick::Trade(trade) = tick else {
+                    return None;
+                };
+                let profile = profile.as_mut().and_then(|profile| profile.update(trade));
+                let values = [
+                    ("poc", profile.map(|profile| profile.poc)),
+                    (
+                        "value_area_low",
+                        profile.map(|profile| profile.value_area_low),
+                    ),
+                    (
+                        "value_area_high",
+                        profile.map(|profile| profile.value_area_high),
+                    ),
+                    ("volume", profile.map(|profile| profile.volume)),
+                ]
+                .into_iter()
+                .map(|(value, profile)| (format!("{}.{}", name, value), profile))
+                .collect();
+                Some(values)
+            }));
+        }
+        IndicatorKind::OrderFlow {
+            window,
+            large_multiple,
+        } => {
+            let mut flow = OrderFlow::new(window, large_multiple);
+            return Source::Tape(Box::new(move |tick| {
+                let flow = match tick {
+                    Tick::Trade(trade) => flow.update(trade),
+                    Tick::Book(book) => flow.on_book(book),
+                };
+                let values = [
+                    ("buy_volume", flow.map(|flow| flow.buy_volume)),
+                    ("sell_volume", flow.map(|flow| flow.sell_volume)),
+                    ("imbalance", flow.map(|flow| flow.imbalance)),
+                    ("large_volume", flow.map(|flow| flow.large_volume)),
+                    ("large", flow.map(|flow| f64::from(u8::from(flow.large)))),
+                    ("book_imbalance", flow.and_then(|flow| flow.book_imbalance)),
+                ]
+                .into_iter()
+                .map(|(value, flow)| (format!("{}.{}", name, value), flow))
+                .collect();
+                Some(values)
+            }));
+        }
+    })
+}
+
+// Check the parameters of the indicators that would otherwise never give a value.
+pub fn validate(configs: &[IndicatorConfig]) -> Result<(), String> {
+    for config in configs {
+        if let IndicatorKind::VolumeProfile { bucket, .. } = config.kind {
+            VolumeProfile::new(bucket, 0.0, 0.0)
+                .map_err(|message| format!("Indicator {}: {}", config.name, message))?;
+        }
+    }
+    Ok(())
+}
+
+// Values of an indicator by name, aligned with the candles they were computed over.
+pub type Series = Vec<(String, Vec<Option<f64>>)>;
+
+// Values of an indicator over a whole series of candles, by name. Simple and exponential averages
+// and deviations are computed in batch, the other indicators candle by candle.
+pub fn series(config: &IndicatorConfig, candles: &[Candle]) -> Series {
+    let values =
+        |field: Field| -> Vec<f64> { candles.iter().map(|candle| field.of(candle)).collect() };
+    let batched = match &config.kind {
+        IndicatorKind::MovingAverage {
+            average: Average::Simple,
+            window,
+            field,
+        } => Some(batch::sma(&values(*field), *window)),
+        IndicatorKind::MovingAverage {
+            average: Average::Exponential,
+            window,
+            field,
+        } => Some(batch::ema(&values(*field), *window)),
+        IndicatorKind::Deviation { window, field } => {
+            Some(batch::deviation(&values(*field), *window))
+        }
+        _ => None,
+    };
+    if let Some(batched) = batched {
+        return vec![(config.name.clone(), batched)];
+    }
+    // trades are not replayed in backtests
+    let Source::Candles(mut compute) = build(config) else {
+        return Vec::new();
+    };
+    let mut series: Vec<(String, Vec<Option<f64>>)> = Vec::new();
+    for (index, candle) in candles.iter().enumerate() {
+        for (position, (name, value)) in compute.compute(candle).into_iter().enumerate() {
+            if position == series.len() {
+                series.push((name, vec![None; index]));
+            }
+            series[position].1.push(value);
+        }
+    }
+    series
+}
+
+// Latest values of the indicators of a pair.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+pub struct Snapshot {
+    // unix time (in s) of the candle the values were computed on
+    pub time: i64,
+    // values by name, None while an indicator is warming up
+    pub values: BTreeMap<String, Option<f64>>,
+}
+
+impl Snapshot {
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.values.get(name).copied().flatten()
+    }
+}
+
+fn snapshots() -> &'static Mutex<HashMap<String, Snapshot>> {
+    static SNAPSHOTS: OnceLock<Mutex<HashMap<String, Snapshot>>> = OnceLock::new();
+    SNAPSHOTS.get_or_init(Mutex::default)
+}
+
+// Replace the latest values of the indicators of a pair.
+pub fn publish(ticker: &str, snapshot: Snapshot) {
+    if let Ok(mut snapshots) = snapshots().lock() {
+        snapshots.insert(ticker.to_string(), snapshot);
+    }
+}
+
+// Latest values of the configured indicators for a pair, None before its first candle.
+pub fn snapshot(ticker: &str) -> Option<Snapshot> {
+    snapshots()
+        .lock()
+        .ok()
+        .and_then(|snapshots| snapshots.get(ticker).cloned())
+}
+
+// Indicator of a pair along with its latest values.
+struct Computed {
+    candles: CandleUpdates,
+    source: Source,
+    values: Vec<(String, Option<f64>)>,
+    // latest update of the forming candle for intrabar indicators, only fed once a later candle
+    // starts so that the updates of a candle replace each other
+    forming: Option<Candle>,
+}
+
+// Computes the configured indicators on the candles of each pair as they arrive and publishes
+// their latest values in the process wide registry.
+pub struct Indicators {
+    configs: Vec<IndicatorConfig>,
+    computed: HashMap<String, Vec<Computed>>,
+}
+
+impl Indicators {
+    pub fn new(configs: Vec<IndicatorConfig>) -> Indicators {
+        Indicators {
+            configs,
+            computed: HashMap::new(),
+        }
+    }
+
+    // Feed a candle, closed or an update of the forming one, to the indicators ingesting it.
+    // Returns the values of every indicator of the pair, None when no indicator ingested it.
+    pub fn update(
+        &mut self,
+        ticker: &str,
+        candle: &Candle,
+        candles: CandleUpdates,
+    ) -> Option<Snapshot> {
+        if !self.configs.iter().any(|config| config.candles == candles) {
+            return None;
+        }
+        let computed = self.computed(ticker);
+        for indicator in computed.iter_mut() {
+            let Source::Candles(compute) = &mut indicator.source else {
+                continue;
+            };
+            if indicator.candles != candles {
+                continue;
+            }
+            match candles {
+                CandleUpdates::Closed => indicator.values = compute.compute(candle),
+                CandleUpdates::Intrabar => {
+                    if let Some(forming) = indicator.forming {
+                        if candle.time < forming.time {
+                            continue;
+                        }
+                        if candle.time > forming.time {
+                            compute.compute(&forming);
+                        }
+                    }
+                    indicator.values = compute.fork().compute(candle);
+                    indicator.forming = Some(*candle);
+                }
+            }
+        }
+        let snapshot = Snapshot {
+            time: candle.time,
+            values: computed
+                .iter()
+                .flat_map(|indicator| indicator.values.iter().cloned())
+                .collect(),
+        };
+        publish(ticker, snapshot.clone());
+        Some(snapshot)
+    }
+
+    // Feed a trade to the indicators computed on trades, their values land in the snapshot of the
+    // next candle.
+    pub fn trade(&mut self, ticker: &str, trade: &Trade) {
+        if self.configs.iter().any(|config| config.kind.trades()) {
+            self.tick(ticker, Tick::Trade(trade));
+        }
+    }
+
+    // Feed the updated book of a pair to the indicators following it.
+    pub fn book(&mut self, ticker: &str, book: &OrderBook) {
+        if self.books() {
+            self.tick(ticker, Tick::Book(book));
+        }
+    }
+
+    // Whether indicators follow the books of the pairs.
+    pub fn books(&self) -> bool {
+        self.configs.iter().any(|config| config.kind.books())
+    }
+
+    fn tick(&mut self, ticker: &str, tick: Tick) {
+        for indicator in self.computed(ticker) {
+            if let Source::Tape(tape) = &mut indicator.source
+                && let Some(values) = tape(tick)
+            {
+                indicator.values = values;
+            }
+        }
+    }
+
+    fn computed(&mut self, ticker: &str) -> &mut Vec<Computed> {
+        self.computed.entry(ticker.to_string()).or_insert_with(|| {
+            self.configs
+                .iter()
+                .map(|config| Computed {
+                    candles: config.candles,
+                    source: build(config),
+                    values: Vec::new(),
+                    forming: None,
+                })
+                .collect()
+        })
+    }
+
+    // Drop the indicators of a pair no longer followed.
+    pub fn forget(&mut self, ticker: &str) {
+        self.computed.remove(ticker);
+        if let Ok(mut snapshots) = snapshots().lock() {
+            snapshots.remove(ticker);
+        }
+    }
+}
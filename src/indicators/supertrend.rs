@@ -0,0 +1,103 @@
+use crate::indicators::Indicator;
+use crate::indicators::atr::AverageTrueRange;
+use crate::market::Candle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trend {
+    // trailing band followed: the lower one in an up trend, the upper one in a down trend
+    pub value: f64,
+    pub direction: Direction,
+    // whether the direction changed on this candle
+    pub flipped: bool,
+}
+
+// Bands trailing the price by a multiple of the average true range around the candle midpoint.
+// The lower band only rises and the upper one only falls while the trend lasts, a close through
+// the band followed flips the direction.
+#[derive(Clone)]
+pub struct SuperTrend {
+    multiplier: f64,
+    atr: AverageTrueRange,
+
+    previous_close: Option<f64>,
+    // (upper, lower) bands and direction of the previous candle
+    bands: Option<(f64, f64)>,
+    direction: Direction,
+    // whether a trend was given
+    trending: bool,
+}
+
+impl SuperTrend {
+    pub fn new(window: usize, multiplier: f64) -> SuperTrend {
+        SuperTrend {
+            multiplier,
+            atr: AverageTrueRange::new(window),
+            previous_close: None,
+            bands: None,
+            direction: Direction::Up,
+            trending: false,
+        }
+    }
+}
+
+impl Indicator for SuperTrend {
+    type Output = Trend;
+
+    fn update(&mut self, candle: &Candle) -> Option<Trend> {
+        let previous_close = self.previous_close.replace(candle.close);
+        let atr = self.atr.update(candle)?;
+        let middle = (candle.high + candle.low) / 2.0;
+        let mut upper = middle + self.multiplier * atr;
+        let mut lower = middle - self.multiplier * atr;
+
+        let Some((previous_upper, previous_lower)) = self.bands else {
+            self.bands = Some((upper, lower));
+            self.direction = if candle.close >= middle {
+                Direction::Up
+            } else {
+                Direction::Down
+            };
+            return None;
+        };
+        let previous_close = previous_close.unwrap_or(candle.close);
+        if upper > previous_upper && previous_close <= previous_upper {
+            upper = previous_upper;
+        }
+        if lower < previous_lower && previous_close >= previous_lower {
+            lower = previous_lower;
+        }
+        self.bands = Some((upper, lower));
+
+        let direction = match self.direction {
+            Direction::Up if candle.close < lower => Direction::Down,
+            Direction::Down if candle.close > upper => Direction::Up,
+            direction => direction,
+        };
+        let flipped = direction != self.direction;
+        self.direction = direction;
+        self.trending = true;
+        Some(Trend {
+            value: match direction {
+                Direction::Up => lower,
+                Direction::Down => upper,
+            },
+            direction,
+            flipped,
+        })
+    }
+
+    fn bars_needed(&self) -> usize {
+        // the first average only sets the bands
+        self.atr.bars_needed() + 1
+    }
+
+    fn ready(&self) -> bool {
+        self.trending
+    }
+}
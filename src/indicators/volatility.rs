@@ -0,0 +1,180 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+use crate::statistics::{deviation, mean, quantile};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+use std::f64::consts::LN_2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Estimator {
+    // deviation of the logarithmic close to close returns
+    CloseToClose,
+    // from the high/low ranges, more efficient when prices move continuously
+    Parkinson,
+    // from the high/low ranges and open/close moves
+    GarmanKlass,
+}
+
+// Scale a per candle volatility to a longer horizon, e.g. a year of candles.
+pub fn annualize(volatility: f64, periods: f64) -> f64 {
+    volatility * periods.sqrt()
+}
+
+// Rolling per candle realized volatility.
+#[derive(Clone)]
+pub struct RealizedVolatility {
+    // number of candles the estimation is made over
+    window: usize,
+    estimator: Estimator,
+    // latest candles, one more than the window for close to close returns
+    candles: VecDeque<Candle>,
+}
+
+impl RealizedVolatility {
+    pub fn new(window: usize, estimator: Estimator) -> RealizedVolatility {
+        let window = window.max(2);
+        RealizedVolatility {
+            window,
+            estimator,
+            candles: VecDeque::with_capacity(window + 1),
+        }
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        if self.candles.len() <= self.window {
+            return None;
+        }
+        let window = self.candles.iter().skip(1);
+        match self.estimator {
+            Estimator::CloseToClose => {
+                let returns: Vec<f64> = self
+                    .candles
+                    .iter()
+                    .zip(window)
+                    .map(|(previous, candle)| (candle.close / previous.close).ln())
+                    .collect();
+                deviation(&returns)
+            }
+            Estimator::Parkinson => {
+                let ranges: Vec<f64> = window
+                    .map(|candle| (candle.high / candle.low).ln().powi(2))
+                    .collect();
+                Some((mean(&ranges)? / (4.0 * LN_2)).sqrt())
+            }
+            Estimator::GarmanKlass => {
+                let terms: Vec<f64> = window
+                    .map(|candle| {
+                        0.5 * (candle.high / candle.low).ln().powi(2)
+                            - (2.0 * LN_2 - 1.0) * (candle.close / candle.open).ln().powi(2)
+                    })
+                    .collect();
+                Some(mean(&terms)?.max(0.0).sqrt())
+            }
+        }
+    }
+}
+
+impl Indicator for RealizedVolatility {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        if self.candles.len() > self.window {
+            self.candles.pop_front();
+        }
+        self.candles.push_back(*candle);
+        self.value().filter(|value| value.is_finite())
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window + 1
+    }
+
+    fn ready(&self) -> bool {
+        self.candles.len() > self.window
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Regime {
+    Low,
+    Normal,
+    High,
+}
+
+// Classifies the realized volatility against its own recent distribution: below the low
+// quantile of the past values the regime is low, above the high quantile it is high.
+pub struct VolatilityRegime {
+    volatility: RealizedVolatility,
+    // number of past volatility values the quantiles are computed over
+    lookback: usize,
+    low: f64,
+    high: f64,
+
+    history: VecDeque<f64>,
+    current: Option<(f64, Regime)>,
+}
+
+impl VolatilityRegime {
+    pub fn new(
+        volatility: RealizedVolatility,
+        lookback: usize,
+        low: f64,
+        high: f64,
+    ) -> Result<VolatilityRegime, String> {
+        if !(0.0..=1.0).contains(&low) || !(low..=1.0).contains(&high) {
+            return Err(format!("Invalid regime quantiles {} and {}", low, high));
+        }
+        Ok(VolatilityRegime {
+            volatility,
+            lookback: lookback.max(1),
+            low,
+            high,
+            history: VecDeque::with_capacity(lookback.max(1)),
+            current: None,
+        })
+    }
+
+    // Latest volatility and its regime.
+    pub fn current(&self) -> Option<(f64, Regime)> {
+        self.current
+    }
+
+    pub fn regime(&self) -> Option<Regime> {
+        self.current.map(|(_, regime)| regime)
+    }
+}
+
+impl Indicator for VolatilityRegime {
+    type Output = (f64, Regime);
+
+    fn update(&mut self, candle: &Candle) -> Option<(f64, Regime)> {
+        let volatility = self.volatility.update(candle)?;
+        if self.history.len() == self.lookback {
+            self.history.pop_front();
+        }
+        self.history.push_back(volatility);
+
+        let history: Vec<f64> = self.history.iter().copied().collect();
+        let regime = if volatility < quantile(&history, self.low)? {
+            Regime::Low
+        } else if volatility > quantile(&history, self.high)? {
+            Regime::High
+        } else {
+            Regime::Normal
+        };
+        self.current = Some((volatility, regime));
+        self.current
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.volatility.bars_needed() + self.lookback - 1
+    }
+
+    fn ready(&self) -> bool {
+        self.history.len() == self.lookback
+    }
+}
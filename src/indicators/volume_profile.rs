@@ -0,0 +1,143 @@
+use crate::indicators::Indicator;
+use crate::market::Trade;
+
+use std::collections::{BTreeMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Profile {
+    // point of control: price level with the most traded volume
+    pub poc: f64,
+    // bounds of the price range around the point of control holding the value area share of the
+    // volume
+    pub value_area_low: f64,
+    pub value_area_high: f64,
+    // volume traded over the session
+    pub volume: f64,
+}
+
+// Histogram of the volume traded at each price level over a rolling session, price levels being
+// buckets of fixed width.
+pub struct VolumeProfile {
+    // width of the price buckets
+    bucket: f64,
+    // duration (in s) of the rolling session
+    session: f64,
+    // share of the volume the value area holds, usually 0.7
+    value_area: f64,
+
+    trades: VecDeque<Trade>,
+    // volume per bucket index
+    histogram: BTreeMap<i64, f64>,
+}
+
+impl VolumeProfile {
+    pub fn new(bucket: f64, session: f64, value_area: f64) -> Result<VolumeProfile, String> {
+        if bucket <= 0.0 {
+            return Err("Volume profile bucket width must be positive.".into());
+        }
+        Ok(VolumeProfile {
+            bucket,
+            session,
+            value_area: value_area.clamp(0.0, 1.0),
+            trades: VecDeque::new(),
+            histogram: BTreeMap::new(),
+        })
+    }
+
+    fn index(&self, price: f64) -> i64 {
+        (price / self.bucket).floor() as i64
+    }
+
+    // Center price of a bucket.
+    fn price(&self, index: i64) -> f64 {
+        (index as f64 + 0.5) * self.bucket
+    }
+
+    pub fn add(&mut self, trade: &Trade) {
+        *self.histogram.entry(self.index(trade.price)).or_insert(0.0) += trade.volume;
+        self.trades.push_back(*trade);
+
+        while let Some(oldest) = self.trades.front().copied() {
+            if oldest.time >= trade.time - self.session {
+                break;
+            }
+            self.trades.pop_front();
+            let index = self.index(oldest.price);
+            if let Some(volume) = self.histogram.get_mut(&index) {
+                *volume -= oldest.volume;
+                if *volume <= 0.0 {
+                    self.histogram.remove(&index);
+                }
+            }
+        }
+    }
+
+    // Volume traded at the price level of the given price.
+    pub fn volume_at(&self, price: f64) -> f64 {
+        self.histogram
+            .get(&self.index(price))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    pub fn profile(&self) -> Option<Profile> {
+        let (poc, _) = self
+            .histogram
+            .iter()
+            .max_by(|first, second| first.1.total_cmp(second.1))?;
+        let total: f64 = self.histogram.values().sum();
+
+        // grow the value area from the point of control towards the heavier neighbouring level
+        let (mut low, mut high) = (*poc, *poc);
+        let mut covered = self.histogram[poc];
+        let volume = |index: &i64| self.histogram.get(index).copied().unwrap_or(0.0);
+        let (first, last) = (
+            *self.histogram.keys().next()?,
+            *self.histogram.keys().next_back()?,
+        );
+        while covered < self.value_area * total && (low > first || high < last) {
+            let below = if low > first {
+                volume(&(low - 1))
+            } else {
+                -1.0
+            };
+            let above = if high < last {
+                volume(&(high + 1))
+            } else {
+                -1.0
+            };
+            if above >= below {
+                high += 1;
+                covered += above;
+            } else {
+                low -= 1;
+                covered += below;
+            }
+        }
+
+        Some(Profile {
+            poc: self.price(*poc),
+            value_area_low: low as f64 * self.bucket,
+            value_area_high: (high + 1) as f64 * self.bucket,
+            volume: total,
+        })
+    }
+}
+
+impl Indicator<Trade> for VolumeProfile {
+    type Output = Profile;
+
+    fn update(&mut self, trade: &Trade) -> Option<Profile> {
+        self.add(trade);
+        self.profile()
+    }
+
+    fn bars_needed(&self) -> usize {
+        // the session is a duration, any trade gives a profile
+        1
+    }
+
+    fn ready(&self) -> bool {
+        !self.trades.is_empty()
+    }
+}
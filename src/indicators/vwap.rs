@@ -0,0 +1,110 @@
+use crate::indicators::Indicator;
+use crate::market::Candle;
+use crate::sessions::TradingHours;
+
+use std::collections::VecDeque;
+
+// Price the volume of a candle traded at: its own VWAP, or the typical price when Kraken gave
+// none.
+fn price(candle: &Candle) -> f64 {
+    if candle.vwap.is_finite() && candle.vwap > 0.0 {
+        candle.vwap
+    } else {
+        (candle.high + candle.low + candle.close) / 3.0
+    }
+}
+
+// Volume weighted average price over the latest candles, None until the window is filled or
+// while no volume was traded over it.
+#[derive(Clone)]
+pub struct RollingVwap {
+    window: usize,
+    // (price times volume, volume) of the latest candles
+    candles: VecDeque<(f64, f64)>,
+    traded: f64,
+    volume: f64,
+}
+
+impl RollingVwap {
+    pub fn new(window: usize) -> RollingVwap {
+        let window = window.max(1);
+        RollingVwap {
+            window,
+            candles: VecDeque::with_capacity(window + 1),
+            traded: 0.0,
+            volume: 0.0,
+        }
+    }
+}
+
+impl Indicator for RollingVwap {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let traded = price(candle) * candle.volume;
+        self.candles.push_back((traded, candle.volume));
+        self.traded += traded;
+        self.volume += candle.volume;
+        if self.candles.len() > self.window
+            && let Some((traded, volume)) = self.candles.pop_front()
+        {
+            self.traded -= traded;
+            self.volume -= volume;
+        }
+        (self.candles.len() == self.window && self.volume > 0.0).then(|| self.traded / self.volume)
+    }
+
+    fn bars_needed(&self) -> usize {
+        self.window
+    }
+
+    fn ready(&self) -> bool {
+        self.candles.len() == self.window && self.volume > 0.0
+    }
+}
+
+// Volume weighted average price accumulated since the trading session opened, None outside of
+// trading hours and until volume is traded in the session.
+#[derive(Clone)]
+pub struct SessionVwap {
+    hours: TradingHours,
+    // unix time (in s) the accumulated session opened
+    opened: Option<i64>,
+    traded: f64,
+    volume: f64,
+}
+
+impl SessionVwap {
+    pub fn new(hours: TradingHours) -> SessionVwap {
+        SessionVwap {
+            hours,
+            opened: None,
+            traded: 0.0,
+            volume: 0.0,
+        }
+    }
+}
+
+impl Indicator for SessionVwap {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let opened = self.hours.opened_at(candle.time)?;
+        if self.opened != Some(opened) {
+            self.opened = Some(opened);
+            self.traded = 0.0;
+            self.volume = 0.0;
+        }
+        self.traded += price(candle) * candle.volume;
+        self.volume += candle.volume;
+        (self.volume > 0.0).then(|| self.traded / self.volume)
+    }
+
+    fn bars_needed(&self) -> usize {
+        1
+    }
+
+    fn ready(&self) -> bool {
+        self.opened.is_some() && self.volume > 0.0
+    }
+}
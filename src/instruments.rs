@@ -0,0 +1,249 @@
+use crate::environment;
+use crate::execution::{Order, OrderKind, normalize_asset};
+use crate::market::to_float;
+
+use kraken_async_rs::clients::core_kraken_client::CoreKrakenClient;
+use kraken_async_rs::clients::http_response_types::ResultErrorResponse;
+use kraken_async_rs::clients::kraken_client::KrakenClient;
+use kraken_async_rs::crypto::nonce_provider::{IncreasingNonceProvider, NonceProvider};
+use kraken_async_rs::request_types::TradableAssetPairsRequest;
+use kraken_async_rs::response_types::TradableAssetPair;
+use kraken_async_rs::secrets::secrets_provider::{SecretsProvider, StaticSecretsProvider};
+
+use serde::{Deserialize, Serialize};
+
+use tokio::sync::Mutex;
+use tokio::time::interval;
+
+use tracing::{info, warn};
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InstrumentConfig {
+    // seconds between reloads of the reference data, never reloaded when unset
+    pub refresh: Option<u64>,
+}
+
+impl Default for InstrumentConfig {
+    fn default() -> InstrumentConfig {
+        InstrumentConfig {
+            refresh: Some(3600),
+        }
+    }
+}
+
+// Reference data of a tradable pair.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Instrument {
+    // pair as named in the websocket feed, e.g. ETH/EUR
+    pub ticker: String,
+    // pair as named in the REST API, e.g. XETHZEUR
+    pub name: String,
+    pub base: String,
+    pub quote: String,
+    // price increment
+    pub tick_size: f64,
+    // volume increment
+    pub lot_size: f64,
+    // smallest volume of an order
+    pub min_volume: f64,
+    // smallest value (in quote currency) of an order
+    pub min_cost: f64,
+    // whether orders are accepted, e.g. not in maintenance or delisted
+    pub online: bool,
+}
+
+impl From<&TradableAssetPair> for Instrument {
+    fn from(pair: &TradableAssetPair) -> Instrument {
+        Instrument {
+            ticker: pair.ws_name.clone(),
+            name: pair.alt_name.clone(),
+            base: normalize_asset(&pair.base),
+            quote: normalize_asset(&pair.quote),
+            tick_size: pair
+                .tick_size
+                .as_ref()
+                .map_or(10f64.powi(-(pair.pair_decimals as i32)), to_float),
+            lot_size: 10f64.powi(-(pair.lot_decimals as i32)),
+            min_volume: pair.order_min.as_ref().map_or(0.0, to_float),
+            min_cost: pair.cost_min.as_ref().map_or(0.0, to_float),
+            online: format!("{:?}", pair.status).eq_ignore_ascii_case("online"),
+        }
+    }
+}
+
+impl Instrument {
+    // Price rounded to the nearest tick.
+    pub fn round_price(&self, price: f64) -> f64 {
+        match self.tick_size > 0.0 {
+            true => (price / self.tick_size).round() * self.tick_size,
+            false => price,
+        }
+    }
+
+    // Volume rounded down to a whole number of lots, so that it is never increased.
+    pub fn round_volume(&self, volume: f64) -> f64 {
+        match self.lot_size > 0.0 {
+            // tolerate the representation error of volumes that are already whole lots
+            true => (volume / self.lot_size + 1e-9).floor() * self.lot_size,
+            false => volume,
+        }
+    }
+
+    // Check an order against the trading rules of the pair, market orders are valued at the
+    // given price when known.
+    pub fn validate(&self, order: &Order, price: Option<f64>) -> Result<(), String> {
+        if !self.online {
+            return Err(format!("{} is not trading", self.ticker));
+        }
+        if order.volume < self.min_volume {
+            return Err(format!(
+                "volume {} below the minimum {} of {}",
+                order.volume, self.min_volume, self.ticker
+            ));
+        }
+        let price = match order.kind {
+            OrderKind::Limit(price) => Some(price),
+            OrderKind::Market => price,
+        };
+        if let Some(price) = price
+            && order.volume * price < self.min_cost
+        {
+            return Err(format!(
+                "value {} below the minimum {} of {}",
+                order.volume * price,
+                self.min_cost,
+                self.ticker
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Process wide cache of the reference data per ticker, empty until loaded.
+fn registry() -> &'static RwLock<HashMap<String, Instrument>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, Instrument>>> = OnceLock::new();
+    REGISTRY.get_or_init(RwLock::default)
+}
+
+// Fetch the reference data of every pair from Kraken and replace the cached one. Returns the
+// number of pairs loaded.
+pub async fn load() -> Result<usize, String> {
+    let secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>> =
+        Box::new(Arc::new(Mutex::new(StaticSecretsProvider::new("", ""))));
+    let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+        Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+    let mut client = CoreKrakenClient::new_with_url(
+        secrets_provider,
+        nonce_provider,
+        environment::endpoints().rest,
+    );
+
+    let request = TradableAssetPairsRequest::builder().build();
+    let pairs = match client.get_tradable_asset_pairs(&request).await {
+        Ok(ResultErrorResponse {
+            result: Some(pairs),
+            ..
+        }) => pairs,
+        Ok(response) => return Err(format!("{:?}", response.error)),
+        Err(network_error) => return Err(format!("{:?}", network_error)),
+    };
+    let instruments: HashMap<String, Instrument> = pairs
+        .iter()
+        .map(|(name, pair)| Instrument {
+            name: name.clone(),
+            ..Instrument::from(pair)
+        })
+        .map(|instrument| (instrument.ticker.clone(), instrument))
+        .collect();
+    let count = instruments.len();
+    match registry().write() {
+        Ok(mut registry) => *registry = instruments,
+        Err(_) => return Err("Instrument registry lock poisoned".into()),
+    }
+    Ok(count)
+}
+
+// Reload the reference data periodically, runs forever when a refresh period is configured.
+pub async fn refresh(config: InstrumentConfig) {
+    let Some(period) = config.refresh else {
+        return;
+    };
+    let mut ticker = interval(Duration::from_secs(period.max(1)));
+    // the first tick completes immediately, the data was just loaded
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        match load().await {
+            Ok(count) => info!("Reloaded the reference data of {} pairs", count),
+            Err(message) => warn!("Could not reload the reference data: {}", message),
+        }
+    }
+}
+
+pub fn get(ticker: &str) -> Option<Instrument> {
+    registry()
+        .read()
+        .ok()
+        .and_then(|registry| registry.get(ticker).cloned())
+}
+
+// Ticker of a pair named as in the REST API, None before the data is loaded.
+pub fn ticker(name: &str) -> Option<String> {
+    registry().read().ok().and_then(|registry| {
+        registry
+            .values()
+            .find(|instrument| instrument.name == name)
+            .map(|instrument| instrument.ticker.clone())
+    })
+}
+
+fn loaded() -> bool {
+    registry().read().is_ok_and(|registry| !registry.is_empty())
+}
+
+// Check that the tickers are known and trading, anything goes before the data is loaded.
+pub fn check_tickers(tickers: &[String]) -> Result<(), String> {
+    if !loaded() {
+        return Ok(());
+    }
+    for ticker in tickers {
+        match get(ticker) {
+            Some(instrument) if !instrument.online => {
+                return Err(format!("{} is not trading", ticker));
+            }
+            Some(_) => (),
+            None => return Err(format!("Unknown pair {}", ticker)),
+        }
+    }
+    Ok(())
+}
+
+// Check an order against the trading rules of its pair, anything goes before the data is
+// loaded.
+pub fn validate(order: &Order, price: Option<f64>) -> Result<(), String> {
+    match get(&order.ticker) {
+        Some(instrument) => instrument.validate(order, price),
+        None if loaded() => Err(format!("unknown pair {}", order.ticker)),
+        None => Ok(()),
+    }
+}
+
+// Order with its volume and limit price rounded to the increments of its pair.
+pub fn round(order: &Order) -> Order {
+    let Some(instrument) = get(&order.ticker) else {
+        return order.clone();
+    };
+    Order {
+        volume: instrument.round_volume(order.volume),
+        kind: match order.kind {
+            OrderKind::Limit(price) => OrderKind::Limit(instrument.round_price(price)),
+            OrderKind::Market => OrderKind::Market,
+        },
+        ..order.clone()
+    }
+}
@@ -0,0 +1,119 @@
+use crate::market::Candle;
+
+use serde::Serialize;
+
+use std::fmt;
+
+// Defect of a stored series of candles.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    // candles missing from a time up to another one (in s, excluded)
+    Gap { from: i64, to: i64 },
+    // further candle at the time of an earlier one
+    Duplicate { time: i64 },
+    // candle stored after a later one
+    OutOfOrder { time: i64, previous: i64 },
+    // candle not opening on a multiple of the interval
+    Misaligned { time: i64 },
+    // candle breaking the OHLC invariants
+    Invalid { time: i64, reason: String },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::Gap { from, to } => write!(formatter, "gap from {} to {}", from, to),
+            Issue::Duplicate { time } => write!(formatter, "duplicate at {}", time),
+            Issue::OutOfOrder { time, previous } => {
+                write!(formatter, "{} stored after {}", time, previous)
+            }
+            Issue::Misaligned { time } => write!(formatter, "misaligned at {}", time),
+            Issue::Invalid { time, reason } => write!(formatter, "invalid at {}: {}", time, reason),
+        }
+    }
+}
+
+// Why a candle breaks the OHLC invariants, None when it holds them.
+fn invalid(candle: &Candle) -> Option<String> {
+    let prices = [candle.open, candle.high, candle.low, candle.close];
+    if prices
+        .iter()
+        .chain([&candle.volume])
+        .any(|value| !value.is_finite())
+    {
+        return Some("non finite value".into());
+    }
+    if prices.iter().any(|price| *price <= 0.0) {
+        return Some("non positive price".into());
+    }
+    if candle.volume < 0.0 {
+        return Some(format!("negative volume {}", candle.volume));
+    }
+    if candle.high < candle.open.max(candle.close) {
+        return Some(format!("high {} below the open or close", candle.high));
+    }
+    if candle.low > candle.open.min(candle.close) {
+        return Some(format!("low {} above the open or close", candle.low));
+    }
+    None
+}
+
+// Issues of a series of candles of an interval (in min), in the order they are stored.
+pub fn check(candles: &[Candle], interval: i32) -> Vec<Issue> {
+    let length = interval.max(1) as i64 * 60;
+    let mut issues = Vec::new();
+    let mut latest: Option<i64> = None;
+    for candle in candles {
+        if candle.time.rem_euclid(length) != 0 {
+            issues.push(Issue::Misaligned { time: candle.time });
+        }
+        if let Some(reason) = invalid(candle) {
+            issues.push(Issue::Invalid {
+                time: candle.time,
+                reason,
+            });
+        }
+        match latest {
+            Some(previous) if candle.time == previous => {
+                issues.push(Issue::Duplicate { time: candle.time });
+            }
+            Some(previous) if candle.time < previous => {
+                issues.push(Issue::OutOfOrder {
+                    time: candle.time,
+                    previous,
+                });
+            }
+            _ => latest = Some(candle.time),
+        }
+    }
+    issues.extend(gaps(&repair(candles, interval), interval));
+    issues
+}
+
+// Missing ranges of a sorted series of candles of an interval (in min).
+pub fn gaps(candles: &[Candle], interval: i32) -> Vec<Issue> {
+    let length = interval.max(1) as i64 * 60;
+    candles
+        .windows(2)
+        .filter(|pair| pair[1].time - pair[0].time > length)
+        .map(|pair| Issue::Gap {
+            from: pair[0].time + length,
+            to: pair[1].time,
+        })
+        .collect()
+}
+
+// Series of candles without the repairable issues: sorted, the first of duplicated candles kept,
+// invalid and misaligned candles dropped. Gaps are left to be filled.
+pub fn repair(candles: &[Candle], interval: i32) -> Vec<Candle> {
+    let length = interval.max(1) as i64 * 60;
+    let mut repaired: Vec<Candle> = candles
+        .iter()
+        .filter(|candle| candle.time.rem_euclid(length) == 0 && invalid(candle).is_none())
+        .copied()
+        .collect();
+    repaired.sort_by_key(|candle| candle.time);
+    repaired.dedup_by_key(|candle| candle.time);
+    repaired
+}
@@ -0,0 +1,128 @@
+use crate::clock;
+use crate::execution::{Order, Side};
+use crate::summary::DailySummary;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Entry {
+    Order {
+        // unix time (in s) of the submission
+        time: i64,
+        // identifier attributed by the executor
+        id: String,
+        order: Order,
+        // the order was not sent to the exchange
+        simulated: bool,
+        // strategy that placed the order, None for administrative orders
+        #[serde(default)]
+        strategy: Option<String>,
+        // price of the ticker when the order was decided, to measure slippage
+        #[serde(default)]
+        decision: Option<f64>,
+    },
+    Cancel {
+        time: i64,
+        id: String,
+        simulated: bool,
+    },
+    // execution of (part of) an order reported by the exchange
+    Fill {
+        time: i64,
+        // identifier of the filled order
+        id: String,
+        ticker: String,
+        side: Side,
+        volume: f64,
+        price: f64,
+        // fee paid in quote currency
+        fee: f64,
+        simulated: bool,
+        // identifier of the trade, None for fills journaled before it was recorded
+        #[serde(default)]
+        trade: Option<String>,
+    },
+    // orders of a signal that were not placed
+    Rejected {
+        time: i64,
+        orders: Vec<Order>,
+        reason: String,
+        simulated: bool,
+    },
+    // trading activity of a day, at its close
+    Summary {
+        time: i64,
+        summary: DailySummary,
+    },
+}
+
+impl Entry {
+    // Unix time (in s) the entry was recorded at.
+    pub fn time(&self) -> i64 {
+        match self {
+            Entry::Order { time, .. }
+            | Entry::Cancel { time, .. }
+            | Entry::Fill { time, .. }
+            | Entry::Rejected { time, .. }
+            | Entry::Summary { time, .. } => *time,
+        }
+    }
+}
+
+pub fn now() -> i64 {
+    clock::seconds()
+}
+
+// Append only record of the trading activity, one JSON entry per line.
+pub struct Journal {
+    file: File,
+}
+
+impl Journal {
+    pub fn open(path: &Path) -> Result<Journal, String> {
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Ok(Journal { file }),
+            Err(error) => Err(format!("Could not open journal {:?}: {:?}", path, error)),
+        }
+    }
+
+    // Entries of a journal file in the order they were recorded.
+    pub fn read(path: &Path) -> Result<Vec<Entry>, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) => return Err(format!("Could not read journal {:?}: {:?}", path, error)),
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Ok(entry),
+                Err(error) => Err(format!("Invalid journal entry {:?}: {}", line, error)),
+            })
+            .collect()
+    }
+
+    // Flush the journal to the disk, failing when the disk no longer takes writes.
+    pub fn sync(&self) -> Result<(), String> {
+        match self.file.sync_data() {
+            Ok(()) => Ok(()),
+            Err(error) => Err(format!("Could not sync the journal: {:?}", error)),
+        }
+    }
+
+    pub fn record(&mut self, entry: &Entry) -> Result<(), String> {
+        let line = match serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(error) => return Err(format!("{:?}", error)),
+        };
+        match writeln!(self.file, "{}", line).and_then(|_| self.file.flush()) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(format!("Could not write journal entry: {:?}", error)),
+        }
+    }
+}
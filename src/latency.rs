@@ -0,0 +1,133 @@
+use crate::alerts::{self, EventKind};
+use crate::metrics;
+use crate::statistics::quantile;
+
+use serde::{Deserialize, Serialize};
+
+use tracing::{info, warn};
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LatencyConfig {
+    // follow the trades of the fed tickers, their timestamps are precise enough to time the feed
+    // where candles only reveal a gross clock skew
+    pub trades: bool,
+    // number of delays the percentiles are computed over
+    pub window: usize,
+    // delay (in s) beyond which the exchange clock is considered ahead of the local one
+    pub max_skew: f64,
+    // median delay (in s) beyond which the connection is considered stalled or the local clock
+    // ahead of the exchange
+    pub max_latency: f64,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> LatencyConfig {
+        LatencyConfig {
+            trades: true,
+            window: 500,
+            max_skew: 1.0,
+            max_latency: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnosis {
+    Healthy,
+    // exchange timestamps in the local future, the local clock lags
+    Skewed,
+    // data received long after the exchange stamped it
+    Delayed,
+}
+
+// Measures the delay between the exchange timestamps of feed data and its local receipt. Delays
+// below zero cannot be network latency and reveal that the local clock drifted, consistently large
+// delays a stalled connection. Diagnosis changes are warned about once.
+pub struct LatencyMonitor {
+    config: LatencyConfig,
+    // latest delays (in s), oldest first
+    delays: VecDeque<f64>,
+    diagnosis: Diagnosis,
+}
+
+impl LatencyMonitor {
+    pub fn new(config: LatencyConfig) -> LatencyMonitor {
+        LatencyMonitor {
+            config,
+            delays: VecDeque::new(),
+            diagnosis: Diagnosis::Healthy,
+        }
+    }
+
+    // Record data stamped at the given exchange time and received at the local time (in s).
+    pub fn observe(&mut self, exchange: f64, received: f64) -> Diagnosis {
+        if self.delays.len() == self.config.window.max(1) {
+            self.delays.pop_front();
+        }
+        self.delays.push_back(received - exchange);
+
+        for (name, q) in [("p50", 0.5), ("p90", 0.9), ("p99", 0.99)] {
+            if let Some(delay) = self.percentile(q) {
+                metrics::set(&format!("feed.latency.{}", name), delay);
+            }
+        }
+        let skew = self.delays.iter().copied().fold(f64::INFINITY, f64::min);
+        metrics::set("feed.skew", skew);
+
+        let diagnosis = if skew < -self.config.max_skew {
+            Diagnosis::Skewed
+        } else if self
+            .percentile(0.5)
+            .is_some_and(|median| median > self.config.max_latency)
+        {
+            Diagnosis::Delayed
+        } else {
+            Diagnosis::Healthy
+        };
+        self.diagnose(diagnosis, skew);
+        diagnosis
+    }
+
+    // Check the opening time (in s) of a candle received at the local time, a candle opening in
+    // the future can only come from a skewed clock. Candles are not timed since their updates are
+    // stamped with the start of their interval.
+    pub fn check_candle(&mut self, time: i64, received: f64) -> Diagnosis {
+        let skew = received - time as f64;
+        if skew < -self.config.max_skew {
+            self.diagnose(Diagnosis::Skewed, skew);
+        }
+        self.diagnosis
+    }
+
+    // Delay (in s) below which the given fraction of the latest delays fall.
+    pub fn percentile(&self, q: f64) -> Option<f64> {
+        let delays: Vec<f64> = self.delays.iter().copied().collect();
+        quantile(&delays, q)
+    }
+
+    fn diagnose(&mut self, diagnosis: Diagnosis, skew: f64) {
+        if diagnosis == self.diagnosis {
+            return;
+        }
+        self.diagnosis = diagnosis;
+        let message = match diagnosis {
+            Diagnosis::Healthy => {
+                info!("Feed latency back to normal");
+                return;
+            }
+            Diagnosis::Skewed => format!(
+                "Exchange timestamps are {:.3}s ahead of the local clock, check its NTP synchronization",
+                -skew
+            ),
+            Diagnosis::Delayed => format!(
+                "Median feed latency above {}s, the connection may be stalled or the local clock ahead",
+                self.config.max_latency
+            ),
+        };
+        warn!("{}", message);
+        alerts::notify(EventKind::Error, "Feed latency", &message);
+    }
+}
@@ -0,0 +1,58 @@
+pub mod accounting;
+pub mod accounts;
+pub mod alerts;
+pub mod analysis;
+pub mod anomalies;
+pub mod api;
+pub mod arbitrage;
+pub mod attribution;
+pub mod backtest;
+pub mod balances;
+pub mod book;
+pub mod calendar;
+pub mod clock;
+pub mod config;
+pub mod control;
+pub mod conversion;
+pub mod cooldown;
+pub mod datasets;
+pub mod environment;
+pub mod events;
+pub mod execution;
+pub mod export;
+pub mod feeds;
+pub mod fills;
+pub mod gaps;
+pub mod health;
+pub mod history;
+pub mod indicators;
+pub mod instruments;
+pub mod integrity;
+pub mod journal;
+pub mod latency;
+pub mod logging;
+pub mod margin;
+pub mod market;
+pub mod metrics;
+pub mod montecarlo;
+pub mod multipair;
+pub mod nonce;
+pub mod optimizer;
+pub mod orders;
+pub mod parity;
+pub mod random;
+pub mod retry;
+pub mod risk;
+pub mod runner;
+pub mod secrets;
+pub mod sessions;
+pub mod slippage;
+pub mod state;
+pub mod statistics;
+pub mod storage;
+pub mod strategies;
+pub mod summary;
+pub mod synchronizer;
+pub mod transforms;
+pub mod tui;
+pub mod var;
@@ -0,0 +1,318 @@
+use chrono::Utc;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+
+use serde::{Deserialize, Serialize};
+
+use tracing::Level;
+
+use tracing_appender::non_blocking::WorkerGuard;
+
+use tracing_subscriber::filter::{FilterFn, LevelFilter};
+use tracing_subscriber::fmt::{self, MakeWriter};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{Layer, Registry};
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    // human readable lines
+    #[default]
+    Text,
+    // a JSON object per line with the event fields and enclosing spans, for log aggregators
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    #[default]
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl Rotation {
+    // Index of the period a unix time (in s) falls in, the file is rotated when it changes.
+    fn period(&self, time: i64) -> Option<i64> {
+        match self {
+            Rotation::Never => None,
+            Rotation::Hourly => Some(time.div_euclid(3600)),
+            Rotation::Daily => Some(time.div_euclid(24 * 3600)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    // file the logs are appended to, rotated files get the time of rotation appended to its name
+    pub file: PathBuf,
+    pub rotation: Rotation,
+    // size (in bytes) above which the file is rotated
+    pub max_size: Option<u64>,
+    // number of rotated files kept, the oldest are deleted first
+    pub retention: Option<usize>,
+    // whether rotated files are gzipped
+    pub compress: bool,
+    // OTLP/HTTP endpoint the spans of the pipeline from candles to fills are exported to, e.g.
+    // http://localhost:4318/v1/traces for Jaeger or Tempo, none are exported without it
+    pub otlp: Option<String>,
+    // service name the spans are exported under
+    pub service: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> LoggingConfig {
+        LoggingConfig {
+            format: LogFormat::default(),
+            file: PathBuf::from("trade-bot.log"),
+            rotation: Rotation::default(),
+            max_size: None,
+            retention: None,
+            compress: false,
+            otlp: None,
+            service: String::from("trade-bot"),
+        }
+    }
+}
+
+// Log file rotated on size and time, the current logs are always at the configured path.
+struct RotatingFile {
+    config: LoggingConfig,
+    file: File,
+    // bytes in the current file
+    size: u64,
+    // period the current file was started in
+    period: Option<i64>,
+}
+
+impl RotatingFile {
+    fn open(config: &LoggingConfig) -> io::Result<RotatingFile> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.file)?;
+        let metadata = file.metadata()?;
+        // a file left by a previous run belongs to the period it was last written in
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(Utc::now().timestamp(), |elapsed| elapsed.as_secs() as i64);
+        Ok(RotatingFile {
+            config: config.clone(),
+            file,
+            size: metadata.len(),
+            period: config.rotation.period(modified),
+        })
+    }
+
+    fn due(&self, length: usize) -> bool {
+        let period = self.config.rotation.period(Utc::now().timestamp());
+        let full = self
+            .config
+            .max_size
+            .is_some_and(|max_size| self.size + length as u64 > max_size);
+        self.size > 0 && (full || period != self.period)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let name = self.name();
+        let rotated = self.config.file.with_file_name(format!(
+            "{}.{}",
+            name,
+            Utc::now().format("%Y%m%dT%H%M%S%.3f")
+        ));
+        fs::rename(&self.config.file, &rotated)?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.file)?;
+        self.size = 0;
+        self.period = self.config.rotation.period(Utc::now().timestamp());
+
+        if self.config.compress {
+            compress(&rotated)?;
+        }
+        self.prune()
+    }
+
+    fn name(&self) -> String {
+        self.config
+            .file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    // Delete the oldest rotated files beyond the retention.
+    fn prune(&self) -> io::Result<()> {
+        let Some(retention) = self.config.retention else {
+            return Ok(());
+        };
+        let directory = match self.config.file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let prefix = format!("{}.", self.name());
+        let mut rotated: Vec<PathBuf> = fs::read_dir(&directory)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+            })
+            .collect();
+        // the rotation times sort chronologically
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(retention);
+        for path in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn compress(path: &Path) -> io::Result<()> {
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(".gz");
+    let mut encoder = GzEncoder::new(File::create(compressed)?, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        if self.due(buffer.len())
+            && let Err(error) = self.rotate()
+        {
+            // keep logging to the current file rather than losing lines
+            eprintln!("Could not rotate {:?}: {:?}", self.config.file, error);
+        }
+        let written = self.file.write(buffer)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = match format {
+        LogFormat::Text => fmt::layer().with_ansi(ansi).with_writer(writer).boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(writer)
+            .boxed(),
+    };
+    layer.with_filter(LevelFilter::INFO).boxed()
+}
+
+// Spans exported over OTLP, with the provider batching them. Spans of the pipeline are at the
+// debug level and events at the info level like the logs, span events are the log lines.
+fn spans(
+    config: &LoggingConfig,
+    endpoint: &str,
+) -> Result<(SdkTracerProvider, BoxedLayer), String> {
+    let exporter = match SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(error) => {
+            return Err(format!(
+                "Could not export spans to {}: {:?}",
+                endpoint, error
+            ));
+        }
+    };
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service.clone())
+                .build(),
+        )
+        .build();
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("trade-bot"))
+        .with_filter(FilterFn::new(|metadata| {
+            let level = if metadata.is_span() {
+                Level::DEBUG
+            } else {
+                Level::INFO
+            };
+            *metadata.level() <= level
+        }))
+        .boxed();
+    Ok((provider, layer))
+}
+
+// Keeps logging until dropped, then flushes the log file and exports the last spans.
+pub struct Logging {
+    _file: WorkerGuard,
+    spans: Option<SdkTracerProvider>,
+}
+
+impl Drop for Logging {
+    fn drop(&mut self) {
+        if let Some(provider) = self.spans.take()
+            && let Err(error) = provider.shutdown()
+        {
+            eprintln!("Could not export the last spans: {:?}", error);
+        }
+    }
+}
+
+// Log to the configured file and, unless the terminal is used for something else, to the
+// standard output, exporting spans when an OTLP endpoint is configured. The file is written from a
+// background thread, the returned guard must be held until the program ends.
+pub fn set_up(config: &LoggingConfig, stdout: bool) -> Result<Logging, String> {
+    let file = match RotatingFile::open(config) {
+        Ok(file) => file,
+        Err(error) => return Err(format!("Could not open log {:?}: {:?}", config.file, error)),
+    };
+    let (writer, guard) = tracing_appender::non_blocking(file);
+    let mut layers = vec![layer(config.format, writer, false)];
+    if stdout {
+        layers.push(layer(config.format, io::stdout, true));
+    }
+    let mut provider = None;
+    if let Some(endpoint) = &config.otlp {
+        let (spans, layer) = spans(config, endpoint)?;
+        layers.push(layer);
+        provider = Some(spans);
+    }
+    match tracing::subscriber::set_global_default(Registry::default().with(layers)) {
+        Ok(()) => Ok(Logging {
+            _file: guard,
+            spans: provider,
+        }),
+        Err(error) => Err(format!("Could not set up logging: {:?}", error)),
+    }
+}
@@ -1,23 +1,1441 @@
-mod feeds;
-use crate::feeds::LiveFeed;
+use trade_bot::accounting::{self, Ledger};
+use trade_bot::accounts::{self, Accounts};
+use trade_bot::alerts::{self, Alerts};
+use trade_bot::anomalies::AnomalyDetector;
+use trade_bot::api::{self, Api};
+use trade_bot::arbitrage::{ScannerConfig, TriangularScanner};
+use trade_bot::attribution;
+use trade_bot::backtest::Checkpoint;
+use trade_bot::balances;
+use trade_bot::book::{BookUpdate, OrderBook};
+use trade_bot::calendar;
+use trade_bot::clock;
+use trade_bot::config::{Config, ConfigWatcher};
+use trade_bot::control::{self, Command};
+use trade_bot::conversion;
+use trade_bot::datasets::{self, Format};
+use trade_bot::environment;
+use trade_bot::events::{self, Event};
+use trade_bot::execution::{DryRunExecutor, Executor, KrakenExecutor, Side};
+use trade_bot::export::{self, Exporter};
+use trade_bot::feeds::{self, HistoricalFeed, LiveFeed, PairChange, PairRequest, Pairs};
+use trade_bot::fills;
+use trade_bot::gaps::GapFiller;
+use trade_bot::health;
+use trade_bot::history;
+use trade_bot::indicators::garch;
+use trade_bot::indicators::snapshot::Indicators;
+use trade_bot::instruments;
+use trade_bot::integrity::{self, Issue};
+use trade_bot::journal::{Journal, now};
+use trade_bot::latency::LatencyMonitor;
+use trade_bot::logging;
+use trade_bot::margin;
+use trade_bot::market::{self, Candle, CandleUpdates, MarketEvent};
+use trade_bot::metrics;
+use trade_bot::montecarlo::{MonteCarlo, Resampling, Robustness};
+use trade_bot::multipair;
+use trade_bot::nonce;
+use trade_bot::optimizer;
+use trade_bot::orders::{self, Iceberg, Oco};
+use trade_bot::parity;
+use trade_bot::random;
+use trade_bot::retry::RetryingExecutor;
+use trade_bot::risk::RiskGuard;
+use trade_bot::runner::{self, History, Runner, Worker};
+use trade_bot::secrets;
+use trade_bot::slippage::SlippageReport;
+use trade_bot::state::StateStore;
+use trade_bot::storage::CandleStore;
+use trade_bot::strategies::market_making::MarketMaker;
+use trade_bot::summary;
+use trade_bot::synchronizer::{Snapshot, Synchronizer};
+use trade_bot::tui;
 
-use kraken_async_rs::test_support::set_up_logging;
+use clap::{Parser, Subcommand};
 
-use tracing::{info, warn};
+use indicatif::{ProgressBar, ProgressDrawTarget};
+
+use tokio::signal::unix::{Signal, SignalKind, signal};
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::watch;
+
+use tracing::{Instrument, debug, debug_span, info, info_span, warn};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Time between checks of the configuration file for changes (in s).
+const RELOAD_PERIOD: u64 = 5;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Configuration file, defaults are used when it does not exist
+    #[arg(long, global = true, default_value = "trade-bot.toml")]
+    config: PathBuf,
+
+    /// Run the live pipeline without sending orders, hypothetical orders are logged and
+    /// journaled as simulated
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Append the raw websocket frames received to a session file that can be replayed
+    #[arg(long, global = true)]
+    record: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Action>,
+}
+
+/// Administrative commands sent to the running bot, it trades when none is given
+#[derive(Subcommand)]
+enum Action {
+    /// Halt trading and cancel every open order
+    Kill {
+        /// Also close every position with market orders
+        #[arg(long)]
+        flatten: bool,
+    },
+    /// Resume trading after a kill
+    Resume,
+    /// Start following a pair, e.g. BTC/EUR, with the strategies not bound to given pairs
+    Subscribe { pair: String },
+    /// Stop following a pair along with the strategies trading it
+    Unsubscribe { pair: String },
+    /// Exit a position with a take profit limit and a stop loss, whichever is reached first
+    /// cancelling the other
+    Oco {
+        /// Pair of the position, e.g. BTC/EUR
+        #[arg(long)]
+        pair: String,
+        /// Exit a short position by buying instead of a long one by selling
+        #[arg(long)]
+        buy: bool,
+        /// Volume exited (in base asset)
+        #[arg(long)]
+        volume: f64,
+        /// Limit price the position is exited at in profit
+        #[arg(long)]
+        take_profit: f64,
+        /// Price the position is exited at market once reached
+        #[arg(long)]
+        stop_loss: f64,
+    },
+    /// Place a limit order showing only a slice of its volume on the book at a time, the next
+    /// slice being placed once the previous one filled
+    Iceberg {
+        /// Pair traded, e.g. BTC/EUR
+        #[arg(long)]
+        pair: String,
+        /// Sell instead of buying
+        #[arg(long)]
+        sell: bool,
+        /// Total volume (in base asset)
+        #[arg(long)]
+        volume: f64,
+        /// Volume of each slice (in base asset)
+        #[arg(long)]
+        visible: f64,
+        /// Limit price of the slices
+        #[arg(long)]
+        price: f64,
+    },
+    /// Print the realized profit, the fees per strategy and ticker and the slippage from the journal
+    Report,
+    /// Export the journaled fills as a CSV for crypto tax tools
+    Tax {
+        /// File the CSV is written to
+        output: PathBuf,
+    },
+    /// Write the candles of pairs and their indicators to Parquet files for offline analysis
+    Export {
+        /// Pairs to export, e.g. BTC/EUR, the followed pairs when none is given
+        #[arg(long = "pair")]
+        pairs: Vec<String>,
+        /// How far back candles are fetched (in h)
+        #[arg(long, default_value_t = 12)]
+        hours: i64,
+        /// Candle interval (in min)
+        #[arg(long, default_value_t = 1)]
+        interval: i32,
+        /// Directory the files are written to instead of the configured one
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Download the trades of pairs from Kraken into candles stored for backtesting, resuming
+    /// after the candles already stored
+    FetchHistory {
+        /// Pairs to download, e.g. BTC/EUR, the followed pairs when none is given
+        #[arg(long = "pair")]
+        pairs: Vec<String>,
+        /// How far back the history starts when nothing is stored yet (in days)
+        #[arg(long, default_value_t = 30)]
+        days: i64,
+        /// Candle interval (in min)
+        #[arg(long, default_value_t = 1)]
+        interval: i32,
+    },
+    /// Add the candles of a public dataset file to the stored candles of a pair
+    Import {
+        /// CSV file or ZIP archive of CSV files
+        file: PathBuf,
+        /// Pair the candles are stored under, e.g. BTC/EUR
+        #[arg(long)]
+        pair: String,
+        /// Layout of the dataset, binance or cryptodatadownload
+        #[arg(long)]
+        format: Format,
+        /// Candle interval of the dataset (in min)
+        #[arg(long, default_value_t = 1)]
+        interval: i32,
+        /// Hours the time zone of dates without one is ahead of UTC
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        offset: i64,
+    },
+    /// Check the stored candles for gaps, duplicates, out of order and invalid candles
+    VerifyData {
+        /// Pairs to check, e.g. BTC/EUR, every stored pair when none is given
+        #[arg(long = "pair")]
+        pairs: Vec<String>,
+        /// Only check the candles of an interval (in min)
+        #[arg(long)]
+        interval: Option<i32>,
+        /// Rewrite the series sorted, without duplicates, invalid or misaligned candles
+        #[arg(long)]
+        repair: bool,
+        /// Download the missing ranges from Kraken, implies --repair
+        #[arg(long)]
+        fetch: bool,
+    },
+    /// Run the pipeline without sending orders on the market events of a recorded session
+    Replay {
+        /// Session file written with --record
+        session: PathBuf,
+        /// Factor the recorded pace is sped up by, events are replayed as fast as possible
+        /// without one
+        #[arg(long)]
+        speed: Option<f64>,
+    },
+    /// Backtest the strategies on the stored candles of pairs in parallel and report the results
+    /// per strategy, per pair and for the whole portfolio
+    Backtest {
+        /// Pairs to backtest, e.g. BTC/EUR, every stored pair when none is given
+        #[arg(long = "pair")]
+        pairs: Vec<String>,
+        /// Candle interval (in min)
+        #[arg(long, default_value_t = 1)]
+        interval: i32,
+        /// Only replay the latest candles (in days)
+        #[arg(long)]
+        days: Option<i64>,
+        /// Cash each strategy instance starts with (in quote currency)
+        #[arg(long, default_value_t = 10000.0)]
+        cash: f64,
+        /// Directory the runs are checkpointed in, interrupted runs resume from their checkpoint
+        #[arg(long)]
+        checkpoints: Option<PathBuf>,
+        /// Steps between checkpoints
+        #[arg(long, default_value_t = 10000)]
+        every: usize,
+        /// Resample the trades of each run this many times and report 95% confidence intervals on
+        /// its return and drawdown
+        #[arg(long)]
+        montecarlo: Option<usize>,
+    },
+    /// Evolve the parameters of a strategy set in the optimizer section with a genetic algorithm
+    /// against backtests on the stored candles of pairs and print the best settings found
+    Optimize {
+        /// Strategy named by its id, or its kind and position without one, e.g. sma#0
+        strategy: String,
+        /// Pairs to backtest, e.g. BTC/EUR, every stored pair when none is given
+        #[arg(long = "pair")]
+        pairs: Vec<String>,
+        /// Candle interval (in min)
+        #[arg(long, default_value_t = 1)]
+        interval: i32,
+        /// Only replay the latest candles (in days)
+        #[arg(long)]
+        days: Option<i64>,
+        /// Cash the strategy starts with on each pair (in quote currency)
+        #[arg(long, default_value_t = 10000.0)]
+        cash: f64,
+    },
+    /// Print the progress and the equity so far of the backtests checkpointed in a directory
+    Checkpoints {
+        /// Directory given to backtest --checkpoints
+        directory: PathBuf,
+    },
+    /// Backtest the strategies on the candles of a session recorded during a dry run and diff
+    /// their signals against the orders the dry run journaled
+    Parity {
+        /// Session file written with --record
+        session: PathBuf,
+        /// Journal of the dry run instead of the configured one
+        #[arg(long)]
+        journal: Option<PathBuf>,
+    },
+    /// Trade with a terminal dashboard of the candles, positions, orders and log instead of
+    /// logging to the console
+    Tui,
+    /// Manage the API credentials kept in the configured secrets source
+    Keys {
+        #[command(subcommand)]
+        action: Keys,
+    },
+}
+
+#[derive(Subcommand)]
+enum Keys {
+    /// Prompt for the API key and secret and store them in the OS keyring or the encrypted file
+    Set {
+        /// Configured account the credentials are for instead of the main one
+        #[arg(long)]
+        account: Option<String>,
+    },
+}
+
+// Stages the feed events go through, along with what is needed to follow further pairs.
+struct Pipeline {
+    anomalies: AnomalyDetector,
+    gaps: GapFiller,
+    latency: LatencyMonitor,
+    indicators: Indicators,
+    exporter: Exporter,
+    synchronizer: Synchronizer,
+    runner: Runner,
+    // candles the strategies are fed
+    candles: CandleUpdates,
+    // market makers by name along with the books followed, those of their tickers and of every
+    // pair when indicators follow them
+    makers: Vec<(String, MarketMaker)>,
+    books: HashMap<String, OrderBook>,
+    // price levels per side of the books
+    depth: usize,
+}
+
+impl Pipeline {
+    // Apply a change to the book of a ticker, feed it to the indicators and bring the quotes of
+    // the market makers of the ticker in line.
+    async fn book<E: Executor + Sync>(
+        &mut self,
+        guard: &RiskGuard<E>,
+        ticker: &str,
+        update: &BookUpdate,
+    ) {
+        let Some(book) = self.books.get_mut(ticker) else {
+            return;
+        };
+        book.apply(update);
+        self.indicators.book(ticker, book);
+        for (name, maker) in &mut self.makers {
+            if maker.ticker() == ticker
+                && let Err(message) = maker.on_book(book, guard).await
+            {
+                warn!(strategy = %name, "Could not quote: {}", message);
+            }
+        }
+    }
+
+    // Stop the market makers and the strategies, letting the latter place their last orders.
+    async fn shutdown<E: Executor + Sync>(mut self, guard: &RiskGuard<E>) {
+        for (name, maker) in &mut self.makers {
+            if let Err(message) = maker.withdraw(guard).await {
+                warn!(strategy = %name, "Could not withdraw the quotes: {}", message);
+            }
+        }
+        self.runner.shutdown().await;
+    }
+}
+
+// Start or stop following a pair along with the strategies trading it.
+async fn follow(
+    feed: &mut LiveFeed,
+    pipeline: &mut Pipeline,
+    change: PairChange,
+) -> Result<String, String> {
+    match change {
+        PairChange::Add(ticker) => {
+            feed.add_ticker(&ticker).await?;
+            if pipeline.indicators.books() {
+                feed.subscribe_book(vec![ticker.clone()]).await?;
+                let book = OrderBook::new(pipeline.depth);
+                pipeline.books.insert(ticker.clone(), book);
+            }
+            let started = pipeline.runner.add_ticker(&ticker)?;
+            pipeline.synchronizer.add_ticker(&ticker);
+            Ok(format!("Following {} with {} strategies", ticker, started))
+        }
+        PairChange::Remove(ticker) => {
+            feed.remove_ticker(&ticker).await?;
+            pipeline.runner.remove_ticker(&ticker);
+            pipeline.anomalies.forget(&ticker);
+            pipeline.gaps.forget(&ticker);
+            pipeline.indicators.forget(&ticker);
+            pipeline.books.remove(&ticker);
+            pipeline.exporter.forget(&ticker);
+            pipeline.synchronizer.remove_ticker(&ticker);
+            Ok(format!("Stopped following {}", ticker))
+        }
+    }
+}
+
+// Pairs whose books are followed: those quoted by market makers, and every pair when indicators
+// follow the books.
+fn books(config: &Config, quoted: impl Iterator<Item = String>) -> Vec<String> {
+    let mut books: Vec<String> = quoted.collect();
+    if config.indicators.iter().any(|config| config.kind.books()) {
+        books.extend(config.tickers());
+    }
+    books.sort();
+    books.dedup();
+    books
+}
+
+// Publish the candles of the followed pairs completed for a time.
+fn synchronized(snapshots: Vec<Snapshot>) {
+    for snapshot in snapshots {
+        debug!(
+            time = snapshot.time,
+            missing = ?snapshot.missing,
+            "Candles synchronized"
+        );
+        events::publish(Event::Synchronized(snapshot));
+    }
+}
+
+// Apply the sections of a reloaded configuration that can change while trading.
+fn reload<E: Executor + Sync>(pipeline: &Pipeline, guard: &RiskGuard<E>, config: Config) {
+    guard.set_config(config.risk);
+    match Alerts::new(&config.alerts) {
+        Ok(channels) => alerts::install(channels),
+        Err(message) => warn!(target: "config_audit", "Alerts not reloaded: {}", message),
+    }
+    if let Err(message) = pipeline.runner.reconfigure(config.strategies) {
+        warn!(target: "config_audit", "Strategies not reloaded until restart: {}", message);
+    }
+}
+
+// Mark the positions and the profits of the strategies to a candle, and feed it to the managed
+// orders and the strategies.
+async fn decide<E: Executor + Sync>(
+    runner: &Runner,
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    ticker: &str,
+    candle: &Candle,
+) {
+    if guard.mark(ticker, candle) {
+        let flatten = guard.config().flatten_on_loss;
+        info!("{}", control::liquidate(guard, journal, flatten).await);
+    }
+    attribution::mark(ticker, candle.close);
+    orders::on_candle(guard, journal, ticker, candle).await;
+    runner.on_candle(ticker, candle).await;
+}
+
+// Wait for ctrl-c or SIGTERM, the latter being what service managers stop the bot with.
+async fn stop(terminate: &mut Signal) {
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => (),
+        _ = terminate.recv() => (),
+    }
+}
+
+async fn trade<E: Executor + Sync>(
+    mut feed: LiveFeed,
+    mut pipeline: Pipeline,
+    mut requests: Receiver<PairRequest>,
+    mut settings: watch::Receiver<Config>,
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+) -> Result<(), String> {
+    // number of events received, identifies an event across the log lines it causes
+    let mut seq: u64 = 0;
+    let mut fills = events::subscribe();
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(error) => return Err(format!("Could not listen to SIGTERM: {:?}", error)),
+    };
+    let mut scanners: Vec<TriangularScanner> = settings
+        .borrow()
+        .triangles
+        .iter()
+        .map(ScannerConfig::scanner)
+        .collect();
+    loop {
+        let event = tokio::select! {
+            event = feed.consume() => event,
+            _ = stop(&mut terminate) => {
+                info!("Stopping");
+                pipeline.shutdown(guard).await;
+                return Ok(());
+            }
+            Some((change, reply)) = requests.recv() => {
+                let outcome = follow(&mut feed, &mut pipeline, change).await;
+                match &outcome {
+                    Ok(message) => info!("{}", message),
+                    Err(message) => warn!("{}", message),
+                }
+                // the requester may have given up waiting
+                let _ = reply.send(outcome);
+                continue;
+            }
+            Ok(Event::Fill { id, fill }) = fills.recv(), if !pipeline.makers.is_empty() => {
+                for (_, maker) in &mut pipeline.makers {
+                    maker.on_fill(&id, fill.volume);
+                }
+                continue;
+            }
+            Ok(()) = settings.changed() => {
+                let config = settings.borrow_and_update().clone();
+                reload(&pipeline, guard, config);
+                continue;
+            }
+        };
+        let event = match event {
+            Ok(event) => event,
+            Err(_) if feed.ended() => {
+                // lets the strategies stop and place their last orders
+                pipeline.shutdown(guard).await;
+                return Ok(());
+            }
+            Err(message) => {
+                warn!(error = %message, "Feed error");
+                continue;
+            }
+        };
+        seq += 1;
+        let received = Instant::now();
+        debug!(seq, event = ?event, "Feed event");
+        metrics::set("feed.last_message", now() as f64);
+        match event {
+            MarketEvent::Candle { ticker, candle } => {
+                metrics::set("feed.last_candle", now() as f64);
+                pipeline.latency.check_candle(candle.time, clock::now());
+                let forming = candle.time;
+                for candle in pipeline.anomalies.check(&ticker, candle) {
+                    for candle in pipeline.gaps.process(&ticker, candle).await {
+                        // decisions on the candle down to the fills of their orders are traced
+                        // under its span
+                        let ingest = debug_span!("candle", pair = %ticker, time = candle.time);
+                        async {
+                            info!(
+                                pair = %ticker,
+                                channel = "ohlc",
+                                seq,
+                                time = candle.time,
+                                close = candle.close,
+                                volume = candle.volume,
+                                "Candle"
+                            );
+                            events::publish(Event::Candle {
+                                ticker: ticker.clone(),
+                                candle,
+                            });
+                            if let Some(snapshot) = debug_span!("indicators").in_scope(|| {
+                                pipeline
+                                    .indicators
+                                    .update(&ticker, &candle, CandleUpdates::Intrabar)
+                            }) {
+                                debug!(pair = %ticker, seq, indicators = ?snapshot.values, "Indicators");
+                            }
+                            pipeline
+                                .exporter
+                                .update(&ticker, candle, CandleUpdates::Intrabar);
+                            conversion::mark(&ticker, candle.close);
+                            // the candles filling a gap are final
+                            if pipeline.candles == CandleUpdates::Intrabar || candle.time < forming
+                            {
+                                decide(&pipeline.runner, guard, journal, &ticker, &candle).await;
+                            }
+                        }
+                        .instrument(ingest)
+                        .await;
+                    }
+                }
+                pipeline.exporter.poll();
+            }
+            MarketEvent::Closed { ticker, candle } => {
+                let ingest = debug_span!("candle", pair = %ticker, time = candle.time);
+                async {
+                    debug!(pair = %ticker, seq, time = candle.time, "Candle closed");
+                    if let Some(snapshot) =
+                        pipeline
+                            .indicators
+                            .update(&ticker, &candle, CandleUpdates::Closed)
+                    {
+                        debug!(pair = %ticker, seq, indicators = ?snapshot.values, "Indicators");
+                    }
+                    pipeline
+                        .exporter
+                        .update(&ticker, candle, CandleUpdates::Closed);
+                    if pipeline.candles == CandleUpdates::Closed {
+                        decide(&pipeline.runner, guard, journal, &ticker, &candle).await;
+                    }
+                    synchronized(pipeline.synchronizer.update(&ticker, &candle, clock::now()));
+                }
+                .instrument(ingest)
+                .await;
+            }
+            MarketEvent::Trade { ticker, trade } => {
+                pipeline.latency.observe(trade.time, clock::now());
+                pipeline.indicators.trade(&ticker, &trade);
+            }
+            MarketEvent::Status(status) => info!(status = %status, "Exchange status"),
+            MarketEvent::Ticker { ticker, quote } => {
+                metrics::set(&format!("feed.spread.{}", ticker), quote.spread());
+                conversion::mark(&ticker, quote.mid());
+                market::update_quote(&ticker, quote);
+                orders::on_quote(guard, journal, &ticker, &quote).await;
+                for scanner in &mut scanners {
+                    if let Some(signal) =
+                        scanner.on_quote(&ticker, quote.bid, quote.ask, guard).await
+                    {
+                        events::publish(Event::Signal {
+                            strategy: "arbitrage".to_string(),
+                            orders: signal.legs,
+                        });
+                    }
+                }
+            }
+            MarketEvent::Book { ticker, update } => pipeline.book(guard, &ticker, &update).await,
+            MarketEvent::Heartbeat => (),
+        }
+        // laggards time out whatever the event
+        synchronized(pipeline.synchronizer.poll(clock::now()));
+        pipeline.runner.poll().await;
+        debug!(
+            seq,
+            latency_us = received.elapsed().as_micros() as u64,
+            "Feed event handled"
+        );
+    }
+}
+
+fn report(config: &Config) -> Result<(), String> {
+    let entries = Journal::read(&config.journal)?;
+    let ledger = Ledger::from_entries(&entries);
+    let currency = &config.conversion.currency;
+    match ledger.realized_in(currency) {
+        Some(realized) => println!("Realized profit: {:.2} {}", realized, currency),
+        None => {
+            println!("Realized profit:");
+            let mut currencies: Vec<_> = ledger.realized_by_currency().into_iter().collect();
+            currencies.sort_by(|first, second| first.0.cmp(&second.0));
+            for (currency, realized) in currencies {
+                println!("  {:.2} {}", realized, currency);
+            }
+        }
+    }
+    if !config.accounts.is_empty() {
+        println!("Realized profit per account:");
+        let mut names: Vec<_> = ledger.realized_by_account.keys().collect();
+        names.sort();
+        for name in names {
+            match ledger.account_realized_in(name, currency) {
+                Some(realized) => println!("  {}: {:.2} {}", name, realized, currency),
+                None => println!("  {}: not convertible to {}", name, currency),
+            }
+        }
+    }
+    println!("Fees: {:.2}", ledger.fees());
+    let mut strategies: Vec<_> = ledger.fees_by_strategy.iter().collect();
+    strategies.sort_by(|first, second| first.0.cmp(second.0));
+    for (strategy, fees) in strategies {
+        println!("  {}: {:.2}", strategy, fees);
+    }
+    let mut tickers: Vec<_> = ledger.fees_by_ticker.iter().collect();
+    tickers.sort_by(|first, second| first.0.cmp(second.0));
+    for (ticker, fees) in tickers {
+        println!(
+            "  {}: {:.2} realized {:.2}",
+            ticker,
+            fees,
+            ledger.realized.get(ticker).copied().unwrap_or(0.0)
+        );
+    }
+    let slippage = SlippageReport::from_entries(&entries);
+    for (label, averages) in [
+        ("order type", slippage.by_kind()),
+        ("pair", slippage.by_ticker()),
+    ] {
+        if !averages.is_empty() {
+            println!("Slippage per {}:", label);
+        }
+        for (key, (average, count)) in averages {
+            println!("  {}: {:.4}% over {} fills", key, 100.0 * average, count);
+        }
+    }
+    Ok(())
+}
+
+async fn export(
+    config: &Config,
+    pairs: Vec<String>,
+    hours: i64,
+    interval: i32,
+    output: Option<PathBuf>,
+) -> Result<(), String> {
+    let directory = output.unwrap_or_else(|| config.export.directory.clone());
+    let mut feed = HistoricalFeed::new(hours * 3600, interval, pairs.clone()).await?;
+    let mut candles: Vec<Vec<Candle>> = vec![Vec::new(); pairs.len()];
+    while let Some(step) = feed.consume().await {
+        for (ticker, candles) in pairs.iter().zip(&mut candles) {
+            if let Some(ohlc) = step.get(ticker) {
+                candles.push(Candle::from(ohlc));
+            }
+        }
+    }
+    for (ticker, candles) in pairs.iter().zip(&candles) {
+        let file = export::path(&directory, ticker);
+        export::write(&file, candles, &config.export)?;
+        println!(
+            "{} candles of {} written to {:?}",
+            candles.len(),
+            ticker,
+            file
+        );
+    }
+    Ok(())
+}
+
+fn parity(config: &Config, session: &Path, journal: Option<PathBuf>) -> Result<(), String> {
+    let events = feeds::session(session)?;
+    let (Some((from, _)), Some((to, _))) = (events.first(), events.last()) else {
+        return Err(format!("{:?} holds no events", session));
+    };
+    let entries = Journal::read(&journal.unwrap_or_else(|| config.journal.clone()))?;
+    let live = parity::live(&entries, *from as i64, to.ceil() as i64);
+
+    let candles = parity::candles(&events);
+    let mut tickers: Vec<String> = candles.iter().map(|(ticker, _)| ticker.clone()).collect();
+    tickers.sort();
+    tickers.dedup();
+    let backtest = parity::backtest(&config.strategies, &tickers, &candles)?;
+
+    let report = parity::compare(&live, &backtest);
+    println!(
+        "{} live and {} backtested signals, {} matching",
+        live.len(),
+        backtest.len(),
+        report.matched
+    );
+    for divergence in &report.divergences {
+        println!(
+            "{} diverges at signal {}:",
+            divergence.strategy, divergence.index
+        );
+        for (path, signal) in [
+            ("live", &divergence.live),
+            ("backtest", &divergence.backtest),
+        ] {
+            match signal {
+                Some(signal) => println!("  {} at {}: {:?}", path, signal.time, signal.order),
+                None => println!("  {}: none", path),
+            }
+        }
+    }
+    Ok(())
+}
+
+// Stored candles of the pairs, of every stored pair when none is given, only the latest ones when
+// a number of days is given.
+fn stored(
+    config: &Config,
+    pairs: Vec<String>,
+    interval: i32,
+    days: Option<i64>,
+) -> Result<HashMap<String, Vec<Candle>>, String> {
+    let store = CandleStore::new(&config.history.directory);
+    let pairs = if pairs.is_empty() {
+        store
+            .series()?
+            .into_iter()
+            .filter(|(_, length)| *length == interval)
+            .map(|(pair, _)| pair)
+            .collect()
+    } else {
+        pairs
+    };
+    let mut candles = HashMap::new();
+    for pair in pairs {
+        let mut series = store.load(&pair, interval)?;
+        if let (Some(days), Some(last)) = (days, series.last()) {
+            let from = last.time - days * 24 * 3600;
+            series.retain(|candle| candle.time > from);
+        }
+        if series.is_empty() {
+            println!("No {}m candles stored for {}", interval, pair);
+            continue;
+        }
+        candles.insert(pair, series);
+    }
+    Ok(candles)
+}
+
+fn backtest(
+    config: &Config,
+    pairs: Vec<String>,
+    interval: i32,
+    days: Option<i64>,
+    cash: f64,
+    checkpoints: Option<(&Path, usize)>,
+    montecarlo: Option<MonteCarlo>,
+) -> Result<(), String> {
+    let candles = stored(config, pairs, interval, days)?;
+    let progress = ProgressBar::hidden();
+    let consolidated = multipair::backtest(
+        &config.strategies,
+        &candles,
+        cash,
+        config.shorting,
+        checkpoints,
+        |runs| {
+            progress.set_length(runs as u64);
+            progress.set_draw_target(ProgressDrawTarget::stderr());
+        },
+        |_| progress.inc(1),
+    )?;
+    progress.finish_and_clear();
+
+    println!("Strategies:");
+    for run in &consolidated.runs {
+        println!(
+            "  {} on {}: pnl {:.2}, fees {:.2}, {} fills, sharpe {:.3}, max drawdown {:.2}%",
+            run.strategy,
+            run.tickers.join("+"),
+            run.report.pnl(),
+            run.report.fees(),
+            run.report.fills.len(),
+            run.report.sharpe().unwrap_or(0.0),
+            100.0 * run.report.max_drawdown()
+        );
+    }
+    println!("Pairs:");
+    for (pair, summary) in &consolidated.pairs {
+        println!(
+            "  {}: realized {:.2} over {} trades, fees {:.2}, {} fills",
+            pair, summary.realized, summary.trades, summary.fees, summary.fills
+        );
+    }
+    let portfolio = &consolidated.portfolio;
+    println!(
+        "Portfolio of {} runs: pnl {:.2}, fees {:.2}, sharpe {:.3}, max drawdown {:.2}%",
+        consolidated.runs.len(),
+        portfolio.pnl(),
+        portfolio.fees(),
+        portfolio.sharpe().unwrap_or(0.0),
+        100.0 * portfolio.max_drawdown()
+    );
+
+    let Some(montecarlo) = montecarlo else {
+        return Ok(());
+    };
+    println!("Monte Carlo over {} resamplings:", montecarlo.runs);
+    for run in &consolidated.runs {
+        let name = format!("{} on {}", run.strategy, run.tickers.join("+"));
+        robustness(&name, montecarlo.analyze_report(&run.report));
+    }
+    robustness("Portfolio", montecarlo.analyze_report(portfolio));
+    Ok(())
+}
+
+fn robustness(name: &str, robustness: Option<Robustness>) {
+    let Some(robustness) = robustness else {
+        println!("  {}: no trade", name);
+        return;
+    };
+    let (total_return, drawdown) = (robustness.total_return, robustness.max_drawdown);
+    print!(
+        "  {}: return {:.2}% [{:.2}%, {:.2}%], ",
+        name,
+        100.0 * total_return.median,
+        100.0 * total_return.low,
+        100.0 * total_return.high
+    );
+    println!(
+        "max drawdown {:.2}% [{:.2}%, {:.2}%], {:.0}% profitable{}",
+        100.0 * drawdown.median,
+        100.0 * drawdown.low,
+        100.0 * drawdown.high,
+        100.0 * robustness.profitable,
+        if robustness.fragile {
+            ", the edge may be luck"
+        } else {
+            ""
+        }
+    );
+}
+
+fn optimize(
+    config: &Config,
+    strategy: &str,
+    pairs: Vec<String>,
+    interval: i32,
+    days: Option<i64>,
+    cash: f64,
+) -> Result<(), String> {
+    let Some(named) = config
+        .strategies
+        .iter()
+        .enumerate()
+        .find(|(index, named)| runner::name(*index, named) == strategy)
+        .map(|(_, named)| named)
+    else {
+        return Err(format!("No strategy {} configured", strategy));
+    };
+    let candles = stored(config, pairs, interval, days)?;
+    let (tuned, best) = optimizer::tune(named, &candles, cash, config.shorting, &config.optimizer)?;
+    println!("Best fitness {:.4} with:", best.fitness);
+    for (parameter, value) in config.optimizer.parameters.iter().zip(&best.values) {
+        println!("  {} = {}", parameter.name, value);
+    }
+    match toml::to_string(&tuned) {
+        Ok(settings) => println!("{}", settings),
+        Err(error) => return Err(format!("Could not print the settings: {}", error)),
+    }
+    Ok(())
+}
+
+fn inspect(directory: &Path) -> Result<(), String> {
+    let files = match fs::read_dir(directory) {
+        Ok(files) => files,
+        Err(error) => return Err(format!("Could not read {:?}: {:?}", directory, error)),
+    };
+    let mut paths: Vec<PathBuf> = files
+        .filter_map(|file| Some(file.ok()?.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+    for path in paths {
+        let Some(checkpoint) = Checkpoint::load(&path)? else {
+            continue;
+        };
+        let report = checkpoint.report();
+        println!(
+            "{:?}: {} steps up to {}, pnl {:.2}, fees {:.2}, max drawdown {:.2}%",
+            path.file_stem().unwrap_or_default(),
+            checkpoint.steps,
+            checkpoint.time.unwrap_or(0),
+            report.pnl(),
+            report.fees(),
+            100.0 * report.max_drawdown()
+        );
+    }
+    Ok(())
+}
+
+// Issues printed per series, the others are counted.
+const ISSUES: usize = 20;
+
+async fn verify(
+    config: &Config,
+    pairs: Vec<String>,
+    interval: Option<i32>,
+    repair: bool,
+    fetch: bool,
+) -> Result<(), String> {
+    let store = CandleStore::new(&config.history.directory);
+    let series = store.series()?.into_iter().filter(|(pair, length)| {
+        (pairs.is_empty() || pairs.contains(pair))
+            && interval.is_none_or(|interval| interval == *length)
+    });
+    for (pair, interval) in series {
+        let candles = store.load(&pair, interval)?;
+        let issues = integrity::check(&candles, interval);
+        println!(
+            "{} {}m: {} candles, {} issues",
+            pair,
+            interval,
+            candles.len(),
+            issues.len()
+        );
+        for issue in issues.iter().take(ISSUES) {
+            println!("  {}", issue);
+        }
+        if issues.len() > ISSUES {
+            println!("  and {} more", issues.len() - ISSUES);
+        }
+        if !repair || issues.is_empty() {
+            continue;
+        }
+
+        let mut repaired = integrity::repair(&candles, interval);
+        if fetch {
+            for gap in integrity::gaps(&repaired, interval) {
+                if let Issue::Gap { from, to } = gap {
+                    repaired
+                        .extend(history::range(&config.history, &pair, interval, from, to).await?);
+                }
+            }
+            repaired = integrity::repair(&repaired, interval);
+        }
+        store.write(&pair, interval, &repaired)?;
+        println!(
+            "  repaired, {} candles and {} gaps left",
+            repaired.len(),
+            integrity::gaps(&repaired, interval).len()
+        );
+    }
+    Ok(())
+}
+
+fn tax(config: &Config, output: &Path) -> Result<(), String> {
+    let entries = Journal::read(&config.journal)?;
+    // only fills quoted in EUR can be valued
+    let csv = accounting::tax_csv(&entries, config.accounting.cost_basis, |currency, _| {
+        (currency == "EUR").then_some(1.0)
+    })?;
+    match fs::write(output, csv) {
+        Ok(()) => Ok(()),
+        Err(error) => Err(format!("Could not write {:?}: {:?}", output, error)),
+    }
+}
+
+// Trade through an executor guarded by the risk limits, taking administrative commands on the
+// control socket and the API, optionally showing the terminal dashboard until it is quit.
+async fn run<E: Executor + Send + Sync + 'static>(
+    path: PathBuf,
+    config: Config,
+    executor: E,
+    workers: Vec<Worker>,
+    journal: Arc<Mutex<Journal>>,
+    feed: LiveFeed,
+    dashboard: bool,
+) -> Result<(), String> {
+    let log = config.logging.file.clone();
+    let executor = Arc::new(RiskGuard::new(executor, config.risk.clone()));
+    if config.risk.var.is_some() {
+        let store = CandleStore::new(&config.history.directory);
+        for pair in config.tickers() {
+            match store.load(&pair, config.feed.interval) {
+                Ok(candles) => executor.seed(&pair, &candles),
+                Err(message) => warn!(
+                    "No stored history of {} to value its risk: {}",
+                    pair, message
+                ),
+            }
+        }
+    }
+    let (watcher, settings) = ConfigWatcher::new(path, config.clone());
+    let reloads = tokio::spawn(
+        watcher
+            .run(Duration::from_secs(RELOAD_PERIOD))
+            .instrument(info_span!("config")),
+    );
+    let (pairs, requests) = Pairs::channel();
+    let control = tokio::spawn({
+        let (executor, journal, pairs) = (executor.clone(), journal.clone(), pairs.clone());
+        let socket = config.control.socket.clone();
+        async move {
+            if let Err(message) = control::serve(&socket, executor, journal, pairs).await {
+                warn!("Control socket unavailable: {}", message);
+            }
+        }
+        .instrument(info_span!("control"))
+    });
+
+    let sync = tokio::spawn(
+        balances::run(config.balances, executor.clone()).instrument(info_span!("balances")),
+    );
+
+    let executions = tokio::spawn(
+        fills::run(
+            config.fills,
+            executor.clone(),
+            journal.clone(),
+            config.journal.clone(),
+        )
+        .instrument(info_span!("fills")),
+    );
+
+    let margin = tokio::spawn(
+        margin::run(config.margin.clone(), executor.clone()).instrument(info_span!("margin")),
+    );
+
+    let drift = tokio::spawn(nonce::run(config.nonce.clone()).instrument(info_span!("clock")));
+
+    let reference = tokio::spawn(
+        instruments::refresh(config.instruments).instrument(info_span!("instruments")),
+    );
+
+    let releases =
+        tokio::spawn(calendar::run(config.calendar.clone()).instrument(info_span!("calendar")));
+
+    let refits = tokio::spawn(garch::run().instrument(info_span!("garch")));
+
+    let summaries = tokio::spawn(
+        summary::run(
+            config.summary.clone(),
+            executor.clone(),
+            journal.clone(),
+            config.journal.clone(),
+        )
+        .instrument(info_span!("summary")),
+    );
+
+    let checks = tokio::spawn(
+        health::run(
+            config.health,
+            executor.clone(),
+            journal.clone(),
+            vec![
+                config.history.directory.clone(),
+                config.state.directory.clone(),
+            ],
+        )
+        .instrument(info_span!("health")),
+    );
+
+    let makers = runner::makers(&config.strategies);
+    let pipeline = Pipeline {
+        anomalies: AnomalyDetector::new(config.feed.anomalies),
+        gaps: GapFiller::new(config.feed.interval as i64 * 60, config.feed.gap_policy),
+        latency: LatencyMonitor::new(config.feed.latency),
+        indicators: Indicators::new(config.indicators.clone()),
+        exporter: Exporter::new(config.export.clone(), config.feed.interval),
+        synchronizer: Synchronizer::new(config.tickers(), config.feed.sync.clone()),
+        runner: Runner::new(
+            config.strategies.clone(),
+            workers,
+            executor.clone(),
+            journal.clone(),
+            config.runner,
+            History {
+                store: CandleStore::new(&config.history.directory),
+                interval: config.feed.interval,
+                candles: config.runner.warmup,
+            },
+            StateStore::new(&config.state.directory),
+        )
+        .with_sync(config.feed.sync.clone()),
+        candles: config.runner.candles,
+        books: books(
+            &config,
+            makers.iter().map(|(_, maker)| maker.ticker().to_string()),
+        )
+        .into_iter()
+        .map(|ticker| (ticker, OrderBook::new(config.feed.depth as usize)))
+        .collect(),
+        makers,
+        depth: config.feed.depth as usize,
+    };
+    let api = tokio::spawn({
+        let api = Api {
+            guard: executor.clone(),
+            journal: journal.clone(),
+            pauses: pipeline.runner.pauses(),
+            pairs,
+            strategies: pipeline.runner.strategies().to_vec(),
+            health: config.health,
+        };
+        let config = config.api.clone();
+        async move {
+            if let Err(message) = api::serve(&config, api).await {
+                warn!("API unavailable: {}", message);
+            }
+        }
+        .instrument(info_span!("api"))
+    });
+    let trading = trade(feed, pipeline, requests, settings, &*executor, &journal)
+        .instrument(info_span!("feed"));
+    let result = if dashboard {
+        tokio::select! {
+            result = trading => result,
+            result = tui::run(executor.clone(), &log) => result,
+        }
+    } else {
+        trading.await
+    };
+    reloads.abort();
+    control.abort();
+    sync.abort();
+    executions.abort();
+    margin.abort();
+    drift.abort();
+    api.abort();
+    reference.abort();
+    releases.abort();
+    refits.abort();
+    summaries.abort();
+    checks.abort();
+    result
+}
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
-    set_up_logging("trade-bot.log");
+    let cli = Cli::parse();
 
-    let mut feed = match LiveFeed::new(10, 5, vec!["ETH/EUR".to_string()]).await {
-        Ok(feed) => feed,
-        Err(message) => return Err(message),
+    let config = if cli.config.exists() {
+        Config::load(&cli.config)?
+    } else {
+        Config::default()
     };
+    conversion::install(&config.conversion);
+    accounts::install(&config.accounts);
+    environment::install(&config.environment);
+    random::install(&config.random);
 
-    loop {
-        match feed.consume().await {
-            Ok(message) => info!("{:?}", message),
-            Err(message) => warn!("{:?}", message),
-        };
+    let dashboard = matches!(cli.command, Some(Action::Tui));
+    let replay = match &cli.command {
+        Some(Action::Replay { session, speed }) => Some((session.clone(), *speed)),
+        _ => None,
+    };
+    let command = match cli.command {
+        Some(Action::Kill { flatten }) => Some(Command::Kill { flatten }),
+        Some(Action::Resume) => Some(Command::Resume),
+        Some(Action::Subscribe { pair }) => Some(Command::Subscribe(pair)),
+        Some(Action::Unsubscribe { pair }) => Some(Command::Unsubscribe(pair)),
+        Some(Action::Oco {
+            pair,
+            buy,
+            volume,
+            take_profit,
+            stop_loss,
+        }) => Some(Command::Oco(Oco {
+            ticker: pair,
+            side: if buy { Side::Buy } else { Side::Sell },
+            volume,
+            take_profit,
+            stop_loss,
+        })),
+        Some(Action::Iceberg {
+            pair,
+            sell,
+            volume,
+            visible,
+            price,
+        }) => Some(Command::Iceberg(Iceberg {
+            ticker: pair,
+            side: if sell { Side::Sell } else { Side::Buy },
+            volume,
+            visible,
+            price,
+        })),
+        Some(Action::Report) => return report(&config),
+        Some(Action::Tax { output }) => return tax(&config, &output),
+        Some(Action::Parity { session, journal }) => return parity(&config, &session, journal),
+        Some(Action::Backtest {
+            pairs,
+            interval,
+            days,
+            cash,
+            checkpoints,
+            every,
+            montecarlo,
+        }) => {
+            let checkpoints = checkpoints.as_deref().map(|directory| (directory, every));
+            let montecarlo =
+                montecarlo.map(|runs| MonteCarlo::new(runs, Resampling::Bootstrap, 0.95));
+            return backtest(
+                &config,
+                pairs,
+                interval,
+                days,
+                cash,
+                checkpoints,
+                montecarlo,
+            );
+        }
+        Some(Action::Optimize {
+            strategy,
+            pairs,
+            interval,
+            days,
+            cash,
+        }) => return optimize(&config, &strategy, pairs, interval, days, cash),
+        Some(Action::Checkpoints { directory }) => return inspect(&directory),
+        Some(Action::Export {
+            mut pairs,
+            hours,
+            interval,
+            output,
+        }) => {
+            if pairs.is_empty() {
+                pairs = config.tickers();
+            }
+            return export(&config, pairs, hours, interval, output).await;
+        }
+        Some(Action::FetchHistory {
+            mut pairs,
+            days,
+            interval,
+        }) => {
+            if pairs.is_empty() {
+                pairs = config.tickers();
+            }
+            let store = CandleStore::new(&config.history.directory);
+            let from = clock::seconds() - days * 86400;
+            for pair in pairs {
+                let stored =
+                    history::download(&config.history, &store, &pair, interval, from).await?;
+                println!(
+                    "{} candles of {} stored in {:?}",
+                    stored,
+                    pair,
+                    store.path(&pair, interval)
+                );
+            }
+            return Ok(());
+        }
+        Some(Action::Import {
+            file,
+            pair,
+            format,
+            interval,
+            offset,
+        }) => {
+            let candles = datasets::import(&file, format, offset * 3600)?;
+            let store = CandleStore::new(&config.history.directory);
+            let added = store.merge(&pair, interval, &candles)?;
+            println!(
+                "{} of {} candles of {} added to {:?}",
+                added,
+                candles.len(),
+                pair,
+                store.path(&pair, interval)
+            );
+            return Ok(());
+        }
+        Some(Action::VerifyData {
+            pairs,
+            interval,
+            repair,
+            fetch,
+        }) => return verify(&config, pairs, interval, repair || fetch, fetch).await,
+        Some(Action::Keys {
+            action: Keys::Set { account },
+        }) => {
+            let source = match &account {
+                Some(name) => match config.accounts.iter().find(|other| other.name == *name) {
+                    Some(account) => account.secrets(),
+                    None => return Err(format!("No account {} configured", name)),
+                },
+                None => config.secrets.clone(),
+            };
+            let credentials = secrets::prompt()?;
+            println!(
+                "{}",
+                secrets::store(&source, account.as_deref(), &credentials)?
+            );
+            return Ok(());
+        }
+        Some(Action::Tui) | Some(Action::Replay { .. }) | None => None,
+    };
+    if let Some(command) = command {
+        println!("{}", control::send(&config.control.socket, command).await?);
+        return Ok(());
+    }
+
+    // the dashboard owns the terminal, logs only go to the file
+    let _logging = logging::set_up(&config.logging, !dashboard)?;
+    if !cli.config.exists() {
+        warn!("No configuration at {:?}, using defaults", cli.config);
+    }
+    alerts::install(Alerts::new(&config.alerts)?);
+    info!(endpoints = ?environment::endpoints(), "Trading on {:?}", config.environment.name);
+    info!("Random seed {}", random::seed());
+
+    match instruments::load().await {
+        Ok(count) => info!("Loaded the reference data of {} pairs", count),
+        Err(message) => warn!(
+            "Could not load the reference data, orders are not validated: {}",
+            message
+        ),
+    }
+
+    let tickers = config.tickers();
+    let workers = runner::plan(&config.strategies, &config.feed.pairs)?;
+    let quoted: Vec<String> = config
+        .strategies
+        .iter()
+        .filter(|config| config.strategy.quotes())
+        .flat_map(|config| config.strategy.tickers().unwrap_or_default())
+        .collect();
+    let journal = Arc::new(Mutex::new(Journal::open(&config.journal)?));
+
+    let mut feed = match &replay {
+        Some((session, speed)) => LiveFeed::replay(session, *speed, config.feed.buffer),
+        None => {
+            let mut feed = LiveFeed::new(
+                config.feed.watchdog,
+                config.feed.interval,
+                config.feed.depth,
+                tickers.clone(),
+                config.feed.buffer,
+            )
+            .await?;
+            let traded = config.indicators.iter().any(|config| config.kind.trades());
+            if config.feed.latency.trades || traded {
+                feed.subscribe_trades(tickers.clone()).await?;
+            }
+            // crosses are only followed to convert to the reporting currency
+            let mut crossed = tickers;
+            crossed.extend(config.conversion.crosses.iter().cloned());
+            crossed.extend(
+                config
+                    .triangles
+                    .iter()
+                    .flat_map(|scanner| scanner.triangle.pairs()),
+            );
+            crossed.sort();
+            crossed.dedup();
+            feed.subscribe_ticker(crossed).await?;
+            let books = books(&config, quoted.iter().cloned());
+            if !books.is_empty() {
+                feed.subscribe_book(books).await?;
+            }
+            feed
+        }
+    };
+    if let Some(path) = &cli.record {
+        feed.record(path)?;
+    }
+
+    let sandboxed = config.environment.simulated();
+    let arbitraging = config.triangles.iter().any(|scanner| scanner.auto_execute);
+    let idle = workers.is_empty() && quoted.is_empty() && !arbitraging;
+    if sandboxed && !idle {
+        warn!("No spot sandbox configured for the demo environment, orders are simulated");
+    }
+    // without strategies no order is ever placed, the feed can be followed without credentials
+    if cli.dry_run || sandboxed || replay.is_some() || idle {
+        return run(
+            cli.config,
+            config,
+            DryRunExecutor::new(),
+            workers,
+            journal,
+            feed,
+            dashboard,
+        )
+        .await;
+    }
+
+    let credentials = secrets::load(&config.secrets, None)?;
+    let main = RetryingExecutor::new(
+        KrakenExecutor::new(&credentials.key, &credentials.secret, &config.nonce.file),
+        config.retry,
+    );
+    let mut others = HashMap::new();
+    for account in &config.accounts {
+        let credentials = secrets::load(&account.secrets(), Some(&account.name))?;
+        let executor = RetryingExecutor::new(
+            KrakenExecutor::new(&credentials.key, &credentials.secret, &account.nonce()),
+            config.retry,
+        );
+        others.insert(account.name.clone(), executor);
     }
+    let executor = Accounts::new(main, others);
+    run(
+        cli.config, config, executor, workers, journal, feed, dashboard,
+    )
+    .await
 }
@@ -0,0 +1,216 @@
+use crate::alerts::{self, EventKind};
+use crate::environment;
+use crate::execution::{Executor, Side};
+use crate::metrics;
+
+use reqwest::Client;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tokio::time::interval;
+
+use tracing::{info, warn};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MarginConfig {
+    // time between margin and perpetual queries (in s), neither is monitored without it
+    pub period: Option<u64>,
+    // margin level (in %) under which an alert is raised, Kraken calls margins at 80%
+    pub alert_level: f64,
+    // margin level (in %) at which positions are liquidated
+    pub liquidation_level: f64,
+    // perpetual contracts whose funding rates and open interest are tracked, e.g. PF_ETHUSD
+    pub perpetuals: Vec<String>,
+}
+
+impl Default for MarginConfig {
+    fn default() -> MarginConfig {
+        MarginConfig {
+            period: None,
+            alert_level: 100.0,
+            liquidation_level: 40.0,
+            perpetuals: Vec::new(),
+        }
+    }
+}
+
+// Margin state of the account, amounts in its base currency.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Margin {
+    // balance plus the unrealized profit of the open positions
+    pub equity: f64,
+    // margin held by the open positions
+    pub used: f64,
+    pub free: f64,
+    // equity over the margin used (in %), None without open positions
+    pub level: Option<f64>,
+}
+
+// Price at which a position opened at an entry price with a leverage reaches the liquidation
+// margin level (in %), assuming the position alone is backed by its margin. None without
+// leverage, such positions are paid in full and cannot be liquidated.
+pub fn liquidation_price(
+    side: Side,
+    entry: f64,
+    leverage: f64,
+    liquidation_level: f64,
+) -> Option<f64> {
+    if leverage <= 1.0 {
+        return None;
+    }
+    // the margin is the entry value over the leverage, the position is liquidated once its loss
+    // leaves the liquidation level of the margin
+    let buffer = (1.0 - liquidation_level / 100.0) / leverage;
+    Some(entry * (1.0 - side.sign() * buffer))
+}
+
+// Funding of a perpetual contract, longs paying shorts when positive, with its open interest.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Funding {
+    // rate (relative to the position value) paid per hour at the current price
+    pub rate: f64,
+    // rate predicted for the next period
+    pub predicted: Option<f64>,
+    pub mark: f64,
+    // contracts outstanding
+    pub open_interest: f64,
+}
+
+impl Funding {
+    // Funding paid by a position of signed value over a number of hours, negative when received.
+    pub fn cost(&self, value: f64, hours: f64) -> f64 {
+        value * self.rate * hours
+    }
+}
+
+fn fundings() -> &'static Mutex<HashMap<String, Funding>> {
+    static FUNDINGS: OnceLock<Mutex<HashMap<String, Funding>>> = OnceLock::new();
+    FUNDINGS.get_or_init(Mutex::default)
+}
+
+// Latest funding and open interest of a perpetual contract, None when it is not tracked.
+pub fn funding(symbol: &str) -> Option<Funding> {
+    fundings()
+        .lock()
+        .ok()
+        .and_then(|fundings| fundings.get(symbol).copied())
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+// Funding of the perpetual contracts listed by Kraken Futures, whose rates are given as amounts per
+// contract and are made relative to the mark price.
+async fn fetch(client: &Client) -> Result<HashMap<String, Funding>, String> {
+    let body: Value = match client
+        .get(format!(
+            "{}/derivatives/api/v3/tickers",
+            environment::endpoints().futures
+        ))
+        .send()
+        .await
+    {
+        Ok(response) => match response.json().await {
+            Ok(body) => body,
+            Err(error) => return Err(format!("Invalid tickers response: {:?}", error)),
+        },
+        Err(error) => return Err(format!("{:?}", error)),
+    };
+    let Some(tickers) = body["tickers"].as_array() else {
+        return Err(format!("Tickers response without tickers: {}", body));
+    };
+    Ok(tickers
+        .iter()
+        .filter_map(|ticker| {
+            let symbol = ticker["symbol"].as_str()?;
+            let mark = number(&ticker["markPrice"]).filter(|mark| *mark > 0.0)?;
+            let funding = Funding {
+                rate: number(&ticker["fundingRate"])? / mark,
+                predicted: number(&ticker["fundingRatePrediction"]).map(|rate| rate / mark),
+                mark,
+                open_interest: number(&ticker["openInterest"]).unwrap_or(0.0),
+            };
+            Some((symbol.to_string(), funding))
+        })
+        .collect())
+}
+
+// Periodically query the margin of the account, alerting when its level falls under the alert
+// level, and the funding rates of the tracked perpetuals.
+pub async fn run<E: Executor + Sync>(config: MarginConfig, executor: Arc<E>) {
+    let Some(period) = config.period else {
+        return;
+    };
+    let client = Client::new();
+    // whether the margin level is under the alert level, alerts are raised on crossing it
+    let mut low = false;
+    let mut ticker = interval(Duration::from_secs(period.max(1)));
+    loop {
+        ticker.tick().await;
+        match executor.margin().await {
+            Ok(margin) => {
+                metrics::set("margin.equity", margin.equity);
+                metrics::set("margin.used", margin.used);
+                metrics::set("margin.free", margin.free);
+                if let Some(level) = margin.level {
+                    metrics::set("margin.level", level);
+                }
+                let under = margin.level.is_some_and(|level| level < config.alert_level);
+                if under && !low {
+                    warn!(
+                        "Margin level of {:?}% under {}%",
+                        margin.level, config.alert_level
+                    );
+                    alerts::notify(
+                        EventKind::Risk,
+                        "Margin level low",
+                        &format!(
+                            "Margin level {:.1}%, liquidation at {}%",
+                            margin.level.unwrap_or(0.0),
+                            config.liquidation_level
+                        ),
+                    );
+                } else if !under && low {
+                    info!("Margin level back over {}%", config.alert_level);
+                }
+                low = under;
+            }
+            Err(message) => warn!("Could not query margin: {}", message),
+        }
+
+        if config.perpetuals.is_empty() {
+            continue;
+        }
+        match fetch(&client).await {
+            Ok(listed) => {
+                let Ok(mut fundings) = fundings().lock() else {
+                    continue;
+                };
+                for symbol in &config.perpetuals {
+                    let Some(funding) = listed.get(symbol) else {
+                        warn!("No funding listed for {}", symbol);
+                        continue;
+                    };
+                    metrics::set(&format!("funding.{}.rate", symbol), funding.rate);
+                    metrics::set(
+                        &format!("funding.{}.open_interest", symbol),
+                        funding.open_interest,
+                    );
+                    fundings.insert(symbol.clone(), *funding);
+                }
+            }
+            Err(message) => warn!("Could not query funding rates: {}", message),
+        }
+    }
+}
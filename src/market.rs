@@ -0,0 +1,322 @@
+use crate::book::{self, BookUpdate};
+use crate::execution::Side;
+
+use kraken_async_rs::response_types::OHLC;
+use kraken_async_rs::wss::{
+    BuySell, ChannelMessage, Ohlc, Ticker as KrakenTicker, Trade as KrakenTrade, WssMessage,
+};
+
+use chrono::DateTime;
+
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+
+use serde::{Deserialize, Serialize};
+
+use tracing::warn;
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+// Crate owned candle representation decoupled from the exchange types. Prices and volumes are
+// stored as floats since they only feed into statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub struct Candle {
+    // unix time (in s) at which the candle opened
+    pub time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub vwap: f64,
+    pub volume: f64,
+    // number of trades aggregated in the candle
+    pub count: i64,
+}
+
+// Numeric field of a candle statistics can be computed over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Open,
+    High,
+    Low,
+    #[default]
+    Close,
+    Vwap,
+    Volume,
+}
+
+impl Field {
+    pub fn of(&self, candle: &Candle) -> f64 {
+        match self {
+            Field::Open => candle.open,
+            Field::High => candle.high,
+            Field::Low => candle.low,
+            Field::Close => candle.close,
+            Field::Vwap => candle.vwap,
+            Field::Volume => candle.volume,
+        }
+    }
+}
+
+pub fn to_float(value: &Decimal) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+impl From<&OHLC> for Candle {
+    fn from(ohlc: &OHLC) -> Candle {
+        Candle {
+            time: ohlc.time,
+            open: to_float(&ohlc.open),
+            high: to_float(&ohlc.high),
+            low: to_float(&ohlc.low),
+            close: to_float(&ohlc.close),
+            vwap: to_float(&ohlc.vwap),
+            volume: to_float(&ohlc.volume),
+            count: ohlc.count,
+        }
+    }
+}
+
+impl TryFrom<&Ohlc> for Candle {
+    type Error = String;
+
+    fn try_from(ohlc: &Ohlc) -> Result<Candle, String> {
+        let time = match DateTime::parse_from_rfc3339(&ohlc.interval_begin) {
+            Ok(time) => time.timestamp(),
+            Err(error) => return Err(format!("{:?}", error)),
+        };
+        Ok(Candle {
+            time,
+            open: to_float(&ohlc.open),
+            high: to_float(&ohlc.high),
+            low: to_float(&ohlc.low),
+            close: to_float(&ohlc.close),
+            vwap: to_float(&ohlc.vwap),
+            volume: to_float(&ohlc.volume),
+            count: ohlc.trades,
+        })
+    }
+}
+
+// Candles carried by a websocket message along with their ticker.
+pub fn candles(message: &WssMessage) -> Vec<(String, Candle)> {
+    let WssMessage::Channel(ChannelMessage::Ohlc(response)) = message else {
+        return Vec::new();
+    };
+    response
+        .data
+        .iter()
+        .filter_map(|ohlc| match Candle::try_from(ohlc) {
+            Ok(candle) => Some((ohlc.symbol.clone(), candle)),
+            Err(message) => {
+                warn!("Discarding candle {:?}: {}", ohlc, message);
+                None
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Trade {
+    // unix time (in s) of the trade
+    pub time: f64,
+    pub price: f64,
+    pub volume: f64,
+    // side of the aggressor (taker)
+    pub side: Side,
+}
+
+impl TryFrom<&KrakenTrade> for Trade {
+    type Error = String;
+
+    fn try_from(trade: &KrakenTrade) -> Result<Trade, String> {
+        let time = match DateTime::parse_from_rfc3339(&trade.timestamp) {
+            Ok(time) => time.timestamp_micros() as f64 / 1e6,
+            Err(error) => return Err(format!("{:?}", error)),
+        };
+        Ok(Trade {
+            time,
+            price: to_float(&trade.price),
+            volume: to_float(&trade.qty),
+            side: match trade.side {
+                BuySell::Buy => Side::Buy,
+                BuySell::Sell => Side::Sell,
+            },
+        })
+    }
+}
+
+// Trades carried by a websocket message along with their ticker.
+pub fn trades(message: &WssMessage) -> Vec<(String, Trade)> {
+    let WssMessage::Channel(ChannelMessage::Trade(response)) = message else {
+        return Vec::new();
+    };
+    response
+        .data
+        .iter()
+        .filter_map(|trade| match Trade::try_from(trade) {
+            Ok(converted) => Some((trade.symbol.clone(), converted)),
+            Err(message) => {
+                warn!("Discarding trade {:?}: {}", trade, message);
+                None
+            }
+        })
+        .collect()
+}
+
+// Best prices of a ticker along with its statistics over the last 24h.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub struct Quote {
+    pub bid: f64,
+    pub bid_volume: f64,
+    pub ask: f64,
+    pub ask_volume: f64,
+    pub last: f64,
+    pub volume: f64,
+    pub vwap: f64,
+    pub low: f64,
+    pub high: f64,
+    // relative price change (in %)
+    pub change: f64,
+}
+
+impl Quote {
+    pub fn spread(&self) -> f64 {
+        self.ask - self.bid
+    }
+
+    pub fn mid(&self) -> f64 {
+        (self.bid + self.ask) / 2.0
+    }
+}
+
+impl From<&KrakenTicker> for Quote {
+    fn from(ticker: &KrakenTicker) -> Quote {
+        Quote {
+            bid: to_float(&ticker.bid),
+            bid_volume: to_float(&ticker.bid_quantity),
+            ask: to_float(&ticker.ask),
+            ask_volume: to_float(&ticker.ask_quantity),
+            last: to_float(&ticker.last),
+            volume: to_float(&ticker.volume),
+            vwap: to_float(&ticker.vwap),
+            low: to_float(&ticker.low),
+            high: to_float(&ticker.high),
+            change: to_float(&ticker.change_pct),
+        }
+    }
+}
+
+fn quotes() -> &'static Mutex<HashMap<String, Quote>> {
+    static QUOTES: OnceLock<Mutex<HashMap<String, Quote>>> = OnceLock::new();
+    QUOTES.get_or_init(Mutex::default)
+}
+
+// Record the latest quote of a ticker in the process wide registry.
+pub fn update_quote(ticker: &str, quote: Quote) {
+    if let Ok(mut quotes) = quotes().lock() {
+        quotes.insert(ticker.to_string(), quote);
+    }
+}
+
+// Latest quote of a ticker, None when its ticker channel is not followed.
+pub fn latest_quote(ticker: &str) -> Option<Quote> {
+    quotes()
+        .lock()
+        .ok()
+        .and_then(|quotes| quotes.get(ticker).copied())
+}
+
+// Candles a consumer ingests: the final version of each candle or every update of the one
+// forming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandleUpdates {
+    #[default]
+    Closed,
+    Intrabar,
+}
+
+// Tells when candles are final from the stream of their updates: a candle is closed once an
+// update of a later candle of its ticker arrives.
+#[derive(Debug, Default)]
+pub struct CandleCloser {
+    forming: HashMap<String, Candle>,
+}
+
+impl CandleCloser {
+    // Track an update of a ticker's candle, returns the candle it closes if any. Late updates of
+    // closed candles are ignored.
+    pub fn update(&mut self, ticker: &str, candle: &Candle) -> Option<Candle> {
+        match self.forming.get_mut(ticker) {
+            Some(forming) if candle.time > forming.time => {
+                Some(std::mem::replace(forming, *candle))
+            }
+            Some(forming) => {
+                if candle.time == forming.time {
+                    *forming = *candle;
+                }
+                None
+            }
+            None => {
+                self.forming.insert(ticker.to_string(), *candle);
+                None
+            }
+        }
+    }
+
+    pub fn forget(&mut self, ticker: &str) {
+        self.forming.remove(ticker);
+    }
+}
+
+// Market data received from a feed, independent of the exchange it comes from.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum MarketEvent {
+    // update of the candle forming, the latest update of a candle is its final version
+    Candle { ticker: String, candle: Candle },
+    // candle no longer updated, follows the last of its updates
+    Closed { ticker: String, candle: Candle },
+    Trade { ticker: String, trade: Trade },
+    Book { ticker: String, update: BookUpdate },
+    Ticker { ticker: String, quote: Quote },
+    // sign of life of an otherwise quiet connection
+    Heartbeat,
+    // exchange system status, e.g. online or maintenance
+    Status(String),
+}
+
+// Market events carried by a Kraken websocket message, other messages (e.g. subscription
+// acknowledgements) carry none.
+pub fn events(message: &WssMessage) -> Vec<MarketEvent> {
+    let WssMessage::Channel(channel) = message else {
+        return Vec::new();
+    };
+    match channel {
+        ChannelMessage::Ohlc(_) => candles(message)
+            .into_iter()
+            .map(|(ticker, candle)| MarketEvent::Candle { ticker, candle })
+            .collect(),
+        ChannelMessage::Trade(_) => trades(message)
+            .into_iter()
+            .map(|(ticker, trade)| MarketEvent::Trade { ticker, trade })
+            .collect(),
+        ChannelMessage::Orderbook(response) => vec![MarketEvent::Book {
+            ticker: book::symbol(&response.data).to_string(),
+            update: BookUpdate::from(&response.data),
+        }],
+        ChannelMessage::Ticker(response) => vec![MarketEvent::Ticker {
+            ticker: response.data.symbol.clone(),
+            quote: Quote::from(&response.data),
+        }],
+        ChannelMessage::Heartbeat => vec![MarketEvent::Heartbeat],
+        ChannelMessage::Status(response) => response
+            .data
+            .iter()
+            .map(|status| MarketEvent::Status(format!("{:?}", status.system).to_lowercase()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,66 @@
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+// Process wide registry of named counters and gauges.
+#[derive(Debug, Default)]
+struct Registry {
+    counters: Mutex<BTreeMap<String, u64>>,
+    gauges: Mutex<BTreeMap<String, f64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct Snapshot {
+    pub counters: BTreeMap<String, u64>,
+    pub gauges: BTreeMap<String, f64>,
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Registry::default)
+}
+
+pub fn increment(name: &str, by: u64) {
+    if let Ok(mut counters) = registry().counters.lock() {
+        *counters.entry(name.to_string()).or_insert(0) += by;
+    }
+}
+
+pub fn set(name: &str, value: f64) {
+    if let Ok(mut gauges) = registry().gauges.lock() {
+        gauges.insert(name.to_string(), value);
+    }
+}
+
+pub fn counter(name: &str) -> u64 {
+    registry()
+        .counters
+        .lock()
+        .ok()
+        .and_then(|counters| counters.get(name).copied())
+        .unwrap_or(0)
+}
+
+pub fn gauge(name: &str) -> Option<f64> {
+    registry()
+        .gauges
+        .lock()
+        .ok()
+        .and_then(|gauges| gauges.get(name).copied())
+}
+
+pub fn snapshot() -> Snapshot {
+    Snapshot {
+        counters: registry()
+            .counters
+            .lock()
+            .map(|counters| counters.clone())
+            .unwrap_or_default(),
+        gauges: registry()
+            .gauges
+            .lock()
+            .map(|gauges| gauges.clone())
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,122 @@
+use crate::backtest::BacktestReport;
+use crate::random;
+use crate::statistics::quantile;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resampling {
+    // permute the trades, keeps the total return but changes the path
+    Shuffle,
+    // draw trades with replacement, changes both the total return and the path
+    Bootstrap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub low: f64,
+    pub median: f64,
+    pub high: f64,
+}
+
+impl Interval {
+    fn new(values: &[f64], confidence: f64) -> Option<Interval> {
+        let tail = (1.0 - confidence) / 2.0;
+        Some(Interval {
+            low: quantile(values, tail)?,
+            median: quantile(values, 0.5)?,
+            high: quantile(values, 1.0 - tail)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Robustness {
+    // relative return over the initial equity
+    pub total_return: Interval,
+    // largest relative drop from a peak
+    pub max_drawdown: Interval,
+    // share of the resampled runs ending with a profit
+    pub profitable: f64,
+    // the lower bound of the return interval is not positive, the edge may be luck
+    pub fragile: bool,
+}
+
+// Monte Carlo analysis of a backtest obtained by resampling its sequence of trades.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonteCarlo {
+    pub runs: usize,
+    pub resampling: Resampling,
+    // probability mass covered by the reported intervals, e.g. 0.95
+    pub confidence: f64,
+}
+
+impl MonteCarlo {
+    pub fn new(runs: usize, resampling: Resampling, confidence: f64) -> MonteCarlo {
+        MonteCarlo {
+            runs,
+            resampling,
+            confidence,
+        }
+    }
+
+    // Resample the profits of successive trades starting from the given equity, drawing from the
+    // montecarlo stream of the seed of the process for a run reproducible from the configured one.
+    pub fn analyze(&self, equity: f64, pnls: &[f64]) -> Option<Robustness> {
+        self.analyze_with(equity, pnls, &mut random::rng("montecarlo"))
+    }
+
+    // Resample the profits of successive trades starting from the given equity with a generator.
+    pub fn analyze_with<R: Rng>(
+        &self,
+        equity: f64,
+        pnls: &[f64],
+        rng: &mut R,
+    ) -> Option<Robustness> {
+        if pnls.is_empty() || self.runs == 0 || equity <= 0.0 {
+            return None;
+        }
+
+        let mut returns = Vec::with_capacity(self.runs);
+        let mut drawdowns = Vec::with_capacity(self.runs);
+        let mut sample = pnls.to_vec();
+
+        for _ in 0..self.runs {
+            match self.resampling {
+                Resampling::Shuffle => sample.shuffle(rng),
+                Resampling::Bootstrap => {
+                    for value in sample.iter_mut() {
+                        *value = pnls[rng.random_range(0..pnls.len())];
+                    }
+                }
+            }
+
+            let mut current = equity;
+            let mut peak = equity;
+            let mut drawdown: f64 = 0.0;
+            for pnl in &sample {
+                current += pnl;
+                peak = peak.max(current);
+                drawdown = drawdown.max(1.0 - current / peak);
+            }
+            returns.push(current / equity - 1.0);
+            drawdowns.push(drawdown);
+        }
+
+        let total_return = Interval::new(&returns, self.confidence)?;
+        Some(Robustness {
+            total_return,
+            max_drawdown: Interval::new(&drawdowns, self.confidence)?,
+            profitable: returns.iter().filter(|value| **value > 0.0).count() as f64
+                / self.runs as f64,
+            fragile: total_return.low <= 0.0,
+        })
+    }
+
+    // Resample the trades of a backtest report.
+    pub fn analyze_report(&self, report: &BacktestReport) -> Option<Robustness> {
+        let (_, equity) = report.equity.first()?;
+        self.analyze(*equity, &report.trade_pnls())
+    }
+}
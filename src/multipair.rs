@@ -0,0 +1,194 @@
+use crate::backtest::{
+    BacktestReport, Backtester, FeeSchedule, Shorting, SimulatedBroker, SlippageModel,
+};
+use crate::config::NamedConfig;
+use crate::market::Candle;
+use crate::runner;
+
+use rayon::prelude::*;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+// Backtest of a strategy instance over the pairs it trades.
+pub struct Run {
+    // name the strategy is journaled under live, e.g. pairs#0
+    pub strategy: String,
+    pub tickers: Vec<String>,
+    pub report: BacktestReport,
+}
+
+// Results of the strategies on a pair.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PairSummary {
+    // profit of the closed trades, net of fees
+    pub realized: f64,
+    pub fees: f64,
+    pub fills: usize,
+    pub trades: usize,
+}
+
+// Results of the backtests of every strategy on every pair.
+pub struct Consolidated {
+    pub runs: Vec<Run>,
+    pub pairs: BTreeMap<String, PairSummary>,
+    // equity of all runs together at every step, fills of all runs
+    pub portfolio: BacktestReport,
+}
+
+// File the checkpoints of a strategy instance are kept in within a directory, e.g.
+// pairs#0-BTC-EUR+ETH-EUR.json.
+pub fn checkpoint(directory: &Path, strategy: &str, tickers: &[String]) -> PathBuf {
+    directory.join(format!(
+        "{}-{}.json",
+        strategy,
+        tickers.join("+").replace('/', "-")
+    ))
+}
+
+// Steps of the candles of the tickers, a step per candle time.
+fn steps(
+    candles: &HashMap<String, Vec<Candle>>,
+    tickers: &[String],
+) -> Vec<HashMap<String, Candle>> {
+    let mut steps: BTreeMap<i64, HashMap<String, Candle>> = BTreeMap::new();
+    for ticker in tickers {
+        for candle in candles.get(ticker).into_iter().flatten() {
+            steps
+                .entry(candle.time)
+                .or_default()
+                .insert(ticker.clone(), *candle);
+        }
+    }
+    steps.into_values().collect()
+}
+
+// Sum of the equity curves of the runs at every time one of them steps, each run keeping its
+// latest equity, its first one before it starts.
+fn combine(runs: &[Run]) -> Vec<(i64, f64)> {
+    let times: BTreeSet<i64> = runs
+        .iter()
+        .flat_map(|run| run.report.equity.iter().map(|(time, _)| *time))
+        .collect();
+    let mut positions = vec![0; runs.len()];
+    times
+        .into_iter()
+        .map(|time| {
+            let total = runs
+                .iter()
+                .zip(&mut positions)
+                .map(|(run, position)| {
+                    let equity = &run.report.equity;
+                    while *position + 1 < equity.len() && equity[*position + 1].0 <= time {
+                        *position += 1;
+                    }
+                    equity.get(*position).map_or(0.0, |(_, equity)| *equity)
+                })
+                .sum();
+            (time, total)
+        })
+        .collect()
+}
+
+impl Consolidated {
+    fn new(runs: Vec<Run>) -> Consolidated {
+        let mut pairs: BTreeMap<String, PairSummary> = BTreeMap::new();
+        for run in &runs {
+            for ticker in &run.tickers {
+                let fills: Vec<_> = run
+                    .report
+                    .fills
+                    .iter()
+                    .filter(|fill| fill.ticker == *ticker)
+                    .cloned()
+                    .collect();
+                let trades = BacktestReport {
+                    fills,
+                    ..BacktestReport::default()
+                };
+                let pnls = trades.trade_pnls();
+                let summary = pairs.entry(ticker.clone()).or_default();
+                summary.realized += pnls.iter().sum::<f64>();
+                summary.fees += trades.fees();
+                summary.fills += trades.fills.len();
+                summary.trades += pnls.len();
+            }
+        }
+        let portfolio = BacktestReport {
+            equity: combine(&runs),
+            fills: runs
+                .iter()
+                .flat_map(|run| run.report.fills.iter().cloned())
+                .collect(),
+            borrow_fees: runs.iter().map(|run| run.report.borrow_fees).sum(),
+            ..BacktestReport::default()
+        };
+        Consolidated {
+            runs,
+            pairs,
+            portfolio,
+        }
+    }
+}
+
+// Backtest every configured strategy on the stored candles of the pairs in parallel, each
+// instance trading with its own cash, the portfolio holding the cash of all of them, short selling
+// as allowed if at all. Strategies bound to pairs without candles are left out. Runs are
+// checkpointed in a directory every number of steps when one is given and resume from there.
+// Started is called with the number of runs before they start and done with the name of each as
+// it finishes, e.g. to report progress.
+pub fn backtest(
+    configs: &[NamedConfig],
+    candles: &HashMap<String, Vec<Candle>>,
+    cash: f64,
+    shorting: Option<Shorting>,
+    checkpoints: Option<(&Path, usize)>,
+    started: impl FnOnce(usize),
+    done: impl Fn(&str) + Sync,
+) -> Result<Consolidated, String> {
+    let mut tickers: Vec<String> = candles.keys().cloned().collect();
+    tickers.sort();
+    let jobs: Vec<_> = runner::plan(configs, &tickers)?
+        .into_iter()
+        .filter(|worker| {
+            worker
+                .tickers
+                .iter()
+                .all(|ticker| candles.contains_key(ticker))
+        })
+        .flat_map(|worker| {
+            let tickers = worker.tickers;
+            worker
+                .strategies
+                .into_iter()
+                .map(move |(name, strategy)| (name, tickers.clone(), strategy))
+        })
+        .collect();
+    started(jobs.len());
+    let mut runs: Vec<Run> = jobs
+        .into_par_iter()
+        .map(|(name, tickers, strategy)| {
+            let mut broker =
+                SimulatedBroker::new(cash, FeeSchedule::default(), SlippageModel::default());
+            if let Some(shorting) = shorting {
+                broker = broker.with_shorting(shorting);
+            }
+            let mut backtester = Backtester::new(strategy, broker);
+            if let Some((directory, every)) = checkpoints {
+                backtester =
+                    backtester.with_checkpoints(&checkpoint(directory, &name, &tickers), every);
+            }
+            let report = backtester.run(steps(candles, &tickers));
+            done(&name);
+            Run {
+                strategy: name,
+                tickers,
+                report,
+            }
+        })
+        .collect();
+    runs.sort_by(|first, second| {
+        (&first.strategy, &first.tickers).cmp(&(&second.strategy, &second.tickers))
+    });
+    Ok(Consolidated::new(runs))
+}
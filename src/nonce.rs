@@ -0,0 +1,122 @@
+use crate::alerts::{self, EventKind};
+use crate::clock;
+use crate::environment;
+use crate::metrics;
+
+use kraken_async_rs::crypto::nonce_provider::NonceProvider;
+
+use reqwest::Client;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tokio::time::interval;
+
+use tracing::warn;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NonceConfig {
+    // file the last nonce used is kept in across restarts
+    pub file: PathBuf,
+    // time between checks of the local clock against the exchange's (in s)
+    pub period: u64,
+    // difference between the local and the exchange clocks that is reported (in s)
+    pub max_offset: f64,
+}
+
+impl Default for NonceConfig {
+    fn default() -> NonceConfig {
+        NonceConfig {
+            file: PathBuf::from("trade-bot.nonce"),
+            period: 3600,
+            max_offset: 1.0,
+        }
+    }
+}
+
+// Nonces for the private API: the current time in ms, kept above the last nonce used even after
+// a restart or when the clock goes back, as Kraken rejects nonces not above the previous one.
+#[derive(Debug)]
+pub struct PersistentNonceProvider {
+    file: PathBuf,
+    last: u64,
+}
+
+impl PersistentNonceProvider {
+    pub fn new(file: &Path) -> PersistentNonceProvider {
+        let last = fs::read_to_string(file)
+            .ok()
+            .and_then(|content| content.trim().parse().ok())
+            .unwrap_or(0);
+        PersistentNonceProvider {
+            file: file.to_path_buf(),
+            last,
+        }
+    }
+}
+
+impl NonceProvider for PersistentNonceProvider {
+    fn get_nonce(&mut self) -> u64 {
+        let now = (clock::now() * 1000.0) as u64;
+        self.last = now.max(self.last + 1);
+        if let Err(error) = fs::write(&self.file, self.last.to_string()) {
+            warn!("Could not write {:?}: {:?}", self.file, error);
+        }
+        self.last
+    }
+}
+
+// Difference between the exchange and the local clocks (in s).
+pub async fn offset(client: &Client) -> Result<f64, String> {
+    let sent = clock::now();
+    let body: Value = match client
+        .get(format!("{}/0/public/Time", environment::endpoints().rest))
+        .send()
+        .await
+    {
+        Ok(response) => match response.json().await {
+            Ok(body) => body,
+            Err(error) => return Err(format!("Invalid time response: {:?}", error)),
+        },
+        Err(error) => return Err(format!("{:?}", error)),
+    };
+    let Some(server) = body["result"]["unixtime"].as_i64() else {
+        return Err(format!("Time response without time: {}", body));
+    };
+    // the server read its clock about halfway through the request, with a second of precision
+    let local = (sent + clock::now()) / 2.0;
+    Ok(server as f64 + 0.5 - local)
+}
+
+// Periodically compare the local clock to the exchange's, alerting when they drift apart.
+pub async fn run(config: NonceConfig) {
+    let client = Client::new();
+    let mut ticker = interval(Duration::from_secs(config.period.max(1)));
+    loop {
+        ticker.tick().await;
+        let offset = match offset(&client).await {
+            Ok(offset) => offset,
+            Err(message) => {
+                warn!("Could not query the exchange time: {}", message);
+                continue;
+            }
+        };
+        metrics::set("exchange.clock_offset", offset);
+        if offset.abs() > config.max_offset {
+            warn!("Local clock is {:.3}s ahead of the exchange's", -offset);
+            alerts::notify(
+                EventKind::Error,
+                "Clock drift",
+                &format!(
+                    "Local clock is {:.3}s ahead of the exchange's, limit {}s",
+                    -offset, config.max_offset
+                ),
+            );
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use crate::backtest::{BacktestReport, Shorting};
+use crate::config::{NamedConfig, OptimizerConfig, StrategyConfig};
+use crate::market::Candle;
+use crate::multipair;
+use crate::random;
+
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use toml::Value;
+
+use tracing::info;
+
+use std::collections::HashMap;
+
+// Range explored for a strategy parameter.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Parameter {
+    // setting of the strategy, e.g. window
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    // round values, for window lengths and the like
+    #[serde(default)]
+    pub integer: bool,
+}
+
+impl Parameter {
+    fn clamp(&self, value: f64) -> f64 {
+        let value = value.clamp(self.min, self.max);
+        if self.integer { value.round() } else { value }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        self.clamp(rng.random_range(self.min..=self.max))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    // values in the order of the optimized parameters
+    pub values: Vec<f64>,
+    pub fitness: f64,
+}
+
+// Fitness of a backtest: Sharpe ratio penalized by the maximal drawdown.
+pub fn fitness(report: &BacktestReport, drawdown_penalty: f64) -> f64 {
+    match report.sharpe() {
+        Some(sharpe) => sharpe - drawdown_penalty * report.max_drawdown(),
+        None => f64::NEG_INFINITY,
+    }
+}
+
+// Evolves strategy parameter sets against a fitness function, usually a backtest scored with
+// `fitness`, using tournament selection, uniform crossover and bounded mutations.
+pub struct GeneticOptimizer {
+    parameters: Vec<Parameter>,
+    settings: OptimizerConfig,
+}
+
+impl GeneticOptimizer {
+    pub fn new(settings: OptimizerConfig) -> Result<GeneticOptimizer, String> {
+        let parameters = settings.parameters.clone();
+        if parameters.is_empty() {
+            return Err("No parameter to optimize.".into());
+        }
+        if let Some(parameter) = parameters
+            .iter()
+            .find(|parameter| parameter.min > parameter.max)
+        {
+            return Err(format!("Empty range for parameter {}.", parameter.name));
+        }
+        if settings.population == 0 || settings.tournament == 0 {
+            return Err("Population and tournament sizes must be positive.".into());
+        }
+        Ok(GeneticOptimizer {
+            parameters,
+            settings,
+        })
+    }
+
+    // Best candidate found over all generations, drawn from the optimizer stream of the seed of
+    // the process so that configuring the seed of a run reproduces it.
+    pub fn optimize<F: FnMut(&[f64]) -> f64>(&self, evaluate: F) -> Candidate {
+        self.optimize_with(evaluate, &mut random::rng("optimizer"))
+    }
+
+    // Best candidate found over all generations. The same generator and evaluation give the same
+    // candidate.
+    pub fn optimize_with<R: Rng, F: FnMut(&[f64]) -> f64>(
+        &self,
+        mut evaluate: F,
+        rng: &mut R,
+    ) -> Candidate {
+        let mut score = |values: Vec<f64>| {
+            let fitness = evaluate(&values);
+            Candidate {
+                values,
+                fitness: if fitness.is_nan() {
+                    f64::NEG_INFINITY
+                } else {
+                    fitness
+                },
+            }
+        };
+
+        let mut population: Vec<Candidate> = (0..self.settings.population)
+            .map(|_| {
+                score(
+                    self.parameters
+                        .iter()
+                        .map(|parameter| parameter.sample(rng))
+                        .collect(),
+                )
+            })
+            .collect();
+
+        for generation in 0..self.settings.generations {
+            population.sort_by(|first, second| second.fitness.total_cmp(&first.fitness));
+            info!(
+                "Generation {} best fitness {:.4} with {:?}",
+                generation, population[0].fitness, population[0].values
+            );
+
+            let mut next: Vec<Candidate> = population
+                .iter()
+                .take(self.settings.elitism)
+                .cloned()
+                .collect();
+            while next.len() < self.settings.population {
+                let first = self.select(&population, rng);
+                let second = self.select(&population, rng);
+                let child = self.mutate(self.crossover(first, second, rng), rng);
+                next.push(score(child));
+            }
+            population = next;
+        }
+
+        population
+            .into_iter()
+            .max_by(|first, second| first.fitness.total_cmp(&second.fitness))
+            .expect("Population is never empty.")
+    }
+
+    // Tournament selection: fittest of a few randomly drawn candidates.
+    fn select<'a, R: Rng>(&self, population: &'a [Candidate], rng: &mut R) -> &'a Candidate {
+        (0..self.settings.tournament)
+            .map(|_| &population[rng.random_range(0..population.len())])
+            .max_by(|first, second| first.fitness.total_cmp(&second.fitness))
+            .expect("Tournament size is positive.")
+    }
+
+    fn crossover<R: Rng>(&self, first: &Candidate, second: &Candidate, rng: &mut R) -> Vec<f64> {
+        if !rng.random_bool(self.settings.crossover_rate.clamp(0.0, 1.0)) {
+            return first.values.clone();
+        }
+        first
+            .values
+            .iter()
+            .zip(&second.values)
+            .map(|(x, y)| if rng.random_bool(0.5) { *x } else { *y })
+            .collect()
+    }
+
+    fn mutate<R: Rng>(&self, mut values: Vec<f64>, rng: &mut R) -> Vec<f64> {
+        let rate = self.settings.mutation_rate.clamp(0.0, 1.0);
+        for (value, parameter) in values.iter_mut().zip(&self.parameters) {
+            if rng.random_bool(rate) {
+                let scale = self.settings.mutation_scale * (parameter.max - parameter.min);
+                *value = parameter.clamp(*value + scale * rng.random_range(-1.0..=1.0));
+            }
+        }
+        values
+    }
+}
+
+// Strategy settings with the given values of the parameters, in their order.
+pub fn apply(
+    strategy: &StrategyConfig,
+    parameters: &[Parameter],
+    values: &[f64],
+) -> Result<StrategyConfig, String> {
+    let mut settings = match Value::try_from(strategy) {
+        Ok(Value::Table(settings)) => settings,
+        Ok(_) => return Err("Strategy settings are not a table.".into()),
+        Err(error) => return Err(format!("{}", error)),
+    };
+    for (parameter, value) in parameters.iter().zip(values) {
+        if !settings.contains_key(&parameter.name) {
+            return Err(format!(
+                "No {} setting in a {} strategy",
+                parameter.name,
+                strategy.kind()
+            ));
+        }
+        let value = if parameter.integer {
+            Value::Integer(*value as i64)
+        } else {
+            Value::Float(*value)
+        };
+        settings.insert(parameter.name.clone(), value);
+    }
+    match Value::Table(settings).try_into() {
+        Ok(strategy) => Ok(strategy),
+        Err(error) => Err(format!("{}", error)),
+    }
+}
+
+// Evolve the parameters of a configured strategy against backtests of it on the candles of the
+// pairs, scoring the portfolio of its runs. Returns its settings with the best values found.
+pub fn tune(
+    config: &NamedConfig,
+    candles: &HashMap<String, Vec<Candle>>,
+    cash: f64,
+    shorting: Option<Shorting>,
+    settings: &OptimizerConfig,
+) -> Result<(StrategyConfig, Candidate), String> {
+    let optimizer = GeneticOptimizer::new(settings.clone())?;
+    let lowest: Vec<f64> = settings
+        .parameters
+        .iter()
+        .map(|parameter| parameter.min)
+        .collect();
+    // settings the strategy does not have fail before any backtest
+    apply(&config.strategy, &settings.parameters, &lowest)?;
+    let best = optimizer.optimize(|values| {
+        let Ok(strategy) = apply(&config.strategy, &settings.parameters, values) else {
+            return f64::NEG_INFINITY;
+        };
+        let candidate = NamedConfig {
+            id: config.id.clone(),
+            strategy,
+        };
+        match multipair::backtest(&[candidate], candles, cash, shorting, None, |_| (), |_| ()) {
+            Ok(consolidated) => fitness(&consolidated.portfolio, settings.drawdown_penalty),
+            Err(_) => f64::NEG_INFINITY,
+        }
+    });
+    let strategy = apply(&config.strategy, &settings.parameters, &best.values)?;
+    Ok((strategy, best))
+}
@@ -0,0 +1,587 @@
+use crate::attribution;
+use crate::clock;
+use crate::execution::{Executor, Order, OrderKind, Side};
+use crate::journal::{Entry, Journal, now};
+use crate::market::{self, Candle, Quote};
+use crate::risk::RiskGuard;
+
+use serde::{Deserialize, Serialize};
+
+use tracing::{debug, info, warn};
+
+use std::sync::{Mutex, OnceLock};
+
+// Exit orders linked as one-cancels-other: a take-profit limit and a stop-loss, whichever is
+// reached first cancels the other.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Oco {
+    pub ticker: String,
+    // side of the exit, Sell to protect a long position
+    pub side: Side,
+    pub volume: f64,
+    pub take_profit: f64,
+    pub stop_loss: f64,
+}
+
+impl Oco {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.volume <= 0.0 {
+            return Err(format!("Invalid volume {}", self.volume));
+        }
+        let ordered = match self.side {
+            Side::Sell => self.stop_loss < self.take_profit,
+            Side::Buy => self.stop_loss > self.take_profit,
+        };
+        if !ordered {
+            return Err(format!(
+                "A {:?} stop loss at {} is not on the other side of the take profit at {}",
+                self.side, self.stop_loss, self.take_profit
+            ));
+        }
+        Ok(())
+    }
+
+    fn stop_reached(&self, candle: &Candle) -> bool {
+        match self.side {
+            Side::Sell => candle.low <= self.stop_loss,
+            Side::Buy => candle.high >= self.stop_loss,
+        }
+    }
+}
+
+// Pair waiting for one of its legs: the take-profit rests on the exchange under its identifier
+// while the stop-loss is watched locally.
+struct Linked {
+    oco: Oco,
+    limit: String,
+    // volume of the take-profit executed so far
+    executed: f64,
+}
+
+fn linked() -> &'static Mutex<Vec<Linked>> {
+    static LINKED: OnceLock<Mutex<Vec<Linked>>> = OnceLock::new();
+    LINKED.get_or_init(Mutex::default)
+}
+
+fn record(journal: &Mutex<Journal>, entries: &[Entry]) {
+    let Ok(mut journal) = journal.lock() else {
+        warn!(
+            "Journal lock poisoned, {} entries not journaled",
+            entries.len()
+        );
+        return;
+    };
+    for entry in entries {
+        if let Err(message) = journal.record(entry) {
+            warn!("{}", message);
+        }
+    }
+}
+
+// Place a pair of exits. Kraken has no native OCO on spot, so the take-profit is sent as a limit
+// order and the stop-loss emulated: it is sent as a market order once a candle reaches it, after
+// cancelling the take-profit. Returns the identifier of the take-profit.
+pub async fn place<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    oco: Oco,
+) -> Result<String, String> {
+    oco.validate()?;
+    let order = Order::limit(&oco.ticker, oco.side, oco.volume, oco.take_profit);
+    let id = guard.submit(&order).await?;
+    record(
+        journal,
+        &[Entry::Order {
+            time: now(),
+            id: id.clone(),
+            order,
+            simulated: guard.simulated(),
+            strategy: None,
+            decision: guard.price(&oco.ticker),
+        }],
+    );
+    info!(id = %id, oco = ?oco, "OCO placed");
+    if let Ok(mut linked) = linked().lock() {
+        linked.push(Linked {
+            oco,
+            limit: id.clone(),
+            executed: 0.0,
+        });
+    }
+    Ok(id)
+}
+
+// Pairs waiting for one of their legs.
+pub fn active() -> Vec<Oco> {
+    linked()
+        .lock()
+        .map(|linked| linked.iter().map(|linked| linked.oco.clone()).collect())
+        .unwrap_or_default()
+}
+
+// Settle the pairs on a ticker whose stop-loss a candle reached: the take-profit is cancelled
+// before exiting the volume it left at market. When the cancellation fails the pair stays linked,
+// it is retried on the next candle unless the take-profit is reported filled in the meantime. The
+// take-profit is only taken as done once the venue reports it entirely executed.
+async fn settle<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    ticker: &str,
+    candle: &Candle,
+) {
+    let triggered: Vec<Linked> = match linked().lock() {
+        Ok(mut linked) => {
+            let (triggered, waiting) =
+                std::mem::take(&mut *linked)
+                    .into_iter()
+                    .partition(|linked: &Linked| {
+                        linked.oco.ticker == ticker && linked.oco.stop_reached(candle)
+                    });
+            *linked = waiting;
+            triggered
+        }
+        Err(_) => return,
+    };
+
+    for pair in triggered {
+        let Linked {
+            oco,
+            limit,
+            executed,
+        } = &pair;
+        if let Err(message) = guard.cancel(limit).await {
+            warn!(
+                "Could not cancel take profit {}, retrying on the next candle: {}",
+                limit, message
+            );
+            if let Ok(mut linked) = linked().lock() {
+                linked.push(pair);
+            }
+            continue;
+        }
+        let mut entries = vec![Entry::Cancel {
+            time: now(),
+            id: limit.clone(),
+            simulated: guard.simulated(),
+        }];
+        let order = Order::market(&oco.ticker, oco.side, oco.volume - executed);
+        match guard.submit(&order).await {
+            Ok(id) => {
+                info!(id = %id, oco = ?oco, "OCO stop loss reached, take profit cancelled");
+                entries.push(Entry::Order {
+                    time: now(),
+                    id,
+                    order,
+                    simulated: guard.simulated(),
+                    strategy: None,
+                    decision: Some(oco.stop_loss),
+                });
+            }
+            Err(message) => warn!("Could not exit {:?} at its stop loss: {}", oco, message),
+        }
+        record(journal, &entries);
+    }
+}
+
+// Unlink the pair whose take-profit was executed, once entirely.
+fn take_profit<E: Executor + Sync>(guard: &RiskGuard<E>, id: &str, volume: f64) {
+    let Ok(mut linked) = linked().lock() else {
+        return;
+    };
+    let Some(index) = linked.iter().position(|linked| linked.limit == id) else {
+        return;
+    };
+    linked[index].executed += volume;
+    if !guard.pending(id) {
+        let Linked { oco, .. } = linked.remove(index);
+        info!(id = %id, oco = ?oco, "OCO take profit filled, stop loss dropped");
+    }
+}
+
+// Limit order of which only a slice rests on the book at a time, the next slice is placed once
+// the venue reported the previous one entirely executed so that the total size is not shown.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Iceberg {
+    pub ticker: String,
+    pub side: Side,
+    // total volume to fill
+    pub volume: f64,
+    // volume of each slice shown on the book
+    pub visible: f64,
+    pub price: f64,
+}
+
+impl Iceberg {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.volume <= 0.0 || self.visible <= 0.0 {
+            return Err(format!(
+                "Invalid volumes {} and {} visible",
+                self.volume, self.visible
+            ));
+        }
+        if self.price <= 0.0 {
+            return Err(format!("Invalid price {}", self.price));
+        }
+        Ok(())
+    }
+}
+
+// Iceberg being filled: the slice resting under its identifier and the volume left after it.
+struct Slicing {
+    iceberg: Iceberg,
+    slice: String,
+    left: f64,
+}
+
+fn slicings() -> &'static Mutex<Vec<Slicing>> {
+    static SLICINGS: OnceLock<Mutex<Vec<Slicing>>> = OnceLock::new();
+    SLICINGS.get_or_init(Mutex::default)
+}
+
+// Send the next slice of an iceberg with a volume left, returns its identifier and the volume
+// left after it.
+async fn slice<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    iceberg: &Iceberg,
+    left: f64,
+) -> Result<(String, f64), String> {
+    let volume = iceberg.visible.min(left);
+    let order = Order::limit(&iceberg.ticker, iceberg.side, volume, iceberg.price);
+    let id = guard.submit(&order).await?;
+    record(
+        journal,
+        &[Entry::Order {
+            time: now(),
+            id: id.clone(),
+            order,
+            simulated: guard.simulated(),
+            strategy: None,
+            decision: guard.price(&iceberg.ticker),
+        }],
+    );
+    Ok((id, left - volume))
+}
+
+// Place the first slice of an iceberg, returns its identifier.
+pub async fn place_iceberg<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    iceberg: Iceberg,
+) -> Result<String, String> {
+    iceberg.validate()?;
+    let (id, left) = slice(guard, journal, &iceberg, iceberg.volume).await?;
+    info!(id = %id, iceberg = ?iceberg, "Iceberg placed");
+    if let Ok(mut slicings) = slicings().lock() {
+        slicings.push(Slicing {
+            iceberg,
+            slice: id.clone(),
+            left,
+        });
+    }
+    Ok(id)
+}
+
+// Icebergs being filled along with the volume left after their resting slice.
+pub fn icebergs() -> Vec<(Iceberg, f64)> {
+    slicings()
+        .lock()
+        .map(|slicings| {
+            slicings
+                .iter()
+                .map(|slicing| (slicing.iceberg.clone(), slicing.left))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Place the next slice of the iceberg whose slice was entirely executed, until no volume is left.
+async fn replenish<E: Executor + Sync>(guard: &RiskGuard<E>, journal: &Mutex<Journal>, id: &str) {
+    let filled = match slicings().lock() {
+        Ok(mut slicings) => match slicings.iter().position(|slicing| slicing.slice == id) {
+            Some(index) => slicings.remove(index),
+            None => return,
+        },
+        Err(_) => return,
+    };
+    let Slicing { iceberg, left, .. } = filled;
+    if left <= 0.0 {
+        info!(id = %id, iceberg = ?iceberg, "Iceberg filled");
+        return;
+    }
+    match slice(guard, journal, &iceberg, left).await {
+        Ok((id, left)) => {
+            info!(id = %id, left, "Iceberg replenished");
+            if let Ok(mut slicings) = slicings().lock() {
+                slicings.push(Slicing {
+                    iceberg,
+                    slice: id,
+                    left,
+                });
+            }
+        }
+        Err(message) => warn!(
+            "Could not replenish {:?}, {} left unfilled: {}",
+            iceberg, left, message
+        ),
+    }
+}
+
+// Follow the orders managed here on an execution of a volume of an order, the icebergs once the
+// order is entirely executed.
+pub async fn on_fill<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    id: &str,
+    volume: f64,
+) {
+    take_profit(guard, id, volume);
+    chased(guard, id, volume);
+    if guard.pending(id) {
+        return;
+    }
+    replenish(guard, journal, id).await;
+}
+
+// Give up the orders managed here whose order left the venue without being entirely executed,
+// e.g. cancelled outside of the bot.
+pub fn on_closed(id: &str) {
+    if let Ok(mut linked) = linked().lock() {
+        linked.retain(|linked| {
+            let kept = linked.limit != id;
+            if !kept {
+                warn!("Take profit {} of {:?} gone, OCO given up", id, linked.oco);
+            }
+            kept
+        });
+    }
+    if let Ok(mut slicings) = slicings().lock() {
+        slicings.retain(|slicing| {
+            let kept = slicing.slice != id;
+            if !kept {
+                warn!(
+                    "Slice {} of {:?} gone, iceberg given up",
+                    id, slicing.iceberg
+                );
+            }
+            kept
+        });
+    }
+    if let Ok(mut chasings) = chasings().lock() {
+        chasings.retain(|chasing| {
+            let kept = chasing.id != id;
+            if !kept {
+                warn!("Chased order {} gone, chase given up", id);
+            }
+            kept
+        });
+    }
+}
+
+// Follow the orders managed here on a candle of their ticker.
+pub async fn on_candle<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    ticker: &str,
+    candle: &Candle,
+) {
+    settle(guard, journal, ticker, candle).await;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChaseConfig {
+    // time (in s) after which the order is sent at market
+    pub timeout: f64,
+    // relative distance from the first price beyond which the order is sent at market
+    pub max_distance: f64,
+}
+
+impl Default for ChaseConfig {
+    fn default() -> ChaseConfig {
+        ChaseConfig {
+            timeout: 30.0,
+            max_distance: 0.002,
+        }
+    }
+}
+
+// Market order being worked as a limit pegged to the best price on its side.
+struct Chasing {
+    config: ChaseConfig,
+    strategy: Option<String>,
+    order: Order,
+    // resting limit and its price
+    id: String,
+    price: f64,
+    // volume of the order executed so far, over all its limits
+    executed: f64,
+    // best price and time (in s) the chase started at
+    start: f64,
+    since: f64,
+}
+
+fn chasings() -> &'static Mutex<Vec<Chasing>> {
+    static CHASINGS: OnceLock<Mutex<Vec<Chasing>>> = OnceLock::new();
+    CHASINGS.get_or_init(Mutex::default)
+}
+
+// Best price on the side of an order, the one a limit joining the book is placed at.
+fn best(side: Side, quote: &Quote) -> f64 {
+    match side {
+        Side::Buy => quote.bid,
+        Side::Sell => quote.ask,
+    }
+}
+
+// Work a market order as a limit at the best price on its side, re-pegged as the quote moves and
+// sent at market after a timeout or once the price moved too far. Orders on tickers without a
+// quote are sent at market. Returns the identifier and the order placed.
+pub async fn chase<E: Executor>(
+    executor: &E,
+    strategy: Option<&str>,
+    order: &Order,
+    config: ChaseConfig,
+) -> Result<(String, Order), String> {
+    let Some(quote) = market::latest_quote(&order.ticker) else {
+        return Ok((executor.submit(order).await?, order.clone()));
+    };
+    let price = best(order.side, &quote);
+    let limit = Order {
+        kind: OrderKind::Limit(price),
+        ..order.clone()
+    };
+    let id = executor.submit(&limit).await?;
+    if let Ok(mut chasings) = chasings().lock() {
+        chasings.push(Chasing {
+            config,
+            strategy: strategy.map(str::to_string),
+            order: order.clone(),
+            id: id.clone(),
+            price,
+            executed: 0.0,
+            start: price,
+            since: clock::now(),
+        });
+    }
+    Ok((id, limit))
+}
+
+// Count the execution of a volume of a chased order, the chase ends once nothing is left to fill.
+fn chased<E: Executor + Sync>(guard: &RiskGuard<E>, id: &str, volume: f64) {
+    let Ok(mut chasings) = chasings().lock() else {
+        return;
+    };
+    let Some(index) = chasings.iter().position(|chasing| chasing.id == id) else {
+        return;
+    };
+    chasings[index].executed += volume;
+    let chasing = &chasings[index];
+    if !guard.pending(id) || chasing.executed >= chasing.order.volume {
+        let chasing = chasings.remove(index);
+        info!(id = %id, price = chasing.price, "Chased order filled");
+    }
+}
+
+// What to do with a chased order on a new quote.
+enum Step {
+    Repeg(f64),
+    Market,
+}
+
+// Follow the chased orders on a ticker on a new quote: an order is re-pegged when the best price
+// moves away and sent at market on timeout or beyond the maximum distance. Chases end once the
+// venue reports their order entirely executed.
+pub async fn on_quote<E: Executor + Sync>(
+    guard: &RiskGuard<E>,
+    journal: &Mutex<Journal>,
+    ticker: &str,
+    quote: &Quote,
+) {
+    let time = clock::now();
+    let moved: Vec<(Chasing, Step)> = match chasings().lock() {
+        Ok(mut chasings) => {
+            let mut moved = Vec::new();
+            for chasing in std::mem::take(&mut *chasings) {
+                if chasing.order.ticker != ticker {
+                    chasings.push(chasing);
+                    continue;
+                }
+                let side = chasing.order.side;
+                let price = best(side, quote);
+                let away = side.sign() * (price - chasing.price) > 0.0;
+                let step = if time - chasing.since >= chasing.config.timeout
+                    || (price - chasing.start).abs() > chasing.config.max_distance * chasing.start
+                {
+                    Step::Market
+                } else if away {
+                    Step::Repeg(price)
+                } else {
+                    chasings.push(chasing);
+                    continue;
+                };
+                moved.push((chasing, step));
+            }
+            moved
+        }
+        Err(_) => return,
+    };
+
+    for (mut chasing, step) in moved {
+        if let Err(message) = guard.cancel(&chasing.id).await {
+            // most likely filled in the meantime
+            warn!("Could not cancel chased order {}: {}", chasing.id, message);
+            continue;
+        }
+        let mut entries = vec![Entry::Cancel {
+            time: now(),
+            id: chasing.id.clone(),
+            simulated: guard.simulated(),
+        }];
+        // only the volume the previous limits left is sent again
+        let volume = chasing.order.volume - chasing.executed;
+        if volume <= 0.0 {
+            info!(id = %chasing.id, "Chased order filled");
+            record(journal, &entries);
+            continue;
+        }
+        let order = match step {
+            Step::Repeg(price) => Order {
+                kind: OrderKind::Limit(price),
+                volume,
+                ..chasing.order.clone()
+            },
+            Step::Market => Order {
+                volume,
+                ..chasing.order.clone()
+            },
+        };
+        match guard.submit(&order).await {
+            Ok(id) => {
+                if let Some(strategy) = &chasing.strategy {
+                    attribution::place(strategy, &id);
+                }
+                entries.push(Entry::Order {
+                    time: now(),
+                    id: id.clone(),
+                    order: order.clone(),
+                    simulated: guard.simulated(),
+                    strategy: chasing.strategy.clone(),
+                    decision: Some(chasing.start),
+                });
+                if let OrderKind::Limit(price) = order.kind {
+                    debug!(id = %id, price, "Chased order re-pegged");
+                    chasing.id = id;
+                    chasing.price = price;
+                    if let Ok(mut chasings) = chasings().lock() {
+                        chasings.push(chasing);
+                    }
+                } else {
+                    info!(id = %id, order = ?order, "Chase given up, sent at market");
+                }
+            }
+            Err(message) => warn!("Could not place chased order {:?}: {}", order, message),
+        }
+        record(journal, &entries);
+    }
+}
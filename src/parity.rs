@@ -0,0 +1,162 @@
+use crate::backtest::{Backtester, FeeSchedule, SimulatedBroker, SlippageModel};
+use crate::config::NamedConfig;
+use crate::execution::{Order, OrderKind};
+use crate::journal::Entry;
+use crate::market::{Candle, MarketEvent};
+use crate::runner;
+
+use std::collections::{BTreeMap, HashMap};
+
+// Relative difference under which volumes and prices are considered equal.
+const TOLERANCE: f64 = 1e-9;
+
+// Order decided by a strategy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    pub strategy: String,
+    // unix time (in s) of the submission when live, of the deciding candle when backtested
+    pub time: i64,
+    pub order: Order,
+}
+
+fn close(first: f64, second: f64) -> bool {
+    (first - second).abs() <= TOLERANCE * first.abs().max(second.abs()).max(1.0)
+}
+
+fn same(first: &Order, second: &Order) -> bool {
+    let kinds = match (first.kind, second.kind) {
+        (OrderKind::Market, OrderKind::Market) => true,
+        (OrderKind::Limit(first), OrderKind::Limit(second)) => close(first, second),
+        _ => false,
+    };
+    kinds
+        && first.ticker == second.ticker
+        && first.side == second.side
+        && close(first.volume, second.volume)
+}
+
+// First point at which the signals of a strategy differ between the two paths, a missing side
+// means the path ran out of signals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub strategy: String,
+    // position of the differing signal in the strategy's sequence
+    pub index: usize,
+    pub live: Option<Signal>,
+    pub backtest: Option<Signal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParityReport {
+    // signals identical on both paths before any divergence
+    pub matched: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+// Signals of the strategies placed by a dry run over a period, from its journal.
+pub fn live(entries: &[Entry], from: i64, to: i64) -> Vec<Signal> {
+    entries
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Order {
+                time,
+                order,
+                simulated: true,
+                strategy: Some(strategy),
+                ..
+            } if (from..=to).contains(time) => Some(Signal {
+                strategy: strategy.clone(),
+                time: *time,
+                order: order.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+// Candles of a recorded session, in the order they were received.
+pub fn candles(events: &[(f64, MarketEvent)]) -> Vec<(String, Candle)> {
+    events
+        .iter()
+        .filter_map(|(_, event)| match event {
+            MarketEvent::Candle { ticker, candle } => Some((ticker.clone(), *candle)),
+            _ => None,
+        })
+        .collect()
+}
+
+// Signals of the configured strategies backtested over candles, each strategy instance on the
+// tickers it would follow live.
+pub fn backtest(
+    configs: &[NamedConfig],
+    tickers: &[String],
+    candles: &[(String, Candle)],
+) -> Result<Vec<Signal>, String> {
+    // candles per time, a later revision of a candle replacing the earlier ones
+    let mut steps: BTreeMap<i64, HashMap<String, Candle>> = BTreeMap::new();
+    for (ticker, candle) in candles {
+        steps
+            .entry(candle.time)
+            .or_default()
+            .insert(ticker.clone(), *candle);
+    }
+
+    let mut signals = Vec::new();
+    for worker in runner::plan(configs, tickers)? {
+        for (name, strategy) in worker.strategies {
+            // orders are only recorded, the broker needs no funds
+            let broker =
+                SimulatedBroker::new(0.0, FeeSchedule::default(), SlippageModel::default());
+            let report = Backtester::new(strategy, broker).run(steps.values().map(|step| {
+                step.iter()
+                    .filter(|(ticker, _)| worker.tickers.contains(ticker))
+                    .map(|(ticker, candle)| (ticker.clone(), *candle))
+                    .collect()
+            }));
+            signals.extend(report.signals.into_iter().map(|(time, order)| Signal {
+                strategy: name.clone(),
+                time,
+                order,
+            }));
+        }
+    }
+    Ok(signals)
+}
+
+// Compare the sequences of signals of each strategy on both paths.
+pub fn compare(live: &[Signal], backtest: &[Signal]) -> ParityReport {
+    let mut sequences: BTreeMap<&str, (Vec<&Signal>, Vec<&Signal>)> = BTreeMap::new();
+    for signal in live {
+        sequences
+            .entry(&signal.strategy)
+            .or_default()
+            .0
+            .push(signal);
+    }
+    for signal in backtest {
+        sequences
+            .entry(&signal.strategy)
+            .or_default()
+            .1
+            .push(signal);
+    }
+
+    let mut report = ParityReport::default();
+    for (strategy, (live, backtest)) in sequences {
+        let matched = live
+            .iter()
+            .zip(&backtest)
+            .take_while(|(live, backtest)| same(&live.order, &backtest.order))
+            .count();
+        report.matched += matched;
+        if matched < live.len().max(backtest.len()) {
+            report.divergences.push(Divergence {
+                strategy: strategy.to_string(),
+                index: matched,
+                live: live.get(matched).map(|signal| (*signal).clone()),
+                backtest: backtest.get(matched).map(|signal| (*signal).clone()),
+            });
+        }
+    }
+    report
+}
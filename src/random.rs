@@ -0,0 +1,43 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use serde::{Deserialize, Serialize};
+
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RandomConfig {
+    // seed every random draw of the process derives from, a fresh one is drawn without it
+    pub seed: Option<u64>,
+}
+
+fn installed() -> &'static OnceLock<u64> {
+    static SEED: OnceLock<u64> = OnceLock::new();
+    &SEED
+}
+
+// Fix the seed of the process, only the first call has an effect.
+pub fn install(config: &RandomConfig) {
+    installed().get_or_init(|| config.seed.unwrap_or_else(rand::random));
+}
+
+// Seed of the process, drawn from the system when none was installed, logged so that a run can
+// be reproduced by configuring it.
+pub fn seed() -> u64 {
+    *installed().get_or_init(rand::random)
+}
+
+// FNV-1a, stable across platforms and compiler versions unlike the standard hasher.
+fn hash(name: &str) -> u64 {
+    name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Generator of a named stream of draws, e.g. montecarlo or optimizer. Each stream is seeded from
+// the seed of the process and its name only, so that drawing more from one leaves the others
+// unchanged. Concurrent users of a stream should name theirs apart, e.g. montecarlo.3.
+pub fn rng(stream: &str) -> StdRng {
+    StdRng::seed_from_u64(seed() ^ hash(stream))
+}
@@ -0,0 +1,230 @@
+use crate::alerts::{self, EventKind};
+use crate::execution::{Execution, Executor, Order};
+use crate::margin::Margin;
+use crate::metrics;
+use crate::random;
+
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::sleep;
+
+use tracing::warn;
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    // attempts at a request failing with a transient error, including the first
+    pub attempts: u32,
+    // time waited before the first retry (in ms), doubled after each failure
+    pub backoff: u64,
+    // time waited after hitting a rate limit (in ms), the limit takes a while to decay
+    pub rate_limit_backoff: u64,
+    // fraction of each wait drawn at random, so that requests failing together are retried apart
+    pub jitter: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> RetryConfig {
+        RetryConfig {
+            attempts: 3,
+            backoff: 500,
+            rate_limit_backoff: 5000,
+            jitter: 0.5,
+        }
+    }
+}
+
+// Cause of a failed exchange request, from the error codes Kraken answers with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Failure {
+    RateLimit,
+    // nonce not above the previous one, e.g. after requests raced each other
+    Nonce,
+    // exchange unavailable, busy or in maintenance
+    Unavailable,
+    // request that may not have reached the exchange
+    Network,
+    InsufficientFunds,
+    InvalidPair,
+    // order refused for its parameters
+    Rejected,
+    Unknown,
+}
+
+impl Failure {
+    pub fn classify(message: &str) -> Failure {
+        let patterns: &[(&[&str], Failure)] = &[
+            (&["Rate limit", "Too many requests"], Failure::RateLimit),
+            (&["Invalid nonce"], Failure::Nonce),
+            (
+                &[
+                    "EService:Unavailable",
+                    "EService:Busy",
+                    "EService:Market in",
+                ],
+                Failure::Unavailable,
+            ),
+            (
+                &["Insufficient funds", "Insufficient margin"],
+                Failure::InsufficientFunds,
+            ),
+            (
+                &["Unknown asset pair", "Invalid asset pair"],
+                Failure::InvalidPair,
+            ),
+            (
+                &["EOrder:", "EGeneral:Invalid arguments"],
+                Failure::Rejected,
+            ),
+            (
+                &["Http", "Reqwest", "timed out", "connect"],
+                Failure::Network,
+            ),
+        ];
+        patterns
+            .iter()
+            .find(|(needles, _)| needles.iter().any(|needle| message.contains(needle)))
+            .map_or(Failure::Unknown, |(_, failure)| *failure)
+    }
+
+    // Whether the same request may succeed later.
+    pub fn transient(&self) -> bool {
+        matches!(
+            self,
+            Failure::RateLimit | Failure::Nonce | Failure::Unavailable | Failure::Network
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Failure::RateLimit => "rate_limit",
+            Failure::Nonce => "nonce",
+            Failure::Unavailable => "unavailable",
+            Failure::Network => "network",
+            Failure::InsufficientFunds => "insufficient_funds",
+            Failure::InvalidPair => "invalid_pair",
+            Failure::Rejected => "rejected",
+            Failure::Unknown => "unknown",
+        }
+    }
+}
+
+// Executor retrying the requests of the executor it wraps that failed with transient errors,
+// waiting longer after each failure, and alerting on the others right away. A submission failing
+// on the network may have reached the exchange, it is not retried so as not to double the order.
+pub struct RetryingExecutor<E> {
+    inner: E,
+    config: RetryConfig,
+    // draws of the jitter of the waits
+    rng: Mutex<StdRng>,
+}
+
+impl<E: Executor + Sync> RetryingExecutor<E> {
+    pub fn new(inner: E, config: RetryConfig) -> RetryingExecutor<E> {
+        RetryingExecutor {
+            inner,
+            config,
+            rng: Mutex::new(random::rng("retry")),
+        }
+    }
+
+    // Wait shortened by up to the jitter fraction of it.
+    fn jittered(&self, wait: u64) -> u64 {
+        let jitter = self.config.jitter.clamp(0.0, 1.0);
+        match self.rng.lock() {
+            Ok(mut rng) if jitter > 0.0 => {
+                (wait as f64 * (1.0 - jitter * rng.random::<f64>())).round() as u64
+            }
+            _ => wait,
+        }
+    }
+
+    async fn retry<T, F: Future<Output = Result<T, String>>>(
+        &self,
+        request: &str,
+        idempotent: bool,
+        attempt: impl Fn() -> F,
+    ) -> Result<T, String> {
+        let mut backoff = self.config.backoff;
+        let mut attempts = 1;
+        loop {
+            let message = match attempt().await {
+                Ok(result) => return Ok(result),
+                Err(message) => message,
+            };
+            let failure = Failure::classify(&message);
+            metrics::increment(&format!("executor.failures.{}", failure.name()), 1);
+            let retried = failure.transient() && (idempotent || failure != Failure::Network);
+            if !retried {
+                alerts::notify(
+                    EventKind::Error,
+                    &format!("{} failed", request),
+                    &format!("{:?}: {}", failure, message),
+                );
+                return Err(message);
+            }
+            if attempts >= self.config.attempts {
+                let message = format!("{} after {} attempts", message, attempts);
+                alerts::notify(EventKind::Error, &format!("{} failed", request), &message);
+                return Err(message);
+            }
+            let wait = match failure {
+                Failure::RateLimit => self.config.rate_limit_backoff.max(backoff),
+                // a fresh nonce is enough
+                Failure::Nonce => 0,
+                _ => self.jittered(backoff),
+            };
+            warn!(
+                "{} failed with {:?}, retrying in {}ms: {}",
+                request, failure, wait, message
+            );
+            sleep(Duration::from_millis(wait)).await;
+            backoff *= 2;
+            attempts += 1;
+        }
+    }
+}
+
+impl<E: Executor + Sync> Executor for RetryingExecutor<E> {
+    async fn submit(&self, order: &Order) -> Result<String, String> {
+        self.retry("Order submission", false, || self.inner.submit(order))
+            .await
+    }
+
+    async fn cancel(&self, id: &str) -> Result<(), String> {
+        self.retry("Order cancellation", true, || self.inner.cancel(id))
+            .await
+    }
+
+    fn simulated(&self) -> bool {
+        self.inner.simulated()
+    }
+
+    async fn balances(&self) -> Result<HashMap<String, f64>, String> {
+        self.retry("Balance query", true, || self.inner.balances())
+            .await
+    }
+
+    async fn margin(&self) -> Result<Margin, String> {
+        self.retry("Margin query", true, || self.inner.margin())
+            .await
+    }
+
+    async fn fills(&self, since: i64) -> Result<Vec<Execution>, String> {
+        self.retry("Fill query", true, || self.inner.fills(since))
+            .await
+    }
+
+    async fn resting(&self) -> Result<HashSet<String>, String> {
+        self.retry("Open order query", true, || self.inner.resting())
+            .await
+    }
+}
@@ -0,0 +1,875 @@
+use crate::alerts::{self, EventKind};
+use crate::backtest::Fill;
+use crate::clock;
+use crate::conversion;
+use crate::events::{self, Event};
+use crate::execution::{Execution, Executor, Order, OrderKind, Side};
+use crate::instruments;
+use crate::margin::Margin;
+use crate::market::{self, Candle};
+use crate::metrics;
+use crate::statistics::correlation;
+use crate::var::{self, ValueAtRisk};
+
+use serde::{Deserialize, Serialize};
+
+use tracing::warn;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Mutex, RwLock};
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    // open orders allowed per ticker
+    pub max_open_per_ticker: Option<usize>,
+    // open orders allowed on the account
+    pub max_open: Option<usize>,
+    // orders submitted in any minute
+    pub max_orders_per_minute: Option<usize>,
+    // loss (in reporting currency) over a UTC day, realized and unrealized, that halts trading
+    pub max_daily_loss: Option<f64>,
+    // whether positions are closed when the daily loss limit is hit, open orders are cancelled
+    // either way
+    pub flatten_on_loss: bool,
+    // whether trading resumes on the next UTC day after hitting the daily loss limit, otherwise
+    // it waits for a resume command
+    pub resume_next_day: bool,
+    // net exposure (in quote currency) allowed per base asset, e.g. ETH
+    pub max_asset_exposure: HashMap<String, f64>,
+    // groups of tickers whose combined exposure is limited
+    pub clusters: Vec<Cluster>,
+    // daily value at risk of the exposures, estimated when given
+    pub var: Option<VarConfig>,
+    // leverage allowed on margin orders, margin orders are rejected without it
+    pub max_leverage: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct VarConfig {
+    // probability for the daily loss not to exceed the value at risk
+    pub confidence: f64,
+    // number of daily closes kept per ticker for the historical simulation
+    pub days: usize,
+    // value at risk (in quote currency) above which orders increasing it are rejected
+    pub max_var: Option<f64>,
+}
+
+impl Default for VarConfig {
+    fn default() -> VarConfig {
+        VarConfig {
+            confidence: 0.95,
+            days: 250,
+            max_var: None,
+        }
+    }
+}
+
+// Tickers on given base assets, along with the tickers moving with them, whose gross exposure is
+// limited together.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Cluster {
+    // base assets the cluster is made of
+    pub assets: Vec<String>,
+    // correlation of returns with a ticker of the assets above which a ticker joins the cluster
+    #[serde(default)]
+    pub correlation: Option<f64>,
+    // number of latest returns correlations are computed over
+    #[serde(default = "correlation_window")]
+    pub window: usize,
+    // gross exposure (in quote currency) allowed over the cluster
+    pub max_exposure: f64,
+}
+
+fn correlation_window() -> usize {
+    100
+}
+
+// Base asset of a ticker, e.g. ETH for ETH/EUR.
+pub fn base(ticker: &str) -> &str {
+    ticker.split('/').next().unwrap_or(ticker)
+}
+
+// Quote currency of a ticker, e.g. EUR for ETH/EUR.
+pub fn quote(ticker: &str) -> &str {
+    ticker.split('/').nth(1).unwrap_or(ticker)
+}
+
+const DAY: i64 = 24 * 60 * 60;
+
+// Time (in s) executions of the orders gone from the venue are still booked for.
+const LATE: i64 = 60 * 60;
+
+// Reason new orders are rejected for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    // administrative kill, lifted by a resume command
+    Manual,
+    // daily loss limit hit on a UTC day (in days since the epoch)
+    DailyLoss(i64),
+}
+
+#[derive(Default)]
+struct State {
+    halt: Option<Halt>,
+    // resting orders by identifier
+    open: HashMap<String, Order>,
+    // orders expected to execute at once by identifier, market orders and simulated orders taken
+    // as filled, until their executions are reported
+    executing: HashMap<String, Order>,
+    // volume executed so far of the orders partly filled
+    executed: HashMap<String, f64>,
+    // orders no longer on the venue without being reported entirely executed by identifier, with
+    // the time (in s) they were found gone, kept a while to book executions reported late
+    closed: HashMap<String, (Order, i64)>,
+    // executions of simulated orders, reported as the venue would
+    simulated: Vec<Execution>,
+    // tickers of the orders being submitted
+    pending: Vec<String>,
+    // times (in s) of the submissions of the last minute
+    submissions: VecDeque<f64>,
+    // base asset quantity per ticker bought minus sold through the guard
+    positions: HashMap<String, f64>,
+    // amount received minus spent through the guard per quote currency
+    cash: HashMap<String, f64>,
+    // latest close per ticker
+    prices: HashMap<String, f64>,
+    // latest logarithmic close to close returns per ticker
+    returns: HashMap<String, VecDeque<f64>>,
+    // latest daily closes per ticker with their UTC day
+    closes: HashMap<String, VecDeque<(i64, f64)>>,
+    // current UTC day (in days since the epoch) and the equity it started with
+    day: Option<(i64, f64)>,
+}
+
+impl State {
+    fn open_on(&self, ticker: &str) -> usize {
+        self.open
+            .values()
+            .filter(|open| open.ticker == ticker)
+            .count()
+            + self
+                .pending
+                .iter()
+                .filter(|pending| *pending == ticker)
+                .count()
+    }
+
+    fn price(&self, ticker: &str) -> f64 {
+        self.prices.get(ticker).copied().unwrap_or(0.0)
+    }
+
+    // Price an order is assumed to fill at, its limit or the latest price.
+    fn fill_price(&self, order: &Order) -> f64 {
+        match order.kind {
+            OrderKind::Limit(price) => price,
+            OrderKind::Market => self.price(&order.ticker),
+        }
+    }
+
+    // Cash plus positions valued at the latest prices, in the reporting currency. Amounts in
+    // quote currencies without a rate to it yet are left out.
+    fn equity(&self) -> f64 {
+        let mut values = self.cash.clone();
+        for (ticker, volume) in &self.positions {
+            *values.entry(quote(ticker).to_string()).or_default() += volume * self.price(ticker);
+        }
+        values
+            .iter()
+            .filter_map(|(currency, value)| conversion::to_reporting(*value, currency))
+            .sum()
+    }
+
+    // Signed quote value per ticker of the positions and of the volume left of the orders not
+    // executed yet as if filled.
+    fn exposures(&self) -> HashMap<String, f64> {
+        let mut exposures: HashMap<String, f64> = HashMap::new();
+        for (ticker, volume) in &self.positions {
+            *exposures.entry(ticker.clone()).or_default() += volume * self.price(ticker);
+        }
+        for (id, order) in self.open.iter().chain(&self.executing) {
+            let left = order.volume - self.executed.get(id).copied().unwrap_or(0.0);
+            *exposures.entry(order.ticker.clone()).or_default() +=
+                order.side.sign() * left * self.fill_price(order);
+        }
+        exposures
+    }
+
+    // Whether a ticker is on an asset of a cluster or its returns correlate enough with those of
+    // such a ticker.
+    fn in_cluster(&self, cluster: &Cluster, ticker: &str) -> bool {
+        let member = |ticker: &str| cluster.assets.iter().any(|asset| asset == base(ticker));
+        if member(ticker) {
+            return true;
+        }
+        let (Some(threshold), Some(returns)) = (cluster.correlation, self.returns.get(ticker))
+        else {
+            return false;
+        };
+        self.returns
+            .iter()
+            .filter(|(other, _)| member(other.as_str()))
+            .any(|(_, other)| {
+                let length = returns.len().min(other.len()).min(cluster.window);
+                let first: Vec<f64> = returns
+                    .iter()
+                    .skip(returns.len() - length)
+                    .copied()
+                    .collect();
+                let second: Vec<f64> = other.iter().skip(other.len() - length).copied().collect();
+                correlation(&first, &second).is_some_and(|value| value >= threshold)
+            })
+    }
+
+    fn value_at_risk(
+        &self,
+        exposures: &HashMap<String, f64>,
+        confidence: f64,
+    ) -> Option<ValueAtRisk> {
+        let closes: HashMap<String, Vec<(i64, f64)>> = self
+            .closes
+            .iter()
+            .map(|(ticker, closes)| (ticker.clone(), closes.iter().copied().collect()))
+            .collect();
+        var::historical(exposures, &closes, confidence)
+    }
+
+    // Keep the close of a ticker on a day, the latest of the day replacing the earlier ones.
+    fn close(&mut self, ticker: &str, day: i64, close: f64, days: usize) {
+        let closes = self.closes.entry(ticker.to_string()).or_default();
+        match closes.back() {
+            Some((latest, _)) if *latest > day => return,
+            Some((latest, _)) if *latest == day => {
+                closes.pop_back();
+            }
+            _ => (),
+        }
+        closes.push_back((day, close));
+        while closes.len() > days.max(2) {
+            closes.pop_front();
+        }
+    }
+
+    // Book the position and cash flow of an execution, its fee being paid in quote currency.
+    fn book(&mut self, fill: &Fill) {
+        *self.positions.entry(fill.ticker.clone()).or_default() += fill.side.sign() * fill.volume;
+        *self
+            .cash
+            .entry(quote(&fill.ticker).to_string())
+            .or_default() -= fill.side.sign() * fill.volume * fill.price + fill.fee;
+    }
+
+    // Report a simulated order as entirely executed at the price it is taken as filled at.
+    fn simulate(&mut self, id: &str, order: &Order) {
+        let fill = Fill {
+            time: clock::seconds(),
+            ticker: order.ticker.clone(),
+            side: order.side,
+            volume: order.volume,
+            price: self.fill_price(order),
+            fee: 0.0,
+            maker: order.kind != OrderKind::Market,
+        };
+        self.simulated.push(Execution {
+            trade: id.to_string(),
+            order: id.to_string(),
+            fill,
+        });
+    }
+}
+
+// Executor enforcing risk limits on the orders passed to the executor it wraps, orders breaching
+// them are rejected with the reason. Limit orders count as open from their submission until they
+// are cancelled or reported as filled, market orders only count against the submission rate.
+// Exposures and the value at risk count the positions and the orders not executed yet at the
+// latest prices, orders reducing them are let through even above their limits.
+// Positions are booked from the executions reported for the orders going through, at their price
+// and net of their fee, so that they can be flattened when trading is halted and valued against
+// the daily loss limit. Simulated orders are reported as executed at their limit or the latest
+// price, limit orders once a candle reaches their limit. Orders found gone from the venue
+// without being executed stop counting as open.
+pub struct RiskGuard<E> {
+    inner: E,
+    config: RwLock<RiskConfig>,
+    state: Mutex<State>,
+}
+
+impl<E: Executor + Sync> RiskGuard<E> {
+    pub fn new(inner: E, config: RiskConfig) -> RiskGuard<E> {
+        RiskGuard {
+            inner,
+            config: RwLock::new(config),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn config(&self) -> RiskConfig {
+        self.config
+            .read()
+            .map(|config| config.clone())
+            .unwrap_or_default()
+    }
+
+    // Change the limits while trading, they apply to the next orders.
+    pub fn set_config(&self, config: RiskConfig) {
+        if let Ok(mut current) = self.config.write() {
+            *current = config;
+        }
+    }
+
+    // Book an execution of an order placed through the guard, the order no longer counts as open
+    // once entirely executed. Returns whether the order was known, executions of other orders are
+    // not booked.
+    pub fn on_fill(&self, execution: &Execution) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        let id = &execution.order;
+        let Some(order) = state
+            .open
+            .get(id)
+            .or_else(|| state.executing.get(id))
+            .or_else(|| state.closed.get(id).map(|(order, _)| order))
+        else {
+            return false;
+        };
+        // the volume sent, rounded to the lots of the pair
+        let volume = instruments::round(order).volume;
+        state.book(&execution.fill);
+        let executed = state.executed.entry(id.clone()).or_default();
+        *executed += execution.fill.volume;
+        if *executed >= volume * (1.0 - 1e-9) {
+            state.executed.remove(id);
+            state.open.remove(id);
+            state.executing.remove(id);
+            state.closed.remove(id);
+        }
+        true
+    }
+
+    // Whether an order placed through the guard is still expected to execute.
+    pub fn pending(&self, id: &str) -> bool {
+        self.state
+            .lock()
+            .is_ok_and(|state| state.open.contains_key(id) || state.executing.contains_key(id))
+    }
+
+    // Stop counting as open the orders no longer resting on the venue, once their executions
+    // reported so far were booked. Orders cancelled or expired outside of the bot no longer count
+    // against the limits, executions of theirs reported late are still booked for a while.
+    // Returns the identifiers of the orders closed.
+    pub async fn reconcile(&self) -> Result<Vec<String>, String> {
+        // orders placed while the venue is queried are not in its answer
+        let known: Vec<String> = match self.state.lock() {
+            Ok(state) => state
+                .open
+                .keys()
+                .chain(state.executing.keys())
+                .cloned()
+                .collect(),
+            Err(_) => return Err("Risk state lock poisoned".into()),
+        };
+        let resting = self.inner.resting().await?;
+        let Ok(mut state) = self.state.lock() else {
+            return Err("Risk state lock poisoned".into());
+        };
+        let now = clock::seconds();
+        state.closed.retain(|_, (_, time)| now - *time < LATE);
+        let mut gone = Vec::new();
+        for id in known.into_iter().filter(|id| !resting.contains(id)) {
+            let order = state
+                .open
+                .remove(&id)
+                .or_else(|| state.executing.remove(&id));
+            if let Some(order) = order {
+                state.closed.insert(id.clone(), (order, now));
+                gone.push(id);
+            }
+        }
+        Ok(gone)
+    }
+
+    // Reject every new order until resumed, overriding the strategies.
+    pub fn halt(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.halt = Some(Halt::Manual);
+        }
+        metrics::set("risk.halted", 1.0);
+        warn!("Trading halted");
+        alerts::notify(EventKind::Risk, "Trading halted", "New orders are rejected");
+    }
+
+    pub fn resume(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.halt = None;
+        }
+        metrics::set("risk.halted", 0.0);
+        warn!("Trading resumed");
+    }
+
+    // Take the daily closes of stored candles of a ticker, oldest first, as the history the value
+    // at risk is estimated on before the live candles.
+    pub fn seed(&self, ticker: &str, candles: &[Candle]) {
+        let Some(var) = self.config().var else {
+            return;
+        };
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        for candle in candles {
+            state.close(ticker, candle.time.div_euclid(DAY), candle.close, var.days);
+        }
+    }
+
+    pub fn halted(&self) -> Option<Halt> {
+        self.state.lock().ok().and_then(|state| state.halt)
+    }
+
+    // Value the positions on a ticker at the close of a candle and check the daily loss limit,
+    // simulated resting orders whose limit the candle reached are taken as filled. Returns
+    // whether trading has just been halted by it.
+    pub fn mark(&self, ticker: &str, candle: &Candle) -> bool {
+        let config = self.config();
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        let day = candle.time.div_euclid(DAY);
+        if state.day.is_none_or(|(current, _)| current < day) {
+            state.day = Some((day, state.equity()));
+            if config.resume_next_day
+                && matches!(state.halt, Some(Halt::DailyLoss(hit)) if hit < day)
+            {
+                state.halt = None;
+                metrics::set("risk.halted", 0.0);
+                warn!("Trading resumed on a new day");
+            }
+        }
+        let window = config
+            .clusters
+            .iter()
+            .filter(|cluster| cluster.correlation.is_some())
+            .map(|cluster| cluster.window)
+            .max();
+        if let (Some(window), Some(previous)) = (window, state.prices.get(ticker).copied()) {
+            let returns = state.returns.entry(ticker.to_string()).or_default();
+            if returns.len() >= window {
+                returns.pop_front();
+            }
+            returns.push_back((candle.close / previous).ln());
+        }
+        state.prices.insert(ticker.to_string(), candle.close);
+        if self.inner.simulated() {
+            let crossed: Vec<String> = state
+                .open
+                .iter()
+                .filter(|(_, order)| order.ticker == ticker)
+                .filter(|(_, order)| match (order.kind, order.side) {
+                    (OrderKind::Limit(price), Side::Buy) => candle.low <= price,
+                    (OrderKind::Limit(price), Side::Sell) => candle.high >= price,
+                    (OrderKind::Market, _) => true,
+                })
+                .map(|(id, _)| id.clone())
+                .collect();
+            for id in crossed {
+                if let Some(order) = state.open.remove(&id) {
+                    state.simulate(&id, &order);
+                    state.executing.insert(id, order);
+                }
+            }
+        }
+
+        if let Some(var) = config.var {
+            state.close(ticker, day, candle.close, var.days);
+            if let Some(risk) = state.value_at_risk(&state.exposures(), var.confidence) {
+                metrics::set("risk.var", risk.var);
+                metrics::set("risk.cvar", risk.cvar);
+            }
+        }
+
+        let pnl = state.equity() - state.day.map_or(0.0, |(_, start)| start);
+        metrics::set("risk.daily_pnl", pnl);
+        events::publish(Event::Pnl { daily: pnl });
+        let Some(max) = config.max_daily_loss else {
+            return false;
+        };
+        if -pnl < max || state.halt.is_some() {
+            return false;
+        }
+        state.halt = Some(Halt::DailyLoss(day));
+        metrics::set("risk.halted", 1.0);
+        warn!(
+            "Daily loss of {} hit the limit of {}, trading halted",
+            -pnl, max
+        );
+        alerts::notify(
+            EventKind::Risk,
+            "Daily loss limit hit",
+            &format!("Daily loss of {}, limit {}", -pnl, max),
+        );
+        true
+    }
+
+    // Identifiers of the resting orders.
+    pub fn open_orders(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .map(|state| state.open.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn positions(&self) -> HashMap<String, f64> {
+        self.state
+            .lock()
+            .map(|state| state.positions.clone())
+            .unwrap_or_default()
+    }
+
+    // Latest close of a ticker.
+    pub fn price(&self, ticker: &str) -> Option<f64> {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|state| state.prices.get(ticker).copied())
+    }
+
+    // Positions summed per base asset along with the cash per quote currency, both as changed
+    // through the guard.
+    pub fn holdings(&self) -> HashMap<String, f64> {
+        let Ok(state) = self.state.lock() else {
+            return HashMap::new();
+        };
+        let mut holdings = state.cash.clone();
+        for (ticker, volume) in &state.positions {
+            *holdings.entry(base(ticker).to_string()).or_default() += volume;
+        }
+        holdings
+    }
+
+    // Correct the holding of an asset by a quantity, booked on a ticker already held on it or on
+    // the cash of a quote currency. Returns whether such a holding was found.
+    pub fn adjust(&self, asset: &str, volume: f64) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        let state = &mut *state;
+        let position = state
+            .positions
+            .iter_mut()
+            .find(|(ticker, _)| base(ticker) == asset)
+            .map(|(_, position)| position)
+            .or_else(|| state.cash.get_mut(asset));
+        match position {
+            Some(position) => {
+                *position += volume;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Cancel every resting order, returning the outcome per identifier.
+    pub async fn cancel_all(&self) -> Vec<(String, Result<(), String>)> {
+        let mut outcomes = Vec::new();
+        for id in self.open_orders() {
+            let result = self.cancel(&id).await;
+            outcomes.push((id, result));
+        }
+        outcomes
+    }
+
+    // Close every position with market orders, bypassing the limits and the halt.
+    pub async fn flatten(&self) -> Vec<(Order, Result<String, String>)> {
+        let mut outcomes = Vec::new();
+        for (ticker, position) in self.positions() {
+            if position.abs() <= f64::EPSILON {
+                continue;
+            }
+            let side = if position > 0.0 {
+                Side::Sell
+            } else {
+                Side::Buy
+            };
+            let order = Order::market(&ticker, side, position.abs());
+            let result = self.inner.submit(&order).await;
+            self.settle(&order, result.as_deref().ok());
+            outcomes.push((order, result));
+        }
+        outcomes
+    }
+
+    // Check the limits for an order and reserve its place if they allow it.
+    fn admit(&self, order: &Order) -> Result<(), String> {
+        let config = self.config();
+        let Ok(mut state) = self.state.lock() else {
+            return Err("Risk state lock poisoned".into());
+        };
+        match state.halt {
+            Some(Halt::Manual) => return Err("trading is halted".into()),
+            Some(Halt::DailyLoss(_)) => return Err("daily loss limit hit".into()),
+            None => (),
+        }
+
+        let now = clock::now();
+        while state
+            .submissions
+            .front()
+            .is_some_and(|time| now - time >= 60.0)
+        {
+            state.submissions.pop_front();
+        }
+        if let Some(max) = config.max_orders_per_minute
+            && state.submissions.len() >= max
+        {
+            return Err(format!(
+                "{} orders submitted in the last minute, limit {}",
+                state.submissions.len(),
+                max
+            ));
+        }
+
+        if let Some(leverage) = order.leverage
+            && config.max_leverage.is_none_or(|max| leverage > max)
+        {
+            return Err(format!(
+                "leverage {} over the limit {:?}",
+                leverage, config.max_leverage
+            ));
+        }
+
+        self.check_flags(&state, order)?;
+        let price = state.prices.get(&order.ticker).copied();
+        instruments::validate(order, price)?;
+        self.check_exposure(&config, &state, order)?;
+        self.check_var(&config, &state, order)?;
+
+        if order.kind != OrderKind::Market {
+            let open = state.open_on(&order.ticker);
+            if let Some(max) = config.max_open_per_ticker
+                && open >= max
+            {
+                return Err(format!(
+                    "{} open orders on {}, limit {}",
+                    open, order.ticker, max
+                ));
+            }
+            let open = state.open.len() + state.pending.len();
+            if let Some(max) = config.max_open
+                && open >= max
+            {
+                return Err(format!("{} open orders, limit {}", open, max));
+            }
+            state.pending.push(order.ticker.clone());
+        }
+        state.submissions.push_back(now);
+        Ok(())
+    }
+
+    // Post-only orders must rest on the book at the latest quote, reduce-only orders must not
+    // exceed the opposite position.
+    fn check_flags(&self, state: &State, order: &Order) -> Result<(), String> {
+        if order.post_only {
+            if order.kind == OrderKind::Market {
+                return Err("post-only market order".into());
+            }
+            if let Some(quote) = market::latest_quote(&order.ticker)
+                && order.crosses(&quote)
+            {
+                return Err(format!(
+                    "post-only order would take liquidity at bid {} ask {}",
+                    quote.bid, quote.ask
+                ));
+            }
+        }
+        if order.reduce_only {
+            let position = state.positions.get(&order.ticker).copied().unwrap_or(0.0);
+            if order.side.sign() * position >= 0.0 || order.volume > position.abs() {
+                return Err(format!(
+                    "reduce-only {:?} of {} against a position of {}",
+                    order.side, order.volume, position
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_exposure(
+        &self,
+        config: &RiskConfig,
+        state: &State,
+        order: &Order,
+    ) -> Result<(), String> {
+        if config.max_asset_exposure.is_empty() && config.clusters.is_empty() {
+            return Ok(());
+        }
+        let before = state.exposures();
+        let exposure = before.get(&order.ticker).copied().unwrap_or(0.0);
+        let change = order.side.sign() * order.volume * state.fill_price(order);
+        // orders reducing the exposure on their ticker are let through whatever the risk
+        if order.reduce_only || (exposure + change).abs() <= exposure.abs() {
+            return Ok(());
+        }
+        let mut after = before.clone();
+        *after.entry(order.ticker.clone()).or_default() += change;
+
+        let asset = base(&order.ticker);
+        if let Some(max) = config.max_asset_exposure.get(asset) {
+            let net = |exposures: &HashMap<String, f64>| {
+                exposures
+                    .iter()
+                    .filter(|(ticker, _)| base(ticker) == asset)
+                    .map(|(_, exposure)| exposure)
+                    .sum::<f64>()
+                    .abs()
+            };
+            let exposure = net(&after);
+            if exposure > *max && exposure > net(&before) {
+                return Err(format!(
+                    "exposure of {} on {}, limit {}",
+                    exposure, asset, max
+                ));
+            }
+        }
+
+        for cluster in &config.clusters {
+            if !state.in_cluster(cluster, &order.ticker) {
+                continue;
+            }
+            let gross = |exposures: &HashMap<String, f64>| {
+                exposures
+                    .iter()
+                    .filter(|(ticker, _)| state.in_cluster(cluster, ticker))
+                    .map(|(_, exposure)| exposure.abs())
+                    .sum::<f64>()
+            };
+            let exposure = gross(&after);
+            if exposure > cluster.max_exposure && exposure > gross(&before) {
+                return Err(format!(
+                    "exposure of {} on cluster {}, limit {}",
+                    exposure,
+                    cluster.assets.join("+"),
+                    cluster.max_exposure
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_var(&self, config: &RiskConfig, state: &State, order: &Order) -> Result<(), String> {
+        let Some(VarConfig {
+            confidence,
+            max_var: Some(max),
+            ..
+        }) = config.var
+        else {
+            return Ok(());
+        };
+        let before = state.exposures();
+        let mut after = before.clone();
+        *after.entry(order.ticker.clone()).or_default() +=
+            order.side.sign() * order.volume * state.fill_price(order);
+        let Some(risk) = state.value_at_risk(&after, confidence) else {
+            return Err(format!(
+                "no daily history of {} to value its risk",
+                order.ticker
+            ));
+        };
+        if risk.var > max
+            && state
+                .value_at_risk(&before, confidence)
+                .is_none_or(|before| risk.var > before.var)
+        {
+            return Err(format!("value at risk of {}, limit {}", risk.var, max));
+        }
+        Ok(())
+    }
+
+    // Wait for the executions of a submitted market order, or turn the reservation of a submitted
+    // limit order into an open order if it rests on the book.
+    fn settle(&self, order: &Order, id: Option<&str>) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if order.kind == OrderKind::Market {
+            if let Some(id) = id {
+                if self.inner.simulated() {
+                    state.simulate(id, order);
+                }
+                state.executing.insert(id.to_string(), order.clone());
+            }
+            return;
+        }
+        if let Some(index) = state
+            .pending
+            .iter()
+            .position(|pending| *pending == order.ticker)
+        {
+            state.pending.remove(index);
+        }
+        if let Some(id) = id {
+            state.open.insert(id.to_string(), order.clone());
+        }
+    }
+}
+
+impl<E: Executor + Sync> Executor for RiskGuard<E> {
+    async fn submit(&self, order: &Order) -> Result<String, String> {
+        if let Err(reason) = self.admit(order) {
+            metrics::increment("risk.rejected", 1);
+            warn!(pair = %order.ticker, order = ?order, reason = %reason, "Rejecting order");
+            return Err(format!("Rejected by risk limits: {}", reason));
+        }
+        let result = self.inner.submit(order).await;
+        self.settle(order, result.as_deref().ok());
+        result
+    }
+
+    async fn cancel(&self, id: &str) -> Result<(), String> {
+        // as the venue would, simulated orders taken as filled can no longer be cancelled
+        if self.inner.simulated()
+            && self
+                .state
+                .lock()
+                .is_ok_and(|state| state.executing.contains_key(id))
+        {
+            return Err(format!("Order {} already filled", id));
+        }
+        self.inner.cancel(id).await?;
+        if let Ok(mut state) = self.state.lock() {
+            state.open.remove(id);
+            state.executed.remove(id);
+        }
+        Ok(())
+    }
+
+    fn simulated(&self) -> bool {
+        self.inner.simulated()
+    }
+
+    async fn balances(&self) -> Result<HashMap<String, f64>, String> {
+        self.inner.balances().await
+    }
+
+    async fn margin(&self) -> Result<Margin, String> {
+        self.inner.margin().await
+    }
+
+    async fn resting(&self) -> Result<HashSet<String>, String> {
+        self.inner.resting().await
+    }
+
+    // Executions of simulated orders are reported once, when first queried.
+    async fn fills(&self, since: i64) -> Result<Vec<Execution>, String> {
+        if !self.inner.simulated() {
+            return self.inner.fills(since).await;
+        }
+        match self.state.lock() {
+            Ok(mut state) => Ok(std::mem::take(&mut state.simulated)),
+            Err(_) => Err("Risk state lock poisoned".into()),
+        }
+    }
+}
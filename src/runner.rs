@@ -0,0 +1,811 @@
+use crate::accounts;
+use crate::alerts::{self, EventKind};
+use crate::attribution;
+use crate::clock;
+use crate::config::{NamedConfig, RunnerConfig, StrategyConfig};
+use crate::cooldown::Cooldowns;
+use crate::events::{self, Event};
+use crate::execution::{Executor, Order, OrderKind, submit_legs};
+use crate::journal::{Entry, Journal, now};
+use crate::market::{self, Candle};
+use crate::metrics;
+use crate::orders::{ChaseConfig, chase};
+use crate::state::StateStore;
+use crate::storage::CandleStore;
+use crate::strategies::market_making::MarketMaker;
+use crate::strategies::{self, Strategy};
+use crate::synchronizer::{Snapshot, SyncConfig, Synchronizer};
+
+use serde_json::json;
+
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+use tracing::{Instrument, Span, debug, debug_span, info, info_span, warn};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Strategy along with the name its orders are journaled under.
+pub type Named = (String, Box<dyn Strategy + Send>);
+
+// Strategies processed together in a task along with the tickers they follow.
+pub struct Worker {
+    pub tickers: Vec<String>,
+    pub strategies: Vec<Named>,
+    // position of each strategy in the configuration
+    pub indices: Vec<usize>,
+}
+
+impl Worker {
+    fn name(&self) -> String {
+        self.tickers.join("+")
+    }
+}
+
+// Price an order was decided at: the close of the candle triggering it or the latest mid price
+// for orders on other tickers and orders not triggered by a candle.
+fn decision(order: &Order, trigger: Option<(&str, &Candle)>) -> Option<f64> {
+    if let Some((ticker, candle)) = trigger
+        && order.ticker == ticker
+    {
+        return Some(candle.close);
+    }
+    market::latest_quote(&order.ticker).map(|quote| quote.mid())
+}
+
+// Name of a configured strategy given its position in the configuration.
+pub fn name(index: usize, config: &NamedConfig) -> String {
+    match &config.id {
+        Some(id) => id.clone(),
+        None => format!("{}#{}", config.strategy.kind(), index),
+    }
+}
+
+// Split the configured strategies into workers. Strategies bound to given tickers run together
+// in a worker per ticker set, the others get an instance per subscribed ticker in that ticker's
+// worker. Strategies are named after their identifier, or their kind and position in the
+// configuration without one, e.g. pairs#0.
+pub fn plan(configs: &[NamedConfig], tickers: &[String]) -> Result<Vec<Worker>, String> {
+    let mut workers: HashMap<Vec<String>, Vec<(usize, Named)>> = HashMap::new();
+    for (index, config) in configs.iter().enumerate() {
+        if config.strategy.quotes() {
+            continue;
+        }
+        let name = name(index, config);
+        match config.strategy.tickers() {
+            Some(mut bound) => {
+                bound.sort();
+                bound.dedup();
+                workers
+                    .entry(bound)
+                    .or_default()
+                    .push((index, (name, strategies::build(&config.strategy)?)));
+            }
+            None => {
+                for ticker in tickers {
+                    workers
+                        .entry(vec![ticker.clone()])
+                        .or_default()
+                        .push((index, (name.clone(), strategies::build(&config.strategy)?)));
+                }
+            }
+        }
+    }
+    Ok(workers
+        .into_iter()
+        .map(|(tickers, strategies)| {
+            let (indices, strategies) = strategies.into_iter().unzip();
+            Worker {
+                tickers,
+                strategies,
+                indices,
+            }
+        })
+        .collect())
+}
+
+// Market makers configured, named as the strategies run by workers are. They quote on the book
+// updates of their ticker.
+pub fn makers(configs: &[NamedConfig]) -> Vec<(String, MarketMaker)> {
+    configs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, config)| match &config.strategy {
+            StrategyConfig::MarketMaking {
+                ticker,
+                half_spread,
+                skew,
+                volume,
+                max_inventory,
+                tolerance,
+            } => Some((
+                name(index, config),
+                MarketMaker::new(
+                    ticker.clone(),
+                    *half_spread,
+                    *skew,
+                    *volume,
+                    *max_inventory,
+                    *tolerance,
+                ),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+// Names of the strategies whose orders are dropped, shared between the runner and its
+// controllers. Paused strategies keep receiving candles so that they are ready when resumed.
+#[derive(Debug, Clone, Default)]
+pub struct Pauses(Arc<Mutex<HashSet<String>>>);
+
+impl Pauses {
+    pub fn pause(&self, name: &str) {
+        if let Ok(mut paused) = self.0.lock() {
+            paused.insert(name.to_string());
+        }
+    }
+
+    pub fn resume(&self, name: &str) {
+        if let Ok(mut paused) = self.0.lock() {
+            paused.remove(name);
+        }
+    }
+
+    pub fn is_paused(&self, name: &str) -> bool {
+        self.0.lock().is_ok_and(|paused| paused.contains(name))
+    }
+
+    pub fn paused(&self) -> Vec<String> {
+        let mut paused: Vec<String> = self
+            .0
+            .lock()
+            .map(|paused| paused.iter().cloned().collect())
+            .unwrap_or_default();
+        paused.sort();
+        paused
+    }
+}
+
+// Candle of a ticker with the span it was ingested in, the decisions it leads to are traced under.
+type Dispatch = (String, Candle, Span);
+
+struct Route {
+    // metrics prefix of the worker
+    name: String,
+    capacity: usize,
+    sender: Sender<Dispatch>,
+    // candles of workers following several tickers are passed on once synchronized
+    synchronizer: Option<Mutex<Synchronizer>>,
+}
+
+type Spawn = Box<dyn Fn(Worker, Receiver<Dispatch>, Span) -> JoinHandle<()> + Send + Sync>;
+
+// Stored candles the strategies are started with.
+#[derive(Debug, Clone)]
+pub struct History {
+    pub store: CandleStore,
+    // interval of the candles the strategies are fed (in min)
+    pub interval: i32,
+    // latest candles per ticker, no history is loaded without
+    pub candles: usize,
+}
+
+impl History {
+    fn load(&self, tickers: &[String]) -> HashMap<String, Vec<Candle>> {
+        let mut history = HashMap::new();
+        if self.candles == 0 {
+            return history;
+        }
+        for ticker in tickers {
+            match self.store.load(ticker, self.interval) {
+                Ok(mut candles) => {
+                    let start = candles.len().saturating_sub(self.candles);
+                    history.insert(ticker.clone(), candles.split_off(start));
+                }
+                Err(message) => warn!("No history for {}: {}", ticker, message),
+            }
+        }
+        history
+    }
+}
+
+// Dispatches candles to workers each running in its own task behind a bounded queue, so that a
+// slow strategy only delays the tickers it follows. When a queue is full the dispatcher waits for
+// room, the time spent waiting and the queue depths are exposed as metrics.
+pub struct Runner {
+    routes: HashMap<String, Vec<Arc<Route>>>,
+    tasks: Vec<JoinHandle<()>>,
+    // names of the strategies run
+    strategies: Vec<String>,
+    pauses: Pauses,
+    queue: usize,
+    // how the candles of workers following several tickers are synchronized
+    sync: SyncConfig,
+    // configuration of the strategies, the workers rebuild those whose configuration changes
+    configs: watch::Sender<Vec<NamedConfig>>,
+    // starts the task of a worker
+    spawn: Spawn,
+}
+
+impl Runner {
+    pub fn new<E: Executor + Send + Sync + 'static>(
+        configs: Vec<NamedConfig>,
+        workers: Vec<Worker>,
+        executor: Arc<E>,
+        journal: Arc<Mutex<Journal>>,
+        config: RunnerConfig,
+        history: History,
+        states: StateStore,
+    ) -> Runner {
+        let pauses = Pauses::default();
+        let (configs, receiver) = watch::channel(configs);
+        let spawn: Spawn = Box::new({
+            let pauses = pauses.clone();
+            move |worker, dispatches, span| {
+                let context = Context {
+                    executor: executor.clone(),
+                    journal: journal.clone(),
+                    pauses: pauses.clone(),
+                    tactic: config.chase,
+                    cooldowns: Mutex::new(Cooldowns::new(config.cooldown)),
+                    configs: receiver.clone(),
+                };
+                tokio::spawn(
+                    work(worker, dispatches, context, history.clone(), states.clone())
+                        .instrument(span),
+                )
+            }
+        });
+        let mut runner = Runner {
+            routes: HashMap::new(),
+            tasks: Vec::new(),
+            strategies: Vec::new(),
+            pauses,
+            queue: config.queue.max(1),
+            sync: SyncConfig::default(),
+            configs,
+            spawn,
+        };
+        for worker in workers {
+            runner.start(worker);
+        }
+        runner
+    }
+
+    fn start(&mut self, worker: Worker) {
+        let (sender, receiver) = mpsc::channel(self.queue);
+        let synchronizer = (worker.tickers.len() > 1)
+            .then(|| Mutex::new(Synchronizer::new(worker.tickers.clone(), self.sync.clone())));
+        let route = Arc::new(Route {
+            name: format!("runner.{}", worker.name()),
+            capacity: self.queue,
+            sender,
+            synchronizer,
+        });
+        for ticker in &worker.tickers {
+            self.routes
+                .entry(ticker.clone())
+                .or_default()
+                .push(route.clone());
+        }
+        self.strategies
+            .extend(worker.strategies.iter().map(|(name, _)| name.clone()));
+        self.strategies.sort();
+        self.strategies.dedup();
+        let span = info_span!("worker", worker = %worker.name());
+        self.tasks.push((self.spawn)(worker, receiver, span));
+    }
+
+    pub fn with_sync(mut self, config: SyncConfig) -> Runner {
+        for route in self.routes.values().flatten() {
+            if let Some(Ok(mut synchronizer)) = route.synchronizer.as_ref().map(Mutex::lock) {
+                synchronizer.set_config(config.clone());
+            }
+        }
+        self.sync = config;
+        self
+    }
+
+    // Start fresh instances of the strategies not bound to given tickers on a further ticker.
+    // Returns the number of strategies started.
+    pub fn add_ticker(&mut self, ticker: &str) -> Result<usize, String> {
+        let route = format!("runner.{}", ticker);
+        if self
+            .routes
+            .get(ticker)
+            .is_some_and(|routes| routes.iter().any(|existing| existing.name == route))
+        {
+            return Err(format!("{} is already dispatched", ticker));
+        }
+        let mut instances = Vec::new();
+        let mut indices = Vec::new();
+        for (index, config) in self.configs.borrow().iter().enumerate() {
+            if config.strategy.tickers().is_none() {
+                instances.push((name(index, config), strategies::build(&config.strategy)?));
+                indices.push(index);
+            }
+        }
+        let started = instances.len();
+        if started > 0 {
+            self.start(Worker {
+                tickers: vec![ticker.to_string()],
+                strategies: instances,
+                indices,
+            });
+        }
+        Ok(started)
+    }
+
+    // Change the parameters of the strategies while running. The strategies whose configuration
+    // changed are rebuilt by their worker, started with its latest candles and handed the state of
+    // the instance they replace. Adding, removing, moving or renaming strategies and binding them
+    // to other tickers only apply on restart.
+    pub fn reconfigure(&self, configs: Vec<NamedConfig>) -> Result<(), String> {
+        let current = self.configs.borrow().clone();
+        let moved = configs.len() != current.len()
+            || configs.iter().zip(&current).any(|(config, before)| {
+                config.id != before.id
+                    || config.strategy.kind() != before.strategy.kind()
+                    || config.strategy.tickers() != before.strategy.tickers()
+            });
+        if moved {
+            return Err(
+                "Strategies added, removed, moved, renamed or bound to other tickers".into(),
+            );
+        }
+        self.configs.send_replace(configs);
+        Ok(())
+    }
+
+    // Stop dispatching the candles of a ticker, workers following only that ticker finish their
+    // queue and stop along with their strategies' state.
+    pub fn remove_ticker(&mut self, ticker: &str) {
+        self.routes.remove(ticker);
+    }
+
+    pub fn strategies(&self) -> &[String] {
+        &self.strategies
+    }
+
+    pub fn pauses(&self) -> Pauses {
+        self.pauses.clone()
+    }
+
+    // Pass a candle on to the workers following its ticker. Workers following several tickers
+    // get the candles of a time together once every ticker delivered it, in the order of their
+    // tickers, along the laggards policy of the synchronization.
+    pub async fn on_candle(&self, ticker: &str, candle: &Candle) {
+        let Some(routes) = self.routes.get(ticker) else {
+            return;
+        };
+        for route in routes {
+            let candles = match &route.synchronizer {
+                None => vec![(ticker.to_string(), *candle)],
+                Some(synchronizer) => match synchronizer.lock() {
+                    Ok(mut synchronizer) => {
+                        released(synchronizer.update(ticker, candle, clock::now()))
+                    }
+                    Err(_) => continue,
+                },
+            };
+            for (ticker, candle) in candles {
+                dispatch(route, ticker, candle).await;
+            }
+        }
+    }
+
+    // Pass on the candles of the times given up waiting for, see on_candle.
+    pub async fn poll(&self) {
+        let mut polled = HashSet::new();
+        for route in self.routes.values().flatten() {
+            let Some(synchronizer) = &route.synchronizer else {
+                continue;
+            };
+            if !polled.insert(route.name.clone()) {
+                continue;
+            }
+            let candles = match synchronizer.lock() {
+                Ok(mut synchronizer) => released(synchronizer.poll(clock::now())),
+                Err(_) => continue,
+            };
+            for (ticker, candle) in candles {
+                dispatch(route, ticker, candle).await;
+            }
+        }
+    }
+
+    // Stop routing candles and wait for the workers to drain their queues.
+    pub async fn shutdown(self) {
+        drop(self.routes);
+        for task in self.tasks {
+            if let Err(error) = task.await {
+                warn!("Worker task failed: {:?}", error);
+            }
+        }
+    }
+}
+
+// Candles delivered in synchronized snapshots, those carried forward were already passed on.
+fn released(snapshots: Vec<Snapshot>) -> Vec<(String, Candle)> {
+    let mut candles = Vec::new();
+    for snapshot in snapshots {
+        let mut delivered: Vec<(String, Candle)> = snapshot
+            .candles
+            .into_iter()
+            .filter(|(ticker, _)| !snapshot.missing.contains(ticker))
+            .collect();
+        delivered.sort_by(|first, second| first.0.cmp(&second.0));
+        candles.extend(delivered);
+    }
+    candles
+}
+
+// Queue a candle for a worker, waiting for room when its queue is full.
+async fn dispatch(route: &Route, ticker: String, candle: Candle) {
+    let message = (ticker, candle, Span::current());
+    let message = match route.sender.try_send(message) {
+        Ok(()) => None,
+        Err(mpsc::error::TrySendError::Full(message)) => Some(message),
+        Err(mpsc::error::TrySendError::Closed((ticker, ..))) => {
+            warn!(worker = %route.name, pair = %ticker, "Worker stopped, dropping candle");
+            return;
+        }
+    };
+    if let Some(message) = message {
+        let ticker = message.0.clone();
+        let start = Instant::now();
+        metrics::increment(&format!("{}.blocked", route.name), 1);
+        if route.sender.send(message).await.is_err() {
+            warn!(worker = %route.name, pair = %ticker, "Worker stopped, dropping candle");
+        }
+        metrics::increment(
+            &format!("{}.blocked_us", route.name),
+            start.elapsed().as_micros() as u64,
+        );
+    }
+    metrics::set(
+        &format!("{}.depth", route.name),
+        (route.capacity - route.sender.capacity()) as f64,
+    );
+}
+
+// What the strategies of a worker place their orders with.
+struct Context<E> {
+    executor: Arc<E>,
+    journal: Arc<Mutex<Journal>>,
+    pauses: Pauses,
+    tactic: Option<ChaseConfig>,
+    cooldowns: Mutex<Cooldowns>,
+    configs: watch::Receiver<Vec<NamedConfig>>,
+}
+
+impl<E: Executor> Context<E> {
+    // Place the orders of a strategy the cooldown rules let through and journal them, returning
+    // their identifiers. Orders are decided at the close of the candle triggering them, when there
+    // is one.
+    async fn act(
+        &self,
+        name: &str,
+        orders: Vec<Order>,
+        trigger: Option<(&str, &Candle)>,
+    ) -> Vec<String> {
+        let time = clock::seconds();
+        let price = |order: &Order| decision(order, trigger);
+        if let Ok(cooldowns) = self.cooldowns.lock()
+            && let Some(block) = cooldowns.check(name, &orders, time, price)
+        {
+            debug!(strategy = %name, orders = ?orders, "Held back by the {:?} rule", block);
+            return Vec::new();
+        }
+        let signal = Event::Signal {
+            strategy: name.to_string(),
+            orders: orders.clone(),
+        };
+        alerts::notify_with(
+            EventKind::Signal,
+            &format!("Signal of {}", name),
+            &format!("{:?}", orders),
+            serde_json::to_value(&signal).unwrap_or_default(),
+        );
+        events::publish(signal);
+        let orders: Vec<Order> = match accounts::route(name) {
+            Some(account) => orders
+                .into_iter()
+                .map(|order| order.with_account(&account))
+                .collect(),
+            None => orders,
+        };
+
+        // legs of a signal are sent together at market, lone orders can be chased
+        let chased = match (self.tactic, orders.as_slice()) {
+            (Some(tactic), [order]) if order.kind == OrderKind::Market => {
+                Some(chase(self.executor.as_ref(), Some(name), order, tactic).await)
+            }
+            _ => None,
+        };
+        let (result, orders) = match chased {
+            Some(Ok((id, placed))) => (Ok(vec![id]), vec![placed]),
+            Some(Err(reason)) => (Err(reason), orders),
+            None => (submit_legs(self.executor.as_ref(), &orders).await, orders),
+        };
+
+        if result.is_ok()
+            && let Ok(mut cooldowns) = self.cooldowns.lock()
+        {
+            cooldowns.record(name, &orders, time, price);
+        }
+
+        let Ok(mut journal) = self.journal.lock() else {
+            warn!("Journal lock poisoned, orders {:?} not journaled", orders);
+            return result.unwrap_or_default();
+        };
+        let simulated = self.executor.simulated();
+        let (ids, entries) = match result {
+            Ok(ids) => {
+                alerts::notify_with(
+                    EventKind::Order,
+                    &format!("Orders of {}", name),
+                    &format!("{:?}", orders),
+                    json!({"strategy": name, "ids": ids, "orders": orders}),
+                );
+                let entries = ids
+                    .iter()
+                    .zip(orders)
+                    .map(|(id, order)| {
+                        attribution::place(name, id);
+                        events::publish(Event::Order {
+                            strategy: Some(name.to_string()),
+                            id: id.clone(),
+                            order: order.clone(),
+                        });
+                        let decision = decision(&order, trigger);
+                        Entry::Order {
+                            time: now(),
+                            id: id.clone(),
+                            order,
+                            simulated,
+                            strategy: Some(name.to_string()),
+                            decision,
+                        }
+                    })
+                    .collect();
+                (ids, entries)
+            }
+            Err(reason) => {
+                warn!(strategy = %name, orders = ?orders, reason = %reason, "Orders failed");
+                alerts::notify(
+                    EventKind::Error,
+                    &format!("Orders of {} failed", name),
+                    &reason,
+                );
+                let entries = vec![Entry::Rejected {
+                    time: now(),
+                    orders,
+                    reason,
+                    simulated,
+                }];
+                (Vec::new(), entries)
+            }
+        };
+        for entry in entries {
+            if let Err(message) = journal.record(&entry) {
+                warn!("{}", message);
+            }
+        }
+        ids
+    }
+}
+
+// Period and first time of the timer of a strategy (in s), if it has one.
+fn schedule(strategy: &impl Strategy, start: i64) -> Option<(i64, i64)> {
+    let period = strategy.timer().filter(|period| *period > 0)?;
+    Some((period, start + period))
+}
+
+// Keep the latest candles of a ticker, an update of the latest candle replaces it.
+fn remember(candles: &mut Vec<Candle>, candle: Candle, depth: usize) {
+    match candles.last_mut() {
+        Some(last) if last.time == candle.time => *last = candle,
+        _ => candles.push(candle),
+    }
+    if candles.len() > depth {
+        candles.drain(..candles.len() - depth);
+    }
+}
+
+// Run the strategies of a worker: started with their history and saved state, fed the candles of
+// their tickers, the fills of their orders and their timers until the dispatcher stops, then
+// stopped and their state saved.
+async fn work<E: Executor>(
+    worker: Worker,
+    mut receiver: Receiver<Dispatch>,
+    context: Context<E>,
+    history: History,
+    states: StateStore,
+) {
+    // latest candles of the tickers, the strategies rebuilt with new parameters start with them
+    let mut recent = history.load(&worker.tickers);
+    let Worker {
+        tickers,
+        mut strategies,
+        indices,
+    } = worker;
+    let mut configs = context.configs.clone();
+    let mut built: Vec<NamedConfig> = {
+        let latest = configs.borrow_and_update();
+        indices.iter().map(|index| latest[*index].clone()).collect()
+    };
+    for (name, strategy) in strategies.iter_mut() {
+        let _span = info_span!("strategy", strategy = %name).entered();
+        strategy.on_start(&recent);
+        for ticker in &tickers {
+            let restored = match states.load(name, ticker) {
+                Ok(Some(state)) => strategy.restore(ticker, state),
+                Ok(None) => continue,
+                Err(message) => Err(message),
+            };
+            match restored {
+                Ok(()) => info!(pair = %ticker, "Restored state"),
+                Err(message) => warn!(pair = %ticker, "Could not restore state: {}", message),
+            }
+        }
+    }
+
+    let mut fills = events::subscribe();
+    // strategy index and span of the orders placed by identifier, to pass their fills on
+    let mut owners: HashMap<String, (usize, Span)> = HashMap::new();
+    // period and next time of the timer of each strategy (in s)
+    let start = clock::seconds();
+    let mut timers: Vec<Option<(i64, i64)>> = strategies
+        .iter()
+        .map(|(_, strategy)| schedule(strategy, start))
+        .collect();
+    let mut timed = timers.iter().any(Option::is_some);
+    let mut ticks = interval(Duration::from_secs(1));
+
+    loop {
+        tokio::select! {
+            message = receiver.recv() => {
+                let Some((ticker, candle, ingest)) = message else {
+                    break;
+                };
+                if let Ok(mut cooldowns) = context.cooldowns.lock() {
+                    cooldowns.on_candle(&ticker);
+                }
+                if history.candles > 0 {
+                    remember(recent.entry(ticker.clone()).or_default(), candle, history.candles);
+                }
+                let dispatch = debug_span!(parent: &ingest, "dispatch", pair = %ticker);
+                async {
+                    for (index, (name, strategy)) in strategies.iter_mut().enumerate() {
+                        let orders = info_span!("strategy", strategy = %name)
+                            .in_scope(|| strategy.on_candle(&ticker, &candle));
+                        if orders.is_empty() || context.pauses.is_paused(name) {
+                            continue;
+                        }
+                        if !strategy.ready(&ticker) {
+                            debug!(
+                                strategy = %name,
+                                pair = %ticker,
+                                "Warming up, dropping {} orders",
+                                orders.len()
+                            );
+                            metrics::increment(
+                                &format!("strategy.{}.warming", name),
+                                orders.len() as u64,
+                            );
+                            continue;
+                        }
+                        let order = debug_span!("order", strategy = %name);
+                        for id in context
+                            .act(name, orders, Some((&ticker, &candle)))
+                            .instrument(order.clone())
+                            .await
+                        {
+                            owners.insert(id, (index, order.clone()));
+                        }
+                    }
+                }
+                .instrument(dispatch)
+                .await;
+            }
+            event = fills.recv() => {
+                let (id, fill) = match event {
+                    Ok(Event::Fill { id, fill }) => (id, fill),
+                    Ok(_) => continue,
+                    Err(error) => {
+                        warn!("Fills missed: {:?}", error);
+                        continue;
+                    }
+                };
+                let Some((index, placed)) = owners.remove(&id) else {
+                    continue;
+                };
+                // the span of the order closes once its legs are filled
+                let filled = debug_span!(parent: &placed, "fill", id = %id);
+                drop(placed);
+                let (name, strategy) = &mut strategies[index];
+                let orders = filled.in_scope(|| {
+                    info_span!("strategy", strategy = %name).in_scope(|| strategy.on_fill(&fill))
+                });
+                if orders.is_empty() || context.pauses.is_paused(name) {
+                    continue;
+                }
+                let order = debug_span!(parent: &filled, "order", strategy = %name);
+                for id in context.act(name, orders, None).instrument(order.clone()).await {
+                    owners.insert(id, (index, order.clone()));
+                }
+            }
+            Ok(()) = configs.changed() => {
+                let latest = configs.borrow_and_update().clone();
+                for (position, (name, strategy)) in strategies.iter_mut().enumerate() {
+                    let config = &latest[indices[position]];
+                    if *config == built[position] {
+                        continue;
+                    }
+                    let _span = info_span!("strategy", strategy = %name).entered();
+                    let mut replacement = match strategies::build(&config.strategy) {
+                        Ok(replacement) => replacement,
+                        Err(message) => {
+                            warn!("Could not apply the new parameters: {}", message);
+                            continue;
+                        }
+                    };
+                    replacement.on_start(&recent);
+                    for ticker in &tickers {
+                        if let Some(state) = strategy.state(ticker)
+                            && let Err(message) = replacement.restore(ticker, state)
+                        {
+                            warn!(pair = %ticker, "Could not hand over state: {}", message);
+                        }
+                    }
+                    *strategy = replacement;
+                    built[position] = config.clone();
+                    timers[position] = schedule(strategy, clock::seconds());
+                    info!("New parameters applied");
+                }
+                timed = timers.iter().any(Option::is_some);
+            }
+            _ = ticks.tick(), if timed => {
+                let time = clock::seconds();
+                for (index, (name, strategy)) in strategies.iter_mut().enumerate() {
+                    let Some((period, next)) = &mut timers[index] else {
+                        continue;
+                    };
+                    if time < *next {
+                        continue;
+                    }
+                    *next += *period * ((time - *next) / *period + 1);
+                    let orders = info_span!("strategy", strategy = %name)
+                        .in_scope(|| strategy.on_timer(time));
+                    if orders.is_empty() || context.pauses.is_paused(name) {
+                        continue;
+                    }
+                    let order = debug_span!("order", strategy = %name);
+                    for id in context.act(name, orders, None).instrument(order.clone()).await {
+                        owners.insert(id, (index, order.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    for (name, strategy) in strategies.iter_mut() {
+        let orders = info_span!("strategy", strategy = %name).in_scope(|| strategy.on_stop());
+        if !orders.is_empty() && !context.pauses.is_paused(name) {
+            context.act(name, orders, None).await;
+        }
+        for ticker in &tickers {
+            if let Some(state) = strategy.state(ticker)
+                && let Err(message) = states.save(name, ticker, &state)
+            {
+                warn!(strategy = %name, pair = %ticker, "{}", message);
+            }
+        }
+    }
+}
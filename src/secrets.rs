@@ -0,0 +1,243 @@
+use age::scrypt;
+use age::secrecy::SecretString;
+
+use keyring::Entry;
+
+use serde::{Deserialize, Serialize};
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const SERVICE: &str = "trade-bot";
+const KEY: &str = "KRAKEN_API_KEY";
+const SECRET: &str = "KRAKEN_API_SECRET";
+// passphrase of an age encrypted file, prompted for when not set
+const PASSPHRASE: &str = "TRADE_BOT_PASSPHRASE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    // KRAKEN_API_KEY and KRAKEN_API_SECRET
+    #[default]
+    Env,
+    // the keyring of the OS, under the trade-bot service
+    Keyring,
+    // a file encrypted with a passphrase, with GPG when named .gpg or .asc and age otherwise
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SecretsConfig {
+    // where the API credentials are loaded from, the environment variables still take
+    // precedence when set
+    pub source: Source,
+    pub file: PathBuf,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> SecretsConfig {
+        SecretsConfig {
+            source: Source::Env,
+            file: PathBuf::from("trade-bot.keys.age"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub struct Credentials {
+    pub key: String,
+    pub secret: String,
+}
+
+// Loaded credentials end up in logs and panics through Debug, the secret is left out.
+impl fmt::Debug for Credentials {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Credentials")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+fn gpg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "gpg" || extension == "asc")
+}
+
+fn passphrase(confirm: bool) -> Result<SecretString, String> {
+    if let Ok(passphrase) = env::var(PASSPHRASE) {
+        return Ok(SecretString::from(passphrase));
+    }
+    let prompt = |text: &str| {
+        rpassword::prompt_password(text)
+            .map_err(|error| format!("Could not read the passphrase: {:?}", error))
+    };
+    let passphrase = prompt("Passphrase: ")?;
+    if confirm && prompt("Passphrase again: ")? != passphrase {
+        return Err("Passphrases differ".into());
+    }
+    Ok(SecretString::from(passphrase))
+}
+
+fn keyring(name: &str) -> Result<Entry, String> {
+    Entry::new(SERVICE, name).map_err(|error| format!("Could not open the keyring: {:?}", error))
+}
+
+fn decrypt(path: &Path) -> Result<Vec<u8>, String> {
+    if gpg(path) {
+        // gpg asks for the passphrase itself, through its agent
+        let output = match Command::new("gpg")
+            .args(["--quiet", "--decrypt"])
+            .arg(path)
+            .output()
+        {
+            Ok(output) => output,
+            Err(error) => return Err(format!("Could not run gpg: {:?}", error)),
+        };
+        if !output.status.success() {
+            return Err(format!(
+                "Could not decrypt {:?}: {}",
+                path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        return Ok(output.stdout);
+    }
+    let encrypted = match fs::read(path) {
+        Ok(encrypted) => encrypted,
+        Err(error) => return Err(format!("Could not read {:?}: {:?}", path, error)),
+    };
+    let identity = scrypt::Identity::new(passphrase(false)?);
+    age::decrypt(&identity, &encrypted)
+        .map_err(|error| format!("Could not decrypt {:?}: {:?}", path, error))
+}
+
+fn encrypt(path: &Path, content: &[u8]) -> Result<(), String> {
+    if gpg(path) {
+        let mut child = match Command::new("gpg")
+            .args(["--quiet", "--yes", "--symmetric", "--output"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(error) => return Err(format!("Could not run gpg: {:?}", error)),
+        };
+        if let Some(mut stdin) = child.stdin.take()
+            && let Err(error) = stdin.write_all(content)
+        {
+            return Err(format!("Could not write to gpg: {:?}", error));
+        }
+        return match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("Could not encrypt {:?}: gpg {}", path, status)),
+            Err(error) => Err(format!("Could not run gpg: {:?}", error)),
+        };
+    }
+    let recipient = scrypt::Recipient::new(passphrase(true)?);
+    let encrypted = match age::encrypt(&recipient, content) {
+        Ok(encrypted) => encrypted,
+        Err(error) => return Err(format!("Could not encrypt {:?}: {:?}", path, error)),
+    };
+    fs::write(path, encrypted).map_err(|error| format!("Could not write {:?}: {:?}", path, error))
+}
+
+// Names the credentials of an account are stored under, in the environment and the keyring, the
+// main account's being unsuffixed.
+fn names(account: Option<&str>) -> (String, String) {
+    match account {
+        Some(account) => {
+            let suffix = account.to_uppercase().replace(['-', ' '], "_");
+            (
+                format!("{}_{}", KEY, suffix),
+                format!("{}_{}", SECRET, suffix),
+            )
+        }
+        None => (KEY.to_string(), SECRET.to_string()),
+    }
+}
+
+// API credentials of an account, the main one without a name, from the environment when set and
+// from the configured source otherwise.
+pub fn load(config: &SecretsConfig, account: Option<&str>) -> Result<Credentials, String> {
+    let (key, secret) = names(account);
+    if let (Ok(key), Ok(secret)) = (env::var(&key), env::var(&secret)) {
+        return Ok(Credentials { key, secret });
+    }
+    match config.source {
+        Source::Env => Err(format!(
+            "Set {} and {}, or store them with `trade-bot keys set`, to trade or use --dry-run.",
+            key, secret
+        )),
+        Source::Keyring => {
+            let read = |name: &str| {
+                keyring(name)?.get_password().map_err(|error| {
+                    format!("Could not read {} from the keyring: {:?}", name, error)
+                })
+            };
+            Ok(Credentials {
+                key: read(&key)?,
+                secret: read(&secret)?,
+            })
+        }
+        Source::File => {
+            let content = decrypt(&config.file)?;
+            let content = String::from_utf8_lossy(&content);
+            toml::from_str(&content)
+                .map_err(|error| format!("Could not parse {:?}: {}", config.file, error))
+        }
+    }
+}
+
+// Store the API credentials of an account in the configured source, the environment cannot be
+// written to.
+pub fn store(
+    config: &SecretsConfig,
+    account: Option<&str>,
+    credentials: &Credentials,
+) -> Result<String, String> {
+    let (key, secret) = names(account);
+    match config.source {
+        Source::Env => Err(
+            "Credentials are read from the environment, set secrets.source to keyring or file."
+                .into(),
+        ),
+        Source::Keyring => {
+            for (name, value) in [(&key, &credentials.key), (&secret, &credentials.secret)] {
+                if let Err(error) = keyring(name)?.set_password(value) {
+                    return Err(format!(
+                        "Could not write {} to the keyring: {:?}",
+                        name, error
+                    ));
+                }
+            }
+            Ok(format!("Credentials stored in the {} keyring", SERVICE))
+        }
+        Source::File => {
+            let content = match toml::to_string(credentials) {
+                Ok(content) => content,
+                Err(error) => return Err(format!("Could not serialize credentials: {}", error)),
+            };
+            encrypt(&config.file, content.as_bytes())?;
+            Ok(format!("Credentials encrypted to {:?}", config.file))
+        }
+    }
+}
+
+// Ask for the API credentials on the terminal without echoing them.
+pub fn prompt() -> Result<Credentials, String> {
+    let read = |text: &str| match rpassword::prompt_password(text) {
+        Ok(value) if !value.trim().is_empty() => Ok(value.trim().to_string()),
+        Ok(_) => Err(format!("No {} given", text.trim_end_matches(": "))),
+        Err(error) => Err(format!("Could not read the credentials: {:?}", error)),
+    };
+    Ok(Credentials {
+        key: read("API key: ")?,
+        secret: read("API secret: ")?,
+    })
+}
@@ -0,0 +1,121 @@
+use chrono::{DateTime, Datelike, Days, NaiveTime, TimeDelta, Weekday};
+
+use serde::{Deserialize, Serialize};
+
+const DAY: i64 = 24 * 60 * 60;
+
+// Major trading sessions by their usual UTC hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Session {
+    Asia,
+    Europe,
+    America,
+}
+
+impl Session {
+    pub const ALL: [Session; 3] = [Session::Asia, Session::Europe, Session::America];
+
+    pub fn hours(&self) -> TradingHours {
+        let (open, close) = match self {
+            Session::Asia => (0, 9),
+            Session::Europe => (7, 16),
+            Session::America => (13, 22),
+        };
+        TradingHours {
+            open: NaiveTime::from_hms_opt(open, 0, 0).unwrap_or_default(),
+            close: NaiveTime::from_hms_opt(close, 0, 0).unwrap_or_default(),
+            weekends: false,
+        }
+    }
+
+    // Sessions open at a unix time (in s).
+    pub fn active(time: i64) -> Vec<Session> {
+        Session::ALL
+            .into_iter()
+            .filter(|session| session.hours().is_open(time))
+            .collect()
+    }
+}
+
+// Daily UTC trading window. A close before the open spans midnight and equal times span the
+// whole day, the default. Sessions belong to the day they open on, which decides whether they
+// fall on a weekend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TradingHours {
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+    // whether sessions opening on saturdays and sundays are traded
+    pub weekends: bool,
+}
+
+impl Default for TradingHours {
+    fn default() -> TradingHours {
+        TradingHours {
+            open: NaiveTime::MIN,
+            close: NaiveTime::MIN,
+            weekends: true,
+        }
+    }
+}
+
+impl TradingHours {
+    // Length of a session (in s).
+    fn length(&self) -> i64 {
+        match (self.close - self.open).num_seconds() {
+            length if length > 0 => length,
+            length => length + DAY,
+        }
+    }
+
+    // Unix time (in s) of the opening of the session on the day of a unix time, if one is held.
+    fn opening(&self, day: i64) -> Option<i64> {
+        let date = DateTime::from_timestamp(day, 0)?.date_naive();
+        if !self.weekends && matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            return None;
+        }
+        Some(date.and_time(self.open).and_utc().timestamp())
+    }
+
+    // Unix time (in s) the session open at a unix time opened, None outside of trading hours.
+    pub fn opened_at(&self, time: i64) -> Option<i64> {
+        [time, time - DAY]
+            .into_iter()
+            .filter_map(|day| self.opening(day))
+            .find(|opening| *opening <= time && time < opening + self.length())
+    }
+
+    pub fn is_open(&self, time: i64) -> bool {
+        self.opened_at(time).is_some()
+    }
+
+    // Unix time (in s) of the first session opening at or after a unix time, to align scheduled
+    // actions with the trading hours.
+    pub fn next_open(&self, time: i64) -> Option<i64> {
+        let date = DateTime::from_timestamp(time, 0)?.date_naive();
+        (0..8)
+            .filter_map(|days| date.checked_add_days(Days::new(days)))
+            .filter_map(|date| self.opening(date.and_time(NaiveTime::MIN).and_utc().timestamp()))
+            .find(|opening| *opening >= time)
+    }
+
+    // Unix time (in s) of the first session closing after a unix time, e.g. to close the trading
+    // day.
+    pub fn next_close(&self, time: i64) -> Option<i64> {
+        let date = DateTime::from_timestamp(time, 0)?.date_naive();
+        (0..9)
+            .filter_map(|days| {
+                date.checked_sub_days(Days::new(1))?
+                    .checked_add_days(Days::new(days))
+            })
+            .filter_map(|date| self.opening(date.and_time(NaiveTime::MIN).and_utc().timestamp()))
+            .map(|opening| opening + self.length())
+            .find(|close| *close > time)
+    }
+
+    // Time elapsed since the session opened, None outside of trading hours.
+    pub fn since_open(&self, time: i64) -> Option<TimeDelta> {
+        Some(TimeDelta::seconds(time - self.opened_at(time)?))
+    }
+}
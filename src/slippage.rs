@@ -0,0 +1,101 @@
+use crate::backtest::SlippageModel;
+use crate::execution::OrderKind;
+use crate::journal::Entry;
+
+use std::collections::{BTreeMap, HashMap};
+
+// Fill compared to the price the order was decided at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub ticker: String,
+    // "market" or "limit"
+    pub kind: &'static str,
+    pub volume: f64,
+    // relative price difference, positive when the fill was worse than the decision price
+    pub slippage: f64,
+}
+
+// Realized slippage of the journaled fills of orders carrying their decision price.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SlippageReport {
+    pub samples: Vec<Sample>,
+}
+
+impl SlippageReport {
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> SlippageReport {
+        // (kind, decision price) of the orders by identifier
+        let mut decisions: HashMap<&str, (&'static str, f64)> = HashMap::new();
+        let mut samples = Vec::new();
+        for entry in entries {
+            match entry {
+                Entry::Order {
+                    id,
+                    order,
+                    decision: Some(decision),
+                    ..
+                } if *decision > 0.0 => {
+                    let kind = match order.kind {
+                        OrderKind::Market => "market",
+                        OrderKind::Limit(_) => "limit",
+                    };
+                    decisions.insert(id, (kind, *decision));
+                }
+                Entry::Fill {
+                    id,
+                    ticker,
+                    side,
+                    volume,
+                    price,
+                    ..
+                } => {
+                    let Some((kind, decision)) = decisions.get(id.as_str()) else {
+                        continue;
+                    };
+                    samples.push(Sample {
+                        ticker: ticker.clone(),
+                        kind,
+                        volume: *volume,
+                        slippage: side.sign() * (price - decision) / decision,
+                    });
+                }
+                _ => (),
+            }
+        }
+        SlippageReport { samples }
+    }
+
+    // Volume weighted average slippage and fill count per key.
+    fn average(&self, key: impl Fn(&Sample) -> String) -> BTreeMap<String, (f64, usize)> {
+        let mut sums: BTreeMap<String, (f64, f64, usize)> = BTreeMap::new();
+        for sample in &self.samples {
+            let (weighted, volume, count) = sums.entry(key(sample)).or_default();
+            *weighted += sample.slippage * sample.volume;
+            *volume += sample.volume;
+            *count += 1;
+        }
+        sums.into_iter()
+            .filter(|(_, (_, volume, _))| *volume > 0.0)
+            .map(|(key, (weighted, volume, count))| (key, (weighted / volume, count)))
+            .collect()
+    }
+
+    pub fn by_kind(&self) -> BTreeMap<String, (f64, usize)> {
+        self.average(|sample| sample.kind.to_string())
+    }
+
+    pub fn by_ticker(&self) -> BTreeMap<String, (f64, usize)> {
+        self.average(|sample| sample.ticker.clone())
+    }
+
+    // Slippage model of the backtester with the spread market orders cross matching the
+    // measured market order slippage, unchanged without market fills.
+    pub fn calibrate(&self, model: SlippageModel) -> SlippageModel {
+        match self.by_kind().get("market") {
+            Some((slippage, _)) => SlippageModel {
+                spread: 2.0 * slippage.max(0.0),
+                ..model
+            },
+            None => model,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StateConfig {
+    // directory the state of the strategies is kept in across restarts, a file per strategy and
+    // pair
+    pub directory: PathBuf,
+}
+
+impl Default for StateConfig {
+    fn default() -> StateConfig {
+        StateConfig {
+            directory: PathBuf::from("state"),
+        }
+    }
+}
+
+// State saved by the strategies on stop and restored on start, as JSON.
+#[derive(Debug, Clone)]
+pub struct StateStore {
+    directory: PathBuf,
+}
+
+impl StateStore {
+    pub fn new(directory: &Path) -> StateStore {
+        StateStore {
+            directory: directory.to_path_buf(),
+        }
+    }
+
+    // File of the state of a strategy, named like sma#0, on a pair, named like BTC/EUR.
+    pub fn path(&self, strategy: &str, ticker: &str) -> PathBuf {
+        self.directory
+            .join(format!("{}-{}.json", strategy, ticker.replace('/', "-")))
+    }
+
+    // State saved by a strategy on a pair, None when it saved none.
+    pub fn load(&self, strategy: &str, ticker: &str) -> Result<Option<Value>, String> {
+        let path = self.path(strategy, ticker);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("Could not read {:?}: {:?}", path, error)),
+        };
+        match serde_json::from_str(&content) {
+            Ok(state) => Ok(Some(state)),
+            Err(error) => Err(format!("Could not parse {:?}: {}", path, error)),
+        }
+    }
+
+    // Replace the state of a strategy on a pair, through a temporary file so that a crash while
+    // writing leaves the previous state.
+    pub fn save(&self, strategy: &str, ticker: &str, state: &Value) -> Result<(), String> {
+        if let Err(error) = fs::create_dir_all(&self.directory) {
+            return Err(format!(
+                "Could not create {:?}: {:?}",
+                self.directory, error
+            ));
+        }
+        let path = self.path(strategy, ticker);
+        let temporary = path.with_extension("json.tmp");
+        if let Err(error) = fs::write(&temporary, state.to_string()) {
+            return Err(format!("Could not write {:?}: {:?}", temporary, error));
+        }
+        fs::rename(&temporary, &path)
+            .map_err(|error| format!("Could not write {:?}: {:?}", path, error))
+    }
+}
@@ -0,0 +1,101 @@
+// Arithmetic mean of the values, None when empty.
+pub fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+// Population variance of the values, None when empty.
+pub fn variance(values: &[f64]) -> Option<f64> {
+    let average = mean(values)?;
+    Some(
+        values
+            .iter()
+            .map(|value| (value - average).powi(2))
+            .sum::<f64>()
+            / values.len() as f64,
+    )
+}
+
+pub fn deviation(values: &[f64]) -> Option<f64> {
+    variance(values).map(f64::sqrt)
+}
+
+// Population covariance of two series of the same length.
+pub fn covariance(first: &[f64], second: &[f64]) -> Option<f64> {
+    if first.len() != second.len() {
+        return None;
+    }
+    let first_mean = mean(first)?;
+    let second_mean = mean(second)?;
+    Some(
+        first
+            .iter()
+            .zip(second)
+            .map(|(x, y)| (x - first_mean) * (y - second_mean))
+            .sum::<f64>()
+            / first.len() as f64,
+    )
+}
+
+// Pearson correlation of two series of the same length, None if either series is constant.
+pub fn correlation(first: &[f64], second: &[f64]) -> Option<f64> {
+    let deviations = deviation(first)? * deviation(second)?;
+    if deviations == 0.0 {
+        return None;
+    }
+    Some(covariance(first, second)? / deviations)
+}
+
+// Ordinary least squares fit of y = intercept + slope * x.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearFit {
+    pub intercept: f64,
+    pub slope: f64,
+}
+
+impl LinearFit {
+    pub fn new(x: &[f64], y: &[f64]) -> Option<LinearFit> {
+        let spread = variance(x)?;
+        if spread == 0.0 {
+            return None;
+        }
+        let slope = covariance(x, y)? / spread;
+        let intercept = mean(y)? - slope * mean(x)?;
+        Some(LinearFit { intercept, slope })
+    }
+
+    pub fn predict(&self, x: f64) -> f64 {
+        self.intercept + self.slope * x
+    }
+
+    // Sum of squared residuals of the fit over the points.
+    pub fn residuals(&self, x: &[f64], y: &[f64]) -> f64 {
+        x.iter()
+            .zip(y)
+            .map(|(x, y)| (y - self.predict(*x)).powi(2))
+            .sum()
+    }
+
+    // Share of the variance of y explained by the fit.
+    pub fn r_squared(&self, x: &[f64], y: &[f64]) -> Option<f64> {
+        let total = variance(y)? * y.len() as f64;
+        if total == 0.0 {
+            return None;
+        }
+        Some(1.0 - self.residuals(x, y) / total)
+    }
+}
+
+// Quantile of the values with linear interpolation between closest ranks, q within [0, 1].
+pub fn quantile(values: &[f64], q: f64) -> Option<f64> {
+    if values.is_empty() || !(0.0..=1.0).contains(&q) {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = q * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    Some(sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64))
+}
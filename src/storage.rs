@@ -0,0 +1,170 @@
+use crate::market::Candle;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "time,open,high,low,close,vwap,volume,count";
+
+fn line(candle: &Candle) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{}\n",
+        candle.time,
+        candle.open,
+        candle.high,
+        candle.low,
+        candle.close,
+        candle.vwap,
+        candle.volume,
+        candle.count
+    )
+}
+
+fn parse(line: &str) -> Option<Candle> {
+    let mut fields = line.split(',').map(str::trim);
+    let candle = Candle {
+        time: fields.next()?.parse().ok()?,
+        open: fields.next()?.parse().ok()?,
+        high: fields.next()?.parse().ok()?,
+        low: fields.next()?.parse().ok()?,
+        close: fields.next()?.parse().ok()?,
+        vwap: fields.next()?.parse().ok()?,
+        volume: fields.next()?.parse().ok()?,
+        count: fields.next()?.parse().ok()?,
+    };
+    fields.next().is_none().then_some(candle)
+}
+
+// Candles kept on disk for backtesting, a CSV file per pair and interval in a directory.
+#[derive(Debug, Clone)]
+pub struct CandleStore {
+    directory: PathBuf,
+}
+
+impl CandleStore {
+    pub fn new(directory: &Path) -> CandleStore {
+        CandleStore {
+            directory: directory.to_path_buf(),
+        }
+    }
+
+    // File of the candles of a pair, pairs are named like BTC/EUR and intervals are in min.
+    pub fn path(&self, ticker: &str, interval: i32) -> PathBuf {
+        self.directory
+            .join(format!("{}-{}m.csv", ticker.replace('/', "-"), interval))
+    }
+
+    // Pairs and intervals stored, sorted.
+    pub fn series(&self) -> Result<Vec<(String, i32)>, String> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => {
+                return Err(format!("Could not list {:?}: {:?}", self.directory, error));
+            }
+        };
+        let mut series: Vec<(String, i32)> = entries
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                let (pair, interval) = name.strip_suffix("m.csv")?.rsplit_once('-')?;
+                Some((pair.replace('-', "/"), interval.parse().ok()?))
+            })
+            .collect();
+        series.sort();
+        Ok(series)
+    }
+
+    // Candles of a pair in the order they were stored, none when nothing was stored yet.
+    pub fn load(&self, ticker: &str, interval: i32) -> Result<Vec<Candle>, String> {
+        let path = self.path(ticker, interval);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(format!("Could not read {:?}: {:?}", path, error)),
+        };
+        content
+            .lines()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match parse(line) {
+                Some(candle) => Ok(candle),
+                None => Err(format!("Invalid candle {:?} in {:?}", line, path)),
+            })
+            .collect()
+    }
+
+    // Latest candle stored for a pair.
+    pub fn last(&self, ticker: &str, interval: i32) -> Result<Option<Candle>, String> {
+        Ok(self
+            .load(ticker, interval)?
+            .into_iter()
+            .max_by_key(|candle| candle.time))
+    }
+
+    // Add candles after the stored ones of a pair.
+    pub fn append(&self, ticker: &str, interval: i32, candles: &[Candle]) -> Result<(), String> {
+        if candles.is_empty() {
+            return Ok(());
+        }
+        let path = self.path(ticker, interval);
+        if let Err(error) = fs::create_dir_all(&self.directory) {
+            return Err(format!(
+                "Could not create {:?}: {:?}",
+                self.directory, error
+            ));
+        }
+        let new = !path.exists();
+        let mut file = match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => file,
+            Err(error) => return Err(format!("Could not open {:?}: {:?}", path, error)),
+        };
+        let mut content = if new {
+            format!("{}\n", HEADER)
+        } else {
+            String::new()
+        };
+        content.extend(candles.iter().map(line));
+        match file.write_all(content.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(format!("Could not write {:?}: {:?}", path, error)),
+        }
+    }
+
+    // Replace the candles of a pair. The file is written aside and moved in place so readers
+    // never see a partial series.
+    pub fn write(&self, ticker: &str, interval: i32, candles: &[Candle]) -> Result<(), String> {
+        let path = self.path(ticker, interval);
+        if let Err(error) = fs::create_dir_all(&self.directory) {
+            return Err(format!(
+                "Could not create {:?}: {:?}",
+                self.directory, error
+            ));
+        }
+        let partial = path.with_extension("csv.partial");
+        let mut content = format!("{}\n", HEADER);
+        content.extend(candles.iter().map(line));
+        let written =
+            File::create(&partial).and_then(|mut file| file.write_all(content.as_bytes()));
+        if let Err(error) = written {
+            return Err(format!("Could not write {:?}: {:?}", partial, error));
+        }
+        match fs::rename(&partial, &path) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(format!("Could not move {:?}: {:?}", partial, error)),
+        }
+    }
+
+    // Add candles to the stored ones of a pair, keeping the stored candle where both have one at
+    // the same time. Returns the number of candles added.
+    pub fn merge(&self, ticker: &str, interval: i32, candles: &[Candle]) -> Result<usize, String> {
+        let mut merged = self.load(ticker, interval)?;
+        let stored = merged.len();
+        merged.extend_from_slice(candles);
+        // the sort is stable, stored candles stay ahead of the added ones
+        merged.sort_by_key(|candle| candle.time);
+        merged.dedup_by_key(|candle| candle.time);
+        let added = merged.len().saturating_sub(stored);
+        self.write(ticker, interval, &merged)?;
+        Ok(added)
+    }
+}
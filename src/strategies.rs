@@ -0,0 +1,280 @@
+pub mod dca;
+pub mod ensemble;
+pub mod market_making;
+pub mod pairs;
+pub mod portfolio;
+pub mod regime;
+pub mod scheduled;
+pub mod script;
+pub mod transformed;
+pub mod wasm;
+
+use crate::backtest::Fill;
+use crate::config::StrategyConfig;
+use crate::execution::Order;
+use crate::market::Candle;
+use crate::strategies::dca::Dca;
+use crate::strategies::ensemble::Ensemble;
+use crate::strategies::pairs::PairsTrading;
+use crate::strategies::portfolio::Portfolio;
+use crate::strategies::regime::RegimeSwitch;
+use crate::strategies::scheduled::Scheduled;
+use crate::strategies::script::ScriptStrategy;
+use crate::strategies::transformed::Transformed;
+use crate::strategies::wasm::WasmStrategy;
+
+use serde_json::Value;
+
+use std::collections::HashMap;
+
+pub trait Strategy {
+    // Called once before the first candle with the latest candles of the followed tickers, oldest
+    // first, e.g. to warm up indicators.
+    fn on_start(&mut self, _history: &HashMap<String, Vec<Candle>>) {}
+
+    // Feed a finalized candle for the given ticker and return the orders to place. Orders
+    // returned together are meant to be executed simultaneously.
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order>;
+
+    // Called with the fills of the orders the strategy placed, returning further orders.
+    fn on_fill(&mut self, _fill: &Fill) -> Vec<Order> {
+        Vec::new()
+    }
+
+    // Period on_timer is called at (in s), None without timer.
+    fn timer(&self) -> Option<i64> {
+        None
+    }
+
+    // Called every timer period with the current unix time (in s), returning orders to place.
+    fn on_timer(&mut self, _time: i64) -> Vec<Order> {
+        Vec::new()
+    }
+
+    // Called once when the strategy is stopped, the orders returned are placed before it stops,
+    // e.g. to close its positions.
+    fn on_stop(&mut self) -> Vec<Order> {
+        Vec::new()
+    }
+
+    // Whether the indicators the strategy trades the ticker on are warm, orders returned before
+    // are not acted on.
+    fn ready(&self, _ticker: &str) -> bool {
+        true
+    }
+
+    // State on a ticker to keep across restarts, saved once stopped. None when there is nothing
+    // to keep.
+    fn state(&self, _ticker: &str) -> Option<Value> {
+        None
+    }
+
+    // Take back the state saved on a ticker before the previous stop, called after on_start.
+    fn restore(&mut self, _ticker: &str, _state: Value) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl Strategy for Box<dyn Strategy + Send> {
+    fn on_start(&mut self, history: &HashMap<String, Vec<Candle>>) {
+        (**self).on_start(history)
+    }
+
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        (**self).on_candle(ticker, candle)
+    }
+
+    fn on_fill(&mut self, fill: &Fill) -> Vec<Order> {
+        (**self).on_fill(fill)
+    }
+
+    fn timer(&self) -> Option<i64> {
+        (**self).timer()
+    }
+
+    fn on_timer(&mut self, time: i64) -> Vec<Order> {
+        (**self).on_timer(time)
+    }
+
+    fn on_stop(&mut self) -> Vec<Order> {
+        (**self).on_stop()
+    }
+
+    fn ready(&self, ticker: &str) -> bool {
+        (**self).ready(ticker)
+    }
+
+    fn state(&self, ticker: &str) -> Option<Value> {
+        (**self).state(ticker)
+    }
+
+    fn restore(&mut self, ticker: &str, state: Value) -> Result<(), String> {
+        (**self).restore(ticker, state)
+    }
+}
+
+// Volume under which a position is flat.
+const FLAT: f64 = 1e-9;
+
+// Net volume per ticker of the orders a wrapper passed on for the strategies it wraps, telling the
+// orders closing the positions they took from those opening new ones.
+#[derive(Debug, Clone, Default)]
+pub struct Exposure(HashMap<String, f64>);
+
+impl Exposure {
+    pub fn flat(&self) -> bool {
+        self.0.values().all(|volume| volume.abs() < FLAT)
+    }
+
+    // Record an order passed on.
+    pub fn pass(&mut self, order: Order) -> Order {
+        *self.0.entry(order.ticker.clone()).or_default() += order.side.sign() * order.volume;
+        order
+    }
+
+    // Part of an order reducing the position held on its ticker, recorded as passed on, None when
+    // it would open or add to a position.
+    pub fn reduce(&mut self, mut order: Order) -> Option<Order> {
+        let held = self.0.get(&order.ticker).copied().unwrap_or(0.0);
+        let reducible = -order.side.sign() * held;
+        if reducible < FLAT {
+            return None;
+        }
+        order.volume = order.volume.min(reducible);
+        Some(self.pass(order))
+    }
+}
+
+// Timers of the strategies a composite strategy runs, the composite one firing at the greatest
+// common divisor of their periods.
+#[derive(Debug, Clone, Default)]
+pub struct Timers {
+    // period and next time of the timer of each strategy (in s)
+    timers: Vec<Option<(i64, Option<i64>)>>,
+}
+
+fn gcd(first: i64, second: i64) -> i64 {
+    match second {
+        0 => first,
+        _ => gcd(second, first % second),
+    }
+}
+
+impl Timers {
+    // Timers of periods given in the order of the strategies.
+    pub fn new(periods: impl Iterator<Item = Option<i64>>) -> Timers {
+        Timers {
+            timers: periods
+                .map(|period| Some((period.filter(|period| *period > 0)?, None)))
+                .collect(),
+        }
+    }
+
+    pub fn period(&self) -> Option<i64> {
+        self.timers
+            .iter()
+            .flatten()
+            .map(|(period, _)| *period)
+            .reduce(gcd)
+    }
+
+    // Positions of the strategies whose timer is due at a unix time (in s), the first call
+    // coming a composite period after the start.
+    pub fn due(&mut self, time: i64) -> Vec<usize> {
+        let Some(step) = self.period() else {
+            return Vec::new();
+        };
+        let mut due = Vec::new();
+        for (position, timer) in self.timers.iter_mut().enumerate() {
+            let Some((period, next)) = timer else {
+                continue;
+            };
+            let next = next.get_or_insert(time - step + *period);
+            if time >= *next {
+                *next += *period * ((time - *next) / *period + 1);
+                due.push(position);
+            }
+        }
+        due
+    }
+}
+
+pub fn build(config: &StrategyConfig) -> Result<Box<dyn Strategy + Send>, String> {
+    match config {
+        StrategyConfig::Pairs {
+            dependent,
+            independent,
+            window,
+            entry,
+            exit,
+            volume,
+            min_correlation,
+        } => Ok(Box::new(
+            PairsTrading::new(
+                dependent.clone(),
+                independent.clone(),
+                *window,
+                *entry,
+                *exit,
+                *volume,
+            )?
+            .with_min_correlation(*min_correlation),
+        )),
+        StrategyConfig::Script { path, capacity } => {
+            Ok(Box::new(ScriptStrategy::new(path.clone(), *capacity)?))
+        }
+        StrategyConfig::Wasm { path, tickers } => {
+            Ok(Box::new(WasmStrategy::new(path, tickers.clone())?))
+        }
+        StrategyConfig::Regime {
+            window,
+            low,
+            high,
+            trending,
+            mean_reverting,
+        } => Ok(Box::new(RegimeSwitch::new(
+            *window,
+            *low,
+            *high,
+            trending.iter().map(build).collect::<Result<_, _>>()?,
+            mean_reverting.iter().map(build).collect::<Result<_, _>>()?,
+        ))),
+        StrategyConfig::Transformed { view, strategy } => {
+            Ok(Box::new(Transformed::new(view.clone(), build(strategy)?)))
+        }
+        StrategyConfig::Scheduled { hours, strategy } => {
+            Ok(Box::new(Scheduled::new(*hours, build(strategy)?)))
+        }
+        StrategyConfig::Dca {
+            ticker,
+            amount,
+            hours,
+        } => Ok(Box::new(Dca::new(ticker.clone(), *amount, *hours)?)),
+        StrategyConfig::MarketMaking { ticker, .. } => Err(format!(
+            "Market maker on {} only quotes on its own, it cannot be combined",
+            ticker
+        )),
+        StrategyConfig::Portfolio {
+            allocation,
+            members,
+        } => Ok(Box::new(Portfolio::new(
+            *allocation,
+            members
+                .iter()
+                .map(|member| Ok((member.weight, build(&member.strategy)?)))
+                .collect::<Result<_, String>>()?,
+        ))),
+        StrategyConfig::Ensemble {
+            rule,
+            memory,
+            voters,
+        } => Ok(Box::new(Ensemble::new(
+            *rule,
+            *memory,
+            voters
+                .iter()
+                .map(|voter| Ok((voter.weight, voter.veto, build(&voter.strategy)?)))
+                .collect::<Result<_, String>>()?,
+        ))),
+    }
+}
@@ -0,0 +1,68 @@
+use crate::execution::{Order, Side};
+use crate::market::Candle;
+use crate::sessions::TradingHours;
+use crate::strategies::Strategy;
+
+use tracing::{debug, warn};
+
+// Time between checks of the schedule (in s).
+const CHECK: i64 = 60;
+
+// Dollar cost averaging: buys a fixed amount of quote currency worth of a ticker at every opening
+// of trading hours, e.g. every weekday at 09:00 UTC, at the latest close seen.
+pub struct Dca {
+    ticker: String,
+    // quote currency spent per purchase
+    amount: f64,
+    hours: TradingHours,
+    // latest close of the ticker
+    price: Option<f64>,
+    // unix time (in s) of the next purchase
+    next: Option<i64>,
+}
+
+impl Dca {
+    pub fn new(ticker: String, amount: f64, hours: TradingHours) -> Result<Dca, String> {
+        if amount <= 0.0 {
+            return Err(format!("Invalid DCA amount {:?}", amount));
+        }
+        Ok(Dca {
+            ticker,
+            amount,
+            hours,
+            price: None,
+            next: None,
+        })
+    }
+}
+
+impl Strategy for Dca {
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        if ticker == self.ticker {
+            self.price = Some(candle.close);
+        }
+        Vec::new()
+    }
+
+    fn timer(&self) -> Option<i64> {
+        Some(CHECK)
+    }
+
+    // the first purchase is at the first opening after the start, none is caught up on
+    fn on_timer(&mut self, time: i64) -> Vec<Order> {
+        // no purchase when the hours never open
+        let next = *self
+            .next
+            .get_or_insert_with(|| self.hours.next_open(time).unwrap_or(i64::MAX));
+        if time < next {
+            return Vec::new();
+        }
+        self.next = Some(self.hours.next_open(time + 1).unwrap_or(i64::MAX));
+        let Some(price) = self.price.filter(|price| *price > 0.0) else {
+            warn!("No price of {}, purchase skipped", self.ticker);
+            return Vec::new();
+        };
+        debug!("Buying {} of {} at {}", self.amount, self.ticker, price);
+        vec![Order::market(&self.ticker, Side::Buy, self.amount / price)]
+    }
+}
@@ -0,0 +1,201 @@
+use crate::backtest::Fill;
+use crate::execution::{Order, Side};
+use crate::market::Candle;
+use crate::strategies::{Strategy, Timers};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+// Rule deciding whether the votes on a ticker lead to an order.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Rule {
+    // more than half of the strategies vote for the side
+    Majority,
+    // every strategy votes for the side
+    Unanimous,
+    // the weights of the strategies voting for the side make up at least a fraction of the total
+    Weighted { threshold: f64 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Vote {
+    side: Side,
+    volume: f64,
+    // candle of the ticker the vote was cast on
+    cast: usize,
+}
+
+struct Voter {
+    weight: f64,
+    // votes against the side block it whatever the rule
+    veto: bool,
+    strategy: Box<dyn Strategy + Send>,
+    votes: HashMap<String, Vote>,
+}
+
+// Combines strategies trading the same tickers into one that only trades when enough of them
+// agree. The orders of each strategy on a ticker count as a vote for the side of their net volume,
+// valid for a number of candles so that signals need not fire on the very same candle. Once
+// carried, the votes are spent and a market order for the weighted average volume of the
+// agreeing strategies is placed. The orders of the strategies on fills, timers and stops are
+// voted on alike.
+pub struct Ensemble {
+    rule: Rule,
+    // candles a vote stays valid for
+    memory: usize,
+    voters: Vec<Voter>,
+    // candles seen per ticker
+    candles: HashMap<String, usize>,
+    timers: Timers,
+}
+
+impl Ensemble {
+    // Strategies along with their weights and whether they have a veto.
+    pub fn new(
+        rule: Rule,
+        memory: usize,
+        voters: Vec<(f64, bool, Box<dyn Strategy + Send>)>,
+    ) -> Ensemble {
+        let timers = Timers::new(voters.iter().map(|(_, _, strategy)| strategy.timer()));
+        Ensemble {
+            rule,
+            memory: memory.max(1),
+            voters: voters
+                .into_iter()
+                .map(|(weight, veto, strategy)| Voter {
+                    weight: weight.max(0.0),
+                    veto,
+                    strategy,
+                    votes: HashMap::new(),
+                })
+                .collect(),
+            candles: HashMap::new(),
+            timers,
+        }
+    }
+
+    // Side carried on a ticker by the currently valid votes, if any.
+    fn decide(&self, ticker: &str) -> Option<Side> {
+        let now = self.candles.get(ticker).copied().unwrap_or(0);
+        let valid: Vec<(&Voter, Vote)> = self
+            .voters
+            .iter()
+            .filter_map(|voter| {
+                let vote = *voter.votes.get(ticker)?;
+                (now - vote.cast < self.memory).then_some((voter, vote))
+            })
+            .collect();
+
+        [Side::Buy, Side::Sell].into_iter().find(|side| {
+            let (count, weight) = valid
+                .iter()
+                .filter(|(_, vote)| vote.side == *side)
+                .fold((0, 0.0), |(count, weight), (voter, _)| {
+                    (count + 1, weight + voter.weight)
+                });
+            let vetoed = valid
+                .iter()
+                .any(|(voter, vote)| voter.veto && vote.side != *side);
+            let total: f64 = self.voters.iter().map(|voter| voter.weight).sum();
+            let carried = match self.rule {
+                Rule::Majority => 2 * count > self.voters.len(),
+                Rule::Unanimous => count == self.voters.len(),
+                Rule::Weighted { threshold } => total > 0.0 && weight / total >= threshold,
+            };
+            count > 0 && carried && !vetoed
+        })
+    }
+
+    // Count the orders of the voters, given by their position, as votes and place the orders
+    // carried on the tickers voted on.
+    fn tally(
+        &mut self,
+        mut signals: impl FnMut(usize, &mut Box<dyn Strategy + Send>) -> Vec<Order>,
+    ) -> Vec<Order> {
+        let mut voted = Vec::new();
+        for (position, voter) in self.voters.iter_mut().enumerate() {
+            let mut volumes: HashMap<String, f64> = HashMap::new();
+            for order in signals(position, &mut voter.strategy) {
+                *volumes.entry(order.ticker).or_default() += order.side.sign() * order.volume;
+            }
+            for (voted_ticker, volume) in volumes {
+                if volume == 0.0 {
+                    continue;
+                }
+                let side = if volume > 0.0 { Side::Buy } else { Side::Sell };
+                let cast = self.candles.get(&voted_ticker).copied().unwrap_or(0);
+                voter.votes.insert(
+                    voted_ticker.clone(),
+                    Vote {
+                        side,
+                        volume: volume.abs(),
+                        cast,
+                    },
+                );
+                if !voted.contains(&voted_ticker) {
+                    voted.push(voted_ticker);
+                }
+            }
+        }
+
+        let mut orders = Vec::new();
+        for voted_ticker in voted {
+            let Some(side) = self.decide(&voted_ticker) else {
+                continue;
+            };
+            let (mut volume, mut weight) = (0.0, 0.0);
+            for voter in self.voters.iter_mut() {
+                let Some(vote) = voter.votes.remove(&voted_ticker) else {
+                    continue;
+                };
+                if vote.side == side {
+                    volume += voter.weight * vote.volume;
+                    weight += voter.weight;
+                }
+            }
+            if weight > 0.0 {
+                orders.push(Order::market(&voted_ticker, side, volume / weight));
+            }
+        }
+        orders
+    }
+}
+
+impl Strategy for Ensemble {
+    fn on_start(&mut self, history: &HashMap<String, Vec<Candle>>) {
+        for voter in self.voters.iter_mut() {
+            voter.strategy.on_start(history);
+        }
+    }
+
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        *self.candles.entry(ticker.to_string()).or_default() += 1;
+        self.tally(|_, strategy| strategy.on_candle(ticker, candle))
+    }
+
+    fn on_fill(&mut self, fill: &Fill) -> Vec<Order> {
+        self.tally(|_, strategy| strategy.on_fill(fill))
+    }
+
+    fn timer(&self) -> Option<i64> {
+        self.timers.period()
+    }
+
+    fn on_timer(&mut self, time: i64) -> Vec<Order> {
+        let due = self.timers.due(time);
+        self.tally(|position, strategy| match due.contains(&position) {
+            true => strategy.on_timer(time),
+            false => Vec::new(),
+        })
+    }
+
+    fn on_stop(&mut self) -> Vec<Order> {
+        self.tally(|_, strategy| strategy.on_stop())
+    }
+
+    fn ready(&self, ticker: &str) -> bool {
+        self.voters.iter().all(|voter| voter.strategy.ready(ticker))
+    }
+}
@@ -0,0 +1,183 @@
+use crate::book::OrderBook;
+use crate::execution::{Executor, Order, Side};
+
+use tracing::info;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Quote {
+    // identifier attributed by the executor
+    id: String,
+    price: f64,
+    // volume left to fill
+    volume: f64,
+}
+
+// Quotes both sides of a ticker around the book mid-price. The reservation price the quotes are
+// centered on is skewed against the held inventory so that fills tend to bring it back to zero,
+// and the side increasing the inventory stops being quoted once the inventory limit is reached.
+pub struct MarketMaker {
+    ticker: String,
+
+    // distance of the quotes to the reservation price as a fraction of mid-price
+    half_spread: f64,
+    // shift of the reservation price per unit of inventory as a fraction of mid-price
+    skew: f64,
+    // volume quoted on each side
+    volume: f64,
+    // absolute inventory beyond which the side increasing it is pulled
+    max_inventory: f64,
+    // relative price change required before a resting quote is replaced
+    tolerance: f64,
+
+    // base asset volume held, negative when short
+    inventory: f64,
+
+    bid: Option<Quote>,
+    ask: Option<Quote>,
+}
+
+impl MarketMaker {
+    pub fn new(
+        ticker: String,
+        half_spread: f64,
+        skew: f64,
+        volume: f64,
+        max_inventory: f64,
+        tolerance: f64,
+    ) -> MarketMaker {
+        MarketMaker {
+            ticker,
+            half_spread,
+            skew,
+            volume,
+            max_inventory,
+            tolerance,
+            inventory: 0.0,
+            bid: None,
+            ask: None,
+        }
+    }
+
+    pub fn ticker(&self) -> &str {
+        &self.ticker
+    }
+
+    pub fn inventory(&self) -> f64 {
+        self.inventory
+    }
+
+    // Prices at which to quote each side given the current book, None for a side that should not
+    // be quoted. Quotes never cross the opposite best level.
+    pub fn quotes(&self, book: &OrderBook) -> (Option<f64>, Option<f64>) {
+        let (Some(mid), Some(best_bid), Some(best_ask)) =
+            (book.mid(), book.best_bid(), book.best_ask())
+        else {
+            return (None, None);
+        };
+
+        let reservation = mid * (1.0 - self.skew * self.inventory);
+        let bid = (reservation * (1.0 - self.half_spread)).min(best_bid.price);
+        let ask = (reservation * (1.0 + self.half_spread)).max(best_ask.price);
+
+        (
+            (self.inventory < self.max_inventory).then_some(bid),
+            (self.inventory > -self.max_inventory).then_some(ask),
+        )
+    }
+
+    // Bring resting quotes in line with the book, cancelling and replacing the ones that moved
+    // beyond the tolerance. A side that could not be requoted keeps its quote tracked, the other
+    // side is handled regardless.
+    pub async fn on_book<E: Executor>(
+        &mut self,
+        book: &OrderBook,
+        executor: &E,
+    ) -> Result<(), String> {
+        let (bid, ask) = self.quotes(book);
+        let mut failures = Vec::new();
+        for (side, target) in [(Side::Buy, bid), (Side::Sell, ask)] {
+            if let Err(message) = self.requote(executor, side, target).await {
+                failures.push(format!("{:?} side: {}", side, message));
+            }
+        }
+        failed(failures)
+    }
+
+    fn slot(&mut self, side: Side) -> &mut Option<Quote> {
+        match side {
+            Side::Buy => &mut self.bid,
+            Side::Sell => &mut self.ask,
+        }
+    }
+
+    async fn requote<E: Executor>(
+        &mut self,
+        executor: &E,
+        side: Side,
+        target: Option<f64>,
+    ) -> Result<(), String> {
+        if let Some(quote) = self.slot(side).clone() {
+            match target {
+                Some(price) if (quote.price - price).abs() <= self.tolerance * price => {
+                    return Ok(());
+                }
+                _ => executor.cancel(&quote.id).await?,
+            }
+            *self.slot(side) = None;
+        }
+
+        let Some(price) = target else {
+            return Ok(());
+        };
+        let order = Order::limit(&self.ticker, side, self.volume, price);
+        let id = executor.submit(&order).await?;
+        info!(
+            "Quoting {:?} {} {} @ {}",
+            side, self.volume, self.ticker, price
+        );
+        *self.slot(side) = Some(Quote {
+            id,
+            price,
+            volume: self.volume,
+        });
+        Ok(())
+    }
+
+    // Account for a fill on one of the resting quotes, returns whether the order was one of them.
+    pub fn on_fill(&mut self, id: &str, volume: f64) -> bool {
+        for (side, slot) in [(Side::Buy, &mut self.bid), (Side::Sell, &mut self.ask)] {
+            if let Some(quote) = slot.as_mut().filter(|quote| quote.id == id) {
+                self.inventory += side.sign() * volume;
+                quote.volume -= volume;
+                if quote.volume <= 0.0 {
+                    *slot = None;
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    // Cancel all resting quotes, those that could not be cancelled stay tracked.
+    pub async fn withdraw<E: Executor>(&mut self, executor: &E) -> Result<(), String> {
+        let mut failures = Vec::new();
+        for side in [Side::Buy, Side::Sell] {
+            let Some(quote) = self.slot(side).clone() else {
+                continue;
+            };
+            match executor.cancel(&quote.id).await {
+                Ok(()) => *self.slot(side) = None,
+                Err(message) => failures.push(format!("{:?} side: {}", side, message)),
+            }
+        }
+        failed(failures)
+    }
+}
+
+fn failed(failures: Vec<String>) -> Result<(), String> {
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join(", "))
+    }
+}
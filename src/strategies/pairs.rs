@@ -0,0 +1,241 @@
+use crate::backtest::Fill;
+use crate::execution::{Order, Side};
+use crate::market::Candle;
+use crate::statistics::{LinearFit, correlation, deviation, mean};
+use crate::strategies::Strategy;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tracing::info;
+
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+enum Position {
+    Flat,
+    // legs held, closing them means offsetting each one
+    Open(Vec<Order>),
+}
+
+// Statistical arbitrage between two cointegrated tickers. The hedge ratio is the OLS slope of the
+// dependent ticker's closes against the independent ticker's over a rolling window, and the
+// spread `dependent - ratio * independent` is traded on its z-score.
+pub struct PairsTrading {
+    // ticker regressed on the independent one
+    dependent: String,
+    independent: String,
+
+    // number of synchronized closes used for the regression and the spread statistics
+    window: usize,
+
+    // absolute z-score above which a position is opened
+    entry: f64,
+    // absolute z-score below which an open position is closed
+    exit: f64,
+    // minimal correlation between the legs for a position to be opened
+    min_correlation: f64,
+
+    // volume traded on the dependent leg, the independent leg is scaled by the hedge ratio
+    volume: f64,
+
+    // last closes received but not yet matched with the other leg
+    pending: (Option<Candle>, Option<Candle>),
+    closes: (VecDeque<f64>, VecDeque<f64>),
+
+    position: Position,
+    // volume of the legs of the open position executed so far per ticker, unknown after a restart
+    executed: HashMap<String, f64>,
+}
+
+impl PairsTrading {
+    pub fn new(
+        dependent: String,
+        independent: String,
+        window: usize,
+        entry: f64,
+        exit: f64,
+        volume: f64,
+    ) -> Result<PairsTrading, String> {
+        if window < 2 {
+            return Err("Pairs trading window needs at least two candles.".into());
+        }
+        if exit >= entry {
+            return Err(format!(
+                "Exit z-score ({}) must be lower than entry z-score ({}).",
+                exit, entry
+            ));
+        }
+        Ok(PairsTrading {
+            dependent,
+            independent,
+            window,
+            entry,
+            exit,
+            min_correlation: 0.0,
+            volume,
+            pending: (None, None),
+            closes: (
+                VecDeque::with_capacity(window),
+                VecDeque::with_capacity(window),
+            ),
+            position: Position::Flat,
+            executed: HashMap::new(),
+        })
+    }
+
+    pub fn with_min_correlation(mut self, min_correlation: f64) -> PairsTrading {
+        self.min_correlation = min_correlation;
+        self
+    }
+
+    // Z-score of the latest spread value along with the hedge ratio it was computed with.
+    pub fn z_score(&self) -> Option<(f64, f64)> {
+        if self.closes.0.len() < self.window {
+            return None;
+        }
+        let (dependent, independent) = self.series();
+        let fit = LinearFit::new(&independent, &dependent)?;
+        let spread: Vec<f64> = dependent
+            .iter()
+            .zip(&independent)
+            .map(|(y, x)| y - fit.slope * x)
+            .collect();
+        let spread_deviation = deviation(&spread)?;
+        if spread_deviation == 0.0 {
+            return None;
+        }
+        let last = *spread.last()?;
+        Some(((last - mean(&spread)?) / spread_deviation, fit.slope))
+    }
+
+    fn series(&self) -> (Vec<f64>, Vec<f64>) {
+        (
+            self.closes.0.iter().copied().collect(),
+            self.closes.1.iter().copied().collect(),
+        )
+    }
+
+    fn correlated(&self) -> bool {
+        let (dependent, independent) = self.series();
+        correlation(&dependent, &independent)
+            .is_some_and(|value| value.abs() >= self.min_correlation)
+    }
+
+    // Legs going long the spread when `side` is Buy, short otherwise.
+    fn legs(&self, side: Side, ratio: f64) -> Vec<Order> {
+        let hedge = if ratio >= 0.0 { side.opposite() } else { side };
+        vec![
+            Order::market(&self.dependent, side, self.volume),
+            Order::market(&self.independent, hedge, self.volume * ratio.abs()),
+        ]
+    }
+
+    // Move pending closes to the rolling windows once both legs delivered the same candle.
+    fn record(&mut self) -> bool {
+        let (Some(dependent), Some(independent)) = (&self.pending.0, &self.pending.1) else {
+            return false;
+        };
+        if dependent.time != independent.time {
+            return false;
+        }
+        if self.closes.0.len() == self.window {
+            self.closes.0.pop_front();
+            self.closes.1.pop_front();
+        }
+        self.closes.0.push_back(dependent.close);
+        self.closes.1.push_back(independent.close);
+        self.pending = (None, None);
+        true
+    }
+}
+
+impl Strategy for PairsTrading {
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        if ticker == self.dependent {
+            self.pending.0 = Some(*candle);
+        } else if ticker == self.independent {
+            self.pending.1 = Some(*candle);
+        } else {
+            return Vec::new();
+        }
+        if !self.record() {
+            return Vec::new();
+        }
+
+        let Some((z_score, ratio)) = self.z_score() else {
+            return Vec::new();
+        };
+
+        match &self.position {
+            Position::Flat if z_score.abs() >= self.entry && self.correlated() => {
+                // a stretched spread is expected to revert, sell it when high and buy it when low
+                let side = if z_score > 0.0 { Side::Sell } else { Side::Buy };
+                let legs = self.legs(side, ratio);
+                info!(
+                    "Opening {:?} spread {}/{} at z-score {:.2} with ratio {:.4}",
+                    side, self.dependent, self.independent, z_score, ratio
+                );
+                self.position = Position::Open(legs.clone());
+                legs
+            }
+            Position::Open(legs) if z_score.abs() <= self.exit => {
+                info!(
+                    "Closing spread {}/{} at z-score {:.2}",
+                    self.dependent, self.independent, z_score
+                );
+                // the legs are closed as far as they were executed, when known
+                let offsets = legs
+                    .iter()
+                    .map(|leg| match self.executed.is_empty() {
+                        true => leg.offset(),
+                        false => Order {
+                            volume: self.executed.get(&leg.ticker).copied().unwrap_or(0.0),
+                            ..leg.offset()
+                        },
+                    })
+                    .filter(|offset| offset.volume > 0.0)
+                    .collect();
+                self.position = Position::Flat;
+                self.executed.clear();
+                offsets
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn on_fill(&mut self, fill: &Fill) -> Vec<Order> {
+        if let Position::Open(legs) = &self.position
+            && legs
+                .iter()
+                .any(|leg| leg.ticker == fill.ticker && leg.side == fill.side)
+        {
+            *self.executed.entry(fill.ticker.clone()).or_default() += fill.volume;
+        }
+        Vec::new()
+    }
+
+    fn ready(&self, _ticker: &str) -> bool {
+        self.closes.0.len() >= self.window
+    }
+
+    // the open legs are kept so that they are closed after a restart, saved on the dependent
+    // ticker
+    fn state(&self, ticker: &str) -> Option<Value> {
+        if ticker != self.dependent {
+            return None;
+        }
+        serde_json::to_value(&self.position).ok()
+    }
+
+    fn restore(&mut self, ticker: &str, state: Value) -> Result<(), String> {
+        if ticker != self.dependent {
+            return Ok(());
+        }
+        self.position = match serde_json::from_value(state) {
+            Ok(position) => position,
+            Err(error) => return Err(format!("Invalid pairs position: {}", error)),
+        };
+        Ok(())
+    }
+}
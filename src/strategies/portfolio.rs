@@ -0,0 +1,192 @@
+use crate::backtest::Fill;
+use crate::execution::{Order, OrderKind, net};
+use crate::market::Candle;
+use crate::statistics::deviation;
+use crate::strategies::{Strategy, Timers};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, VecDeque};
+
+// How capital is split among the strategies of a portfolio.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Allocation {
+    // in proportion to the configured weights
+    Fixed,
+    // in proportion to the configured weights over the deviation of each strategy's profit and
+    // loss per candle over a window, so that every strategy contributes similar risk
+    InverseVolatility { window: usize },
+}
+
+struct Member {
+    weight: f64,
+    strategy: Box<dyn Strategy + Send>,
+
+    // hypothetical book of the strategy's unscaled orders, filled at their limit or the latest
+    // close, to measure its volatility
+    cash: f64,
+    positions: HashMap<String, f64>,
+    equity: Option<f64>,
+    pnls: VecDeque<f64>,
+}
+
+impl Member {
+    fn mark(&mut self, prices: &HashMap<String, f64>, window: usize) {
+        let equity = self.cash
+            + self
+                .positions
+                .iter()
+                .map(|(ticker, position)| position * prices.get(ticker).copied().unwrap_or(0.0))
+                .sum::<f64>();
+        if let Some(previous) = self.equity.replace(equity) {
+            if self.pnls.len() == window {
+                self.pnls.pop_front();
+            }
+            self.pnls.push_back(equity - previous);
+        }
+    }
+
+    fn book(&mut self, orders: &[Order], prices: &HashMap<String, f64>) {
+        for order in orders {
+            let price = match order.kind {
+                OrderKind::Limit(price) => Some(price),
+                OrderKind::Market => prices.get(&order.ticker).copied(),
+            };
+            let Some(price) = price else {
+                continue;
+            };
+            let signed = order.side.sign() * order.volume;
+            *self.positions.entry(order.ticker.clone()).or_default() += signed;
+            self.cash -= signed * price;
+        }
+    }
+}
+
+// Runs several strategies as one, each sizing its orders as if it had all of the capital. Their
+// orders are scaled down to the share of capital allocated to them and netted before execution,
+// so that opposing signals do not pay fees to cancel out.
+pub struct Portfolio {
+    allocation: Allocation,
+    members: Vec<Member>,
+    // latest close per ticker
+    prices: HashMap<String, f64>,
+    timers: Timers,
+}
+
+impl Portfolio {
+    // Strategies along with their weights.
+    pub fn new(allocation: Allocation, members: Vec<(f64, Box<dyn Strategy + Send>)>) -> Portfolio {
+        let timers = Timers::new(members.iter().map(|(_, strategy)| strategy.timer()));
+        Portfolio {
+            allocation,
+            members: members
+                .into_iter()
+                .map(|(weight, strategy)| Member {
+                    weight: weight.max(0.0),
+                    strategy,
+                    cash: 0.0,
+                    positions: HashMap::new(),
+                    equity: None,
+                    pnls: VecDeque::new(),
+                })
+                .collect(),
+            prices: HashMap::new(),
+            timers,
+        }
+    }
+
+    // Share of capital of each strategy, falling back to the configured weights until every
+    // strategy has a volatility.
+    pub fn shares(&self) -> Vec<f64> {
+        let fixed: Vec<f64> = self.members.iter().map(|member| member.weight).collect();
+        let weights = match self.allocation {
+            Allocation::Fixed => fixed,
+            Allocation::InverseVolatility { .. } => self
+                .members
+                .iter()
+                .map(|member| {
+                    let pnls: Vec<f64> = member.pnls.iter().copied().collect();
+                    deviation(&pnls)
+                        .filter(|deviation| *deviation > 0.0)
+                        .map(|deviation| member.weight / deviation)
+                })
+                .collect::<Option<Vec<f64>>>()
+                .unwrap_or(fixed),
+        };
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return vec![0.0; weights.len()];
+        }
+        weights.iter().map(|weight| weight / total).collect()
+    }
+
+    // Scale the orders the members give, by their position, to their share of capital and net
+    // them.
+    fn allocate(
+        &mut self,
+        mut signals: impl FnMut(usize, &mut Box<dyn Strategy + Send>) -> Vec<Order>,
+    ) -> Vec<Order> {
+        let shares = self.shares();
+        let mut orders = Vec::new();
+        for (position, (member, share)) in self.members.iter_mut().zip(shares).enumerate() {
+            let signals = signals(position, &mut member.strategy);
+            member.book(&signals, &self.prices);
+            orders.extend(
+                signals
+                    .into_iter()
+                    .map(|order| Order {
+                        volume: order.volume * share,
+                        ..order
+                    })
+                    .filter(|order| order.volume > 0.0),
+            );
+        }
+        net(orders)
+    }
+}
+
+impl Strategy for Portfolio {
+    fn on_start(&mut self, history: &HashMap<String, Vec<Candle>>) {
+        for member in self.members.iter_mut() {
+            member.strategy.on_start(history);
+        }
+    }
+
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        self.prices.insert(ticker.to_string(), candle.close);
+        if let Allocation::InverseVolatility { window } = self.allocation {
+            for member in self.members.iter_mut() {
+                member.mark(&self.prices, window.max(2));
+            }
+        }
+
+        self.allocate(|_, strategy| strategy.on_candle(ticker, candle))
+    }
+
+    fn on_fill(&mut self, fill: &Fill) -> Vec<Order> {
+        self.allocate(|_, strategy| strategy.on_fill(fill))
+    }
+
+    fn timer(&self) -> Option<i64> {
+        self.timers.period()
+    }
+
+    fn on_timer(&mut self, time: i64) -> Vec<Order> {
+        let due = self.timers.due(time);
+        self.allocate(|position, strategy| match due.contains(&position) {
+            true => strategy.on_timer(time),
+            false => Vec::new(),
+        })
+    }
+
+    fn on_stop(&mut self) -> Vec<Order> {
+        self.allocate(|_, strategy| strategy.on_stop())
+    }
+
+    fn ready(&self, ticker: &str) -> bool {
+        self.members
+            .iter()
+            .all(|member| member.strategy.ready(ticker))
+    }
+}
@@ -0,0 +1,195 @@
+use crate::backtest::Fill;
+use crate::execution::Order;
+use crate::indicators::Indicator;
+use crate::indicators::hurst::{Behaviour, Hurst};
+use crate::market::Candle;
+use crate::strategies::{Exposure, Strategy, Timers};
+
+use tracing::info;
+
+use std::collections::HashMap;
+
+// Strategies switched on and off together, along with the positions their orders took.
+#[derive(Default)]
+struct Family {
+    strategies: Vec<Box<dyn Strategy + Send>>,
+    exposure: Exposure,
+}
+
+impl Family {
+    // Orders the strategies give, by their position, only those closing positions on the tickers
+    // the family is not active on.
+    fn poll(
+        &mut self,
+        mut signals: impl FnMut(usize, &mut Box<dyn Strategy + Send>) -> Vec<Order>,
+        active: impl Fn(&str) -> bool,
+    ) -> Vec<Order> {
+        let mut orders = Vec::new();
+        for (position, strategy) in self.strategies.iter_mut().enumerate() {
+            for order in signals(position, strategy) {
+                if active(&order.ticker) {
+                    orders.push(self.exposure.pass(order));
+                } else if let Some(order) = self.exposure.reduce(order) {
+                    orders.push(order);
+                }
+            }
+        }
+        orders
+    }
+}
+
+// Switches between strategy families following the Hurst exponent of each ticker: trend
+// following strategies trade trending tickers and mean reversion strategies the mean reverting
+// ones, neither trades a random walk. Only the active family sees the candles, so that no
+// strategy believes in positions never taken. A family switched off while holding positions keeps
+// seeing them until it closed them, only its orders reducing them are passed on. Fills and timers
+// go to the families active on some ticker or holding positions alike.
+pub struct RegimeSwitch {
+    window: usize,
+    low: f64,
+    high: f64,
+
+    trending: Family,
+    mean_reverting: Family,
+
+    // estimator per ticker
+    hurst: HashMap<String, Hurst>,
+    // of the trending strategies followed by the mean reverting ones
+    timers: Timers,
+}
+
+impl RegimeSwitch {
+    pub fn new(
+        window: usize,
+        low: f64,
+        high: f64,
+        trending: Vec<Box<dyn Strategy + Send>>,
+        mean_reverting: Vec<Box<dyn Strategy + Send>>,
+    ) -> RegimeSwitch {
+        let timers = Timers::new(
+            trending
+                .iter()
+                .chain(&mean_reverting)
+                .map(|strategy| strategy.timer()),
+        );
+        RegimeSwitch {
+            window,
+            low,
+            high,
+            trending: Family {
+                strategies: trending,
+                ..Family::default()
+            },
+            mean_reverting: Family {
+                strategies: mean_reverting,
+                ..Family::default()
+            },
+            hurst: HashMap::new(),
+            timers,
+        }
+    }
+
+    pub fn behaviour(&self, ticker: &str) -> Option<Behaviour> {
+        self.hurst.get(ticker)?.behaviour()
+    }
+
+    // Orders of the families active on some ticker or holding positions, given by the position
+    // of the strategies across the families.
+    fn poll(
+        &mut self,
+        mut signals: impl FnMut(usize, &mut Box<dyn Strategy + Send>) -> Vec<Order>,
+    ) -> Vec<Order> {
+        let hurst = &self.hurst;
+        let offset = self.trending.strategies.len();
+        let mut orders = Vec::new();
+        for (family, regime, offset) in [
+            (&mut self.trending, Behaviour::Trending, 0),
+            (&mut self.mean_reverting, Behaviour::MeanReverting, offset),
+        ] {
+            let active =
+                |ticker: &str| hurst.get(ticker).and_then(Hurst::behaviour) == Some(regime);
+            let engaged = hurst
+                .values()
+                .any(|hurst| hurst.behaviour() == Some(regime));
+            if engaged || !family.exposure.flat() {
+                orders.extend(family.poll(
+                    |position, strategy| signals(offset + position, strategy),
+                    active,
+                ));
+            }
+        }
+        orders
+    }
+}
+
+impl Strategy for RegimeSwitch {
+    fn on_start(&mut self, history: &HashMap<String, Vec<Candle>>) {
+        for (ticker, candles) in history {
+            let hurst = self
+                .hurst
+                .entry(ticker.clone())
+                .or_insert_with(|| Hurst::new(self.window, self.low, self.high));
+            for candle in candles {
+                hurst.update(candle);
+            }
+        }
+        for strategy in self
+            .trending
+            .strategies
+            .iter_mut()
+            .chain(self.mean_reverting.strategies.iter_mut())
+        {
+            strategy.on_start(history);
+        }
+    }
+
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        let hurst = self
+            .hurst
+            .entry(ticker.to_string())
+            .or_insert_with(|| Hurst::new(self.window, self.low, self.high));
+        let before = hurst.behaviour();
+        let behaviour = hurst.update(candle).map(|(_, behaviour)| behaviour);
+        if behaviour != before {
+            info!("{} behaviour switched to {:?}", ticker, behaviour);
+        }
+
+        let mut orders = Vec::new();
+        for (family, regime) in [
+            (&mut self.trending, Behaviour::Trending),
+            (&mut self.mean_reverting, Behaviour::MeanReverting),
+        ] {
+            let active = behaviour == Some(regime);
+            if active || !family.exposure.flat() {
+                orders.extend(
+                    family.poll(|_, strategy| strategy.on_candle(ticker, candle), |_| active),
+                );
+            }
+        }
+        orders
+    }
+
+    fn on_fill(&mut self, fill: &Fill) -> Vec<Order> {
+        self.poll(|_, strategy| strategy.on_fill(fill))
+    }
+
+    fn timer(&self) -> Option<i64> {
+        self.timers.period()
+    }
+
+    fn on_timer(&mut self, time: i64) -> Vec<Order> {
+        let due = self.timers.due(time);
+        self.poll(|position, strategy| match due.contains(&position) {
+            true => strategy.on_timer(time),
+            false => Vec::new(),
+        })
+    }
+
+    fn on_stop(&mut self) -> Vec<Order> {
+        self.poll(|_, strategy| strategy.on_stop())
+    }
+
+    fn ready(&self, ticker: &str) -> bool {
+        self.hurst.get(ticker).is_some_and(|hurst| hurst.ready())
+    }
+}
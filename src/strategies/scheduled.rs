@@ -0,0 +1,100 @@
+use crate::backtest::Fill;
+use crate::execution::Order;
+use crate::market::Candle;
+use crate::sessions::TradingHours;
+use crate::strategies::{Exposure, Strategy};
+
+use serde_json::Value;
+
+use tracing::debug;
+
+use std::collections::HashMap;
+
+// Restricts the entries of a strategy to trading hours: it keeps seeing every candle, outside of
+// them only its orders reducing the positions it took are passed on.
+pub struct Scheduled {
+    hours: TradingHours,
+    strategy: Box<dyn Strategy + Send>,
+    exposure: Exposure,
+}
+
+impl Scheduled {
+    pub fn new(hours: TradingHours, strategy: Box<dyn Strategy + Send>) -> Scheduled {
+        Scheduled {
+            hours,
+            strategy,
+            exposure: Exposure::default(),
+        }
+    }
+
+    // Orders passed on at a unix time (in s).
+    fn filter(&mut self, orders: Vec<Order>, time: i64) -> Vec<Order> {
+        if self.hours.is_open(time) {
+            return orders
+                .into_iter()
+                .map(|order| self.exposure.pass(order))
+                .collect();
+        }
+        let count = orders.len();
+        let passed: Vec<Order> = orders
+            .into_iter()
+            .filter_map(|order| self.exposure.reduce(order))
+            .collect();
+        if passed.len() < count {
+            debug!(
+                "Dropping {} entries outside of trading hours",
+                count - passed.len()
+            );
+        }
+        passed
+    }
+}
+
+impl Strategy for Scheduled {
+    fn on_start(&mut self, history: &HashMap<String, Vec<Candle>>) {
+        self.strategy.on_start(history)
+    }
+
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        let orders = self.strategy.on_candle(ticker, candle);
+        self.filter(orders, candle.time)
+    }
+
+    // fills and stops are passed on outside of trading hours, positions still have to be managed
+    fn on_fill(&mut self, fill: &Fill) -> Vec<Order> {
+        let orders = self.strategy.on_fill(fill);
+        orders
+            .into_iter()
+            .map(|order| self.exposure.pass(order))
+            .collect()
+    }
+
+    fn timer(&self) -> Option<i64> {
+        self.strategy.timer()
+    }
+
+    fn on_timer(&mut self, time: i64) -> Vec<Order> {
+        let orders = self.strategy.on_timer(time);
+        self.filter(orders, time)
+    }
+
+    fn on_stop(&mut self) -> Vec<Order> {
+        let orders = self.strategy.on_stop();
+        orders
+            .into_iter()
+            .map(|order| self.exposure.pass(order))
+            .collect()
+    }
+
+    fn ready(&self, ticker: &str) -> bool {
+        self.strategy.ready(ticker)
+    }
+
+    fn state(&self, ticker: &str) -> Option<Value> {
+        self.strategy.state(ticker)
+    }
+
+    fn restore(&mut self, ticker: &str, state: Value) -> Result<(), String> {
+        self.strategy.restore(ticker, state)
+    }
+}
@@ -0,0 +1,307 @@
+use crate::backtest::Fill;
+use crate::execution::{Order, Side};
+use crate::indicators::snapshot;
+use crate::margin;
+use crate::market::Candle;
+use crate::statistics::{deviation, mean};
+use crate::strategies::Strategy;
+
+use rhai::{AST, Array, CallFnOptions, Dynamic, Engine, FLOAT, FuncArgs, INT, Map, Scope};
+
+use tracing::{info, warn};
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+// Operations a script may run per call, a runaway loop fails the call instead of hanging the
+// worker.
+const MAX_OPERATIONS: u64 = 10_000_000;
+// Depth of nested function calls, bounding the recursion of scripts.
+const MAX_CALL_LEVELS: usize = 64;
+// Elements of the arrays and maps and characters of the strings a script builds.
+const MAX_SIZE: usize = 1_000_000;
+
+// Strategy defined in a Rhai script. The script implements
+//
+//     fn on_candle(ticker, candle, closes) { ... }
+//
+// where `candle` is a map of the candle fields and `closes` the array of the latest closes of
+// the ticker (oldest first). It returns nothing, an order built with `buy(ticker, volume)`,
+// `sell(ticker, volume)`, `buy_limit(ticker, volume, price)` or `sell_limit(...)`, or an array of
+// such orders. The script may also implement
+//
+//     fn on_fill(fill) { ... }
+//     fn timer() { ... }
+//     fn on_timer(time) { ... }
+//     fn on_stop() { ... }
+//
+// where `fill` is a map of the fill fields, the side being "buy" or "sell", and `timer` returns
+// the period `on_timer` is called at (in s), returning orders alike. Indicator helpers `sma`,
+// `ema`, `stddev`, `highest` and `lowest` taking the closes and a lookback are available, as are
+// `funding(perpetual)` and `open_interest(perpetual)` giving the latest hourly funding rate and
+// open interest of the perpetual contracts tracked in the margin section, and
+// `indicator(ticker, name)` giving the latest value of a configured indicator, all NaN when
+// unknown. `this` is a map persisted across calls and reloads for the script to keep
+// its own state. The script is recompiled whenever the file changes on disk.
+pub struct ScriptStrategy {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    // modification time of the compiled version of the script
+    modified: Option<SystemTime>,
+
+    // state exposed to the script as `this`
+    state: Dynamic,
+
+    // number of closes kept per ticker
+    capacity: usize,
+    closes: HashMap<String, VecDeque<f64>>,
+}
+
+fn lookback(closes: &Array, length: INT) -> Vec<f64> {
+    let length = (length.max(0) as usize).min(closes.len());
+    closes[closes.len() - length..]
+        .iter()
+        .filter_map(|close| close.as_float().ok())
+        .collect()
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_array_size(MAX_SIZE)
+        .set_max_map_size(MAX_SIZE)
+        .set_max_string_size(MAX_SIZE)
+        .register_type_with_name::<Order>("Order")
+        .register_fn("buy", |ticker: &str, volume: FLOAT| {
+            Order::market(ticker, Side::Buy, volume)
+        })
+        .register_fn("sell", |ticker: &str, volume: FLOAT| {
+            Order::market(ticker, Side::Sell, volume)
+        })
+        .register_fn("buy_limit", |ticker: &str, volume: FLOAT, price: FLOAT| {
+            Order::limit(ticker, Side::Buy, volume, price)
+        })
+        .register_fn("sell_limit", |ticker: &str, volume: FLOAT, price: FLOAT| {
+            Order::limit(ticker, Side::Sell, volume, price)
+        })
+        .register_fn("sma", |closes: Array, length: INT| {
+            mean(&lookback(&closes, length)).unwrap_or(FLOAT::NAN)
+        })
+        .register_fn("ema", |closes: Array, length: INT| {
+            let alpha = 2.0 / (length.max(1) as FLOAT + 1.0);
+            lookback(&closes, length)
+                .into_iter()
+                .reduce(|average, close| alpha * close + (1.0 - alpha) * average)
+                .unwrap_or(FLOAT::NAN)
+        })
+        .register_fn("stddev", |closes: Array, length: INT| {
+            deviation(&lookback(&closes, length)).unwrap_or(FLOAT::NAN)
+        })
+        .register_fn("highest", |closes: Array, length: INT| {
+            lookback(&closes, length)
+                .into_iter()
+                .fold(FLOAT::NAN, FLOAT::max)
+        })
+        .register_fn("lowest", |closes: Array, length: INT| {
+            lookback(&closes, length)
+                .into_iter()
+                .fold(FLOAT::NAN, FLOAT::min)
+        })
+        .register_fn("funding", |perpetual: &str| {
+            margin::funding(perpetual).map_or(FLOAT::NAN, |funding| funding.rate)
+        })
+        .register_fn("open_interest", |perpetual: &str| {
+            margin::funding(perpetual).map_or(FLOAT::NAN, |funding| funding.open_interest)
+        })
+        .register_fn("indicator", |ticker: &str, name: &str| {
+            snapshot::snapshot(ticker)
+                .and_then(|snapshot| snapshot.get(name))
+                .unwrap_or(FLOAT::NAN)
+        });
+    engine
+}
+
+fn fill_map(fill: &Fill) -> Map {
+    let side = match fill.side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    };
+    let mut map = Map::new();
+    map.insert("time".into(), Dynamic::from_int(fill.time));
+    map.insert("ticker".into(), fill.ticker.clone().into());
+    map.insert("side".into(), side.into());
+    map.insert("volume".into(), Dynamic::from_float(fill.volume));
+    map.insert("price".into(), Dynamic::from_float(fill.price));
+    map.insert("fee".into(), Dynamic::from_float(fill.fee));
+    map.insert("maker".into(), Dynamic::from_bool(fill.maker));
+    map
+}
+
+fn candle_map(candle: &Candle) -> Map {
+    let mut map = Map::new();
+    map.insert("time".into(), Dynamic::from_int(candle.time));
+    map.insert("open".into(), Dynamic::from_float(candle.open));
+    map.insert("high".into(), Dynamic::from_float(candle.high));
+    map.insert("low".into(), Dynamic::from_float(candle.low));
+    map.insert("close".into(), Dynamic::from_float(candle.close));
+    map.insert("vwap".into(), Dynamic::from_float(candle.vwap));
+    map.insert("volume".into(), Dynamic::from_float(candle.volume));
+    map.insert("count".into(), Dynamic::from_int(candle.count));
+    map
+}
+
+impl ScriptStrategy {
+    pub fn new(path: PathBuf, capacity: usize) -> Result<ScriptStrategy, String> {
+        let engine = engine();
+        let (ast, modified) = ScriptStrategy::compile(&engine, &path)?;
+        Ok(ScriptStrategy {
+            path,
+            engine,
+            ast,
+            modified,
+            state: Dynamic::from_map(Map::new()),
+            capacity,
+            closes: HashMap::new(),
+        })
+    }
+
+    fn compile(engine: &Engine, path: &PathBuf) -> Result<(AST, Option<SystemTime>), String> {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        match engine.compile_file(path.clone()) {
+            Ok(ast) => Ok((ast, modified)),
+            Err(error) => Err(format!("Could not compile {:?}: {}", path, error)),
+        }
+    }
+
+    // Recompile the script if it changed on disk, keeping the previous version on failure.
+    fn reload(&mut self) {
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        match ScriptStrategy::compile(&self.engine, &self.path) {
+            Ok((ast, modified)) => {
+                info!("Reloaded strategy script {:?}", self.path);
+                self.ast = ast;
+                self.modified = modified;
+            }
+            Err(message) => {
+                warn!("{}, keeping previous version", message);
+                self.modified = modified;
+            }
+        }
+    }
+
+    fn defines(&self, name: &str, arity: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.len() == arity)
+    }
+
+    // Call a function of the script with `this` bound to its state, returning the orders it gives.
+    // Functions the script does not implement give none.
+    fn call(&mut self, name: &str, arity: usize, arguments: impl FuncArgs) -> Vec<Order> {
+        if !self.defines(name, arity) {
+            return Vec::new();
+        }
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        match self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut Scope::new(),
+            &self.ast,
+            name,
+            arguments,
+        ) {
+            Ok(value) => ScriptStrategy::orders(value),
+            Err(error) => {
+                warn!("Strategy script {:?} failed: {}", self.path, error);
+                Vec::new()
+            }
+        }
+    }
+
+    fn orders(value: Dynamic) -> Vec<Order> {
+        if value.is_unit() {
+            return Vec::new();
+        }
+        if value.is_array() {
+            return value
+                .into_array()
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(ScriptStrategy::orders)
+                .collect();
+        }
+        match value.try_cast::<Order>() {
+            Some(order) => vec![order],
+            None => {
+                warn!("Strategy script returned something else than orders");
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl Strategy for ScriptStrategy {
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        self.reload();
+
+        let closes = self.closes.entry(ticker.to_string()).or_default();
+        if closes.len() == self.capacity {
+            closes.pop_front();
+        }
+        closes.push_back(candle.close);
+        let closes: Array = closes
+            .iter()
+            .map(|close| Dynamic::from_float(*close))
+            .collect();
+
+        self.call(
+            "on_candle",
+            3,
+            (ticker.to_string(), candle_map(candle), closes),
+        )
+    }
+
+    fn on_fill(&mut self, fill: &Fill) -> Vec<Order> {
+        self.reload();
+        self.call("on_fill", 1, (fill_map(fill),))
+    }
+
+    // read when the strategy starts, changes of the period need a restart
+    fn timer(&self) -> Option<i64> {
+        if !self.defines("timer", 0) {
+            return None;
+        }
+        match self
+            .engine
+            .call_fn::<INT>(&mut Scope::new(), &self.ast, "timer", ())
+        {
+            Ok(period) => Some(period),
+            Err(error) => {
+                warn!("Strategy script {:?} timer failed: {}", self.path, error);
+                None
+            }
+        }
+    }
+
+    fn on_timer(&mut self, time: i64) -> Vec<Order> {
+        self.reload();
+        self.call("on_timer", 1, (time,))
+    }
+
+    fn on_stop(&mut self) -> Vec<Order> {
+        self.call("on_stop", 0, ())
+    }
+}
@@ -0,0 +1,87 @@
+use crate::backtest::Fill;
+use crate::execution::Order;
+use crate::market::Candle;
+use crate::strategies::Strategy;
+use crate::transforms::{Transform, View};
+
+use serde_json::Value;
+
+use std::collections::HashMap;
+
+// Runs a strategy on an alternative view of the candles, e.g. Heikin-Ashi candles or Renko
+// bricks, instead of the raw stream.
+pub struct Transformed {
+    view: View,
+    strategy: Box<dyn Strategy + Send>,
+    // transform per ticker
+    transforms: HashMap<String, Box<dyn Transform + Send>>,
+}
+
+impl Transformed {
+    pub fn new(view: View, strategy: Box<dyn Strategy + Send>) -> Transformed {
+        Transformed {
+            view,
+            strategy,
+            transforms: HashMap::new(),
+        }
+    }
+}
+
+impl Strategy for Transformed {
+    // the history is passed through the transforms, leaving them primed for the live candles
+    fn on_start(&mut self, history: &HashMap<String, Vec<Candle>>) {
+        let mut transformed = HashMap::new();
+        for (ticker, candles) in history {
+            let transform = self
+                .transforms
+                .entry(ticker.clone())
+                .or_insert_with(|| self.view.transform());
+            let candles: Vec<Candle> = candles
+                .iter()
+                .flat_map(|candle| transform.apply(candle))
+                .collect();
+            transformed.insert(ticker.clone(), candles);
+        }
+        self.strategy.on_start(&transformed)
+    }
+
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        let transform = self
+            .transforms
+            .entry(ticker.to_string())
+            .or_insert_with(|| self.view.transform());
+        transform
+            .apply(candle)
+            .iter()
+            .flat_map(|transformed| self.strategy.on_candle(ticker, transformed))
+            .collect()
+    }
+
+    fn on_fill(&mut self, fill: &Fill) -> Vec<Order> {
+        self.strategy.on_fill(fill)
+    }
+
+    fn timer(&self) -> Option<i64> {
+        self.strategy.timer()
+    }
+
+    fn on_timer(&mut self, time: i64) -> Vec<Order> {
+        self.strategy.on_timer(time)
+    }
+
+    fn on_stop(&mut self) -> Vec<Order> {
+        self.strategy.on_stop()
+    }
+
+    fn ready(&self, ticker: &str) -> bool {
+        self.strategy.ready(ticker)
+    }
+
+    fn state(&self, ticker: &str) -> Option<Value> {
+        self.strategy.state(ticker)
+    }
+
+    fn restore(&mut self, ticker: &str, state: Value) -> Result<(), String> {
+        self.strategy.restore(ticker, state)
+    }
+}
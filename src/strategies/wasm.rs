@@ -0,0 +1,248 @@
+use crate::backtest::Fill;
+use crate::execution::{Order, Side};
+use crate::market::Candle;
+use crate::strategies::Strategy;
+
+use wasmtime::{Caller, Config, Engine, Instance, Linker, Module, Store, TypedFunc};
+
+use tracing::warn;
+
+use std::path::Path;
+
+// Version of the host API plugins are built against, exported by plugins as `api_version`.
+pub const API_VERSION: i32 = 1;
+
+// Instructions budget granted to each plugin call.
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+// (ticker, time, open, high, low, close, volume)
+type CandleArguments = (i32, i64, f64, f64, f64, f64, f64);
+// (ticker, side, volume, price)
+type FillArguments = (i32, i32, f64, f64);
+
+struct Host {
+    // tickers followed by the plugin, referred to by index in the host API
+    tickers: Vec<String>,
+    // orders placed during the current call
+    orders: Vec<Order>,
+}
+
+fn side(value: i32) -> Side {
+    if value >= 0 { Side::Buy } else { Side::Sell }
+}
+
+// Strategy compiled to WebAssembly and run sandboxed with a bounded instruction budget. Tickers
+// are referred to by their index in the list given at load time and sides are encoded as a
+// positive integer for buys and a negative one for sells. Plugins export
+//
+//     api_version() -> i32
+//     on_candle(ticker: i32, time: i64, open: f64, high: f64, low: f64, close: f64, volume: f64)
+//     on_fill(ticker: i32, side: i32, volume: f64, price: f64)   (optional)
+//     timer() -> i64                                             (optional)
+//     on_timer(time: i64)                                        (optional)
+//     on_stop()                                                  (optional)
+//
+// where `timer` gives the period `on_timer` is called at (in s), read once at load time. Plugins
+// may import from the `env` module
+//
+//     place_order(ticker: i32, side: i32, volume: f64, price: f64) -> i32
+//
+// where a non positive price places a market order. It returns 0 on success and -1 for an
+// unknown ticker.
+pub struct WasmStrategy {
+    store: Store<Host>,
+    on_candle: TypedFunc<CandleArguments, ()>,
+    on_fill: Option<TypedFunc<FillArguments, ()>>,
+    on_timer: Option<TypedFunc<i64, ()>>,
+    on_stop: Option<TypedFunc<(), ()>>,
+    // period of the timer (in s)
+    period: Option<i64>,
+}
+
+impl WasmStrategy {
+    pub fn new(path: &Path, tickers: Vec<String>) -> Result<WasmStrategy, String> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = match Engine::new(&config) {
+            Ok(engine) => engine,
+            Err(error) => return Err(format!("{:?}", error)),
+        };
+        let module = match Module::from_file(&engine, path) {
+            Ok(module) => module,
+            Err(error) => return Err(format!("Could not load {:?}: {:?}", path, error)),
+        };
+
+        let mut linker: Linker<Host> = Linker::new(&engine);
+        if let Err(error) = linker.func_wrap(
+            "env",
+            "place_order",
+            |mut caller: Caller<'_, Host>,
+             ticker: i32,
+             order_side: i32,
+             volume: f64,
+             price: f64| {
+                let host = caller.data_mut();
+                let Some(name) = usize::try_from(ticker)
+                    .ok()
+                    .and_then(|index| host.tickers.get(index))
+                else {
+                    return -1;
+                };
+                let order = if price > 0.0 {
+                    Order::limit(name, side(order_side), volume, price)
+                } else {
+                    Order::market(name, side(order_side), volume)
+                };
+                host.orders.push(order);
+                0
+            },
+        ) {
+            return Err(format!("{:?}", error));
+        }
+
+        let mut store = Store::new(
+            &engine,
+            Host {
+                tickers,
+                orders: Vec::new(),
+            },
+        );
+        if let Err(error) = store.set_fuel(FUEL_PER_CALL) {
+            return Err(format!("{:?}", error));
+        }
+        let instance: Instance = match linker.instantiate(&mut store, &module) {
+            Ok(instance) => instance,
+            Err(error) => return Err(format!("Could not instantiate {:?}: {:?}", path, error)),
+        };
+
+        let version = match instance.get_typed_func::<(), i32>(&mut store, "api_version") {
+            Ok(function) => function.call(&mut store, ()),
+            Err(error) => return Err(format!("{:?}", error)),
+        };
+        match version {
+            Ok(API_VERSION) => (),
+            Ok(version) => {
+                return Err(format!(
+                    "Plugin {:?} targets API version {} but host provides {}",
+                    path, version, API_VERSION
+                ));
+            }
+            Err(error) => return Err(format!("{:?}", error)),
+        }
+
+        let on_candle = match instance.get_typed_func(&mut store, "on_candle") {
+            Ok(function) => function,
+            Err(error) => return Err(format!("{:?}", error)),
+        };
+        let on_fill = instance.get_typed_func(&mut store, "on_fill").ok();
+        let on_timer = instance.get_typed_func(&mut store, "on_timer").ok();
+        let on_stop = instance.get_typed_func(&mut store, "on_stop").ok();
+        let period = match instance.get_typed_func::<(), i64>(&mut store, "timer") {
+            Ok(function) => match function.call(&mut store, ()) {
+                Ok(period) => Some(period),
+                Err(error) => return Err(format!("{:?}", error)),
+            },
+            Err(_) => None,
+        };
+
+        Ok(WasmStrategy {
+            store,
+            on_candle,
+            on_fill,
+            on_timer,
+            on_stop,
+            period,
+        })
+    }
+
+    fn index(&self, ticker: &str) -> Option<i32> {
+        self.store
+            .data()
+            .tickers
+            .iter()
+            .position(|name| name == ticker)
+            .and_then(|index| i32::try_from(index).ok())
+    }
+
+    // Notify the plugin of a fill, orders it places in response are returned.
+    pub fn on_fill(
+        &mut self,
+        ticker: &str,
+        fill_side: Side,
+        volume: f64,
+        price: f64,
+    ) -> Vec<Order> {
+        let (Some(index), Some(on_fill)) = (self.index(ticker), self.on_fill.clone()) else {
+            return Vec::new();
+        };
+        let encoded = match fill_side {
+            Side::Buy => 1,
+            Side::Sell => -1,
+        };
+        self.call(|store| on_fill.call(store, (index, encoded, volume, price)))
+    }
+
+    fn call<F>(&mut self, function: F) -> Vec<Order>
+    where
+        F: FnOnce(&mut Store<Host>) -> wasmtime::Result<()>,
+    {
+        if let Err(error) = self.store.set_fuel(FUEL_PER_CALL) {
+            warn!("Could not refuel plugin: {:?}", error);
+            return Vec::new();
+        }
+        let result = function(&mut self.store);
+        let orders = std::mem::take(&mut self.store.data_mut().orders);
+        match result {
+            Ok(()) => orders,
+            Err(error) => {
+                warn!("Plugin call failed, discarding its orders: {:?}", error);
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl Strategy for WasmStrategy {
+    fn on_candle(&mut self, ticker: &str, candle: &Candle) -> Vec<Order> {
+        let Some(index) = self.index(ticker) else {
+            return Vec::new();
+        };
+        let on_candle = self.on_candle.clone();
+        self.call(|store| {
+            on_candle.call(
+                store,
+                (
+                    index,
+                    candle.time,
+                    candle.open,
+                    candle.high,
+                    candle.low,
+                    candle.close,
+                    candle.volume,
+                ),
+            )
+        })
+    }
+
+    fn on_fill(&mut self, fill: &Fill) -> Vec<Order> {
+        WasmStrategy::on_fill(self, &fill.ticker, fill.side, fill.volume, fill.price)
+    }
+
+    fn timer(&self) -> Option<i64> {
+        self.period
+    }
+
+    fn on_timer(&mut self, time: i64) -> Vec<Order> {
+        let Some(on_timer) = self.on_timer.clone() else {
+            return Vec::new();
+        };
+        self.call(|store| on_timer.call(store, time))
+    }
+
+    fn on_stop(&mut self) -> Vec<Order> {
+        let Some(on_stop) = self.on_stop.clone() else {
+            return Vec::new();
+        };
+        self.call(|store| on_stop.call(store, ()))
+    }
+}
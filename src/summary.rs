@@ -0,0 +1,202 @@
+use crate::accounting::Ledger;
+use crate::alerts::{self, EventKind};
+use crate::clock;
+use crate::execution::Executor;
+use crate::journal::{Entry, Journal};
+use crate::risk::RiskGuard;
+use crate::sessions::TradingHours;
+
+use chrono::DateTime;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::sleep;
+
+use tracing::{info, warn};
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SummaryConfig {
+    // whether the trading day is summarized when it closes
+    pub enabled: bool,
+    // trading day summarized, whole UTC days by default
+    pub hours: TradingHours,
+    // whether summaries are written to the journal besides being sent to the alert channels
+    // subscribed to summaries
+    pub journal: bool,
+}
+
+impl Default for SummaryConfig {
+    fn default() -> SummaryConfig {
+        SummaryConfig {
+            enabled: false,
+            hours: TradingHours::default(),
+            journal: true,
+        }
+    }
+}
+
+// Trading activity over a day.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+pub struct DailySummary {
+    // unix times (in s) the day opened and closed at
+    pub from: i64,
+    pub to: i64,
+    pub orders: usize,
+    pub fills: usize,
+    // profit realized net of fees per quote currency
+    pub realized: BTreeMap<String, f64>,
+    pub fees: f64,
+    // base volume held per ticker at the close, negative when short
+    pub positions: BTreeMap<String, f64>,
+    // reasons signals were rejected for, with their number
+    pub rejections: BTreeMap<String, usize>,
+    pub cancels: usize,
+    // whether trading was halted at the close
+    pub halted: bool,
+}
+
+impl DailySummary {
+    // Summary of the journaled activity between unix times (in s), with the positions held at the
+    // end. Profits are those of the fills of the day against the cost basis of all previous ones.
+    pub fn new(
+        entries: &[Entry],
+        from: i64,
+        to: i64,
+        positions: HashMap<String, f64>,
+        halted: bool,
+    ) -> DailySummary {
+        let before = Ledger::from_entries(entries.iter().filter(|entry| entry.time() < from));
+        let after = Ledger::from_entries(entries.iter().filter(|entry| entry.time() < to));
+        let mut realized: BTreeMap<String, f64> =
+            after.realized_by_currency().into_iter().collect();
+        for (currency, profit) in before.realized_by_currency() {
+            *realized.entry(currency).or_default() -= profit;
+        }
+        realized.retain(|_, profit| profit.abs() > 1e-12);
+
+        let mut summary = DailySummary {
+            from,
+            to,
+            realized,
+            fees: after.fees() - before.fees(),
+            positions: positions
+                .into_iter()
+                .filter(|(_, volume)| volume.abs() > 1e-12)
+                .collect(),
+            halted,
+            ..DailySummary::default()
+        };
+        for entry in entries
+            .iter()
+            .filter(|entry| (from..to).contains(&entry.time()))
+        {
+            match entry {
+                Entry::Order { .. } => summary.orders += 1,
+                Entry::Fill { .. } => summary.fills += 1,
+                Entry::Cancel { .. } => summary.cancels += 1,
+                Entry::Rejected { reason, .. } => {
+                    *summary.rejections.entry(reason.clone()).or_default() += 1
+                }
+                Entry::Summary { .. } => (),
+            }
+        }
+        summary
+    }
+
+    pub fn title(&self) -> String {
+        let day = DateTime::from_timestamp(self.from, 0)
+            .map(|time| time.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        format!("Daily summary {}", day)
+    }
+
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "{} orders, {} fills, {} cancels, fees {:.2}",
+            self.orders, self.fills, self.cancels, self.fees
+        )];
+        if self.realized.is_empty() {
+            lines.push(String::from("Nothing realized"));
+        }
+        for (currency, profit) in &self.realized {
+            lines.push(format!("Realized {:.2} {}", profit, currency));
+        }
+        if !self.positions.is_empty() {
+            lines.push(String::from("Open positions:"));
+        }
+        for (ticker, volume) in &self.positions {
+            lines.push(format!("  {}: {}", ticker, volume));
+        }
+        for (reason, count) in &self.rejections {
+            lines.push(format!("Rejected {} times: {}", count, reason));
+        }
+        if self.halted {
+            lines.push(String::from("Trading is halted"));
+        }
+        lines.join("\n")
+    }
+}
+
+// Summarize every trading day when it closes, sending the summary to the alert channels and
+// journaling it when configured.
+pub async fn run<E: Executor + Sync>(
+    config: SummaryConfig,
+    guard: Arc<RiskGuard<E>>,
+    journal: Arc<Mutex<Journal>>,
+    path: PathBuf,
+) {
+    if !config.enabled {
+        return;
+    }
+    // time the last summarized day closed
+    let mut closed = clock::seconds();
+    loop {
+        let Some(close) = config.hours.next_close(closed) else {
+            warn!("Trading hours never close, no daily summary");
+            return;
+        };
+        sleep(Duration::from_secs((close - clock::seconds()).max(0) as u64)).await;
+        closed = close;
+
+        let entries = match Journal::read(&path) {
+            Ok(entries) => entries,
+            Err(message) => {
+                warn!("Could not summarize the day: {}", message);
+                continue;
+            }
+        };
+        let from = config
+            .hours
+            .opened_at(close - 1)
+            .unwrap_or(close - 24 * 3600);
+        let summary = DailySummary::new(
+            &entries,
+            from,
+            close,
+            guard.positions(),
+            guard.halted().is_some(),
+        );
+        info!("{}:\n{}", summary.title(), summary.render());
+        alerts::notify_with(
+            EventKind::Summary,
+            &summary.title(),
+            &summary.render(),
+            serde_json::to_value(&summary).unwrap_or_default(),
+        );
+        if config.journal
+            && let Ok(mut journal) = journal.lock()
+            && let Err(message) = journal.record(&Entry::Summary {
+                time: close,
+                summary,
+            })
+        {
+            warn!("Could not journal the daily summary: {}", message);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::market::Candle;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap};
+
+// What to do with the pairs missing from a time once it is given up on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Laggards {
+    // the time is dropped
+    #[default]
+    Skip,
+    // the snapshot is emitted without the missing pairs
+    Partial,
+    // the snapshot is emitted with the latest candle of the missing pairs
+    CarryForward,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    // time waited for every pair to deliver the candle of a time since the first one did (in s)
+    pub timeout: f64,
+    pub laggards: Laggards,
+}
+
+impl Default for SyncConfig {
+    fn default() -> SyncConfig {
+        SyncConfig {
+            timeout: 10.0,
+            laggards: Laggards::default(),
+        }
+    }
+}
+
+// Candles of the followed pairs for a time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    // unix time (in s) the candles opened at
+    pub time: i64,
+    pub candles: HashMap<String, Candle>,
+    // pairs that did not deliver the candle in time, carried forward or left out
+    pub missing: Vec<String>,
+}
+
+// Time waiting for the candles of some pairs.
+struct Pending {
+    // local time (in s) the first candle of the time arrived at
+    since: f64,
+    candles: HashMap<String, Candle>,
+}
+
+// Combines the closed candles of several pairs into snapshots once every pair delivered the
+// candle of a time. Candles of a pair arrive in time order, so a time is also complete for the
+// pairs that already delivered a later candle: these are laggards that will not catch up. Times
+// are released in order, a time waits for the earlier ones.
+pub struct Synchronizer {
+    config: SyncConfig,
+    tickers: Vec<String>,
+    pending: BTreeMap<i64, Pending>,
+    // latest candle of each pair
+    latest: HashMap<String, Candle>,
+    // time of the latest snapshot released, later candles for it or earlier times are ignored
+    released: Option<i64>,
+}
+
+impl Synchronizer {
+    pub fn new(tickers: Vec<String>, config: SyncConfig) -> Synchronizer {
+        Synchronizer {
+            config,
+            tickers,
+            pending: BTreeMap::new(),
+            latest: HashMap::new(),
+            released: None,
+        }
+    }
+
+    pub fn set_config(&mut self, config: SyncConfig) {
+        self.config = config;
+    }
+
+    pub fn add_ticker(&mut self, ticker: &str) {
+        if !self.tickers.iter().any(|followed| followed == ticker) {
+            self.tickers.push(ticker.to_string());
+        }
+    }
+
+    pub fn remove_ticker(&mut self, ticker: &str) {
+        self.tickers.retain(|followed| followed != ticker);
+        self.latest.remove(ticker);
+        for pending in self.pending.values_mut() {
+            pending.candles.remove(ticker);
+        }
+    }
+
+    // Add the closed candle of a pair received at a local time (in s), returns the snapshots it
+    // completes.
+    pub fn update(&mut self, ticker: &str, candle: &Candle, now: f64) -> Vec<Snapshot> {
+        if self
+            .released
+            .is_some_and(|released| candle.time <= released)
+            || !self.tickers.iter().any(|followed| followed == ticker)
+        {
+            return Vec::new();
+        }
+        self.pending
+            .entry(candle.time)
+            .or_insert_with(|| Pending {
+                since: now,
+                candles: HashMap::new(),
+            })
+            .candles
+            .insert(ticker.to_string(), *candle);
+        self.poll(now)
+    }
+
+    // Release the earliest times that are complete, whose laggards delivered later candles or
+    // that timed out.
+    pub fn poll(&mut self, now: f64) -> Vec<Snapshot> {
+        let mut snapshots = Vec::new();
+        while let Some((&time, pending)) = self.pending.first_key_value() {
+            let missing: Vec<String> = self
+                .tickers
+                .iter()
+                .filter(|ticker| !pending.candles.contains_key(*ticker))
+                .cloned()
+                .collect();
+            // a pair that delivered a later time has moved past this one
+            let overtaken = missing.iter().all(|ticker| {
+                self.pending
+                    .range(time + 1..)
+                    .any(|(_, later)| later.candles.contains_key(ticker))
+            });
+            let expired = now - pending.since >= self.config.timeout;
+            if !missing.is_empty() && !overtaken && !expired {
+                break;
+            }
+            let Some((_, pending)) = self.pending.pop_first() else {
+                break;
+            };
+            self.released = Some(time);
+            for (ticker, candle) in &pending.candles {
+                self.latest.insert(ticker.clone(), *candle);
+            }
+            let mut candles = pending.candles;
+            if !missing.is_empty() {
+                match self.config.laggards {
+                    Laggards::Skip => continue,
+                    Laggards::Partial => (),
+                    Laggards::CarryForward => {
+                        for ticker in &missing {
+                            if let Some(latest) = self.latest.get(ticker) {
+                                candles.insert(ticker.clone(), *latest);
+                            }
+                        }
+                    }
+                }
+            }
+            snapshots.push(Snapshot {
+                time,
+                candles,
+                missing,
+            });
+        }
+        snapshots
+    }
+}
@@ -0,0 +1,149 @@
+use crate::indicators::Indicator;
+use crate::indicators::atr::AverageTrueRange;
+use crate::market::Candle;
+
+use serde::{Deserialize, Serialize};
+
+// Alternative view of a ticker's candle stream.
+pub trait Transform {
+    // Feed the next candle and return the transformed candles it completes, oldest first.
+    fn apply(&mut self, candle: &Candle) -> Vec<Candle>;
+}
+
+// Transform selected by its `type`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum View {
+    HeikinAshi,
+    // bricks of a fixed price size
+    Renko { size: f64 },
+    // bricks sized by a multiple of the average true range at the time they are laid
+    AtrRenko { window: usize, multiple: f64 },
+}
+
+impl View {
+    pub fn transform(&self) -> Box<dyn Transform + Send> {
+        match self {
+            View::HeikinAshi => Box::new(HeikinAshi::new()),
+            View::Renko { size } => Box::new(Renko::new(BrickSize::Fixed(*size))),
+            View::AtrRenko { window, multiple } => Box::new(Renko::new(BrickSize::Atr(
+                AverageTrueRange::new(*window),
+                *multiple,
+            ))),
+        }
+    }
+}
+
+// Heikin-Ashi candles: the close averages the candle's prices and the open is the midpoint of
+// the previous Heikin-Ashi candle's body, smoothing out noise in trends.
+pub struct HeikinAshi {
+    // last Heikin-Ashi candle of the preceding interval
+    previous: Option<Candle>,
+    latest: Option<Candle>,
+}
+
+impl HeikinAshi {
+    pub fn new() -> HeikinAshi {
+        HeikinAshi {
+            previous: None,
+            latest: None,
+        }
+    }
+}
+
+impl Default for HeikinAshi {
+    fn default() -> HeikinAshi {
+        HeikinAshi::new()
+    }
+}
+
+impl Transform for HeikinAshi {
+    fn apply(&mut self, candle: &Candle) -> Vec<Candle> {
+        // revisions of the latest candle are computed from the same preceding candle
+        if let Some(latest) = self.latest
+            && latest.time != candle.time
+        {
+            self.previous = Some(latest);
+        }
+        let close = (candle.open + candle.high + candle.low + candle.close) / 4.0;
+        let open = match self.previous {
+            Some(previous) => (previous.open + previous.close) / 2.0,
+            None => (candle.open + candle.close) / 2.0,
+        };
+        let transformed = Candle {
+            open,
+            high: candle.high.max(open).max(close),
+            low: candle.low.min(open).min(close),
+            close,
+            ..*candle
+        };
+        self.latest = Some(transformed);
+        vec![transformed]
+    }
+}
+
+pub enum BrickSize {
+    Fixed(f64),
+    // average true range of the candles and its multiple
+    Atr(AverageTrueRange, f64),
+}
+
+// Renko bricks: a brick is laid each time the close moves a brick size beyond the last brick,
+// reversals needing to move past its opposite side. Time is ignored, a candle lays any number of
+// bricks including none.
+pub struct Renko {
+    size: BrickSize,
+    // bottom and top of the last brick, the first close is a brick of no height
+    last: Option<(f64, f64)>,
+}
+
+impl Renko {
+    pub fn new(size: BrickSize) -> Renko {
+        Renko { size, last: None }
+    }
+}
+
+impl Transform for Renko {
+    fn apply(&mut self, candle: &Candle) -> Vec<Candle> {
+        let size = match &mut self.size {
+            BrickSize::Fixed(size) => Some(*size),
+            BrickSize::Atr(atr, multiple) => atr.update(candle).map(|range| range * *multiple),
+        };
+        let Some(size) = size.filter(|size| *size > 0.0) else {
+            return Vec::new();
+        };
+        let Some((mut bottom, mut top)) = self.last else {
+            self.last = Some((candle.close, candle.close));
+            return Vec::new();
+        };
+
+        let mut bricks = Vec::new();
+        while candle.close >= top + size {
+            bricks.push((top, top + size));
+            (bottom, top) = (top, top + size);
+        }
+        while candle.close <= bottom - size {
+            bricks.push((bottom, bottom - size));
+            (bottom, top) = (bottom - size, bottom);
+        }
+        self.last = Some((bottom, top));
+
+        // bricks share the candle's volume and get consecutive times ending at the candle's so
+        // that downstream they stay distinct candles
+        let count = bricks.len();
+        bricks
+            .into_iter()
+            .enumerate()
+            .map(|(index, (open, close))| Candle {
+                time: candle.time - (count - 1 - index) as i64,
+                open,
+                high: open.max(close),
+                low: open.min(close),
+                close,
+                vwap: (open + close) / 2.0,
+                volume: candle.volume / count as f64,
+                count: candle.count,
+            })
+            .collect()
+    }
+}
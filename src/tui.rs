@@ -0,0 +1,238 @@
+use crate::events::{self, Event};
+use crate::execution::Executor;
+use crate::market::Candle;
+use crate::risk::RiskGuard;
+
+use ratatui::Frame;
+use ratatui::crossterm::event::{self as input, Event as Input, KeyCode};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::widgets::{Block, List, Paragraph, Row, Sparkline, Table};
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::interval;
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Number of closes drawn per ticker.
+const HISTORY: usize = 120;
+
+// Number of orders and log lines shown.
+const RECENT: usize = 10;
+
+// State of the terminal dashboard built from the live events.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Dashboard {
+    // latest closes per ticker, oldest first
+    closes: BTreeMap<String, VecDeque<f64>>,
+    latest: BTreeMap<String, Candle>,
+    // descriptions of the latest signals and orders, newest first
+    orders: VecDeque<String>,
+    daily_pnl: Option<f64>,
+}
+
+impl Dashboard {
+    pub fn new() -> Dashboard {
+        Dashboard::default()
+    }
+
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::Candle { ticker, candle } => {
+                let closes = self.closes.entry(ticker.clone()).or_default();
+                if closes.len() == HISTORY {
+                    closes.pop_front();
+                }
+                closes.push_back(candle.close);
+                self.latest.insert(ticker.clone(), *candle);
+            }
+            Event::Signal { strategy, orders } => {
+                self.push(format!("{} signalled {:?}", strategy, orders));
+            }
+            Event::Order {
+                strategy,
+                id,
+                order,
+                ..
+            } => {
+                let strategy = strategy.as_deref().unwrap_or("admin");
+                self.push(format!(
+                    "{} {}: {:?} {} {}",
+                    strategy, id, order.side, order.volume, order.ticker
+                ));
+            }
+            Event::Pnl { daily } => self.daily_pnl = Some(*daily),
+            Event::Synchronized(_) | Event::Fill { .. } => (),
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        if self.orders.len() == RECENT {
+            self.orders.pop_back();
+        }
+        self.orders.push_front(line);
+    }
+
+    pub fn render(&self, frame: &mut Frame, positions: &HashMap<String, f64>, log: &[String]) {
+        let [market, middle, bottom] = Layout::vertical([
+            Constraint::Length(8),
+            Constraint::Min(6),
+            Constraint::Length(RECENT as u16 + 2),
+        ])
+        .areas(frame.area());
+        let [positions_area, orders_area] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(middle);
+
+        // first ticker with candles, the others are listed in the positions
+        if let Some((ticker, closes)) = self.closes.iter().next() {
+            let low = closes.iter().copied().fold(f64::INFINITY, f64::min);
+            let high = closes.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let data: Vec<u64> = closes
+                .iter()
+                .map(|close| match high > low {
+                    true => ((close - low) / (high - low) * 100.0) as u64,
+                    false => 50,
+                })
+                .collect();
+            let title = match self.latest.get(ticker) {
+                Some(candle) => format!(
+                    " {} O {} H {} L {} C {} V {:.4} ",
+                    ticker, candle.open, candle.high, candle.low, candle.close, candle.volume
+                ),
+                None => format!(" {} ", ticker),
+            };
+            frame.render_widget(
+                Sparkline::default()
+                    .block(Block::bordered().title(title))
+                    .data(&data),
+                market,
+            );
+        } else {
+            frame.render_widget(
+                Paragraph::new("Waiting for candles").block(Block::bordered()),
+                market,
+            );
+        }
+
+        let mut tickers: Vec<&String> = positions.keys().collect();
+        tickers.sort();
+        let rows: Vec<Row> = tickers
+            .into_iter()
+            .map(|ticker| {
+                let price = self
+                    .latest
+                    .get(ticker)
+                    .map_or(String::new(), |candle| format!("{}", candle.close));
+                Row::new(vec![
+                    ticker.clone(),
+                    format!("{:.6}", positions[ticker]),
+                    price,
+                ])
+            })
+            .collect();
+        let title = match self.daily_pnl {
+            Some(pnl) => format!(" Positions, daily PnL {:.2} ", pnl),
+            None => " Positions ".to_string(),
+        };
+        frame.render_widget(
+            Table::new(
+                rows,
+                [
+                    Constraint::Length(12),
+                    Constraint::Length(14),
+                    Constraint::Min(10),
+                ],
+            )
+            .header(Row::new(vec!["Ticker", "Volume", "Price"]))
+            .block(Block::bordered().title(title)),
+            positions_area,
+        );
+
+        frame.render_widget(
+            List::new(self.orders.iter().cloned())
+                .block(Block::bordered().title(" Signals and orders ")),
+            orders_area,
+        );
+        frame.render_widget(
+            List::new(log.iter().cloned()).block(Block::bordered().title(" Log ")),
+            bottom,
+        );
+    }
+}
+
+// Last lines of a file, read from its end.
+fn tail(path: &Path, lines: usize) -> Vec<String> {
+    let Ok(mut file) = File::open(path) else {
+        return Vec::new();
+    };
+    let length = file.metadata().map_or(0, |metadata| metadata.len());
+    if file
+        .seek(SeekFrom::Start(length.saturating_sub(16 * 1024)))
+        .is_err()
+    {
+        return Vec::new();
+    }
+    let mut content = Vec::new();
+    if file.read_to_end(&mut content).is_err() {
+        return Vec::new();
+    }
+    let content = String::from_utf8_lossy(&content);
+    let mut tail: Vec<String> = content
+        .lines()
+        .rev()
+        .take(lines)
+        .map(|line| line.to_string())
+        .collect();
+    tail.reverse();
+    tail
+}
+
+// Whether q or escape was pressed since the last call.
+fn quit_requested() -> bool {
+    while input::poll(Duration::ZERO).unwrap_or(false) {
+        if let Ok(Input::Key(key)) = input::read()
+            && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+// Draw the dashboard in the terminal until q is pressed, the positions are those of the risk
+// guard and the log tail that of the given file.
+pub async fn run<E: Executor + Sync>(guard: Arc<RiskGuard<E>>, log: &Path) -> Result<(), String> {
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(error) => return Err(format!("Could not set up the terminal: {:?}", error)),
+    };
+    let mut dashboard = Dashboard::new();
+    let mut receiver = events::subscribe();
+    let mut ticker = interval(Duration::from_millis(250));
+    let result = loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Ok(event) => dashboard.apply(&event),
+                Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => break Ok(()),
+            },
+            _ = ticker.tick() => {
+                if quit_requested() {
+                    break Ok(());
+                }
+                let positions = guard.positions();
+                let log = tail(log, RECENT);
+                if let Err(error) = terminal.draw(|frame| dashboard.render(frame, &positions, &log)) {
+                    break Err(format!("Could not draw the dashboard: {:?}", error));
+                }
+            }
+        }
+    };
+    ratatui::restore();
+    result
+}
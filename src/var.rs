@@ -0,0 +1,66 @@
+use crate::statistics::{mean, quantile};
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueAtRisk {
+    // loss (in quote currency) over a day not exceeded with the confidence
+    pub var: f64,
+    // mean loss beyond the value at risk (conditional value at risk or expected shortfall)
+    pub cvar: f64,
+}
+
+// Daily value at risk of exposures (in quote currency per ticker) by historical simulation: the
+// exposures are revalued under each of the past daily returns of their tickers, taken on the
+// same days. Daily closes are given with their day (in days since the epoch), None when the held
+// tickers have no day in common with the close of the day before.
+pub fn historical(
+    exposures: &HashMap<String, f64>,
+    closes: &HashMap<String, Vec<(i64, f64)>>,
+    confidence: f64,
+) -> Option<ValueAtRisk> {
+    let held: Vec<(f64, HashMap<i64, f64>)> = exposures
+        .iter()
+        .filter(|(_, exposure)| **exposure != 0.0)
+        .map(|(ticker, exposure)| Some((*exposure, closes.get(ticker)?.iter().copied().collect())))
+        .collect::<Option<_>>()?;
+    let Some((_, first)) = held.first() else {
+        return Some(ValueAtRisk {
+            var: 0.0,
+            cvar: 0.0,
+        });
+    };
+
+    // returns of the days all the tickers have a close for, along with the day before
+    let mut days: Vec<i64> = first
+        .keys()
+        .copied()
+        .filter(|day| {
+            held.iter()
+                .all(|(_, closes)| closes.contains_key(day) && closes.contains_key(&(day - 1)))
+        })
+        .collect();
+    days.sort();
+    let pnls: Vec<f64> = days
+        .iter()
+        .map(|day| {
+            held.iter()
+                .map(|(exposure, closes)| exposure * (closes[day] / closes[&(day - 1)] - 1.0))
+                .sum()
+        })
+        .collect();
+    if pnls.is_empty() {
+        return None;
+    }
+
+    let threshold = quantile(&pnls, 1.0 - confidence)?;
+    let tail: Vec<f64> = pnls
+        .iter()
+        .copied()
+        .filter(|pnl| *pnl <= threshold)
+        .collect();
+    Some(ValueAtRisk {
+        var: (-threshold).max(0.0),
+        cvar: (-mean(&tail)?).max(0.0),
+    })
+}